@@ -6,8 +6,14 @@ use std::{
 };
 
 use octo::{
-    b64::{decode, encode},
-    mzml::{bin_to_mzml::bin_to_mzml, parse_mzml::parse_mzml, structs::*},
+    b64::{BlockSize, EncodeOptions, decode, encode_with},
+    mzml::{
+        bin_to_mzml::bin_to_mzml,
+        lenient::parse_mzml_lenient,
+        parse_mzml::{ParseOptions, parse_mzml, parse_mzml_with},
+        reindex::{check_index, reindex},
+        structs::*,
+    },
 };
 
 const VERSION: &str = "0.0.0";
@@ -19,25 +25,44 @@ USAGE:
   octo -v | --version
 
   octo convert (--mzml-to-b64 | --mzml-to-b32 | --b64-to-mzml) [--input-path DIR] [--output-path DIR] [--level 0..22]
+               [--block-bytes N | --items-per-block N] [--threads N] [--lenient]
+               [--verify-checksum]
   octo cat --file-path PATH
+  octo index --file-path PATH [--output-path PATH]
 
 CAT FLAGS:
-  --file-path PATH     input file (.b64/.b32), prints full parsed JSON
+  --file-path PATH     input file (.mzML/.mzML.gz/.b64/.b32), prints full parsed JSON
+
+INDEX FLAGS:
+  --file-path PATH     input file (.mzML/.mzML.gz); reports wrong, missing or duplicate
+                       indexList offsets and fails when there are any
+  --output-path PATH   write the document with a rebuilt index (plain mzML is wrapped
+                       into indexedmzML); binary arrays are copied, not re-encoded
 
 CONVERT FLAGS:
-  --mzml-to-b64        .mzML -> .b64
-  --mzml-to-b32        .mzML -> .b32
+  --mzml-to-b64        .mzML/.mzML.gz -> .b64
+  --mzml-to-b32        .mzML/.mzML.gz -> .b32
   --b64-to-mzml        .b64/.b32 -> .mzML
   --input-path DIR     default: crates/parser/data/mzml
   --output-path DIR    default: crates/parser/data/b64
   --level 0..22        default: 12
+  --block-bytes N      close array blocks at N uncompressed bytes (default: 536870912)
+  --items-per-block N  close array blocks after N spectra/chromatograms (1 = one block each)
+  --threads N          threads decoding mzML binary arrays (default: available cores;
+                       not with --lenient, which decodes on one thread)
   --overwrite          default: false (skip if output already exists)
+  --lenient            keep the intact spectra/chromatograms of truncated or corrupted
+                       .mzML files and report the rest (default: false)
+  --verify-checksum    fail .mzML files whose indexedmzML fileChecksum does not match
+                       (default: false)
 
 EXAMPLES:
   octo convert --mzml-to-b64 --input-path crates/parser/data/mzml --output-path crates/parser/data/b64
   octo convert --b64-to-mzml --input-path crates/parser/data/b64 --output-path crates/parser/data/mzml_out
 
   octo cat --file-path crates/parser/data/b64/tiny.msdata.mzML0.99.9.b64
+
+  octo index --file-path crates/parser/data/mzml/tiny1.mzML0.99.0.mzML --output-path tiny1.indexed.mzML
 "#;
 
 #[derive(Parser)]
@@ -64,6 +89,7 @@ struct Cli {
 enum Cmd {
     Convert(ConvertArgs),
     Cat(CatArgs),
+    Index(IndexArgs),
 }
 
 #[derive(Args)]
@@ -85,9 +111,24 @@ struct ConvertArgs {
     #[arg(long = "level", default_value_t = 12, value_parser = clap::value_parser!(u8).range(0..=22))]
     compression_level: u8,
 
+    #[arg(long = "block-bytes", conflicts_with = "items_per_block", value_parser = clap::value_parser!(u32).range(1..))]
+    block_bytes: Option<u32>,
+
+    #[arg(long = "items-per-block", value_parser = clap::value_parser!(u32).range(1..))]
+    items_per_block: Option<u32>,
+
+    #[arg(long = "threads", conflicts_with = "lenient", value_parser = clap::value_parser!(u32).range(1..))]
+    threads: Option<u32>,
+
     #[arg(long, default_value_t = false, action = ArgAction::SetTrue)]
     overwrite: bool,
 
+    #[arg(long, default_value_t = false, action = ArgAction::SetTrue)]
+    lenient: bool,
+
+    #[arg(long = "verify-checksum", default_value_t = false, action = ArgAction::SetTrue)]
+    verify_checksum: bool,
+
     #[command(flatten)]
     which: ConvertWhich,
 }
@@ -110,6 +151,15 @@ struct CatArgs {
     file_path: PathBuf,
 }
 
+#[derive(Args)]
+struct IndexArgs {
+    #[arg(long = "file-path")]
+    file_path: PathBuf,
+
+    #[arg(long = "output-path")]
+    output_path: Option<PathBuf>,
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
@@ -126,6 +176,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     match cli.cmd.unwrap() {
         Cmd::Convert(cmd) => convert(cmd).map_err(|e| e.into()),
         Cmd::Cat(cmd) => cat(cmd).map_err(|e| e.into()),
+        Cmd::Index(cmd) => index(cmd).map_err(|e| e.into()),
     }
 }
 
@@ -143,6 +194,44 @@ fn cat(cmd: CatArgs) -> Result<(), String> {
     print_json_full(&mzml)
 }
 
+fn index(cmd: IndexArgs) -> Result<(), String> {
+    let cwd = std::env::current_dir().map_err(|e| format!("get current dir failed: {e}"))?;
+    let file_path = resolve_user_path(&cwd, &cmd.file_path);
+    let bytes = fs::read(&file_path).map_err(|e| format!("read failed: {e}"))?;
+
+    if let Some(out) = cmd.output_path.as_deref() {
+        let out_path = resolve_user_path(&cwd, out);
+        let fixed = reindex(&bytes).map_err(|e| format!("reindex failed: {e}"))?;
+        fs::write(&out_path, &fixed).map_err(|e| format!("write failed: {e}"))?;
+        println!("wrote {} ({} bytes)", out_path.display(), fixed.len());
+        return Ok(());
+    }
+
+    let report = check_index(&bytes).map_err(|e| format!("check failed: {e}"))?;
+    for issue in &report.issues {
+        println!("{}: {issue}", file_path.display());
+    }
+    if !report.indexed {
+        return Err(format!("{}: not an indexedmzML file", file_path.display()));
+    }
+    if !report.is_valid() {
+        return Err(format!(
+            "{}: {} index problems ({} spectra, {} chromatograms)",
+            file_path.display(),
+            report.issues.len(),
+            report.spectra,
+            report.chromatograms
+        ));
+    }
+    println!(
+        "{}: index ok ({} spectra, {} chromatograms)",
+        file_path.display(),
+        report.spectra,
+        report.chromatograms
+    );
+    Ok(())
+}
+
 fn workspace_root() -> PathBuf {
     let here = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
     here.ancestors()
@@ -158,17 +247,36 @@ fn file_ext_lower(path: &Path) -> String {
         .to_ascii_lowercase()
 }
 
+/// `.mzML` or `.mzML.gz`
+fn is_mzml_file(path: &Path) -> bool {
+    let name = path
+        .file_name()
+        .and_then(|s| s.to_str())
+        .unwrap_or("")
+        .to_ascii_lowercase();
+    name.ends_with(".mzml") || name.ends_with(".mzml.gz")
+}
+
+fn is_bin_file(path: &Path) -> bool {
+    let ext = file_ext_lower(path);
+    ext == "b64" || ext == "b32"
+}
+
 fn out_name_for_mzml_file(path: &Path, out_ext: &str) -> Option<String> {
-    if file_ext_lower(path) != "mzml" {
+    if !is_mzml_file(path) {
         return None;
     }
+    let path = if file_ext_lower(path) == "gz" {
+        Path::new(path.file_stem()?)
+    } else {
+        path
+    };
     let stem = path.file_stem()?.to_string_lossy();
     Some(format!("{stem}.{out_ext}"))
 }
 
 fn out_name_for_bin_file_as_mzml(path: &Path) -> Option<String> {
-    let ext = file_ext_lower(path);
-    if ext != "b64" && ext != "b32" {
+    if !is_bin_file(path) {
         return None;
     }
     let stem = path.file_stem()?.to_string_lossy();
@@ -177,21 +285,10 @@ fn out_name_for_bin_file_as_mzml(path: &Path) -> Option<String> {
 
 fn read_mzml_or_b64(file_path: &Path) -> Result<MzML, String> {
     let bytes = fs::read(file_path).map_err(|e| format!("read failed: {e}"))?;
-    let ext = file_ext_lower(file_path);
-
-    if ext == "b64" || ext == "b32" {
-        return decode(&bytes).map_err(|e| format!("decode failed: {e}"));
-    }
-    if ext == "mzml" {
-        return parse_mzml(&bytes, false).map_err(|e| format!("parse_mzml failed: {e}"));
-    }
-
-    Err(format!(
-        "unsupported file extension: {ext:?} (expected .mzML or .b64/.b32)"
-    ))
+    read_mzml_or_b64_from_bytes(file_path, &bytes)
 }
 
-fn collect_files_with_exts(input_root: &Path, exts: &[&str]) -> Result<Vec<PathBuf>, String> {
+fn collect_files(input_root: &Path, keep: fn(&Path) -> bool) -> Result<Vec<PathBuf>, String> {
     let mut out = Vec::new();
     let mut stack = vec![input_root.to_path_buf()];
 
@@ -207,15 +304,7 @@ fn collect_files_with_exts(input_root: &Path, exts: &[&str]) -> Result<Vec<PathB
             if !p.is_file() {
                 continue;
             }
-            let ext = file_ext_lower(&p);
-            let mut ok = false;
-            for want in exts {
-                if ext == *want {
-                    ok = true;
-                    break;
-                }
-            }
-            if ok {
+            if keep(&p) {
                 out.push(p);
             }
         }
@@ -247,10 +336,10 @@ fn convert(cmd: ConvertArgs) -> Result<(), String> {
         let out_ext = if cmd.which.mzml_to_b32 { "b32" } else { "b64" };
         let f32_compress = cmd.which.mzml_to_b32;
 
-        let files = collect_files_with_exts(&input_root, &["mzml"])?;
+        let files = collect_files(&input_root, is_mzml_file)?;
         if files.is_empty() {
             return Err(format!(
-                "no .mzML files found under {}",
+                "no .mzML/.mzML.gz files found under {}",
                 input_root.display()
             ));
         }
@@ -319,17 +408,56 @@ fn convert(cmd: ConvertArgs) -> Result<(), String> {
                 }
             };
 
-            let mzml = match parse_mzml(&bytes, false) {
+            let mzml = if cmd.lenient {
+                let (mzml, recovery) = parse_mzml_lenient(&bytes);
+                for e in &recovery.diagnostics {
+                    eprintln!("{}: dropped: {e}", in_path.display());
+                }
+                if !recovery.is_clean() {
+                    eprintln!(
+                        "{}: recovered {} spectra ({} dropped), {} chromatograms ({} dropped), {:.1}% of {} bytes{}",
+                        in_path.display(),
+                        recovery.spectra,
+                        recovery.dropped_spectra,
+                        recovery.chromatograms,
+                        recovery.dropped_chromatograms,
+                        recovery.recovered_fraction() * 100.0,
+                        recovery.total_bytes,
+                        if recovery.complete { "" } else { ", truncated" }
+                    );
+                }
+                mzml
+            } else {
+                let mut options = ParseOptions::default();
+                if let Some(n) = cmd.threads {
+                    options.threads = n as usize;
+                }
+                options.verify_checksum = cmd.verify_checksum;
+                match parse_mzml_with(&bytes, &options) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        eprintln!("{}: parse_mzml failed: {e}", in_path.display());
+                        failed += 1;
+                        continue;
+                    }
+                }
+            };
+
+            let mut options = EncodeOptions::new(cmd.compression_level, f32_compress);
+            if let Some(n) = cmd.block_bytes {
+                options.block_size = BlockSize::Bytes(n);
+            } else if let Some(n) = cmd.items_per_block {
+                options.block_size = BlockSize::Items(n);
+            }
+            let encoded = match encode_with(&mzml, &options) {
                 Ok(v) => v,
                 Err(e) => {
-                    eprintln!("{}: parse_mzml failed: {e}", in_path.display());
+                    eprintln!("{}: encode failed: {e}", in_path.display());
                     failed += 1;
                     continue;
                 }
             };
 
-            let encoded = encode(&mzml, cmd.compression_level, f32_compress);
-
             let in_mb = bytes.len() as f64 / MB;
             let out_mb = encoded.len() as f64 / MB;
 
@@ -359,7 +487,7 @@ fn convert(cmd: ConvertArgs) -> Result<(), String> {
     }
 
     if cmd.which.b64_to_mzml {
-        let files = collect_files_with_exts(&input_root, &["b64", "b32"])?;
+        let files = collect_files(&input_root, is_bin_file)?;
         if files.is_empty() {
             return Err(format!(
                 "no .b64/.b32 files found under {}",
@@ -481,17 +609,16 @@ fn convert(cmd: ConvertArgs) -> Result<(), String> {
 }
 
 fn read_mzml_or_b64_from_bytes(file_path: &Path, bytes: &[u8]) -> Result<MzML, String> {
-    let ext = file_ext_lower(file_path);
-
-    if ext == "b64" || ext == "b32" {
+    if is_bin_file(file_path) {
         return decode(bytes).map_err(|e| format!("decode failed: {e}"));
     }
-    if ext == "mzml" {
+    if is_mzml_file(file_path) {
         return parse_mzml(bytes, false).map_err(|e| format!("parse_mzml failed: {e}"));
     }
 
     Err(format!(
-        "unsupported file extension: {ext:?} (expected .mzML or .b64/.b32)"
+        "unsupported file extension: {:?} (expected .mzML, .mzML.gz or .b64/.b32)",
+        file_ext_lower(file_path)
     ))
 }
 
@@ -1,11 +1,16 @@
-use std::collections::HashMap;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, VecDeque},
+};
 
 use crate::{
     b64::utilities::{
-        Header, common::*, parse_chromatogram_list, parse_cv_and_user_params, parse_cv_list,
-        parse_data_processing_list, parse_file_description::parse_file_description,
+        CvPrefixes, Header, MetadataLayout, SectionChecksums, apply_extension_rows,
+        checksums::BLOCK_FLAG_CRC32C, common::*, crc32c, parse_chromatogram_list,
+        parse_cv_and_user_params, parse_cv_list, parse_data_processing_list,
+        parse_file_description::parse_file_description,
         parse_global_metadata::parse_global_metadata, parse_header, parse_instrument_list,
-        parse_metadata, parse_referenceable_param_group_list, parse_sample_list,
+        parse_metadata_with, parse_referenceable_param_group_list, parse_sample_list,
         parse_scan_settings_list, parse_software_list, parse_spectrum_list,
     },
     mzml::{attr_meta::*, schema::TagId, structs::*},
@@ -14,20 +19,34 @@ use crate::{
 pub const INDEX_ENTRY_SIZE: usize = 32;
 const BLOCK_DIR_ENTRY_SIZE: usize = 32;
 
+/// Default byte budget for the decompressed blocks kept by a `ContainerReader`.
+pub const DEFAULT_BLOCK_CACHE_BYTES: usize = 256 * 1024 * 1024;
+
 // const ARRAY_FILTER_NONE: u8 = 0;
 const ARRAY_FILTER_BYTE_SHUFFLE: u8 = 1;
 
-const ACC_MZ_ARRAY: u32 = 1_000_514;
-const ACC_INTENSITY_ARRAY: u32 = 1_000_515;
-const ACC_TIME_ARRAY: u32 = 1_000_595;
+pub(crate) const ACC_MZ_ARRAY: u32 = 1_000_514;
+pub(crate) const ACC_INTENSITY_ARRAY: u32 = 1_000_515;
+pub(crate) const ACC_TIME_ARRAY: u32 = 1_000_595;
 
 const ACC_32BIT_FLOAT: u32 = 1_000_521;
 const ACC_64BIT_FLOAT: u32 = 1_000_523;
 
 const HDR_FLAG_GLOBAL_META_COMP: u8 = 1 << 6;
 
+const AUX_HEADER_SIZE: usize = 16;
+const AUX_CONTAINER_ENTRY_SIZE: usize = 32;
+const AUX_INDEX_ENTRY_SIZE: usize = 32;
+
+const FMT_F32: u8 = 1;
+const FMT_F64: u8 = 2;
+const FMT_I16: u8 = 3;
+const FMT_I32: u8 = 4;
+const FMT_I64: u8 = 5;
+
 pub fn decode(bytes: &[u8]) -> Result<MzML, String> {
     let header = parse_header(bytes)?;
+    SectionChecksums::from_file(bytes, &header)?.verify_all(bytes)?;
     let global_meta = parse_global_metadata_section(bytes, &header)?;
     let global_child_index = ChildIndex::new(&global_meta);
 
@@ -35,7 +54,7 @@ pub fn decode(bytes: &[u8]) -> Result<MzML, String> {
 
     let cv_list = parse_cv_list(&global_meta_ref, &global_child_index);
 
-    Ok(MzML {
+    let mut mzml = MzML {
         cv_list,
         file_description: parse_file_description(&global_meta_ref, &global_child_index)
             .expect("missing <fileDescription> in global metadata"),
@@ -49,34 +68,46 @@ pub fn decode(bytes: &[u8]) -> Result<MzML, String> {
         data_processing_list: parse_data_processing_list(&global_meta_ref, &global_child_index),
         scan_settings_list: parse_scan_settings_list(&global_meta_ref, &global_child_index),
         run: parse_run(bytes, &header, &global_meta)?,
-    })
+        ..Default::default()
+    };
+    apply_extension_rows(&mut mzml, &global_meta_ref);
+
+    Ok(mzml)
 }
 
 /// <run>
 #[inline]
 fn parse_run(bytes: &[u8], header: &Header, global_meta: &[Metadatum]) -> Result<Run, String> {
+    let cv_prefixes = CvPrefixes::from_metadata(global_meta);
+
     let spec_meta = parse_metadata_section(
         bytes,
-        header,
         header.off_spec_meta,
         header.off_chrom_meta,
-        header.spectrum_count,
-        header.spec_meta_count,
-        header.spec_num_count,
-        header.spec_str_count,
-        4,
+        &MetadataLayout {
+            item_count: header.spectrum_count,
+            meta_count: header.spec_meta_count,
+            num_count: header.spec_num_count,
+            str_count: header.spec_str_count,
+            compressed: (header.reserved_flags & (1u8 << 4)) != 0,
+            reserved_flags: header.reserved_flags,
+            cv_prefixes: &cv_prefixes,
+        },
     );
 
     let chrom_meta = parse_metadata_section(
         bytes,
-        header,
         header.off_chrom_meta,
         header.off_global_meta,
-        header.chrom_count,
-        header.chrom_meta_count,
-        header.chrom_num_count,
-        header.chrom_str_count,
-        5,
+        &MetadataLayout {
+            item_count: header.chrom_count,
+            meta_count: header.chrom_meta_count,
+            num_count: header.chrom_num_count,
+            str_count: header.chrom_str_count,
+            compressed: (header.reserved_flags & (1u8 << 5)) != 0,
+            reserved_flags: header.reserved_flags,
+            cv_prefixes: &cv_prefixes,
+        },
     );
 
     let run_child_index = ChildIndex::new(global_meta);
@@ -133,6 +164,7 @@ fn parse_run(bytes: &[u8], header: &Header, global_meta: &[Metadatum]) -> Result
     let chromatogram_list = parse_chromatogram_list(&chrom_meta_ref, &chrom_child_index);
 
     let (spectra_pairs, chrom_pairs) = parse_binaries(bytes, header)?;
+    let (spectra_aux, chrom_aux) = parse_aux_arrays(bytes, header)?;
 
     let mut run = Run {
         id,
@@ -148,28 +180,29 @@ fn parse_run(bytes: &[u8], header: &Header, global_meta: &[Metadatum]) -> Result
     };
 
     attach_pairs_to_run_lists(&mut run, &spectra_pairs, &chrom_pairs);
+    attach_aux_arrays_to_run_lists(&mut run, spectra_aux, chrom_aux);
 
     Ok(run)
 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct SpectrumIndexEntry {
-    mz_element_off: u64,
-    inten_element_off: u64,
-    mz_element_len: u32,
-    inten_element_len: u32,
-    mz_block_id: u32,
-    inten_block_id: u32,
+    pub(crate) mz_element_off: u64,
+    pub(crate) inten_element_off: u64,
+    pub(crate) mz_element_len: u32,
+    pub(crate) inten_element_len: u32,
+    pub(crate) mz_block_id: u32,
+    pub(crate) inten_block_id: u32,
 }
 
 #[derive(Clone, Copy, Debug)]
 pub struct ChromIndexEntry {
-    time_element_off: u64,
-    inten_element_off: u64,
-    time_element_len: u32,
-    inten_element_len: u32,
-    time_block_id: u32,
-    inten_block_id: u32,
+    pub(crate) time_element_off: u64,
+    pub(crate) inten_element_off: u64,
+    pub(crate) time_element_len: u32,
+    pub(crate) inten_element_len: u32,
+    pub(crate) time_block_id: u32,
+    pub(crate) inten_block_id: u32,
 }
 
 #[inline]
@@ -206,13 +239,53 @@ pub fn read_u64_le_at(bytes: &[u8], pos: &mut usize, field: &'static str) -> Res
 }
 
 #[inline]
-fn parse_chrom_index(bytes: &[u8], header: &Header) -> Result<Vec<ChromIndexEntry>, String> {
-    let count = header.chrom_count as usize;
-    let need = (count as u64)
+pub(crate) fn index_byte_len(count: u32, field: &'static str) -> Result<u64, String> {
+    (count as u64)
         .checked_mul(INDEX_ENTRY_SIZE as u64)
-        .ok_or_else(|| "chrom index size overflow".to_string())?;
+        .ok_or_else(|| format!("{field} size overflow"))
+}
+
+#[inline]
+fn parse_spectrum_index(bytes: &[u8], header: &Header) -> Result<Vec<SpectrumIndexEntry>, String> {
+    let need = index_byte_len(header.spectrum_count, "spectrum index")?;
+    let raw = slice_at(bytes, header.off_spec_index, need, "spectrum index")?;
+    parse_spectrum_index_entries(raw, header.spectrum_count)
+}
+
+#[inline]
+pub(crate) fn parse_spectrum_index_entries(
+    raw: &[u8],
+    count: u32,
+) -> Result<Vec<SpectrumIndexEntry>, String> {
+    let count = count as usize;
+    let mut pos = 0usize;
+    let mut out = Vec::with_capacity(count);
+    for _ in 0..count {
+        out.push(SpectrumIndexEntry {
+            mz_element_off: read_u64_le_at(raw, &mut pos, "mz_element_off")?,
+            inten_element_off: read_u64_le_at(raw, &mut pos, "inten_element_off")?,
+            mz_element_len: read_u32_le_at(raw, &mut pos, "mz_element_len")?,
+            inten_element_len: read_u32_le_at(raw, &mut pos, "inten_element_len")?,
+            mz_block_id: read_u32_le_at(raw, &mut pos, "mz_block_id")?,
+            inten_block_id: read_u32_le_at(raw, &mut pos, "inten_block_id")?,
+        });
+    }
+    Ok(out)
+}
+
+#[inline]
+fn parse_chrom_index(bytes: &[u8], header: &Header) -> Result<Vec<ChromIndexEntry>, String> {
+    let need = index_byte_len(header.chrom_count, "chrom index")?;
     let raw = slice_at(bytes, header.off_chrom_index, need, "chrom index")?;
+    parse_chrom_index_entries(raw, header.chrom_count)
+}
 
+#[inline]
+pub(crate) fn parse_chrom_index_entries(
+    raw: &[u8],
+    count: u32,
+) -> Result<Vec<ChromIndexEntry>, String> {
+    let count = count as usize;
     let mut pos = 0usize;
     let mut out = Vec::with_capacity(count);
     for _ in 0..count {
@@ -233,100 +306,266 @@ struct BlockDirEntry {
     comp_off: u64,
     comp_size: u64,
     uncomp_bytes: u64,
+    format: u8,
+    crc32c: Option<u32>,
 }
 
 pub struct ContainerReader<'a> {
     bytes: &'a [u8],
-    elem_size: usize,
+    name: String,
     compression_level: u8,
     array_filter: u8,
     dir: Vec<BlockDirEntry>,
     comp_buf_start: usize,
-    cache: Vec<Option<Vec<u8>>>,
+    cache: BlockCache,
     scratch: Vec<u8>,
 }
 
+/// Decompressed blocks kept in least-recently-used order under a byte budget.
+///
+/// The most recent block is always kept, even when it alone exceeds the
+/// budget. When the remaining uses of each block are known, a block is also
+/// dropped as soon as its last item has been read.
+struct BlockCache {
+    blocks: Vec<Option<Vec<u8>>>,
+    lru: VecDeque<u32>,
+    bytes: usize,
+    budget: usize,
+    uses: Vec<u32>,
+}
+
+impl BlockCache {
+    #[inline]
+    fn new(block_count: usize, budget: usize) -> Self {
+        Self {
+            blocks: vec![None; block_count],
+            lru: VecDeque::new(),
+            bytes: 0,
+            budget,
+            uses: Vec::new(),
+        }
+    }
+
+    #[inline]
+    fn get(&mut self, block_id: u32) -> Option<&[u8]> {
+        self.blocks.get(block_id as usize)?.as_ref()?;
+        if self.lru.back() != Some(&block_id) {
+            if let Some(p) = self.lru.iter().position(|&b| b == block_id) {
+                self.lru.remove(p);
+            }
+            self.lru.push_back(block_id);
+        }
+        self.blocks[block_id as usize].as_deref()
+    }
+
+    #[inline]
+    fn insert(&mut self, block_id: u32, block: Vec<u8>) {
+        while self.bytes + block.len() > self.budget {
+            let Some(oldest) = self.lru.pop_front() else {
+                break;
+            };
+            self.drop_block(oldest);
+        }
+        self.bytes += block.len();
+        self.blocks[block_id as usize] = Some(block);
+        self.lru.push_back(block_id);
+    }
+
+    #[inline]
+    fn drop_block(&mut self, block_id: u32) {
+        if let Some(block) = self.blocks[block_id as usize].take() {
+            self.bytes -= block.len();
+        }
+    }
+
+    #[inline]
+    fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        while self.bytes > self.budget && self.lru.len() > 1 {
+            let oldest = self.lru.pop_front().unwrap();
+            self.drop_block(oldest);
+        }
+    }
+
+    #[inline]
+    fn consume(&mut self, block_id: u32) {
+        let Some(n) = self.uses.get_mut(block_id as usize) else {
+            return;
+        };
+        *n = n.saturating_sub(1);
+        if *n == 0 {
+            self.drop_block(block_id);
+            self.lru.retain(|&b| b != block_id);
+        }
+    }
+}
+
 impl<'a> ContainerReader<'a> {
     #[inline]
     pub fn new(
         bytes: &'a [u8],
         block_count: u32,
-        elem_size: usize,
+        default_format: u8,
         compression_level: u8,
         array_filter: u8,
     ) -> Result<Self, String> {
-        let bc = block_count as usize;
-        let dir_bytes = bc
+        let dir_bytes = Self::directory_len(block_count)?;
+        if bytes.len() < dir_bytes {
+            return Err("container too small for directory".to_string());
+        }
+
+        let mut reader = Self::from_directory(
+            &bytes[..dir_bytes],
+            block_count,
+            default_format,
+            compression_level,
+            array_filter,
+        )?;
+        reader.bytes = bytes;
+        Ok(reader)
+    }
+
+    /// Size in bytes of the block directory that opens a container.
+    #[inline]
+    pub fn directory_len(block_count: u32) -> Result<usize, String> {
+        (block_count as usize)
             .checked_mul(BLOCK_DIR_ENTRY_SIZE)
-            .ok_or_else(|| "container dir size overflow".to_string())?;
+            .ok_or_else(|| "container dir size overflow".to_string())
+    }
 
-        if bytes.len() < dir_bytes {
+    /// Reader over a container whose blocks are fetched on demand through
+    /// [`ContainerReader::block_bytes_with`]; offsets passed to the fetcher
+    /// are relative to the start of the container.
+    #[inline]
+    pub fn from_directory(
+        dir_raw: &[u8],
+        block_count: u32,
+        default_format: u8,
+        compression_level: u8,
+        array_filter: u8,
+    ) -> Result<Self, String> {
+        let bc = block_count as usize;
+        let dir_bytes = Self::directory_len(block_count)?;
+        if dir_raw.len() < dir_bytes {
             return Err("container too small for directory".to_string());
         }
 
         let mut pos = 0usize;
-        let dir_raw = &bytes[..dir_bytes];
+        let dir_raw = &dir_raw[..dir_bytes];
         let mut dir = Vec::with_capacity(bc);
 
         for _ in 0..bc {
             let comp_off = read_u64_le_at(dir_raw, &mut pos, "comp_off")?;
             let comp_size = read_u64_le_at(dir_raw, &mut pos, "comp_size")?;
             let uncomp_bytes = read_u64_le_at(dir_raw, &mut pos, "uncomp_bytes")?;
-            let _ = take(dir_raw, &mut pos, 8, "reserved")?;
+            let reserved = take(dir_raw, &mut pos, 8, "reserved")?;
+            // Files written before per-block formats leave this byte zeroed.
+            let format = match reserved[0] {
+                0 => default_format,
+                f => f,
+            };
+            format_elem_size(format, "block format")?;
+            let crc32c = (reserved[1] & BLOCK_FLAG_CRC32C != 0)
+                .then(|| u32::from_le_bytes(reserved[4..8].try_into().unwrap()));
             dir.push(BlockDirEntry {
                 comp_off,
                 comp_size,
                 uncomp_bytes,
+                format,
+                crc32c,
             });
         }
 
         Ok(Self {
-            bytes,
-            elem_size,
+            bytes: &[],
+            name: "container".to_string(),
             compression_level,
             array_filter,
             dir,
             comp_buf_start: dir_bytes,
-            cache: vec![None; bc],
+            cache: BlockCache::new(bc, DEFAULT_BLOCK_CACHE_BYTES),
             scratch: Vec::new(),
         })
     }
 
+    /// Names the container in checksum errors.
+    #[inline]
+    pub fn set_name(&mut self, name: impl Into<String>) {
+        self.name = name.into();
+    }
+
+    #[inline]
+    pub fn block_count(&self) -> u32 {
+        self.dir.len() as u32
+    }
+
+    /// Caps the bytes of decompressed blocks kept between reads.
+    #[inline]
+    pub fn set_cache_budget(&mut self, bytes: usize) {
+        self.cache.set_budget(bytes);
+    }
+
+    /// Bytes of decompressed blocks currently cached.
+    #[inline]
+    pub fn cached_bytes(&self) -> usize {
+        self.cache.bytes
+    }
+
+    /// Records how many items will be read from each block, so that
+    /// [`ContainerReader::consume`] can drop a block after its last item.
     #[inline]
-    fn ensure_block(&mut self, block_id: u32) -> Result<(), String> {
+    pub fn track_block_uses<I: IntoIterator<Item = u32>>(&mut self, block_ids: I) {
+        let mut uses = vec![0u32; self.dir.len()];
+        for b in block_ids {
+            if let Some(n) = uses.get_mut(b as usize) {
+                *n += 1;
+            }
+        }
+        self.cache.uses = uses;
+    }
+
+    /// Marks one item of `block_id` as read.
+    #[inline]
+    pub fn consume(&mut self, block_id: u32) {
+        self.cache.consume(block_id);
+    }
+
+    #[inline]
+    fn ensure_block<'b, F>(&mut self, block_id: u32, fetch: F) -> Result<(), String>
+    where
+        F: FnOnce(u64, u64) -> Result<Cow<'b, [u8]>, String>,
+    {
         let i = block_id as usize;
-        if i >= self.cache.len() {
+        if i >= self.dir.len() {
             return Err(format!("block_id out of range: {block_id}"));
         }
-        if self.cache[i].is_some() {
+        if self.cache.get(block_id).is_some() {
             return Ok(());
         }
 
         let e = self.dir[i];
 
-        let comp_off = usize::try_from(e.comp_off).map_err(|_| "comp_off overflow".to_string())?;
-        let comp_size =
-            usize::try_from(e.comp_size).map_err(|_| "comp_size overflow".to_string())?;
         let expected =
             usize::try_from(e.uncomp_bytes).map_err(|_| "uncomp_bytes overflow".to_string())?;
-
-        let start = self
-            .comp_buf_start
-            .checked_add(comp_off)
+        let start = (self.comp_buf_start as u64)
+            .checked_add(e.comp_off)
             .ok_or_else(|| "comp start overflow".to_string())?;
-        let end = start
-            .checked_add(comp_size)
-            .ok_or_else(|| "comp end overflow".to_string())?;
 
-        if end > self.bytes.len() {
+        let comp = fetch(start, e.comp_size)?;
+        if comp.len() as u64 != e.comp_size {
             return Err("container: block range out of bounds".to_string());
         }
+        if e.crc32c.is_some_and(|crc| crc32c(&comp) != crc) {
+            return Err(format!(
+                "{}: checksum mismatch in block {block_id}",
+                self.name
+            ));
+        }
 
-        let comp = &self.bytes[start..end];
         let mut out = if self.compression_level == 0 {
-            comp.to_vec()
+            comp.into_owned()
         } else {
-            decompress_zstd(comp)?
+            decompress_zstd(&comp)?
         };
 
         if out.len() != expected {
@@ -337,21 +576,45 @@ impl<'a> ContainerReader<'a> {
             ));
         }
 
-        if self.array_filter == ARRAY_FILTER_BYTE_SHUFFLE && self.elem_size > 1 {
+        let elem_size = format_elem_size(e.format, "block format")?;
+        if self.array_filter == ARRAY_FILTER_BYTE_SHUFFLE && elem_size > 1 {
             self.scratch.resize(out.len(), 0);
-            byte_unshuffle_into(&out, &mut self.scratch, self.elem_size);
+            byte_unshuffle_into(&out, &mut self.scratch, elem_size);
             std::mem::swap(&mut out, &mut self.scratch);
             self.scratch.clear();
         }
 
-        self.cache[i] = Some(out);
+        self.cache.insert(block_id, out);
         Ok(())
     }
 
     #[inline]
-    fn block_bytes(&mut self, block_id: u32) -> Result<&[u8], String> {
-        self.ensure_block(block_id)?;
-        Ok(self.cache[block_id as usize].as_ref().unwrap().as_slice())
+    pub fn block_format(&self, block_id: u32) -> Result<u8, String> {
+        self.dir
+            .get(block_id as usize)
+            .map(|e| e.format)
+            .ok_or_else(|| format!("block_id out of range: {block_id}"))
+    }
+
+    #[inline]
+    pub fn block_bytes(&mut self, block_id: u32) -> Result<&[u8], String> {
+        let bytes = self.bytes;
+        self.block_bytes_with(block_id, |start, len| {
+            slice_at(bytes, start, len, "container block")
+                .map(Cow::Borrowed)
+                .map_err(|_| "container: block range out of bounds".to_string())
+        })
+    }
+
+    /// Decompressed bytes of `block_id`, asking `fetch(offset, len)` for the
+    /// compressed range only when the block is not cached yet.
+    #[inline]
+    pub fn block_bytes_with<'b, F>(&mut self, block_id: u32, fetch: F) -> Result<&[u8], String>
+    where
+        F: FnOnce(u64, u64) -> Result<Cow<'b, [u8]>, String>,
+    {
+        self.ensure_block(block_id, fetch)?;
+        Ok(self.cache.blocks[block_id as usize].as_deref().unwrap())
     }
 }
 
@@ -370,6 +633,42 @@ fn byte_unshuffle_into(input: &[u8], output: &mut [u8], elem_size: usize) {
 pub enum ArrayData {
     F32(Vec<f32>),
     F64(Vec<f64>),
+    I16(Vec<i16>),
+    I32(Vec<i32>),
+    I64(Vec<i64>),
+}
+
+impl ArrayData {
+    #[inline]
+    pub fn numeric_type(&self) -> NumericType {
+        match self {
+            ArrayData::F32(_) => NumericType::Float32,
+            ArrayData::F64(_) => NumericType::Float64,
+            ArrayData::I16(_) => NumericType::Int16,
+            ArrayData::I32(_) => NumericType::Int32,
+            ArrayData::I64(_) => NumericType::Int64,
+        }
+    }
+
+    #[inline]
+    pub fn into_binary_data(self) -> BinaryData {
+        match self {
+            ArrayData::F32(v) => BinaryData::F32(v),
+            ArrayData::F64(v) => BinaryData::F64(v),
+            ArrayData::I16(v) => BinaryData::I16(v),
+            ArrayData::I32(v) => BinaryData::I32(v),
+            ArrayData::I64(v) => BinaryData::I64(v),
+        }
+    }
+}
+
+/// Non m/z, time or intensity array restored from the auxiliary section.
+#[derive(Clone, Debug)]
+pub struct AuxArray {
+    pub array_index: u32,
+    pub kind_cv_ref: u8,
+    pub kind_accession: u32,
+    pub data: ArrayData,
 }
 
 #[inline]
@@ -392,6 +691,39 @@ fn bytes_to_f64_vec(raw: &[u8]) -> Vec<f64> {
     out
 }
 
+#[inline]
+fn bytes_to_i16_vec(raw: &[u8]) -> Vec<i16> {
+    raw.chunks_exact(2)
+        .map(|c| i16::from_le_bytes(c.try_into().unwrap()))
+        .collect()
+}
+
+#[inline]
+fn bytes_to_i32_vec(raw: &[u8]) -> Vec<i32> {
+    raw.chunks_exact(4)
+        .map(|c| i32::from_le_bytes(c.try_into().unwrap()))
+        .collect()
+}
+
+#[inline]
+fn bytes_to_i64_vec(raw: &[u8]) -> Vec<i64> {
+    raw.chunks_exact(8)
+        .map(|c| i64::from_le_bytes(c.try_into().unwrap()))
+        .collect()
+}
+
+#[inline]
+fn format_elem_size(fmt: u8, field: &'static str) -> Result<usize, String> {
+    match fmt {
+        FMT_F32 | FMT_I32 => Ok(4),
+        FMT_F64 | FMT_I64 => Ok(8),
+        FMT_I16 => Ok(2),
+        _ => Err(format!(
+            "{field}: invalid format {fmt} (expected 1=f32, 2=f64, 3=i16, 4=i32 or 5=i64)"
+        )),
+    }
+}
+
 #[inline]
 pub fn compute_block_starts_for_x(
     index: &[SpectrumIndexEntry],
@@ -447,7 +779,7 @@ pub fn compute_block_starts_for_cx(
 }
 
 #[inline]
-fn compute_block_starts_for_cy(
+pub fn compute_block_starts_for_cy(
     index: &[ChromIndexEntry],
     block_count: u32,
 ) -> Result<Vec<u64>, String> {
@@ -472,6 +804,43 @@ fn decode_item_array(
     global_off_elems: u64,
     len_elems: u32,
 ) -> Result<ArrayData, String> {
+    let (format, off_bytes, len_bytes) =
+        item_byte_range(reader, block_starts, block_id, global_off_elems, len_elems)?;
+    let raw = reader.block_bytes(block_id)?;
+    let data = array_data_from_block(raw, format, off_bytes, len_bytes)?;
+    reader.consume(block_id);
+    Ok(data)
+}
+
+/// Same as `decode_item_array`, reading compressed blocks through `fetch`.
+#[inline]
+pub(crate) fn decode_item_array_with<'b, F>(
+    reader: &mut ContainerReader<'_>,
+    block_starts: &[u64],
+    block_id: u32,
+    global_off_elems: u64,
+    len_elems: u32,
+    fetch: F,
+) -> Result<ArrayData, String>
+where
+    F: FnOnce(u64, u64) -> Result<Cow<'b, [u8]>, String>,
+{
+    let (format, off_bytes, len_bytes) =
+        item_byte_range(reader, block_starts, block_id, global_off_elems, len_elems)?;
+    let raw = reader.block_bytes_with(block_id, fetch)?;
+    let data = array_data_from_block(raw, format, off_bytes, len_bytes)?;
+    reader.consume(block_id);
+    Ok(data)
+}
+
+#[inline]
+fn item_byte_range(
+    reader: &ContainerReader<'_>,
+    block_starts: &[u64],
+    block_id: u32,
+    global_off_elems: u64,
+    len_elems: u32,
+) -> Result<(u8, usize, usize), String> {
     let bi = block_id as usize;
     if bi >= block_starts.len() {
         return Err("block_id out of range for starts".to_string());
@@ -486,10 +855,8 @@ fn decode_item_array(
         .checked_sub(start)
         .ok_or_else(|| "negative local offset".to_string())?;
 
-    let elem_size = reader.elem_size;
-    if elem_size != 4 && elem_size != 8 {
-        return Err("unsupported elem_size".to_string());
-    }
+    let format = reader.block_format(block_id)?;
+    let elem_size = format_elem_size(format, "array format")?;
 
     let local_off_elems =
         usize::try_from(local_off_elems).map_err(|_| "local offset overflow".to_string())?;
@@ -502,7 +869,16 @@ fn decode_item_array(
         .checked_mul(elem_size)
         .ok_or_else(|| "len bytes overflow".to_string())?;
 
-    let raw = reader.block_bytes(block_id)?;
+    Ok((format, off_bytes, len_bytes))
+}
+
+#[inline]
+fn array_data_from_block(
+    raw: &[u8],
+    format: u8,
+    off_bytes: usize,
+    len_bytes: usize,
+) -> Result<ArrayData, String> {
     let end = off_bytes
         .checked_add(len_bytes)
         .ok_or_else(|| "slice end overflow".to_string())?;
@@ -511,10 +887,12 @@ fn decode_item_array(
     }
 
     let slice = &raw[off_bytes..end];
-    Ok(if elem_size == 4 {
-        ArrayData::F32(bytes_to_f32_vec(slice))
-    } else {
-        ArrayData::F64(bytes_to_f64_vec(slice))
+    Ok(match format {
+        FMT_F32 => ArrayData::F32(bytes_to_f32_vec(slice)),
+        FMT_F64 => ArrayData::F64(bytes_to_f64_vec(slice)),
+        FMT_I16 => ArrayData::I16(bytes_to_i16_vec(slice)),
+        FMT_I32 => ArrayData::I32(bytes_to_i32_vec(slice)),
+        _ => ArrayData::I64(bytes_to_i64_vec(slice)),
     })
 }
 
@@ -573,7 +951,7 @@ fn ensure_float_flag(bda: &mut BinaryDataArray, is_f32: bool) {
 }
 
 #[inline]
-fn attach_xy_arrays_to_bdal(
+pub(crate) fn attach_xy_arrays_to_bdal(
     list: &mut BinaryDataArrayList,
     x: &ArrayData,
     y: &ArrayData,
@@ -624,6 +1002,10 @@ fn attach_xy_arrays_to_bdal(
                 }
                 ensure_float_flag(bda, false);
             }
+            other => {
+                bda.numeric_type = Some(other.numeric_type());
+                bda.binary = Some(other.clone().into_binary_data());
+            }
         }
     }
 
@@ -643,6 +1025,10 @@ fn attach_xy_arrays_to_bdal(
                 }
                 ensure_float_flag(bda, false);
             }
+            other => {
+                bda.numeric_type = Some(other.numeric_type());
+                bda.binary = Some(other.clone().into_binary_data());
+            }
         }
     }
 
@@ -664,6 +1050,9 @@ pub struct Metadatum {
     pub tag_id: TagId,
     pub accession: Option<String>,
     pub unit_accession: Option<String>,
+    /// `cvRef` when it is not the accession prefix.
+    pub cv_ref: Option<String>,
+    pub unit_cv_ref: Option<String>,
     pub value: MetadatumValue,
 }
 
@@ -687,14 +1076,9 @@ fn ms_float_param(accession_tail: u32) -> CvParam {
 
 fn parse_metadata_section(
     bytes: &[u8],
-    header: &Header,
     start_off: u64,
     end_off: u64,
-    item_count: u32,
-    meta_count: u32,
-    num_count: u32,
-    str_count: u32,
-    compression_flag_bit: u8,
+    layout: &MetadataLayout,
 ) -> Vec<Metadatum> {
     let c0 = start_off as usize;
     let c1 = end_off as usize;
@@ -705,19 +1089,9 @@ fn parse_metadata_section(
         "invalid metadata offsets: end out of bounds"
     );
 
-    let compressed = (header.reserved_flags & (1u8 << compression_flag_bit)) != 0;
     let slice = &bytes[c0..c1];
 
-    parse_metadata(
-        slice,
-        item_count,
-        meta_count,
-        num_count,
-        str_count,
-        compressed,
-        header.reserved_flags,
-    )
-    .expect("parse_metadata failed")
+    parse_metadata_with(slice, layout).expect("parse_metadata failed")
 }
 
 fn parse_global_metadata_section(bytes: &[u8], header: &Header) -> Result<Vec<Metadatum>, String> {
@@ -812,6 +1186,7 @@ fn parse_source_file_ref_list(
     Some(SourceFileRefList {
         count,
         source_file_refs,
+        ..Default::default()
     })
 }
 
@@ -826,17 +1201,6 @@ fn parse_binaries(
     ),
     String,
 > {
-    #[inline]
-    fn fmt_to_elem_size(fmt: u8, field: &'static str) -> Result<usize, String> {
-        match fmt {
-            1 => Ok(4), // f32
-            2 => Ok(8), // f64
-            _ => Err(format!(
-                "{field}: invalid format {fmt} (expected 1=f32 or 2=f64)"
-            )),
-        }
-    }
-
     let spect_x = slice_at(
         bytes,
         header.off_container_spect_x,
@@ -862,29 +1226,13 @@ fn parse_binaries(
         "container_chrom_y",
     )?;
 
-    let spect_x_elem = fmt_to_elem_size(header.spect_x_format, "spect_x_format")?;
-    let spect_y_elem = fmt_to_elem_size(header.spect_y_format, "spect_y_format")?;
-    let chrom_x_elem = fmt_to_elem_size(header.chrom_x_format, "chrom_x_format")?;
-    let chrom_y_elem = fmt_to_elem_size(header.chrom_y_format, "chrom_y_format")?;
+    format_elem_size(header.spect_x_format, "spect_x_format")?;
+    format_elem_size(header.spect_y_format, "spect_y_format")?;
+    format_elem_size(header.chrom_x_format, "chrom_x_format")?;
+    format_elem_size(header.chrom_y_format, "chrom_y_format")?;
 
-    let spec_count = header.spectrum_count as usize;
-    let spec_need = (spec_count as u64)
-        .checked_mul(INDEX_ENTRY_SIZE as u64)
-        .ok_or_else(|| "spectrum index size overflow".to_string())?;
-    let spec_raw = slice_at(bytes, header.off_spec_index, spec_need, "spectrum index")?;
-
-    let mut spec_pos = 0usize;
-    let mut spec_index: Vec<SpectrumIndexEntry> = Vec::with_capacity(spec_count);
-    for _ in 0..spec_count {
-        spec_index.push(SpectrumIndexEntry {
-            mz_element_off: read_u64_le_at(spec_raw, &mut spec_pos, "mz_element_off")?,
-            inten_element_off: read_u64_le_at(spec_raw, &mut spec_pos, "inten_element_off")?,
-            mz_element_len: read_u32_le_at(spec_raw, &mut spec_pos, "mz_element_len")?,
-            inten_element_len: read_u32_le_at(spec_raw, &mut spec_pos, "inten_element_len")?,
-            mz_block_id: read_u32_le_at(spec_raw, &mut spec_pos, "mz_block_id")?,
-            inten_block_id: read_u32_le_at(spec_raw, &mut spec_pos, "inten_block_id")?,
-        });
-    }
+    let spec_index = parse_spectrum_index(bytes, header)?;
+    let spec_count = spec_index.len();
 
     let chrom_index = parse_chrom_index(bytes, header)?;
     let chrom_count = chrom_index.len();
@@ -897,32 +1245,42 @@ fn parse_binaries(
     let mut r_spec_x = ContainerReader::new(
         spect_x,
         header.block_count_spect_x,
-        spect_x_elem,
+        header.spect_x_format,
         header.compression_level,
         header.array_filter,
     )?;
     let mut r_spec_y = ContainerReader::new(
         spect_y,
         header.block_count_spect_y,
-        spect_y_elem,
+        header.spect_y_format,
         header.compression_level,
         header.array_filter,
     )?;
     let mut r_chrom_x = ContainerReader::new(
         chrom_x,
         header.block_count_chrom_x,
-        chrom_x_elem,
+        header.chrom_x_format,
         header.compression_level,
         header.array_filter,
     )?;
     let mut r_chrom_y = ContainerReader::new(
         chrom_y,
         header.block_count_chrom_y,
-        chrom_y_elem,
+        header.chrom_y_format,
         header.compression_level,
         header.array_filter,
     )?;
 
+    r_spec_x.set_name("spectrum x container");
+    r_spec_y.set_name("spectrum y container");
+    r_chrom_x.set_name("chromatogram x container");
+    r_chrom_y.set_name("chromatogram y container");
+
+    r_spec_x.track_block_uses(spec_index.iter().map(|e| e.mz_block_id));
+    r_spec_y.track_block_uses(spec_index.iter().map(|e| e.inten_block_id));
+    r_chrom_x.track_block_uses(chrom_index.iter().map(|e| e.time_block_id));
+    r_chrom_y.track_block_uses(chrom_index.iter().map(|e| e.inten_block_id));
+
     let mut spectra_pairs: Vec<Vec<(ArrayData, ArrayData)>> = Vec::with_capacity(spec_count);
     for e in &spec_index {
         let x = decode_item_array(
@@ -964,6 +1322,253 @@ fn parse_binaries(
     Ok((spectra_pairs, chrom_pairs))
 }
 
+type AuxArraysByItem = Vec<Vec<AuxArray>>;
+
+#[derive(Clone, Copy)]
+pub(crate) struct AuxContainerEntry {
+    pub(crate) kind_accession: u32,
+    pub(crate) kind_cv_ref: u8,
+    pub(crate) format: u8,
+    pub(crate) block_count: u32,
+    pub(crate) off: u64,
+    pub(crate) size: u64,
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct AuxIndexEntry {
+    pub(crate) element_off: u64,
+    pub(crate) element_len: u32,
+    pub(crate) block_id: u32,
+    pub(crate) item_index: u32,
+    pub(crate) container_id: u32,
+    pub(crate) array_index: u32,
+}
+
+/// Container table, index entries and block starts of the aux section.
+pub(crate) struct AuxLayout {
+    pub(crate) containers: Vec<AuxContainerEntry>,
+    pub(crate) entries: Vec<AuxIndexEntry>,
+    pub(crate) spectrum_entry_count: usize,
+    pub(crate) block_starts: Vec<Vec<u64>>,
+}
+
+/// Bytes needed from the start of the aux section to parse its layout;
+/// `prefix` must hold at least the 16-byte aux header.
+#[inline]
+pub(crate) fn aux_layout_len(prefix: &[u8]) -> Result<usize, String> {
+    let mut pos = 0usize;
+    let container_count = read_u32_le_at(prefix, &mut pos, "aux_container_count")? as usize;
+    let spec_entry_count = read_u32_le_at(prefix, &mut pos, "aux_spectrum_entry_count")? as usize;
+    let chrom_entry_count = read_u32_le_at(prefix, &mut pos, "aux_chrom_entry_count")? as usize;
+
+    container_count
+        .checked_mul(AUX_CONTAINER_ENTRY_SIZE)
+        .and_then(|t| {
+            spec_entry_count
+                .checked_add(chrom_entry_count)?
+                .checked_mul(AUX_INDEX_ENTRY_SIZE)?
+                .checked_add(t)
+        })
+        .and_then(|n| n.checked_add(AUX_HEADER_SIZE))
+        .ok_or_else(|| "aux arrays: layout size overflow".to_string())
+}
+
+#[inline]
+pub(crate) fn parse_aux_layout(section: &[u8]) -> Result<AuxLayout, String> {
+    let mut pos = 0usize;
+    let container_count = read_u32_le_at(section, &mut pos, "aux_container_count")? as usize;
+    let spec_entry_count = read_u32_le_at(section, &mut pos, "aux_spectrum_entry_count")? as usize;
+    let chrom_entry_count = read_u32_le_at(section, &mut pos, "aux_chrom_entry_count")? as usize;
+    let _ = take(section, &mut pos, 4, "aux_reserved")?;
+    let entry_count = spec_entry_count
+        .checked_add(chrom_entry_count)
+        .ok_or_else(|| "aux arrays: entry count overflow".to_string())?;
+
+    let table_len = container_count
+        .checked_mul(AUX_CONTAINER_ENTRY_SIZE)
+        .ok_or_else(|| "aux arrays: container table size overflow".to_string())?;
+    let table = take(section, &mut pos, table_len, "aux container table")?;
+    let mut tpos = 0usize;
+    let mut containers = Vec::with_capacity(container_count);
+    for _ in 0..container_count {
+        let kind_accession = read_u32_le_at(table, &mut tpos, "aux_kind_accession")?;
+        let b = take(table, &mut tpos, 4, "aux_kind")?;
+        let block_count = read_u32_le_at(table, &mut tpos, "aux_block_count")?;
+        let _ = take(table, &mut tpos, 4, "aux_reserved")?;
+        let off = read_u64_le_at(table, &mut tpos, "aux_container_off")?;
+        let size = read_u64_le_at(table, &mut tpos, "aux_container_size")?;
+        if (block_count as u64)
+            .checked_mul(BLOCK_DIR_ENTRY_SIZE as u64)
+            .is_none_or(|dir_len| dir_len > size)
+        {
+            return Err(format!(
+                "aux arrays: container too small for {block_count} blocks ({size} bytes)"
+            ));
+        }
+        containers.push(AuxContainerEntry {
+            kind_accession,
+            kind_cv_ref: b[0],
+            format: b[1],
+            block_count,
+            off,
+            size,
+        });
+    }
+
+    let index_len = entry_count
+        .checked_mul(AUX_INDEX_ENTRY_SIZE)
+        .ok_or_else(|| "aux arrays: index size overflow".to_string())?;
+    let raw_index = take(section, &mut pos, index_len, "aux index")?;
+    let mut ipos = 0usize;
+    let mut entries = Vec::with_capacity(entry_count);
+    for _ in 0..entry_count {
+        let entry = AuxIndexEntry {
+            element_off: read_u64_le_at(raw_index, &mut ipos, "aux_element_off")?,
+            element_len: read_u32_le_at(raw_index, &mut ipos, "aux_element_len")?,
+            block_id: read_u32_le_at(raw_index, &mut ipos, "aux_block_id")?,
+            item_index: read_u32_le_at(raw_index, &mut ipos, "aux_item_index")?,
+            container_id: read_u32_le_at(raw_index, &mut ipos, "aux_container_id")?,
+            array_index: read_u32_le_at(raw_index, &mut ipos, "aux_array_index")?,
+        };
+        let _ = take(raw_index, &mut ipos, 4, "aux_reserved")?;
+        if entry.container_id as usize >= containers.len() {
+            return Err(format!(
+                "aux arrays: container_id out of range: {}",
+                entry.container_id
+            ));
+        }
+        entries.push(entry);
+    }
+
+    let mut block_starts: Vec<Vec<u64>> = containers
+        .iter()
+        .map(|c| vec![u64::MAX; c.block_count as usize])
+        .collect();
+    for e in &entries {
+        let starts = &mut block_starts[e.container_id as usize];
+        let bi = e.block_id as usize;
+        if bi >= starts.len() {
+            return Err("aux block_id out of range".to_string());
+        }
+        starts[bi] = starts[bi].min(e.element_off);
+    }
+
+    Ok(AuxLayout {
+        containers,
+        entries,
+        spectrum_entry_count: spec_entry_count,
+        block_starts,
+    })
+}
+
+#[inline]
+fn parse_aux_arrays(
+    bytes: &[u8],
+    header: &Header,
+) -> Result<(AuxArraysByItem, AuxArraysByItem), String> {
+    let mut spectra_aux: Vec<Vec<AuxArray>> = vec![Vec::new(); header.spectrum_count as usize];
+    let mut chrom_aux: Vec<Vec<AuxArray>> = vec![Vec::new(); header.chrom_count as usize];
+
+    if header.off_aux_arrays == 0 {
+        return Ok((spectra_aux, chrom_aux));
+    }
+
+    let section_len = (bytes.len() as u64)
+        .checked_sub(header.off_aux_arrays)
+        .ok_or_else(|| "aux arrays: offset out of range".to_string())?;
+    let section = slice_at(bytes, header.off_aux_arrays, section_len, "aux arrays")?;
+
+    let AuxLayout {
+        containers,
+        entries,
+        spectrum_entry_count,
+        block_starts,
+    } = parse_aux_layout(section)?;
+
+    let mut readers = Vec::with_capacity(containers.len());
+    for (cid, c) in containers.iter().enumerate() {
+        let raw = slice_at(section, c.off, c.size, "aux container")?;
+        let mut reader = ContainerReader::new(
+            raw,
+            c.block_count,
+            c.format,
+            header.compression_level,
+            header.array_filter,
+        )?;
+        reader.set_name(format!("aux container {cid}"));
+        reader.track_block_uses(
+            entries
+                .iter()
+                .filter(|e| e.container_id as usize == cid)
+                .map(|e| e.block_id),
+        );
+        readers.push(reader);
+    }
+
+    for (i, e) in entries.iter().enumerate() {
+        let cid = e.container_id as usize;
+        let c = containers[cid];
+        let data = decode_item_array(
+            &mut readers[cid],
+            &block_starts[cid],
+            e.block_id,
+            e.element_off,
+            e.element_len,
+        )?;
+
+        let items = if i < spectrum_entry_count {
+            &mut spectra_aux
+        } else {
+            &mut chrom_aux
+        };
+        let slot = items
+            .get_mut(e.item_index as usize)
+            .ok_or_else(|| format!("aux arrays: item_index out of range: {}", e.item_index))?;
+
+        slot.push(AuxArray {
+            array_index: e.array_index,
+            kind_cv_ref: c.kind_cv_ref,
+            kind_accession: c.kind_accession,
+            data,
+        });
+    }
+
+    Ok((spectra_aux, chrom_aux))
+}
+
+#[inline]
+pub(crate) fn attach_aux_arrays_to_bdal(list: &mut BinaryDataArrayList, aux: Vec<AuxArray>) {
+    for a in aux {
+        if let Some(bda) = list.binary_data_arrays.get_mut(a.array_index as usize) {
+            bda.numeric_type = Some(a.data.numeric_type());
+            bda.binary = Some(a.data.into_binary_data());
+        }
+    }
+}
+
+#[inline]
+fn attach_aux_arrays_to_run_lists(
+    run: &mut Run,
+    spectra_aux: Vec<Vec<AuxArray>>,
+    chrom_aux: Vec<Vec<AuxArray>>,
+) {
+    if let Some(sl) = run.spectrum_list.as_mut() {
+        for (sp, aux) in sl.spectra.iter_mut().zip(spectra_aux) {
+            if let Some(bdal) = sp.binary_data_array_list.as_mut() {
+                attach_aux_arrays_to_bdal(bdal, aux);
+            }
+        }
+    }
+
+    if let Some(cl) = run.chromatogram_list.as_mut() {
+        for (ch, aux) in cl.chromatograms.iter_mut().zip(chrom_aux) {
+            if let Some(bdal) = ch.binary_data_array_list.as_mut() {
+                attach_aux_arrays_to_bdal(bdal, aux);
+            }
+        }
+    }
+}
+
 #[inline]
 fn attach_pairs_to_run_lists(
     run: &mut Run,
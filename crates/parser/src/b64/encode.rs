@@ -1,22 +1,176 @@
 use serde::Serialize;
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::Range};
 use zstd::bulk::compress as zstd_compress;
 
 use crate::{
     BinaryData, NumericType,
-    b64::utilities::assign_attributes,
+    b64::utilities::{
+        CvPrefixes, Section, SectionChecksums, assign_attributes,
+        checksums::{BLOCK_FLAG_CRC32C, CHECKSUM_KIND_CRC32C, HDR_OFF_CHECKSUM_KIND},
+        crc32c, extension_rows,
+    },
     decode::MetadatumValue,
     mzml::{
         attr_meta::*,
+        provenance::{Conversion, with_provenance},
         schema::TagId,
         structs::{
-            BinaryDataArray, BinaryDataArrayList, Chromatogram, CvParam, MzML, Precursor,
-            PrecursorList, Product, ProductList, ReferenceableParamGroup,
-            ReferenceableParamGroupRef, ScanList, Spectrum,
+            BinaryDataArray, BinaryDataArrayList, Chromatogram, CvParam, DataProcessingList, MzML,
+            Precursor, PrecursorList, Product, ProductList, ReferenceableParamGroup,
+            ReferenceableParamGroupRef, ScanList, SoftwareList, Spectrum, UserParam,
         },
     },
 };
 
+/// Width policy for float X/Y arrays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FloatPrecision {
+    /// Keep the width each array declares.
+    #[default]
+    Preserve,
+    /// Narrow every float X/Y array to f32.
+    F32,
+}
+
+/// Filter applied to array bytes before compression.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ArrayFilter {
+    None,
+    #[default]
+    ByteShuffle,
+}
+
+/// Codec used for array blocks and, optionally, metadata sections.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Codec {
+    None,
+    #[default]
+    Zstd,
+}
+
+/// How array data is split into independently compressed blocks. Smaller
+/// blocks give cheaper random access at some cost in compression ratio.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockSize {
+    /// Close a block once it would exceed this many uncompressed bytes.
+    Bytes(u32),
+    /// Close a block after this many spectra or chromatograms. `Items(1)`
+    /// gives one block per item.
+    Items(u32),
+}
+
+impl BlockSize {
+    #[inline]
+    pub fn to_header(self) -> (u8, u32) {
+        match self {
+            BlockSize::Bytes(n) => (HDR_BLOCK_SIZE_BYTES, n),
+            BlockSize::Items(n) => (HDR_BLOCK_SIZE_ITEMS, n),
+        }
+    }
+
+    #[inline]
+    pub fn from_header(mode: u8, value: u32) -> Option<Self> {
+        match mode {
+            HDR_BLOCK_SIZE_BYTES => Some(BlockSize::Bytes(value)),
+            HDR_BLOCK_SIZE_ITEMS => Some(BlockSize::Items(value)),
+            _ => None,
+        }
+    }
+}
+
+impl Default for BlockSize {
+    fn default() -> Self {
+        BlockSize::Bytes(TARGET_BLOCK_UNCOMP_BYTES)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodeOptions {
+    /// zstd level, 0..=22. 0 stores blocks uncompressed.
+    pub compression_level: u8,
+    pub codec: Codec,
+    pub float_precision: FloatPrecision,
+    pub array_filter: ArrayFilter,
+    pub block_size: BlockSize,
+    /// Only takes effect when a codec is active and the level is non-zero.
+    pub compress_metadata: bool,
+    /// Append octo's `<software>` and a `<dataProcessing>` step recording the
+    /// compression level and any f32 narrowing.
+    pub provenance: bool,
+}
+
+impl Default for EncodeOptions {
+    fn default() -> Self {
+        Self {
+            compression_level: 12,
+            codec: Codec::Zstd,
+            float_precision: FloatPrecision::Preserve,
+            array_filter: ArrayFilter::ByteShuffle,
+            block_size: BlockSize::default(),
+            compress_metadata: true,
+            provenance: true,
+        }
+    }
+}
+
+impl EncodeOptions {
+    /// The options `encode(mzml, compression_level, f32_compress)` has always used.
+    pub fn new(compression_level: u8, f32_compress: bool) -> Self {
+        let compress = compression_level != 0;
+        Self {
+            compression_level,
+            float_precision: if f32_compress {
+                FloatPrecision::F32
+            } else {
+                FloatPrecision::Preserve
+            },
+            array_filter: if compress {
+                ArrayFilter::ByteShuffle
+            } else {
+                ArrayFilter::None
+            },
+            compress_metadata: compress,
+            ..Self::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EncodeError {
+    InvalidCompressionLevel(u8),
+    InvalidBlockSize(BlockSize),
+    /// A count, length or offset does not fit its fixed-width field.
+    Overflow {
+        field: &'static str,
+        value: usize,
+    },
+    Compression(String),
+}
+
+impl std::fmt::Display for EncodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            EncodeError::InvalidCompressionLevel(level) => {
+                write!(f, "invalid compression level {level} (expected 0..=22)")
+            }
+            EncodeError::InvalidBlockSize(size) => {
+                write!(f, "invalid block size {size:?} (must be non-zero)")
+            }
+            EncodeError::Overflow { field, value } => {
+                write!(f, "{field} does not fit in 32 bits: {value}")
+            }
+            EncodeError::Compression(e) => write!(f, "compression failed: {e}"),
+        }
+    }
+}
+
+impl std::error::Error for EncodeError {}
+
+#[inline]
+fn checked_u32(value: usize, field: &'static str) -> Result<u32, EncodeError> {
+    u32::try_from(value).map_err(|_| EncodeError::Overflow { field, value })
+}
+
 #[derive(Debug)]
 pub struct PackedMeta {
     pub index_offsets: Vec<u32>,          // CI
@@ -60,7 +214,12 @@ const HEADER_SIZE: usize = 192;
 const INDEX_ENTRY_SIZE: usize = 32;
 const BLOCK_DIR_ENTRY_SIZE: usize = 32;
 
-const TARGET_BLOCK_UNCOMP_BYTES: usize = 512 * 1024 * 1024;
+const TARGET_BLOCK_UNCOMP_BYTES: u32 = 512 * 1024 * 1024;
+
+const HDR_OFF_BLOCK_SIZE_MODE: usize = 179;
+const HDR_OFF_BLOCK_SIZE_VALUE: usize = 180;
+pub const HDR_BLOCK_SIZE_BYTES: u8 = 1;
+pub const HDR_BLOCK_SIZE_ITEMS: u8 = 2;
 
 const ACC_MZ_ARRAY: u32 = 1_000_514;
 const ACC_INTENSITY_ARRAY: u32 = 1_000_515;
@@ -80,12 +239,28 @@ const HDR_ARRAY_FILTER_OFF: usize = 178;
 const ARRAY_FILTER_NONE: u8 = 0;
 const ARRAY_FILTER_BYTE_SHUFFLE: u8 = 1;
 
+const HDR_OFF_AUX_ARRAYS: usize = 184;
+
+const AUX_HEADER_SIZE: usize = 16;
+const AUX_CONTAINER_ENTRY_SIZE: usize = 32;
+const AUX_INDEX_ENTRY_SIZE: usize = 32;
+
+const AUX_SCOPE_SPECTRUM: u8 = 0;
+const AUX_SCOPE_CHROMATOGRAM: u8 = 1;
+
+const FMT_F32: u8 = 1;
+const FMT_F64: u8 = 2;
+const FMT_I16: u8 = 3;
+const FMT_I32: u8 = 4;
+const FMT_I64: u8 = 5;
+
 #[inline]
-fn compress_bytes(input: &[u8], compression_level: u8) -> Vec<u8> {
+fn compress_bytes(input: &[u8], compression_level: u8) -> Result<Vec<u8>, EncodeError> {
     if compression_level == 0 {
-        return input.to_vec();
+        return Ok(input.to_vec());
     }
-    zstd_compress(input, compression_level as i32).unwrap_or_else(|_| input.to_vec())
+    zstd_compress(input, compression_level as i32)
+        .map_err(|e| EncodeError::Compression(e.to_string()))
 }
 
 #[inline]
@@ -112,6 +287,9 @@ fn header_codec_and_flags(
 enum ArrayRef<'a> {
     F32(&'a [f32]),
     F64(&'a [f64]),
+    I16(&'a [i16]),
+    I32(&'a [i32]),
+    I64(&'a [i64]),
 }
 
 impl<'a> ArrayRef<'a> {
@@ -120,15 +298,13 @@ impl<'a> ArrayRef<'a> {
         match self {
             ArrayRef::F32(s) => s.len(),
             ArrayRef::F64(s) => s.len(),
+            ArrayRef::I16(s) => s.len(),
+            ArrayRef::I32(s) => s.len(),
+            ArrayRef::I64(s) => s.len(),
         }
     }
 }
 
-#[inline]
-fn elem_size(store_f64: bool) -> usize {
-    if store_f64 { 8 } else { 4 }
-}
-
 #[inline]
 fn write_u32_slice_le(buf: &mut Vec<u8>, xs: &[u32]) {
     if cfg!(target_endian = "little") {
@@ -198,6 +374,7 @@ fn write_array_as_f64(buf: &mut Vec<u8>, arr: ArrayRef<'_>) {
             }
         }
         ArrayRef::F64(xs) => write_f64_slice_le(buf, xs),
+        _ => write_array_native(buf, arr),
     }
 }
 
@@ -210,15 +387,93 @@ fn write_array_as_f32(buf: &mut Vec<u8>, arr: ArrayRef<'_>) {
                 write_f64_as_f32(buf, v);
             }
         }
+        _ => write_array_native(buf, arr),
     }
 }
 
 #[inline]
-fn write_array(buf: &mut Vec<u8>, arr: ArrayRef<'_>, store_f64: bool) {
-    if store_f64 {
-        write_array_as_f64(buf, arr);
-    } else {
-        write_array_as_f32(buf, arr);
+fn write_array_native(buf: &mut Vec<u8>, arr: ArrayRef<'_>) {
+    match arr {
+        ArrayRef::F32(xs) => write_f32_slice_le(buf, xs),
+        ArrayRef::F64(xs) => write_f64_slice_le(buf, xs),
+        ArrayRef::I16(xs) => {
+            for &v in xs {
+                buf.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        ArrayRef::I32(xs) => {
+            for &v in xs {
+                buf.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        ArrayRef::I64(xs) => {
+            for &v in xs {
+                buf.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+    }
+}
+
+#[inline]
+fn write_array(buf: &mut Vec<u8>, arr: ArrayRef<'_>, format: u8) {
+    match format {
+        FMT_F64 => write_array_as_f64(buf, arr),
+        FMT_F32 => write_array_as_f32(buf, arr),
+        _ => write_array_native(buf, arr),
+    }
+}
+
+#[inline]
+fn format_elem_size(format: u8) -> usize {
+    match format {
+        FMT_I16 => 2,
+        FMT_F32 | FMT_I32 => 4,
+        _ => 8,
+    }
+}
+
+#[inline]
+fn binary_format(bin: &BinaryData) -> u8 {
+    match bin {
+        BinaryData::F32(_) => FMT_F32,
+        BinaryData::F64(_) => FMT_F64,
+        BinaryData::I16(_) => FMT_I16,
+        BinaryData::I32(_) => FMT_I32,
+        BinaryData::I64(_) => FMT_I64,
+    }
+}
+
+#[inline]
+fn binary_len(bin: &BinaryData) -> usize {
+    match bin {
+        BinaryData::F32(v) => v.len(),
+        BinaryData::F64(v) => v.len(),
+        BinaryData::I16(v) => v.len(),
+        BinaryData::I32(v) => v.len(),
+        BinaryData::I64(v) => v.len(),
+    }
+}
+
+#[inline]
+fn write_binary_data(buf: &mut Vec<u8>, bin: &BinaryData) {
+    match bin {
+        BinaryData::F32(xs) => write_f32_slice_le(buf, xs),
+        BinaryData::F64(xs) => write_f64_slice_le(buf, xs),
+        BinaryData::I16(xs) => {
+            for &v in xs {
+                buf.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        BinaryData::I32(xs) => {
+            for &v in xs {
+                buf.extend_from_slice(&v.to_le_bytes());
+            }
+        }
+        BinaryData::I64(xs) => {
+            for &v in xs {
+                buf.extend_from_slice(&v.to_le_bytes());
+            }
+        }
     }
 }
 
@@ -235,144 +490,187 @@ fn byte_shuffle_into(input: &[u8], output: &mut [u8], elem_size: usize) {
     }
 }
 
+/// Integer arrays keep their own type; floats follow the declared width unless `f32_compress`.
 #[inline]
-fn merge_declared_width(cur: &mut Option<bool>, next_is_f64: bool, axis: &'static str) {
-    match *cur {
-        None => *cur = Some(next_is_f64),
-        Some(prev) if prev == next_is_f64 => {}
-        Some(prev) => {
-            panic!(
-                "Mixed float widths for {axis}: saw {} then {}. \
-                 Your container format requires a single width per axis. \
-                 Either set f32_compress=true (normalize to f32) or normalize the input mzML.",
-                if prev { "f64" } else { "f32" },
-                if next_is_f64 { "f64" } else { "f32" },
-            );
-        }
-    }
+fn item_format(ba: Option<&BinaryDataArray>, arr: ArrayRef<'_>, f32_compress: bool) -> u8 {
+    match arr {
+        ArrayRef::I16(_) => return FMT_I16,
+        ArrayRef::I32(_) => return FMT_I32,
+        ArrayRef::I64(_) => return FMT_I64,
+        _ => {}
+    }
+    if f32_compress {
+        return FMT_F32;
+    }
+    let store_f64 = ba
+        .and_then(bda_declared_is_f64)
+        .unwrap_or(matches!(arr, ArrayRef::F64(_)));
+    if store_f64 { FMT_F64 } else { FMT_F32 }
 }
 
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Default)]
 struct BlockDirEntry {
     comp_off: u64,
     comp_size: u64,
     uncomp_bytes: u64,
+    format: u8,
 }
 
+/// Block still being filled; its id is taken when it is opened.
+struct OpenBlock {
+    id: u32,
+    format: u8,
+    bytes: Vec<u8>,
+    items: u32,
+}
+
+/// Blocks hold a single element format. One block per format is kept open, so
+/// items of different widths interleave without cutting each other's blocks
+/// short; element offsets count the elements of one format only.
 struct ContainerBuilder {
-    target_uncomp_bytes: usize,
+    block_size: BlockSize,
     compression_level: u8,
-    elem_size: usize,
+    format: u8,
     do_shuffle: bool,
-    current: Vec<u8>,
+    open: Vec<OpenBlock>,
+    /// Directory entries by block id, filled in as blocks are flushed.
     entries: Vec<BlockDirEntry>,
     compressed: Vec<u8>,
     scratch: Vec<u8>,
+    /// Next element offset of each format.
+    element_offs: [u64; FMT_I64 as usize + 1],
+    last_format: Option<u8>,
 }
 
 impl ContainerBuilder {
     #[inline]
-    fn new(
-        target_uncomp_bytes: usize,
-        compression_level: u8,
-        elem_size: usize,
-        do_shuffle: bool,
-    ) -> Self {
+    fn new(block_size: BlockSize, compression_level: u8, format: u8, do_shuffle: bool) -> Self {
         Self {
-            target_uncomp_bytes,
+            block_size,
             compression_level,
-            elem_size,
+            format,
             do_shuffle,
-            current: Vec::new(),
+            open: Vec::new(),
             entries: Vec::new(),
             compressed: Vec::new(),
             scratch: Vec::new(),
+            element_offs: [0; FMT_I64 as usize + 1],
+            last_format: None,
         }
     }
 
     #[inline]
-    fn current_block_id(&self) -> u32 {
-        self.entries.len() as u32
+    fn open_block(&self, format: u8) -> Option<usize> {
+        self.open.iter().position(|b| b.format == format)
     }
 
-    #[inline]
-    fn flush_current(&mut self) {
-        if self.current.is_empty() {
-            return;
-        }
-
-        let uncomp_bytes = self.current.len() as u64;
+    /// Compresses the open block at `at` into its directory entry.
+    fn flush(&mut self, at: usize) -> Result<(), EncodeError> {
+        let block = self.open.swap_remove(at);
+        let uncomp_bytes = block.bytes.len() as u64;
         let comp_off = self.compressed.len() as u64;
 
-        if self.compression_level == 0 {
-            self.entries.push(BlockDirEntry {
-                comp_off,
-                comp_size: uncomp_bytes,
-                uncomp_bytes,
-            });
-            self.compressed.extend_from_slice(&self.current);
-            self.current.clear();
-            return;
-        }
-
-        let to_compress: &[u8] = if self.do_shuffle && self.elem_size > 1 {
-            self.scratch.resize(self.current.len(), 0);
-            byte_shuffle_into(
-                self.current.as_slice(),
-                self.scratch.as_mut_slice(),
-                self.elem_size,
-            );
+        let elem_size = format_elem_size(block.format);
+        let to_compress: &[u8] = if self.do_shuffle && elem_size > 1 {
+            self.scratch.resize(block.bytes.len(), 0);
+            byte_shuffle_into(&block.bytes, self.scratch.as_mut_slice(), elem_size);
             self.scratch.as_slice()
         } else {
-            self.current.as_slice()
+            block.bytes.as_slice()
         };
 
-        let comp = compress_bytes(to_compress, self.compression_level);
-        let comp_size = comp.len() as u64;
-
-        self.entries.push(BlockDirEntry {
+        let comp = compress_bytes(to_compress, self.compression_level)?;
+        self.entries[block.id as usize] = BlockDirEntry {
             comp_off,
-            comp_size,
+            comp_size: comp.len() as u64,
             uncomp_bytes,
-        });
-
+            format: block.format,
+        };
         self.compressed.extend_from_slice(&comp);
-        self.current.clear();
+        Ok(())
     }
 
+    /// Whether `block` must be flushed before an item of `item_bytes` joins it.
     #[inline]
-    fn ensure_room_for_item(&mut self, item_bytes: usize) {
-        if !self.current.is_empty() && self.current.len() + item_bytes > self.target_uncomp_bytes {
-            self.flush_current();
+    fn is_full(&self, block: &OpenBlock, item_bytes: usize) -> bool {
+        match self.block_size {
+            BlockSize::Items(n) => block.items >= n,
+            BlockSize::Bytes(n) => {
+                !block.bytes.is_empty() && block.bytes.len() + item_bytes > n as usize
+            }
         }
     }
 
+    /// Writes an item in the container's own format; returns its block id.
     #[inline]
-    fn write_item<F>(&mut self, item_bytes: usize, write_fn: F) -> u32
+    fn write_item<F>(&mut self, item_bytes: usize, write_fn: F) -> Result<u32, EncodeError>
     where
         F: FnOnce(&mut Vec<u8>),
     {
-        if item_bytes > self.target_uncomp_bytes {
-            if !self.current.is_empty() {
-                self.flush_current();
+        self.write_item_as(self.format, item_bytes, write_fn)
+            .map(|(block_id, _)| block_id)
+    }
+
+    /// Writes an item of `format` into the open block of that format; returns
+    /// its block id and element offset. Empty items join the last block
+    /// written to, whatever its format.
+    fn write_item_as<F>(
+        &mut self,
+        format: u8,
+        item_bytes: usize,
+        write_fn: F,
+    ) -> Result<(u32, u64), EncodeError>
+    where
+        F: FnOnce(&mut Vec<u8>),
+    {
+        let format = match self.last_format {
+            Some(last) if item_bytes == 0 => last,
+            _ => format,
+        };
+
+        let full = self
+            .open_block(format)
+            .filter(|&at| self.is_full(&self.open[at], item_bytes));
+        if let Some(at) = full {
+            self.flush(at)?;
+        }
+        let at = match self.open_block(format) {
+            Some(at) => at,
+            None => {
+                let id = checked_u32(self.entries.len(), "block count")?;
+                self.entries.push(BlockDirEntry::default());
+                self.open.push(OpenBlock {
+                    id,
+                    format,
+                    bytes: Vec::new(),
+                    items: 0,
+                });
+                self.open.len() - 1
             }
-            let block_id = self.current_block_id();
-            self.current.reserve(item_bytes);
-            write_fn(&mut self.current);
-            self.flush_current();
-            return block_id;
+        };
+
+        let block = &mut self.open[at];
+        block.bytes.reserve(item_bytes);
+        write_fn(&mut block.bytes);
+        block.items += 1;
+        let block_id = block.id;
+
+        if matches!(self.block_size, BlockSize::Bytes(n) if item_bytes > n as usize) {
+            self.flush(at)?;
         }
 
-        self.ensure_room_for_item(item_bytes);
-        let block_id = self.current_block_id();
-        self.current.reserve(item_bytes);
-        write_fn(&mut self.current);
-        block_id
+        let element_off = self.element_offs[format as usize];
+        self.element_offs[format as usize] += (item_bytes / format_elem_size(format)) as u64;
+        self.last_format = Some(format);
+        Ok((block_id, element_off))
     }
 
     #[inline]
-    fn finalize(mut self) -> (Vec<u8>, u32) {
-        self.flush_current();
+    fn finalize(mut self) -> Result<(Vec<u8>, u32), EncodeError> {
+        self.open.sort_by_key(|b| std::cmp::Reverse(b.id));
+        while !self.open.is_empty() {
+            self.flush(self.open.len() - 1)?;
+        }
 
         let block_count = self.entries.len() as u32;
         let dir_bytes = self.entries.len() * BLOCK_DIR_ENTRY_SIZE;
@@ -382,11 +680,219 @@ impl ContainerBuilder {
             write_u64_le(&mut container, e.comp_off);
             write_u64_le(&mut container, e.comp_size);
             write_u64_le(&mut container, e.uncomp_bytes);
-            container.extend_from_slice(&[0u8; 8]);
+            container.push(e.format);
+            container.push(BLOCK_FLAG_CRC32C);
+            container.extend_from_slice(&[0u8; 2]);
+            let start = e.comp_off as usize;
+            let block = &self.compressed[start..start + e.comp_size as usize];
+            write_u32_le(&mut container, crc32c(block));
         }
         container.extend_from_slice(&self.compressed);
 
-        (container, block_count)
+        Ok((container, block_count))
+    }
+}
+
+struct AuxEntry {
+    item_index: u32,
+    container_id: u32,
+    array_index: u32,
+    element_off: u64,
+    element_len: u32,
+    block_id: u32,
+}
+
+struct AuxContainer {
+    scope: u8,
+    kind_cv_ref: u8,
+    kind_accession: u32,
+    format: u8,
+    next_element_off: u64,
+    builder: ContainerBuilder,
+}
+
+/// Finished Section F.
+#[derive(Default)]
+struct AuxSection {
+    bytes: Vec<u8>,
+    /// Aux header, container table and aux index.
+    layout_len: usize,
+    /// Block directory of each container, relative to the section start.
+    directories: Vec<Range<usize>>,
+}
+
+struct AuxArraysBuilder {
+    block_size: BlockSize,
+    compression_level: u8,
+    do_shuffle: bool,
+    containers: Vec<AuxContainer>,
+    lookup: HashMap<(u8, u8, u32, u8), u32>,
+    spectrum_entries: Vec<AuxEntry>,
+    chrom_entries: Vec<AuxEntry>,
+}
+
+impl AuxArraysBuilder {
+    #[inline]
+    fn new(block_size: BlockSize, compression_level: u8, do_shuffle: bool) -> Self {
+        Self {
+            block_size,
+            compression_level,
+            do_shuffle,
+            containers: Vec::new(),
+            lookup: HashMap::new(),
+            spectrum_entries: Vec::new(),
+            chrom_entries: Vec::new(),
+        }
+    }
+
+    #[inline]
+    fn is_empty(&self) -> bool {
+        self.spectrum_entries.is_empty() && self.chrom_entries.is_empty()
+    }
+
+    #[inline]
+    fn container_id(&mut self, scope: u8, kind_cv_ref: u8, kind_accession: u32, format: u8) -> u32 {
+        let key = (scope, kind_cv_ref, kind_accession, format);
+        if let Some(&id) = self.lookup.get(&key) {
+            return id;
+        }
+
+        let id = self.containers.len() as u32;
+        self.containers.push(AuxContainer {
+            scope,
+            kind_cv_ref,
+            kind_accession,
+            format,
+            next_element_off: 0,
+            builder: ContainerBuilder::new(
+                self.block_size,
+                self.compression_level,
+                format,
+                self.do_shuffle,
+            ),
+        });
+        self.lookup.insert(key, id);
+        id
+    }
+
+    /// <binaryDataArrayList>
+    fn push_list(
+        &mut self,
+        scope: u8,
+        item_index: u32,
+        list: Option<&BinaryDataArrayList>,
+        xy: (Option<&BinaryDataArray>, Option<&BinaryDataArray>),
+    ) -> Result<(), EncodeError> {
+        let Some(list) = list else {
+            return Ok(());
+        };
+
+        for (array_index, ba) in list.binary_data_arrays.iter().enumerate() {
+            let is_xy = xy.0.is_some_and(|x| std::ptr::eq(x, ba))
+                || xy.1.is_some_and(|y| std::ptr::eq(y, ba));
+            if is_xy {
+                continue;
+            }
+            let Some(bin) = ba.binary.as_ref() else {
+                continue;
+            };
+
+            let (kind_cv_ref, kind_accession) = array_kind(ba);
+            let format = binary_format(bin);
+            let container_id = self.container_id(scope, kind_cv_ref, kind_accession, format);
+
+            let c = &mut self.containers[container_id as usize];
+            let len = binary_len(bin);
+            let element_len = checked_u32(len, "auxiliary array length")?;
+            let item_bytes = len * format_elem_size(format);
+            let block_id = c
+                .builder
+                .write_item(item_bytes, |buf| write_binary_data(buf, bin))?;
+
+            let entry = AuxEntry {
+                item_index,
+                container_id,
+                array_index: checked_u32(array_index, "binary data array index")?,
+                element_off: c.next_element_off,
+                element_len,
+                block_id,
+            };
+            c.next_element_off += len as u64;
+
+            if scope == AUX_SCOPE_SPECTRUM {
+                self.spectrum_entries.push(entry);
+            } else {
+                self.chrom_entries.push(entry);
+            }
+        }
+        Ok(())
+    }
+
+    fn finalize(self) -> Result<AuxSection, EncodeError> {
+        let mut table = Vec::with_capacity(self.containers.len());
+        for c in self.containers {
+            let (bytes, block_count) = c.builder.finalize()?;
+            table.push((
+                c.scope,
+                c.kind_cv_ref,
+                c.kind_accession,
+                c.format,
+                block_count,
+                bytes,
+            ));
+        }
+
+        let entry_count = self.spectrum_entries.len() + self.chrom_entries.len();
+        let mut off = AUX_HEADER_SIZE
+            + table.len() * AUX_CONTAINER_ENTRY_SIZE
+            + entry_count * AUX_INDEX_ENTRY_SIZE;
+        let layout_len = off;
+        let mut directories = Vec::with_capacity(table.len());
+
+        let mut out = Vec::with_capacity(off);
+        write_u32_le(&mut out, table.len() as u32);
+        write_u32_le(&mut out, self.spectrum_entries.len() as u32);
+        write_u32_le(&mut out, self.chrom_entries.len() as u32);
+        write_u32_le(&mut out, 0);
+
+        for (scope, kind_cv_ref, kind_accession, format, block_count, bytes) in &table {
+            off = align_to_8(off);
+            write_u32_le(&mut out, *kind_accession);
+            out.push(*kind_cv_ref);
+            out.push(*format);
+            out.push(*scope);
+            out.push(0);
+            write_u32_le(&mut out, *block_count);
+            write_u32_le(&mut out, 0);
+            write_u64_le(&mut out, off as u64);
+            write_u64_le(&mut out, bytes.len() as u64);
+            directories.push(off..off + *block_count as usize * BLOCK_DIR_ENTRY_SIZE);
+            off += bytes.len();
+        }
+
+        for e in self
+            .spectrum_entries
+            .iter()
+            .chain(self.chrom_entries.iter())
+        {
+            write_u64_le(&mut out, e.element_off);
+            write_u32_le(&mut out, e.element_len);
+            write_u32_le(&mut out, e.block_id);
+            write_u32_le(&mut out, e.item_index);
+            write_u32_le(&mut out, e.container_id);
+            write_u32_le(&mut out, e.array_index);
+            write_u32_le(&mut out, 0);
+        }
+
+        for (_, _, _, _, _, bytes) in &table {
+            append_aligned_8(&mut out, bytes);
+        }
+
+        Ok(AuxSection {
+            bytes: out,
+            layout_len,
+            directories,
+        })
     }
 }
 
@@ -552,6 +1058,37 @@ impl<'a> MetaAcc<'a> {
         for r in refs {
             if let Some(g) = ref_groups.get(r.r#ref.as_str()) {
                 self.extend_tagged_ids(tag, owner_id, parent_owner_id, &g.cv_params);
+                self.extend_user_params_ids(tag, owner_id, parent_owner_id, &g.user_params);
+            }
+        }
+    }
+
+    /// <userParam>
+    #[inline]
+    fn extend_user_params_ids(
+        &mut self,
+        tag: TagId,
+        owner_id: u32,
+        parent_owner_id: u32,
+        ups: &[UserParam],
+    ) {
+        for up in ups {
+            let mut name = user_param_field(ACC_USER_PARAM_NAME, &up.name);
+            name.unit_cv_ref = up.unit_cv_ref.clone();
+            name.unit_accession = up.unit_accession.clone();
+            self.push_tagged_ids(tag, owner_id, parent_owner_id, name);
+
+            if let Some(t) = up.r#type.as_deref() {
+                let cv = user_param_field(ACC_USER_PARAM_TYPE, t);
+                self.push_tagged_ids(tag, owner_id, parent_owner_id, cv);
+            }
+            if let Some(v) = up.value.as_deref() {
+                let cv = user_param_field(ACC_USER_PARAM_VALUE, v);
+                self.push_tagged_ids(tag, owner_id, parent_owner_id, cv);
+            }
+            if let Some(u) = up.unit_name.as_deref() {
+                let cv = user_param_field(ACC_USER_PARAM_UNIT_NAME, u);
+                self.push_tagged_ids(tag, owner_id, parent_owner_id, cv);
             }
         }
     }
@@ -655,7 +1192,12 @@ fn extend_binary_data_array_cv_params_ids(
         }
     }
 
-    let desired_float_tail = if f32_compress && (is_x || is_y) {
+    let is_integer = matches!(
+        ba.binary,
+        Some(BinaryData::I16(_) | BinaryData::I32(_) | BinaryData::I64(_))
+    );
+
+    let desired_float_tail = if f32_compress && (is_x || is_y) && !is_integer {
         Some(ACC_32BIT_FLOAT)
     } else {
         None
@@ -708,6 +1250,19 @@ fn extend_binary_data_array_cv_params_ids(
     }
 }
 
+#[inline]
+fn user_param_field(accession_tail: u32, value: &str) -> CvParam {
+    CvParam {
+        cv_ref: Some(CV_REF_USER_PARAM.to_string()),
+        accession: Some(format!("{CV_REF_USER_PARAM}:{accession_tail}")),
+        name: String::new(),
+        value: Some(value.to_string()),
+        unit_cv_ref: None,
+        unit_name: None,
+        unit_accession: None,
+    }
+}
+
 #[inline]
 fn ms_float_param(accession_tail: u32) -> CvParam {
     let name = if accession_tail == ACC_32BIT_FLOAT {
@@ -751,6 +1306,7 @@ fn flatten_scan_list_ids(
 
         // <scan> cvParams
         meta.extend_tagged_ids(TagId::Scan, scan_id, scan_list_owner_id, &scan.cv_params);
+        meta.extend_user_params_ids(TagId::Scan, scan_id, scan_list_owner_id, &scan.user_params);
 
         // <scanWindowList>/<scanWindow>
         if let Some(wl) = &scan.scan_window_list {
@@ -762,6 +1318,7 @@ fn flatten_scan_list_ids(
 
                 // <scanWindow> cvParams
                 meta.extend_tagged_ids(TagId::ScanWindow, win_id, scan_id, &w.cv_params);
+                meta.extend_user_params_ids(TagId::ScanWindow, win_id, scan_id, &w.user_params);
             }
         }
     }
@@ -837,6 +1394,7 @@ fn flatten_precursor_ids(
         );
 
         meta.extend_tagged_ids(TagId::IsolationWindow, iw_id, precursor_id, &iw.cv_params);
+        meta.extend_user_params_ids(TagId::IsolationWindow, iw_id, precursor_id, &iw.user_params);
     }
 
     if let Some(sil) = &precursor.selected_ion_list {
@@ -856,6 +1414,7 @@ fn flatten_precursor_ids(
             );
 
             meta.extend_tagged_ids(TagId::SelectedIon, ion_id, precursor_id, &ion.cv_params);
+            meta.extend_user_params_ids(TagId::SelectedIon, ion_id, precursor_id, &ion.user_params);
         }
     }
 
@@ -873,6 +1432,7 @@ fn flatten_precursor_ids(
         );
 
         meta.extend_tagged_ids(TagId::Activation, act_id, precursor_id, &act.cv_params);
+        meta.extend_user_params_ids(TagId::Activation, act_id, precursor_id, &act.user_params);
     }
 }
 
@@ -916,6 +1476,7 @@ fn flatten_product_ids(
 
         // Tag: IsolationWindow
         meta.extend_tagged_ids(TagId::IsolationWindow, iw_id, product_id, &iw.cv_params);
+        meta.extend_user_params_ids(TagId::IsolationWindow, iw_id, product_id, &iw.user_params);
     }
 }
 
@@ -940,6 +1501,9 @@ fn flatten_spectrum_metadata_into_owned(
         parent_owner_id,
         attrs,
     );
+    for cv in extension_rows(spectrum) {
+        meta.push_tagged_ids(TagId::Spectrum, spectrum_id, parent_owner_id, cv);
+    }
 
     meta.extend_ref_group_cv_params_ids(
         TagId::Spectrum,
@@ -956,6 +1520,13 @@ fn flatten_spectrum_metadata_into_owned(
         &spectrum.cv_params,
     );
 
+    meta.extend_user_params_ids(
+        TagId::Spectrum,
+        spectrum_id,
+        parent_owner_id,
+        &spectrum.user_params,
+    );
+
     if let Some(sd) = &spectrum.spectrum_description {
         let sd_id = id_gen.alloc();
         meta.extend_ref_group_cv_params_ids(
@@ -973,6 +1544,13 @@ fn flatten_spectrum_metadata_into_owned(
             &sd.cv_params,
         );
 
+        meta.extend_user_params_ids(
+            TagId::SpectrumDescription,
+            sd_id,
+            spectrum_id,
+            &sd.user_params,
+        );
+
         if let Some(sl) = &sd.scan_list {
             let sl_id = id_gen.alloc();
 
@@ -980,6 +1558,7 @@ fn flatten_spectrum_metadata_into_owned(
             meta.push_assigned_attributes_as_cv_params(TagId::ScanList, sl_id, sd_id, attrs);
 
             meta.extend_tagged_ids(TagId::ScanList, sl_id, sd_id, &sl.cv_params);
+            meta.extend_user_params_ids(TagId::ScanList, sl_id, sd_id, &sl.user_params);
 
             flatten_scan_list_ids(meta, sl, sl_id, id_gen, ref_groups);
         }
@@ -1010,6 +1589,7 @@ fn flatten_spectrum_metadata_into_owned(
         meta.push_assigned_attributes_as_cv_params(TagId::ScanList, sl_id, spectrum_id, attrs);
 
         meta.extend_tagged_ids(TagId::ScanList, sl_id, spectrum_id, &sl.cv_params);
+        meta.extend_user_params_ids(TagId::ScanList, sl_id, spectrum_id, &sl.user_params);
 
         flatten_scan_list_ids(meta, sl, sl_id, id_gen, ref_groups);
     }
@@ -1066,6 +1646,8 @@ fn flatten_spectrum_metadata_into_owned(
                 f32_compress,
             );
 
+            meta.extend_user_params_ids(TagId::BinaryDataArray, ba_id, bal_id, &ba.user_params);
+
             let _ = (x_store_f64, y_store_f64);
         }
     }
@@ -1092,6 +1674,9 @@ fn flatten_chromatogram_metadata_into(
         parent_owner_id,
         attrs,
     );
+    for cv in extension_rows(chrom) {
+        meta.push_tagged_ids(TagId::Chromatogram, chrom_id, parent_owner_id, cv);
+    }
 
     meta.extend_ref_group_cv_params_ids(
         TagId::Chromatogram,
@@ -1108,6 +1693,13 @@ fn flatten_chromatogram_metadata_into(
         &chrom.cv_params,
     );
 
+    meta.extend_user_params_ids(
+        TagId::Chromatogram,
+        chrom_id,
+        parent_owner_id,
+        &chrom.user_params,
+    );
+
     if let Some(p) = &chrom.precursor {
         flatten_precursor_ids(meta, p, chrom_id, id_gen, ref_groups);
     }
@@ -1150,6 +1742,8 @@ fn flatten_chromatogram_metadata_into(
                 f32_compress,
             );
 
+            meta.extend_user_params_ids(TagId::BinaryDataArray, ba_id, bal_id, &ba.user_params);
+
             let _ = (x_store_f64, y_store_f64);
         }
     }
@@ -1158,7 +1752,10 @@ fn flatten_chromatogram_metadata_into(
 /// <cvList>
 fn build_global_meta_items(
     mzml: &MzML,
+    software_list: Option<&SoftwareList>,
+    data_processing_list: Option<&DataProcessingList>,
     ref_groups: &HashMap<&str, &ReferenceableParamGroup>,
+    file_desc_id: u32,
     id_gen: &mut NodeIdGen,
 ) -> (Vec<GlobalMetaItem>, GlobalCounts) {
     let mut items: Vec<GlobalMetaItem> = Vec::new();
@@ -1173,7 +1770,9 @@ fn build_global_meta_items(
         let mut meta = MetaAcc::new(&mut out, &mut tags, &mut owners, &mut parents);
 
         // <fileDescription>
-        let file_desc_id = id_gen.alloc();
+        for cv in extension_rows(mzml) {
+            meta.push_tagged_ids(TagId::FileDescription, file_desc_id, 0, cv);
+        }
 
         // <fileContent>
         let file_content_id = id_gen.alloc();
@@ -1193,6 +1792,13 @@ fn build_global_meta_items(
             &fd.file_content.cv_params,
         );
 
+        meta.extend_user_params_ids(
+            TagId::FileContent,
+            file_content_id,
+            file_desc_id,
+            &fd.file_content.user_params,
+        );
+
         let sfl_id = id_gen.alloc();
 
         meta.push_attr_usize_tagged_ids(
@@ -1239,6 +1845,7 @@ fn build_global_meta_items(
             );
 
             meta.extend_tagged_ids(TagId::SourceFile, sf_id, sfl_id, &sf.cv_param);
+            meta.extend_user_params_ids(TagId::SourceFile, sf_id, sfl_id, &sf.user_param);
         }
 
         for c in &fd.contacts {
@@ -1253,6 +1860,7 @@ fn build_global_meta_items(
             );
 
             meta.extend_tagged_ids(TagId::Contact, contact_id, file_desc_id, &c.cv_params);
+            meta.extend_user_params_ids(TagId::Contact, contact_id, file_desc_id, &c.user_params);
         }
 
         items.push(GlobalMetaItem {
@@ -1346,6 +1954,7 @@ fn build_global_meta_items(
         if !run.cv_params.is_empty() {
             meta.extend_tagged_ids(TagId::Run, run_id, 0, &run.cv_params);
         }
+        meta.extend_user_params_ids(TagId::Run, run_id, 0, &run.user_params);
 
         items.push(GlobalMetaItem {
             cvs: out,
@@ -1375,6 +1984,7 @@ fn build_global_meta_items(
             );
 
             meta.extend_tagged_ids(TagId::ReferenceableParamGroup, g_id, 0, &g.cv_params);
+            meta.extend_user_params_ids(TagId::ReferenceableParamGroup, g_id, 0, &g.user_params);
 
             items.push(GlobalMetaItem {
                 cvs: out,
@@ -1413,17 +2023,16 @@ fn build_global_meta_items(
                 s.name.as_str(),
             );
 
-            if let Some(r) = &s.referenceable_param_group_ref {
-                meta.extend_ref_group_cv_params_ids(
-                    TagId::Sample,
-                    sample_id,
-                    0,
-                    std::slice::from_ref(r),
-                    ref_groups,
-                );
-            }
+            meta.extend_ref_group_cv_params_ids(
+                TagId::Sample,
+                sample_id,
+                0,
+                &s.referenceable_param_group_ref,
+                ref_groups,
+            );
 
-            meta.extend_tagged_ids(TagId::Sample, sample_id, 0, &[]);
+            meta.extend_tagged_ids(TagId::Sample, sample_id, 0, &s.cv_param);
+            meta.extend_user_params_ids(TagId::Sample, sample_id, 0, &s.user_param);
 
             items.push(GlobalMetaItem {
                 cvs: out,
@@ -1463,6 +2072,7 @@ fn build_global_meta_items(
             );
 
             meta.extend_tagged_ids(TagId::Instrument, inst_id, 0, &ic.cv_param);
+            meta.extend_user_params_ids(TagId::Instrument, inst_id, 0, &ic.user_param);
 
             if let Some(cl) = &ic.component_list {
                 for s in &cl.source {
@@ -1484,6 +2094,12 @@ fn build_global_meta_items(
                     );
 
                     meta.extend_tagged_ids(TagId::ComponentSource, cid, inst_id, &s.cv_param);
+                    meta.extend_user_params_ids(
+                        TagId::ComponentSource,
+                        cid,
+                        inst_id,
+                        &s.user_param,
+                    );
                 }
 
                 for a in &cl.analyzer {
@@ -1506,6 +2122,12 @@ fn build_global_meta_items(
                     );
 
                     meta.extend_tagged_ids(TagId::ComponentAnalyzer, cid, inst_id, &a.cv_param);
+                    meta.extend_user_params_ids(
+                        TagId::ComponentAnalyzer,
+                        cid,
+                        inst_id,
+                        &a.user_param,
+                    );
                 }
 
                 for d in &cl.detector {
@@ -1528,6 +2150,12 @@ fn build_global_meta_items(
                     );
 
                     meta.extend_tagged_ids(TagId::ComponentDetector, cid, inst_id, &d.cv_param);
+                    meta.extend_user_params_ids(
+                        TagId::ComponentDetector,
+                        cid,
+                        inst_id,
+                        &d.user_param,
+                    );
                 }
             }
 
@@ -1542,7 +2170,7 @@ fn build_global_meta_items(
     let n_instrument_configs = (items.len() - instr_start) as u32;
 
     let sw_start = items.len();
-    if let Some(sw) = &mzml.software_list {
+    if let Some(sw) = software_list {
         for s in &sw.software {
             let mut out = Vec::new();
             let mut tags = Vec::new();
@@ -1581,7 +2209,16 @@ fn build_global_meta_items(
                 );
             }
 
+            meta.extend_ref_group_cv_params_ids(
+                TagId::Software,
+                sw_id,
+                0,
+                &s.referenceable_param_group_ref,
+                ref_groups,
+            );
+
             meta.extend_tagged_ids(TagId::Software, sw_id, 0, &s.cv_param);
+            meta.extend_user_params_ids(TagId::Software, sw_id, 0, &s.user_param);
 
             items.push(GlobalMetaItem {
                 cvs: out,
@@ -1594,7 +2231,7 @@ fn build_global_meta_items(
     let n_software = (items.len() - sw_start) as u32;
 
     let dp_start = items.len();
-    if let Some(dpl) = &mzml.data_processing_list {
+    if let Some(dpl) = data_processing_list {
         for dp in &dpl.data_processing {
             let mut out = Vec::new();
             let mut tags = Vec::new();
@@ -1611,10 +2248,36 @@ fn build_global_meta_items(
                 ACC_ATTR_ID,
                 dp.id.as_str(),
             );
+            if let Some(sw) = dp.software_ref.as_deref() {
+                meta.push_attr_string_tagged_ids(
+                    TagId::DataProcessing,
+                    dp_id,
+                    0,
+                    ACC_ATTR_SOFTWARE_REF,
+                    sw,
+                );
+            }
 
             for m in &dp.processing_method {
                 let pm_id = id_gen.alloc();
 
+                meta.push_attr_usize_tagged_ids(
+                    TagId::ProcessingMethod,
+                    pm_id,
+                    dp_id,
+                    ACC_ATTR_ORDER,
+                    m.order,
+                );
+                if let Some(sw) = m.software_ref.as_deref() {
+                    meta.push_attr_string_tagged_ids(
+                        TagId::ProcessingMethod,
+                        pm_id,
+                        dp_id,
+                        ACC_ATTR_SOFTWARE_REF,
+                        sw,
+                    );
+                }
+
                 meta.extend_ref_group_cv_params_ids(
                     TagId::ProcessingMethod,
                     pm_id,
@@ -1624,6 +2287,7 @@ fn build_global_meta_items(
                 );
 
                 meta.extend_tagged_ids(TagId::ProcessingMethod, pm_id, dp_id, &m.cv_param);
+                meta.extend_user_params_ids(TagId::ProcessingMethod, pm_id, dp_id, &m.user_param);
             }
 
             items.push(GlobalMetaItem {
@@ -1693,6 +2357,7 @@ fn build_global_meta_items(
             );
 
             meta.extend_tagged_ids(TagId::ScanSettings, ss_id, 0, &ss.cv_params);
+            meta.extend_user_params_ids(TagId::ScanSettings, ss_id, 0, &ss.user_params);
 
             if let Some(tl) = &ss.target_list {
                 for t in &tl.targets {
@@ -1707,6 +2372,7 @@ fn build_global_meta_items(
                     );
 
                     meta.extend_tagged_ids(TagId::Target, tgt_id, ss_id, &t.cv_params);
+                    meta.extend_user_params_ids(TagId::Target, tgt_id, ss_id, &t.user_params);
                 }
             }
 
@@ -1824,6 +2490,7 @@ fn pack_cv_param(
     owner_id: u32,
     parent_owner_id: u32,
     cv: &CvParam,
+    cv_prefixes: &mut CvPrefixes,
     tag_ids: &mut Vec<u8>,
     owner_ids: &mut Vec<u32>,
     parent_indices: &mut Vec<u32>,
@@ -1845,20 +2512,21 @@ fn pack_cv_param(
     parent_indices.push(parent_owner_id);
 
     let cv_ref = cv_ref_from_accession(cv.accession.as_deref()).or(cv.cv_ref.as_deref());
-    ref_codes.push(cv_ref_code_from_str(cv_ref));
-
-    accession_numbers.push(parse_accession_tail(cv.accession.as_deref()));
+    let (code, tail) = cv_prefixes.code_of(cv.cv_ref.as_deref(), cv.accession.as_deref());
+    ref_codes.push(code);
+    accession_numbers.push(tail);
 
-    let unit_ref =
-        cv_ref_from_accession(cv.unit_accession.as_deref()).or(cv.unit_cv_ref.as_deref());
-    unit_ref_codes.push(cv_ref_code_from_str(unit_ref));
+    let (unit_code, unit_tail) =
+        cv_prefixes.code_of(cv.unit_cv_ref.as_deref(), cv.unit_accession.as_deref());
+    unit_ref_codes.push(unit_code);
+    unit_accession_numbers.push(unit_tail);
 
-    unit_accession_numbers.push(parse_accession_tail(cv.unit_accession.as_deref()));
+    let keep_text = cv_ref == Some(CV_REF_USER_PARAM);
 
     let (kind, idx) = match cv.value.as_deref() {
         None | Some("") => (2u8, 0u32),
         Some(val) => {
-            if let Ok(num) = val.parse::<f64>() {
+            if let (false, Some(num)) = (keep_text, canonical_number(val)) {
                 let i = *numeric_index;
                 numeric_values.push(num);
                 *numeric_index += 1;
@@ -1883,13 +2551,21 @@ fn pack_cv_param(
     value_indices.push(idx);
 }
 
+/// `val` as a number if decoding it back gives the same text; "1.0", "0005"
+/// or "1e3" stay strings.
+#[inline]
+fn canonical_number(val: &str) -> Option<f64> {
+    let num = val.parse::<f64>().ok()?;
+    (num.to_string() == val).then_some(num)
+}
+
 #[inline]
 fn cv_ref_from_accession<'a>(acc: Option<&'a str>) -> Option<&'a str> {
     acc.and_then(|s| s.split_once(':').map(|(p, _)| p))
 }
 
 /// <cvParam>
-fn pack_meta_streaming<T, F>(items: &[T], mut fill: F) -> PackedMeta
+fn pack_meta_streaming<T, F>(items: &[T], cv_prefixes: &mut CvPrefixes, mut fill: F) -> PackedMeta
 where
     F: FnMut(&mut MetaAcc<'_>, &T),
 {
@@ -1950,6 +2626,7 @@ where
                 scratch_owners[i],
                 scratch_parents[i],
                 &scratch[i],
+                cv_prefixes,
                 &mut tag_ids,
                 &mut owner_ids,
                 &mut parent_indices,
@@ -1990,7 +2667,7 @@ where
     }
 }
 
-fn pack_meta_slices<T, F>(items: &[T], meta_of: F) -> PackedMeta
+fn pack_meta_slices<T, F>(items: &[T], cv_prefixes: &mut CvPrefixes, meta_of: F) -> PackedMeta
 where
     F: Fn(&T) -> (&[CvParam], &[u8], &[u32], &[u32]),
 {
@@ -2037,6 +2714,7 @@ where
                 os[i],
                 ps[i],
                 &xs[i],
+                cv_prefixes,
                 &mut tag_ids,
                 &mut owner_ids,
                 &mut parent_indices,
@@ -2155,25 +2833,13 @@ fn append_aligned_8(output: &mut Vec<u8>, bytes: &[u8]) -> u64 {
 /// <binaryDataArray>
 #[inline]
 fn array_ref<'a>(ba: &'a BinaryDataArray) -> Option<ArrayRef<'a>> {
-    let bin = ba.binary.as_ref()?;
-
-    match bda_declared_is_f64(ba) {
-        Some(false) => match bin {
-            BinaryData::F32(v) => Some(ArrayRef::F32(v.as_slice())),
-            BinaryData::F64(v) => Some(ArrayRef::F64(v.as_slice())),
-            _ => None,
-        },
-        Some(true) => match bin {
-            BinaryData::F64(v) => Some(ArrayRef::F64(v.as_slice())),
-            BinaryData::F32(v) => Some(ArrayRef::F32(v.as_slice())),
-            _ => None,
-        },
-        None => match bin {
-            BinaryData::F64(v) => Some(ArrayRef::F64(v.as_slice())),
-            BinaryData::F32(v) => Some(ArrayRef::F32(v.as_slice())),
-            _ => None,
-        },
-    }
+    Some(match ba.binary.as_ref()? {
+        BinaryData::F32(v) => ArrayRef::F32(v.as_slice()),
+        BinaryData::F64(v) => ArrayRef::F64(v.as_slice()),
+        BinaryData::I16(v) => ArrayRef::I16(v.as_slice()),
+        BinaryData::I32(v) => ArrayRef::I32(v.as_slice()),
+        BinaryData::I64(v) => ArrayRef::I64(v.as_slice()),
+    })
 }
 
 #[inline]
@@ -2277,6 +2943,35 @@ fn bda_declared_is_f64(ba: &BinaryDataArray) -> Option<bool> {
     }
 }
 
+/// <binaryDataArray>
+#[inline]
+fn array_kind(ba: &BinaryDataArray) -> (u8, u32) {
+    for cv in &ba.cv_params {
+        let cv_ref = cv_ref_from_accession(cv.accession.as_deref()).or(cv.cv_ref.as_deref());
+        let code = cv_ref_code_from_str(cv_ref);
+        let tail = parse_accession_tail(cv.accession.as_deref());
+
+        if code == CV_CODE_MS && is_array_encoding_accession(tail) {
+            continue;
+        }
+        return (code, tail);
+    }
+    (CV_CODE_UNKNOWN, 0)
+}
+
+#[inline]
+fn is_array_encoding_accession(tail: u32) -> bool {
+    matches!(
+        tail,
+        1_000_518..=1_000_523
+            | 1_000_574
+            | 1_000_576
+            | 1_002_312..=1_002_314
+            | 1_002_746..=1_002_748
+            | 1_003_089..=1_003_090
+    )
+}
+
 #[inline]
 fn find_xy_ba<'a>(
     list: Option<&'a BinaryDataArrayList>,
@@ -2318,7 +3013,23 @@ fn find_xy_ba<'a>(
 
 /// <mzML>
 pub fn encode(mzml: &MzML, compression_level: u8, f32_compress: bool) -> Vec<u8> {
-    assert!(compression_level <= 22);
+    encode_with(mzml, &EncodeOptions::new(compression_level, f32_compress))
+        .unwrap_or_else(|e| panic!("encode failed: {e}"))
+}
+
+/// <mzML>
+pub fn encode_with(mzml: &MzML, options: &EncodeOptions) -> Result<Vec<u8>, EncodeError> {
+    if options.compression_level > 22 {
+        return Err(EncodeError::InvalidCompressionLevel(
+            options.compression_level,
+        ));
+    }
+    if matches!(
+        options.block_size,
+        BlockSize::Bytes(0) | BlockSize::Items(0)
+    ) {
+        return Err(EncodeError::InvalidBlockSize(options.block_size));
+    }
 
     #[inline]
     fn fix_attr_values(out: &mut Vec<CvParam>) {
@@ -2332,8 +3043,15 @@ pub fn encode(mzml: &MzML, compression_level: u8, f32_compress: bool) -> Vec<u8>
         }
     }
 
-    let compress_meta = compression_level != 0;
-    let do_shuffle = compress_meta;
+    let (codec_id, compression_level) = match options.codec {
+        Codec::None => (0, 0),
+        Codec::Zstd => (HDR_CODEC_ZSTD, options.compression_level),
+    };
+    let f32_compress = options.float_precision == FloatPrecision::F32;
+    let block_size = options.block_size;
+
+    let compress_meta = options.compress_metadata && compression_level != 0;
+    let do_shuffle = options.array_filter == ArrayFilter::ByteShuffle;
 
     let array_filter_id = if do_shuffle {
         ARRAY_FILTER_BYTE_SHUFFLE
@@ -2355,13 +3073,12 @@ pub fn encode(mzml: &MzML, compression_level: u8, f32_compress: bool) -> Vec<u8>
         .map(|cl| cl.chromatograms.as_slice())
         .unwrap_or(&[]);
 
-    let spectrum_count = spectra.len() as u32;
-    let chrom_count = chromatograms.len() as u32;
+    let spectrum_count = checked_u32(spectra.len(), "spectrum count")?;
+    let chrom_count = checked_u32(chromatograms.len(), "chromatogram count")?;
 
-    let mut spectrum_x_decl_f64: Option<bool> = None;
-    let mut spectrum_y_decl_f64: Option<bool> = None;
     let mut spectrum_xy_cache: Vec<(ArrayRef<'_>, ArrayRef<'_>)> =
         Vec::with_capacity(spectra.len());
+    let mut spectrum_xy_formats: Vec<(u8, u8)> = Vec::with_capacity(spectra.len());
 
     for s in spectra {
         let (xba, yba) = find_xy_ba(
@@ -2373,23 +3090,16 @@ pub fn encode(mzml: &MzML, compression_level: u8, f32_compress: bool) -> Vec<u8>
         let x_arr = xba.and_then(array_ref).unwrap_or(ArrayRef::F32(&[]));
         let y_arr = yba.and_then(array_ref).unwrap_or(ArrayRef::F32(&[]));
 
-        if let Some(xba) = xba {
-            let x_decl_f64 = bda_declared_is_f64(xba).unwrap_or(matches!(x_arr, ArrayRef::F64(_)));
-            merge_declared_width(&mut spectrum_x_decl_f64, x_decl_f64, "spectrum x");
-        }
-
-        if let Some(yba) = yba {
-            let y_decl_f64 = bda_declared_is_f64(yba).unwrap_or(matches!(y_arr, ArrayRef::F64(_)));
-            merge_declared_width(&mut spectrum_y_decl_f64, y_decl_f64, "spectrum y");
-        }
-
+        spectrum_xy_formats.push((
+            item_format(xba, x_arr, f32_compress),
+            item_format(yba, y_arr, f32_compress),
+        ));
         spectrum_xy_cache.push((x_arr, y_arr));
     }
 
-    let mut chrom_x_decl_f64: Option<bool> = None;
-    let mut chrom_y_decl_f64: Option<bool> = None;
     let mut chrom_xy_cache: Vec<(ArrayRef<'_>, ArrayRef<'_>)> =
         Vec::with_capacity(chromatograms.len());
+    let mut chrom_xy_formats: Vec<(u8, u8)> = Vec::with_capacity(chromatograms.len());
 
     for c in chromatograms {
         let (xba, yba) = find_xy_ba(
@@ -2401,44 +3111,22 @@ pub fn encode(mzml: &MzML, compression_level: u8, f32_compress: bool) -> Vec<u8>
         let x_arr = xba.and_then(array_ref).unwrap_or(ArrayRef::F32(&[]));
         let y_arr = yba.and_then(array_ref).unwrap_or(ArrayRef::F32(&[]));
 
-        if let Some(xba) = xba {
-            let x_decl_f64 = bda_declared_is_f64(xba).unwrap_or(matches!(x_arr, ArrayRef::F64(_)));
-            merge_declared_width(&mut chrom_x_decl_f64, x_decl_f64, "chromatogram x");
-        }
-
-        if let Some(yba) = yba {
-            let y_decl_f64 = bda_declared_is_f64(yba).unwrap_or(matches!(y_arr, ArrayRef::F64(_)));
-            merge_declared_width(&mut chrom_y_decl_f64, y_decl_f64, "chromatogram y");
-        }
-
+        chrom_xy_formats.push((
+            item_format(xba, x_arr, f32_compress),
+            item_format(yba, y_arr, f32_compress),
+        ));
         chrom_xy_cache.push((x_arr, y_arr));
     }
 
-    let spect_x_store_f64 = if f32_compress {
-        false
-    } else {
-        spectrum_x_decl_f64.unwrap_or(false)
-    };
-    let spect_y_store_f64 = if f32_compress {
-        false
-    } else {
-        spectrum_y_decl_f64.unwrap_or(false)
-    };
-    let chrom_x_store_f64 = if f32_compress {
-        false
-    } else {
-        chrom_x_decl_f64.unwrap_or(false)
-    };
-    let chrom_y_store_f64 = if f32_compress {
-        false
-    } else {
-        chrom_y_decl_f64.unwrap_or(false)
-    };
-
-    let spec_x_elem_size = elem_size(spect_x_store_f64);
-    let spec_y_elem_size = elem_size(spect_y_store_f64);
-    let chrom_x_elem_size = elem_size(chrom_x_store_f64);
-    let chrom_y_elem_size = elem_size(chrom_y_store_f64);
+    // Header formats are the per-axis defaults; each block records its own width.
+    let spect_x_format = spectrum_xy_formats.first().map_or(FMT_F32, |f| f.0);
+    let spect_y_format = spectrum_xy_formats.first().map_or(FMT_F32, |f| f.1);
+    let chrom_x_format = chrom_xy_formats.first().map_or(FMT_F32, |f| f.0);
+    let chrom_y_format = chrom_xy_formats.first().map_or(FMT_F32, |f| f.1);
+    let spect_x_store_f64 = spect_x_format == FMT_F64;
+    let spect_y_store_f64 = spect_y_format == FMT_F64;
+    let chrom_x_store_f64 = chrom_x_format == FMT_F64;
+    let chrom_y_store_f64 = chrom_y_format == FMT_F64;
 
     let ref_groups = build_ref_group_map(mzml);
 
@@ -2455,14 +3143,46 @@ pub fn encode(mzml: &MzML, compression_level: u8, f32_compress: bool) -> Vec<u8>
         0
     };
 
-    let (mut global_items, global_counts) = build_global_meta_items(mzml, &ref_groups, &mut id_gen);
+    let provenance = options.provenance.then(|| {
+        let narrowed = |(x, y): &(ArrayRef<'_>, ArrayRef<'_>)| {
+            matches!(x, ArrayRef::F64(_)) || matches!(y, ArrayRef::F64(_))
+        };
+        with_provenance(
+            mzml.software_list.as_ref(),
+            mzml.data_processing_list.as_ref(),
+            Conversion::ToB000 {
+                compression_level,
+                precision_loss: f32_compress
+                    && (spectrum_xy_cache.iter().any(narrowed)
+                        || chrom_xy_cache.iter().any(narrowed)),
+            },
+        )
+    });
+    let (software_list, data_processing_list) = match &provenance {
+        Some((sw, dpl)) => (Some(sw), Some(dpl)),
+        None => (
+            mzml.software_list.as_ref(),
+            mzml.data_processing_list.as_ref(),
+        ),
+    };
+
+    let file_desc_id = id_gen.alloc();
+    let (mut global_items, global_counts) = build_global_meta_items(
+        mzml,
+        software_list,
+        data_processing_list,
+        &ref_groups,
+        file_desc_id,
+        &mut id_gen,
+    );
     for item in &mut global_items {
         fix_attr_values(&mut item.cvs);
     }
 
+    let mut cv_prefixes = CvPrefixes::default();
     let mut spec_i: usize = 0;
 
-    let spectrum_meta = pack_meta_streaming(spectra, |meta, s| {
+    let spectrum_meta = pack_meta_streaming(spectra, &mut cv_prefixes, |meta, s| {
         let idx = spec_i;
         spec_i += 1;
 
@@ -2495,7 +3215,7 @@ pub fn encode(mzml: &MzML, compression_level: u8, f32_compress: bool) -> Vec<u8>
 
     let mut chrom_i: usize = 0;
 
-    let chromatogram_meta = pack_meta_streaming(chromatograms, |meta, c| {
+    let chromatogram_meta = pack_meta_streaming(chromatograms, &mut cv_prefixes, |meta, c| {
         let idx = chrom_i;
         chrom_i += 1;
 
@@ -2531,7 +3251,25 @@ pub fn encode(mzml: &MzML, compression_level: u8, f32_compress: bool) -> Vec<u8>
         fix_attr_values(meta.out);
     });
 
-    let global_meta = pack_meta_slices(&global_items, |m| {
+    // The table goes with the global rows, so it must be complete before they
+    // are packed.
+    for cv in global_items.iter().flat_map(|item| &item.cvs) {
+        cv_prefixes.code_of(cv.cv_ref.as_deref(), cv.accession.as_deref());
+        cv_prefixes.code_of(cv.unit_cv_ref.as_deref(), cv.unit_accession.as_deref());
+    }
+    if let Some(item) = global_items.first_mut() {
+        let mut meta = MetaAcc::new(
+            &mut item.cvs,
+            &mut item.tags,
+            &mut item.owners,
+            &mut item.parents,
+        );
+        for cv in cv_prefixes.rows() {
+            meta.push_tagged_ids(TagId::FileDescription, file_desc_id, 0, cv);
+        }
+    }
+
+    let global_meta = pack_meta_slices(&global_items, &mut cv_prefixes, |m| {
         (
             m.cvs.as_slice(),
             m.tags.as_slice(),
@@ -2540,15 +3278,31 @@ pub fn encode(mzml: &MzML, compression_level: u8, f32_compress: bool) -> Vec<u8>
         )
     });
 
-    let spec_meta_count = spectrum_meta.ref_codes.len() as u32;
+    checked_u32(
+        spectrum_meta.string_bytes.len(),
+        "spectrum metadata string pool",
+    )?;
+    checked_u32(
+        chromatogram_meta.string_bytes.len(),
+        "chromatogram metadata string pool",
+    )?;
+    checked_u32(
+        global_meta.string_bytes.len(),
+        "global metadata string pool",
+    )?;
+
+    let spec_meta_count = checked_u32(spectrum_meta.ref_codes.len(), "spectrum metadata count")?;
     let spec_num_count = spectrum_meta.numeric_values.len() as u32;
     let spec_str_count = spectrum_meta.string_offsets.len() as u32;
 
-    let chrom_meta_count = chromatogram_meta.ref_codes.len() as u32;
+    let chrom_meta_count = checked_u32(
+        chromatogram_meta.ref_codes.len(),
+        "chromatogram metadata count",
+    )?;
     let chrom_num_count = chromatogram_meta.numeric_values.len() as u32;
     let chrom_str_count = chromatogram_meta.string_offsets.len() as u32;
 
-    let global_meta_count = global_meta.ref_codes.len() as u32;
+    let global_meta_count = checked_u32(global_meta.ref_codes.len(), "global metadata count")?;
     let global_num_count = global_meta.numeric_values.len() as u32;
     let global_str_count = global_meta.string_offsets.len() as u32;
 
@@ -2557,95 +3311,95 @@ pub fn encode(mzml: &MzML, compression_level: u8, f32_compress: bool) -> Vec<u8>
     let mut global_meta_bytes = write_global_meta_bytes(&global_counts, &global_meta);
 
     if compress_meta {
-        spectrum_meta_bytes = compress_bytes(&spectrum_meta_bytes, compression_level);
-        chromatogram_meta_bytes = compress_bytes(&chromatogram_meta_bytes, compression_level);
-        global_meta_bytes = compress_bytes(&global_meta_bytes, compression_level);
+        spectrum_meta_bytes = compress_bytes(&spectrum_meta_bytes, compression_level)?;
+        chromatogram_meta_bytes = compress_bytes(&chromatogram_meta_bytes, compression_level)?;
+        global_meta_bytes = compress_bytes(&global_meta_bytes, compression_level)?;
     }
 
-    let mut spec_x_builder = ContainerBuilder::new(
-        TARGET_BLOCK_UNCOMP_BYTES,
-        compression_level,
-        spec_x_elem_size,
-        do_shuffle,
-    );
-    let mut spec_y_builder = ContainerBuilder::new(
-        TARGET_BLOCK_UNCOMP_BYTES,
-        compression_level,
-        spec_y_elem_size,
-        do_shuffle,
-    );
-    let mut chrom_x_builder = ContainerBuilder::new(
-        TARGET_BLOCK_UNCOMP_BYTES,
-        compression_level,
-        chrom_x_elem_size,
-        do_shuffle,
-    );
-    let mut chrom_y_builder = ContainerBuilder::new(
-        TARGET_BLOCK_UNCOMP_BYTES,
-        compression_level,
-        chrom_y_elem_size,
-        do_shuffle,
-    );
+    let mut spec_x_builder =
+        ContainerBuilder::new(block_size, compression_level, spect_x_format, do_shuffle);
+    let mut spec_y_builder =
+        ContainerBuilder::new(block_size, compression_level, spect_y_format, do_shuffle);
+    let mut chrom_x_builder =
+        ContainerBuilder::new(block_size, compression_level, chrom_x_format, do_shuffle);
+    let mut chrom_y_builder =
+        ContainerBuilder::new(block_size, compression_level, chrom_y_format, do_shuffle);
 
     let mut spec_index_bytes = Vec::with_capacity(spectra.len() * INDEX_ENTRY_SIZE);
     let mut chrom_index_bytes = Vec::with_capacity(chromatograms.len() * INDEX_ENTRY_SIZE);
 
-    let mut spec_x_off_elems: u64 = 0;
-    let mut spec_y_off_elems: u64 = 0;
-
-    for &(x, y) in &spectrum_xy_cache {
-        let x_len = x.len() as u32;
-        let y_len = y.len() as u32;
+    for (&(x, y), &(x_format, y_format)) in spectrum_xy_cache.iter().zip(&spectrum_xy_formats) {
+        let x_len = checked_u32(x.len(), "array length")?;
+        let y_len = checked_u32(y.len(), "array length")?;
 
-        let x_item_bytes = x.len() * spec_x_elem_size;
-        let y_item_bytes = y.len() * spec_y_elem_size;
+        let x_item_bytes = x.len() * format_elem_size(x_format);
+        let y_item_bytes = y.len() * format_elem_size(y_format);
 
-        let x_block_id =
-            spec_x_builder.write_item(x_item_bytes, |buf| write_array(buf, x, spect_x_store_f64));
-        let y_block_id =
-            spec_y_builder.write_item(y_item_bytes, |buf| write_array(buf, y, spect_y_store_f64));
+        let (x_block_id, x_off_elems) =
+            spec_x_builder
+                .write_item_as(x_format, x_item_bytes, |buf| write_array(buf, x, x_format))?;
+        let (y_block_id, y_off_elems) =
+            spec_y_builder
+                .write_item_as(y_format, y_item_bytes, |buf| write_array(buf, y, y_format))?;
 
-        write_u64_le(&mut spec_index_bytes, spec_x_off_elems);
-        write_u64_le(&mut spec_index_bytes, spec_y_off_elems);
+        write_u64_le(&mut spec_index_bytes, x_off_elems);
+        write_u64_le(&mut spec_index_bytes, y_off_elems);
         write_u32_le(&mut spec_index_bytes, x_len);
         write_u32_le(&mut spec_index_bytes, y_len);
         write_u32_le(&mut spec_index_bytes, x_block_id);
         write_u32_le(&mut spec_index_bytes, y_block_id);
-
-        spec_x_off_elems += x_len as u64;
-        spec_y_off_elems += y_len as u64;
     }
 
-    let mut chrom_x_off_elems: u64 = 0;
-    let mut chrom_y_off_elems: u64 = 0;
-
-    for &(x, y) in &chrom_xy_cache {
-        let x_len = x.len() as u32;
-        let y_len = y.len() as u32;
+    for (&(x, y), &(x_format, y_format)) in chrom_xy_cache.iter().zip(&chrom_xy_formats) {
+        let x_len = checked_u32(x.len(), "array length")?;
+        let y_len = checked_u32(y.len(), "array length")?;
 
-        let x_item_bytes = x.len() * chrom_x_elem_size;
-        let y_item_bytes = y.len() * chrom_y_elem_size;
+        let x_item_bytes = x.len() * format_elem_size(x_format);
+        let y_item_bytes = y.len() * format_elem_size(y_format);
 
-        let x_block_id =
-            chrom_x_builder.write_item(x_item_bytes, |buf| write_array(buf, x, chrom_x_store_f64));
-        let y_block_id =
-            chrom_y_builder.write_item(y_item_bytes, |buf| write_array(buf, y, chrom_y_store_f64));
+        let (x_block_id, x_off_elems) =
+            chrom_x_builder
+                .write_item_as(x_format, x_item_bytes, |buf| write_array(buf, x, x_format))?;
+        let (y_block_id, y_off_elems) =
+            chrom_y_builder
+                .write_item_as(y_format, y_item_bytes, |buf| write_array(buf, y, y_format))?;
 
-        write_u64_le(&mut chrom_index_bytes, chrom_x_off_elems);
-        write_u64_le(&mut chrom_index_bytes, chrom_y_off_elems);
+        write_u64_le(&mut chrom_index_bytes, x_off_elems);
+        write_u64_le(&mut chrom_index_bytes, y_off_elems);
         write_u32_le(&mut chrom_index_bytes, x_len);
         write_u32_le(&mut chrom_index_bytes, y_len);
         write_u32_le(&mut chrom_index_bytes, x_block_id);
         write_u32_le(&mut chrom_index_bytes, y_block_id);
+    }
 
-        chrom_x_off_elems += x_len as u64;
-        chrom_y_off_elems += y_len as u64;
+    let mut aux_builder = AuxArraysBuilder::new(block_size, compression_level, do_shuffle);
+
+    for (i, s) in spectra.iter().enumerate() {
+        let list = s.binary_data_array_list.as_ref();
+        let xy = find_xy_ba(list, ACC_MZ_ARRAY, ACC_INTENSITY_ARRAY);
+        aux_builder.push_list(AUX_SCOPE_SPECTRUM, i as u32, list, xy)?;
+    }
+
+    for (i, c) in chromatograms.iter().enumerate() {
+        let list = c.binary_data_array_list.as_ref();
+        let xy = find_xy_ba(list, ACC_TIME_ARRAY, ACC_INTENSITY_ARRAY);
+        aux_builder.push_list(AUX_SCOPE_CHROMATOGRAM, i as u32, list, xy)?;
     }
 
-    let (container_spect_x, block_count_spect_x) = spec_x_builder.finalize();
-    let (container_spect_y, block_count_spect_y) = spec_y_builder.finalize();
-    let (container_chrom_x, block_count_chrom_x) = chrom_x_builder.finalize();
-    let (container_chrom_y, block_count_chrom_y) = chrom_y_builder.finalize();
+    let AuxSection {
+        bytes: aux_arrays_bytes,
+        layout_len: aux_layout_len,
+        directories: aux_directories,
+    } = if aux_builder.is_empty() {
+        AuxSection::default()
+    } else {
+        aux_builder.finalize()?
+    };
+
+    let (container_spect_x, block_count_spect_x) = spec_x_builder.finalize()?;
+    let (container_spect_y, block_count_spect_y) = spec_y_builder.finalize()?;
+    let (container_chrom_x, block_count_chrom_x) = chrom_x_builder.finalize()?;
+    let (container_chrom_y, block_count_chrom_y) = chrom_y_builder.finalize()?;
 
     let mut output = Vec::with_capacity(
         HEADER_SIZE
@@ -2658,6 +3412,7 @@ pub fn encode(mzml: &MzML, compression_level: u8, f32_compress: bool) -> Vec<u8>
             + container_spect_y.len()
             + container_chrom_x.len()
             + container_chrom_y.len()
+            + aux_arrays_bytes.len()
             + 64,
     );
 
@@ -2685,13 +3440,19 @@ pub fn encode(mzml: &MzML, compression_level: u8, f32_compress: bool) -> Vec<u8>
     let off_container_chrom_y = append_aligned_8(&mut output, &container_chrom_y);
     let size_container_chrom_y = container_chrom_y.len() as u64;
 
+    let off_aux_arrays = if aux_arrays_bytes.is_empty() {
+        0
+    } else {
+        append_aligned_8(&mut output, &aux_arrays_bytes)
+    };
+
     {
         let header = &mut output[0..HEADER_SIZE];
 
         header[0..4].copy_from_slice(b"B000");
         set_u8_at(header, 4, 0);
         header[5] = 1;
-        header[6] = 0;
+        header[HDR_OFF_CHECKSUM_KIND] = CHECKSUM_KIND_CRC32C;
         header[7] = 0;
 
         set_u64_at(header, 8, off_spec_index);
@@ -2735,17 +3496,71 @@ pub fn encode(mzml: &MzML, compression_level: u8, f32_compress: bool) -> Vec<u8>
         set_u8_at(
             header,
             172,
-            header_codec_and_flags(HDR_CODEC_ZSTD, compress_meta, compress_meta, compress_meta),
+            header_codec_and_flags(codec_id, compress_meta, compress_meta, compress_meta),
         );
 
-        set_u8_at(header, 173, if chrom_x_store_f64 { 2 } else { 1 });
-        set_u8_at(header, 174, if chrom_y_store_f64 { 2 } else { 1 });
-        set_u8_at(header, 175, if spect_x_store_f64 { 2 } else { 1 });
-        set_u8_at(header, 176, if spect_y_store_f64 { 2 } else { 1 });
+        set_u8_at(header, 173, chrom_x_format);
+        set_u8_at(header, 174, chrom_y_format);
+        set_u8_at(header, 175, spect_x_format);
+        set_u8_at(header, 176, spect_y_format);
 
         set_u8_at(header, 177, compression_level);
         set_u8_at(header, HDR_ARRAY_FILTER_OFF, array_filter_id);
+        let (block_size_mode, block_size_value) = block_size.to_header();
+        set_u8_at(header, HDR_OFF_BLOCK_SIZE_MODE, block_size_mode);
+        set_u32_at(header, HDR_OFF_BLOCK_SIZE_VALUE, block_size_value);
+        set_u64_at(header, HDR_OFF_AUX_ARRAYS, off_aux_arrays);
+    }
+
+    let mut checksums = SectionChecksums::default();
+    checksums.push(Section::Header, 0, &output[..HEADER_SIZE]);
+    checksums.push(Section::SpectrumIndex, off_spec_index, &spec_index_bytes);
+    checksums.push(Section::ChromIndex, off_chrom_index, &chrom_index_bytes);
+    checksums.push(Section::SpectrumMeta, off_spec_meta, &spectrum_meta_bytes);
+    checksums.push(Section::ChromMeta, off_chrom_meta, &chromatogram_meta_bytes);
+    checksums.push(Section::GlobalMeta, off_global_meta, &global_meta_bytes);
+    for (section, off, container, block_count) in [
+        (
+            Section::SpectXDirectory,
+            off_container_spect_x,
+            &container_spect_x,
+            block_count_spect_x,
+        ),
+        (
+            Section::SpectYDirectory,
+            off_container_spect_y,
+            &container_spect_y,
+            block_count_spect_y,
+        ),
+        (
+            Section::ChromXDirectory,
+            off_container_chrom_x,
+            &container_chrom_x,
+            block_count_chrom_x,
+        ),
+        (
+            Section::ChromYDirectory,
+            off_container_chrom_y,
+            &container_chrom_y,
+            block_count_chrom_y,
+        ),
+    ] {
+        let dir_len = block_count as usize * BLOCK_DIR_ENTRY_SIZE;
+        checksums.push(section, off, &container[..dir_len]);
+    }
+    checksums.push(
+        Section::AuxLayout,
+        off_aux_arrays,
+        &aux_arrays_bytes[..aux_layout_len],
+    );
+    for dir in aux_directories {
+        checksums.push(
+            Section::AuxDirectory,
+            off_aux_arrays + dir.start as u64,
+            &aux_arrays_bytes[dir],
+        );
     }
+    checksums.write(&mut output);
 
-    output
+    Ok(output)
 }
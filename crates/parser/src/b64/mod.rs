@@ -1,7 +1,11 @@
 pub mod decode;
 pub use decode::decode;
 pub mod encode;
-pub use encode::encode;
+pub use encode::{
+    ArrayFilter, BlockSize, Codec, EncodeError, EncodeOptions, FloatPrecision, encode, encode_with,
+};
+pub mod reader;
+pub use reader::B000Reader;
 pub mod utilities;
 
 #[cfg(test)]
@@ -0,0 +1,754 @@
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use crate::{
+    b64::{
+        decode::{
+            ACC_INTENSITY_ARRAY, ACC_MZ_ARRAY, ACC_TIME_ARRAY, ArrayData, AuxArray, AuxLayout,
+            ChromIndexEntry, ContainerReader, Metadatum, SpectrumIndexEntry,
+            attach_aux_arrays_to_bdal, attach_xy_arrays_to_bdal, aux_layout_len,
+            compute_block_starts_for_cx, compute_block_starts_for_cy, compute_block_starts_for_x,
+            compute_block_starts_for_y, decode_item_array_with, index_byte_len, parse_aux_layout,
+            parse_chrom_index_entries, parse_spectrum_index_entries,
+        },
+        utilities::{
+            CvPrefixes, Header, MetadataLayout, Section, SectionChecksums,
+            checksums::CHECKSUM_FOOTER_SIZE, parse_binary_data_array_list, parse_chromatogram_item,
+            parse_chromatogram_list_defaults, parse_global_metadata::parse_global_metadata,
+            parse_header, parse_metadata_with, parse_spectrum_item, parse_spectrum_list_defaults,
+        },
+    },
+    mzml::structs::{BinaryDataArrayList, Chromatogram, Spectrum},
+};
+
+const HEADER_SIZE: u64 = 192;
+const AUX_HEADER_SIZE: u64 = 16;
+
+const HDR_FLAG_SPEC_META_COMP: u8 = 1 << 4;
+const HDR_FLAG_CHROM_META_COMP: u8 = 1 << 5;
+const HDR_FLAG_GLOBAL_META_COMP: u8 = 1 << 6;
+
+/// Random-access reader over a B000 file.
+///
+/// Opening reads the header, the spectrum and chromatogram indices, their
+/// metadata sections (and the CV prefix table from the global one) and the
+/// aux array layout. Binary containers are left on
+/// the source; each call fetches and decompresses only the blocks that hold
+/// the requested item. Files written with checksums have each section and
+/// block verified as it is read.
+pub struct B000Reader<R> {
+    source: R,
+    stream_len: u64,
+    header: Header,
+    spectra: ItemSection<SpectrumIndexEntry>,
+    chromatograms: ItemSection<ChromIndexEntry>,
+    spect_x: Container,
+    spect_y: Container,
+    chrom_x: Container,
+    chrom_y: Container,
+    aux: Option<AuxSection>,
+}
+
+/// Index entries and metadata rows of one item kind.
+struct ItemSection<E> {
+    index: Vec<E>,
+    meta: Vec<Metadatum>,
+    rows_by_item: Vec<Vec<usize>>,
+    default_data_processing_ref: Option<String>,
+}
+
+impl<E> ItemSection<E> {
+    #[inline]
+    fn rows(&self, i: usize, kind: &str) -> Result<Vec<&Metadatum>, String> {
+        if i >= self.index.len() {
+            return Err(format!("{kind} index out of range: {i}"));
+        }
+        Ok(self
+            .rows_by_item
+            .get(i)
+            .map(|rows| rows.iter().map(|&r| &self.meta[r]).collect())
+            .unwrap_or_default())
+    }
+}
+
+/// A container whose blocks live at `base` in the source.
+struct Container {
+    base: u64,
+    size: u64,
+    reader: ContainerReader<'static>,
+    block_starts: Vec<u64>,
+}
+
+/// File header and checksums a container is opened against, and the trailer
+/// section covering its block directory.
+struct ContainerContext<'a> {
+    header: &'a Header,
+    checksums: &'a SectionChecksums,
+    directory: Section,
+}
+
+struct AuxSection {
+    layout: AuxLayout,
+    containers: Vec<Container>,
+    spectrum_entries: Vec<Vec<usize>>,
+    chrom_entries: Vec<Vec<usize>>,
+}
+
+impl B000Reader<BufReader<File>> {
+    /// Opens the B000 file at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, String> {
+        let file = File::open(path.as_ref())
+            .map_err(|e| format!("open {}: {e}", path.as_ref().display()))?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read + Seek> B000Reader<R> {
+    /// Parses the header, indices and metadata from `source`.
+    pub fn new(mut source: R) -> Result<Self, String> {
+        let stream_len = source
+            .seek(SeekFrom::End(0))
+            .map_err(|e| format!("seek to end failed: {e}"))?;
+        let header_bytes = read_at(&mut source, stream_len, 0, HEADER_SIZE, "header")?;
+        let header = parse_header(&header_bytes)?;
+        let checksums = read_checksums(&mut source, stream_len, &header)?;
+        checksums.verify(Section::Header, 0, &header_bytes)?;
+
+        let spec_index_raw = read_at(
+            &mut source,
+            stream_len,
+            header.off_spec_index,
+            index_byte_len(header.spectrum_count, "spectrum index")?,
+            "spectrum index",
+        )?;
+        checksums.verify(
+            Section::SpectrumIndex,
+            header.off_spec_index,
+            &spec_index_raw,
+        )?;
+        let spec_index = parse_spectrum_index_entries(&spec_index_raw, header.spectrum_count)?;
+
+        let chrom_index_raw = read_at(
+            &mut source,
+            stream_len,
+            header.off_chrom_index,
+            index_byte_len(header.chrom_count, "chrom index")?,
+            "chrom index",
+        )?;
+        checksums.verify(
+            Section::ChromIndex,
+            header.off_chrom_index,
+            &chrom_index_raw,
+        )?;
+        let chrom_index = parse_chrom_index_entries(&chrom_index_raw, header.chrom_count)?;
+
+        let cv_prefixes = read_cv_prefixes(&mut source, stream_len, &header, &checksums)?;
+        let spec_meta = read_metadata_section(
+            &mut source,
+            stream_len,
+            &header,
+            false,
+            &cv_prefixes,
+            &checksums,
+        )?;
+        let chrom_meta = read_metadata_section(
+            &mut source,
+            stream_len,
+            &header,
+            true,
+            &cv_prefixes,
+            &checksums,
+        )?;
+
+        let mut spect_x = Container::open(
+            &mut source,
+            stream_len,
+            &ContainerContext {
+                header: &header,
+                checksums: &checksums,
+                directory: Section::SpectXDirectory,
+            },
+            header.off_container_spect_x,
+            header.size_container_spect_x,
+            header.block_count_spect_x,
+            header.spect_x_format,
+        )?;
+        let mut spect_y = Container::open(
+            &mut source,
+            stream_len,
+            &ContainerContext {
+                header: &header,
+                checksums: &checksums,
+                directory: Section::SpectYDirectory,
+            },
+            header.off_container_spect_y,
+            header.size_container_spect_y,
+            header.block_count_spect_y,
+            header.spect_y_format,
+        )?;
+        let mut chrom_x = Container::open(
+            &mut source,
+            stream_len,
+            &ContainerContext {
+                header: &header,
+                checksums: &checksums,
+                directory: Section::ChromXDirectory,
+            },
+            header.off_container_chrom_x,
+            header.size_container_chrom_x,
+            header.block_count_chrom_x,
+            header.chrom_x_format,
+        )?;
+        let mut chrom_y = Container::open(
+            &mut source,
+            stream_len,
+            &ContainerContext {
+                header: &header,
+                checksums: &checksums,
+                directory: Section::ChromYDirectory,
+            },
+            header.off_container_chrom_y,
+            header.size_container_chrom_y,
+            header.block_count_chrom_y,
+            header.chrom_y_format,
+        )?;
+
+        spect_x.block_starts = compute_block_starts_for_x(&spec_index, header.block_count_spect_x)?;
+        spect_y.block_starts = compute_block_starts_for_y(&spec_index, header.block_count_spect_y)?;
+        chrom_x.block_starts =
+            compute_block_starts_for_cx(&chrom_index, header.block_count_chrom_x)?;
+        chrom_y.block_starts =
+            compute_block_starts_for_cy(&chrom_index, header.block_count_chrom_y)?;
+
+        spect_x.reader.set_name("spectrum x container");
+        spect_y.reader.set_name("spectrum y container");
+        chrom_x.reader.set_name("chromatogram x container");
+        chrom_y.reader.set_name("chromatogram y container");
+
+        let aux = AuxSection::open(&mut source, stream_len, &header, &checksums)?;
+
+        let spectra = ItemSection::new(spec_index, spec_meta, header.spectrum_count, |rows| {
+            parse_spectrum_list_defaults(rows)
+        });
+        let chromatograms = ItemSection::new(chrom_index, chrom_meta, header.chrom_count, |rows| {
+            parse_chromatogram_list_defaults(rows)
+        });
+
+        Ok(Self {
+            source,
+            stream_len,
+            header,
+            spectra,
+            chromatograms,
+            spect_x,
+            spect_y,
+            chrom_x,
+            chrom_y,
+            aux,
+        })
+    }
+
+    /// Caps the decompressed bytes each container keeps cached between calls.
+    pub fn set_cache_budget(&mut self, bytes: usize) {
+        for c in self.containers_mut() {
+            c.reader.set_cache_budget(bytes);
+        }
+    }
+
+    /// Decompressed bytes currently cached across all containers.
+    pub fn cached_bytes(&self) -> usize {
+        let aux = self.aux.iter().flat_map(|a| &a.containers);
+        [&self.spect_x, &self.spect_y, &self.chrom_x, &self.chrom_y]
+            .into_iter()
+            .chain(aux)
+            .map(|c| c.reader.cached_bytes())
+            .sum()
+    }
+
+    fn containers_mut(&mut self) -> impl Iterator<Item = &mut Container> {
+        let aux = self.aux.iter_mut().flat_map(|a| &mut a.containers);
+        [
+            &mut self.spect_x,
+            &mut self.spect_y,
+            &mut self.chrom_x,
+            &mut self.chrom_y,
+        ]
+        .into_iter()
+        .chain(aux)
+    }
+
+    /// Underlying source.
+    #[inline]
+    pub fn get_ref(&self) -> &R {
+        &self.source
+    }
+
+    #[inline]
+    pub fn header(&self) -> &Header {
+        &self.header
+    }
+
+    /// Number of spectra.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.spectra.index.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.spectra.index.is_empty()
+    }
+
+    /// Number of chromatograms.
+    #[inline]
+    pub fn chromatogram_count(&self) -> usize {
+        self.chromatograms.index.len()
+    }
+
+    /// <spectrum> `i` with its binary arrays.
+    pub fn spectrum(&mut self, i: usize) -> Result<Spectrum, String> {
+        let rows = self.spectra.rows(i, "spectrum")?;
+        let mut spectrum = parse_spectrum_item(
+            &rows,
+            i as u32,
+            self.spectra.default_data_processing_ref.as_deref(),
+        )
+        .ok_or_else(|| format!("spectrum {i}: no metadata"))?;
+
+        if let Some(bdal) = spectrum.binary_data_array_list.as_mut() {
+            self.fill_spectrum_arrays(i, bdal)?;
+        }
+        Ok(spectrum)
+    }
+
+    /// <binaryDataArrayList> of spectrum `i`.
+    pub fn spectrum_arrays(&mut self, i: usize) -> Result<BinaryDataArrayList, String> {
+        let rows = self.spectra.rows(i, "spectrum")?;
+        let mut bdal = parse_binary_data_array_list(&rows).unwrap_or_default();
+        self.fill_spectrum_arrays(i, &mut bdal)?;
+        Ok(bdal)
+    }
+
+    /// <chromatogram> `i` with its binary arrays.
+    pub fn chromatogram(&mut self, i: usize) -> Result<Chromatogram, String> {
+        let e = *self
+            .chromatograms
+            .index
+            .get(i)
+            .ok_or_else(|| format!("chromatogram index out of range: {i}"))?;
+
+        let rows = self.chromatograms.rows(i, "chromatogram")?;
+        let mut chromatogram = parse_chromatogram_item(
+            &rows,
+            i as u32,
+            self.chromatograms.default_data_processing_ref.as_deref(),
+        )
+        .ok_or_else(|| format!("chromatogram {i}: no metadata"))?;
+
+        let Some(bdal) = chromatogram.binary_data_array_list.as_mut() else {
+            return Ok(chromatogram);
+        };
+
+        let x = self.chrom_x.decode(
+            &mut self.source,
+            self.stream_len,
+            e.time_block_id,
+            e.time_element_off,
+            e.time_element_len,
+        )?;
+        let y = self.chrom_y.decode(
+            &mut self.source,
+            self.stream_len,
+            e.inten_block_id,
+            e.inten_element_off,
+            e.inten_element_len,
+        )?;
+        attach_xy_arrays_to_bdal(bdal, &x, &y, ACC_TIME_ARRAY, ACC_INTENSITY_ARRAY);
+
+        if let Some(aux) = self.aux.as_mut() {
+            let arrays = aux.arrays(&mut self.source, self.stream_len, false, i)?;
+            attach_aux_arrays_to_bdal(bdal, arrays);
+        }
+        Ok(chromatogram)
+    }
+
+    fn fill_spectrum_arrays(
+        &mut self,
+        i: usize,
+        bdal: &mut BinaryDataArrayList,
+    ) -> Result<(), String> {
+        let e = *self
+            .spectra
+            .index
+            .get(i)
+            .ok_or_else(|| format!("spectrum index out of range: {i}"))?;
+
+        let x = self.spect_x.decode(
+            &mut self.source,
+            self.stream_len,
+            e.mz_block_id,
+            e.mz_element_off,
+            e.mz_element_len,
+        )?;
+        let y = self.spect_y.decode(
+            &mut self.source,
+            self.stream_len,
+            e.inten_block_id,
+            e.inten_element_off,
+            e.inten_element_len,
+        )?;
+        attach_xy_arrays_to_bdal(bdal, &x, &y, ACC_MZ_ARRAY, ACC_INTENSITY_ARRAY);
+
+        if let Some(aux) = self.aux.as_mut() {
+            let arrays = aux.arrays(&mut self.source, self.stream_len, true, i)?;
+            attach_aux_arrays_to_bdal(bdal, arrays);
+        }
+        Ok(())
+    }
+}
+
+impl<E> ItemSection<E> {
+    fn new<F>(index: Vec<E>, meta: Vec<Metadatum>, item_count: u32, defaults: F) -> Self
+    where
+        F: FnOnce(&[&Metadatum]) -> Option<String>,
+    {
+        let mut rows_by_item = vec![Vec::new(); item_count as usize];
+        for (r, m) in meta.iter().enumerate() {
+            if let Some(rows) = rows_by_item.get_mut(m.item_index as usize) {
+                rows.push(r);
+            }
+        }
+
+        let meta_ref: Vec<&Metadatum> = meta.iter().collect();
+        let default_data_processing_ref = defaults(&meta_ref);
+
+        Self {
+            index,
+            meta,
+            rows_by_item,
+            default_data_processing_ref,
+        }
+    }
+}
+
+impl Container {
+    fn open<R: Read + Seek>(
+        source: &mut R,
+        stream_len: u64,
+        context: &ContainerContext,
+        base: u64,
+        size: u64,
+        block_count: u32,
+        default_format: u8,
+    ) -> Result<Self, String> {
+        let ContainerContext {
+            header,
+            checksums,
+            directory,
+        } = *context;
+        let dir_len = ContainerReader::directory_len(block_count)? as u64;
+        if dir_len > size {
+            return Err("container too small for directory".to_string());
+        }
+        let dir_raw = read_at(source, stream_len, base, dir_len, "container directory")?;
+        checksums.verify(directory, base, &dir_raw)?;
+        let reader = ContainerReader::from_directory(
+            &dir_raw,
+            block_count,
+            default_format,
+            header.compression_level,
+            header.array_filter,
+        )?;
+
+        Ok(Self {
+            base,
+            size,
+            reader,
+            block_starts: Vec::new(),
+        })
+    }
+
+    #[inline]
+    fn decode<R: Read + Seek>(
+        &mut self,
+        source: &mut R,
+        stream_len: u64,
+        block_id: u32,
+        element_off: u64,
+        element_len: u32,
+    ) -> Result<ArrayData, String> {
+        let (base, size) = (self.base, self.size);
+        decode_item_array_with(
+            &mut self.reader,
+            &self.block_starts,
+            block_id,
+            element_off,
+            element_len,
+            |off, len| {
+                let end = off
+                    .checked_add(len)
+                    .ok_or_else(|| "comp end overflow".to_string())?;
+                if end > size {
+                    return Err("container: block range out of bounds".to_string());
+                }
+                read_at(source, stream_len, base + off, len, "container block").map(Cow::Owned)
+            },
+        )
+    }
+}
+
+impl AuxSection {
+    fn open<R: Read + Seek>(
+        source: &mut R,
+        stream_len: u64,
+        header: &Header,
+        checksums: &SectionChecksums,
+    ) -> Result<Option<Self>, String> {
+        if header.off_aux_arrays == 0 {
+            return Ok(None);
+        }
+
+        let base = header.off_aux_arrays;
+        let prefix = read_at(source, stream_len, base, AUX_HEADER_SIZE, "aux arrays")?;
+        let layout_len = aux_layout_len(&prefix)? as u64;
+        let layout_raw = read_at(source, stream_len, base, layout_len, "aux arrays")?;
+        checksums.verify(Section::AuxLayout, base, &layout_raw)?;
+        let layout = parse_aux_layout(&layout_raw)?;
+
+        let mut containers = Vec::with_capacity(layout.containers.len());
+        for (cid, (c, starts)) in layout
+            .containers
+            .iter()
+            .zip(&layout.block_starts)
+            .enumerate()
+        {
+            let mut container = Container::open(
+                source,
+                stream_len,
+                &ContainerContext {
+                    header,
+                    checksums,
+                    directory: Section::AuxDirectory,
+                },
+                base + c.off,
+                c.size,
+                c.block_count,
+                c.format,
+            )?;
+            container.reader.set_name(format!("aux container {cid}"));
+            container.block_starts = starts.clone();
+            containers.push(container);
+        }
+
+        let mut spectrum_entries = vec![Vec::new(); header.spectrum_count as usize];
+        let mut chrom_entries = vec![Vec::new(); header.chrom_count as usize];
+        for (n, e) in layout.entries.iter().enumerate() {
+            let items = if n < layout.spectrum_entry_count {
+                &mut spectrum_entries
+            } else {
+                &mut chrom_entries
+            };
+            items
+                .get_mut(e.item_index as usize)
+                .ok_or_else(|| format!("aux arrays: item_index out of range: {}", e.item_index))?
+                .push(n);
+        }
+
+        Ok(Some(Self {
+            layout,
+            containers,
+            spectrum_entries,
+            chrom_entries,
+        }))
+    }
+
+    fn arrays<R: Read + Seek>(
+        &mut self,
+        source: &mut R,
+        stream_len: u64,
+        spectrum: bool,
+        i: usize,
+    ) -> Result<Vec<AuxArray>, String> {
+        let entries = if spectrum {
+            &self.spectrum_entries
+        } else {
+            &self.chrom_entries
+        };
+        let Some(entries) = entries.get(i) else {
+            return Ok(Vec::new());
+        };
+
+        let mut out = Vec::with_capacity(entries.len());
+        for &n in entries {
+            let e = self.layout.entries[n];
+            let cid = e.container_id as usize;
+            let c = self.layout.containers[cid];
+            let data = self.containers[cid].decode(
+                source,
+                stream_len,
+                e.block_id,
+                e.element_off,
+                e.element_len,
+            )?;
+            out.push(AuxArray {
+                array_index: e.array_index,
+                kind_cv_ref: c.kind_cv_ref,
+                kind_accession: c.kind_accession,
+                data,
+            });
+        }
+        Ok(out)
+    }
+}
+
+#[inline]
+fn read_at<R: Read + Seek>(
+    source: &mut R,
+    stream_len: u64,
+    off: u64,
+    len: u64,
+    field: &'static str,
+) -> Result<Vec<u8>, String> {
+    if off.checked_add(len).is_none_or(|stop| stop > stream_len) {
+        return Err(format!(
+            "{field}: out of bounds (off={off}, len={len}, file len={stream_len})"
+        ));
+    }
+    let len = usize::try_from(len).map_err(|_| format!("{field}: len overflow"))?;
+    source
+        .seek(SeekFrom::Start(off))
+        .map_err(|e| format!("{field}: seek to {off} failed: {e}"))?;
+    let mut buf = vec![0u8; len];
+    source
+        .read_exact(&mut buf)
+        .map_err(|e| format!("{field}: read failed (off={off}, len={len}): {e}"))?;
+    Ok(buf)
+}
+
+/// Section checksums from the trailer. A valid footer is used even when
+/// `header` does not flag checksums; otherwise the trailer is empty for such
+/// files.
+fn read_checksums<R: Read + Seek>(
+    source: &mut R,
+    stream_len: u64,
+    header: &Header,
+) -> Result<SectionChecksums, String> {
+    let found = read_checksum_footer(source, stream_len);
+    if found.is_err() && !header.has_checksums() {
+        return Ok(SectionChecksums::default());
+    }
+    found
+}
+
+fn read_checksum_footer<R: Read + Seek>(
+    source: &mut R,
+    stream_len: u64,
+) -> Result<SectionChecksums, String> {
+    let footer_off = stream_len
+        .checked_sub(CHECKSUM_FOOTER_SIZE as u64)
+        .ok_or_else(|| "checksums: file too small for footer".to_string())?;
+    let footer = read_at(
+        source,
+        stream_len,
+        footer_off,
+        CHECKSUM_FOOTER_SIZE as u64,
+        "checksum footer",
+    )?;
+    let (off, len) = SectionChecksums::locate(&footer)?;
+    let entries = read_at(source, stream_len, off, len as u64, "checksum trailer")?;
+    SectionChecksums::parse(&entries, &footer)
+}
+
+/// CV prefix table from the global metadata section.
+fn read_cv_prefixes<R: Read + Seek>(
+    source: &mut R,
+    stream_len: u64,
+    header: &Header,
+    checksums: &SectionChecksums,
+) -> Result<CvPrefixes, String> {
+    let (start_off, end_off) = (header.off_global_meta, header.off_container_spect_x);
+    if start_off >= end_off {
+        return Err("invalid global metadata offsets: start >= end".to_string());
+    }
+    let raw = read_at(
+        source,
+        stream_len,
+        start_off,
+        end_off - start_off,
+        "global metadata",
+    )?;
+    checksums.verify(Section::GlobalMeta, start_off, &raw)?;
+    let meta = parse_global_metadata(
+        &raw,
+        0,
+        header.global_meta_count,
+        header.global_num_count,
+        header.global_str_count,
+        header.reserved_flags & HDR_FLAG_GLOBAL_META_COMP != 0,
+        header.reserved_flags,
+    )?;
+    Ok(CvPrefixes::from_metadata(&meta))
+}
+
+/// Spectrum or chromatogram metadata rows.
+fn read_metadata_section<R: Read + Seek>(
+    source: &mut R,
+    stream_len: u64,
+    header: &Header,
+    chromatograms: bool,
+    cv_prefixes: &CvPrefixes,
+    checksums: &SectionChecksums,
+) -> Result<Vec<Metadatum>, String> {
+    let (section, start_off, end_off, item_count, meta_count, num_count, str_count, flag) =
+        if chromatograms {
+            (
+                Section::ChromMeta,
+                header.off_chrom_meta,
+                header.off_global_meta,
+                header.chrom_count,
+                header.chrom_meta_count,
+                header.chrom_num_count,
+                header.chrom_str_count,
+                HDR_FLAG_CHROM_META_COMP,
+            )
+        } else {
+            (
+                Section::SpectrumMeta,
+                header.off_spec_meta,
+                header.off_chrom_meta,
+                header.spectrum_count,
+                header.spec_meta_count,
+                header.spec_num_count,
+                header.spec_str_count,
+                HDR_FLAG_SPEC_META_COMP,
+            )
+        };
+
+    if start_off >= end_off {
+        return Err("invalid metadata offsets: start >= end".to_string());
+    }
+    let raw = read_at(
+        source,
+        stream_len,
+        start_off,
+        end_off - start_off,
+        "metadata",
+    )?;
+    checksums.verify(section, start_off, &raw)?;
+    parse_metadata_with(
+        &raw,
+        &MetadataLayout {
+            item_count,
+            meta_count,
+            num_count,
+            str_count,
+            compressed: header.reserved_flags & flag != 0,
+            reserved_flags: header.reserved_flags,
+            cv_prefixes,
+        },
+    )
+}
@@ -0,0 +1,91 @@
+use std::sync::OnceLock;
+
+use crate::b64::{
+    BlockSize, EncodeOptions,
+    decode::{ContainerReader, slice_at},
+    encode_with,
+    utilities::{Header, parse_header},
+};
+use crate::{mzml::structs::MzML, utilities::test::mzml};
+
+static MZML_CACHE: OnceLock<MzML> = OnceLock::new();
+
+const PATH: &str = "data/mzml/test.mzML";
+
+fn encoded_items_per_block() -> Vec<u8> {
+    let options = EncodeOptions {
+        block_size: BlockSize::Items(1),
+        ..EncodeOptions::default()
+    };
+    encode_with(mzml(&MZML_CACHE, PATH), &options).unwrap()
+}
+
+fn spect_x_reader<'a>(bytes: &'a [u8], header: &Header) -> ContainerReader<'a> {
+    let raw = slice_at(
+        bytes,
+        header.off_container_spect_x,
+        header.size_container_spect_x,
+        "container_spect_x",
+    )
+    .unwrap();
+    ContainerReader::new(
+        raw,
+        header.block_count_spect_x,
+        header.spect_x_format,
+        header.compression_level,
+        header.array_filter,
+    )
+    .unwrap()
+}
+
+#[test]
+fn cache_budget_keeps_only_recent_blocks() {
+    let bytes = encoded_items_per_block();
+    let header = parse_header(&bytes).unwrap();
+    assert_eq!(header.block_count_spect_x, 2);
+
+    let mut reader = spect_x_reader(&bytes, &header);
+    let sizes: Vec<usize> = (0..2)
+        .map(|b| reader.block_bytes(b).unwrap().len())
+        .collect();
+    assert_eq!(reader.cached_bytes(), sizes[0] + sizes[1]);
+
+    reader.set_cache_budget(0);
+    assert_eq!(reader.cached_bytes(), sizes[1]);
+
+    reader.block_bytes(0).unwrap();
+    assert_eq!(reader.cached_bytes(), sizes[0]);
+
+    reader.set_cache_budget(sizes[0] + sizes[1]);
+    reader.block_bytes(1).unwrap();
+    assert_eq!(reader.cached_bytes(), sizes[0] + sizes[1]);
+
+    reader.block_bytes(0).unwrap();
+    reader.set_cache_budget(sizes[0]);
+    assert_eq!(reader.cached_bytes(), sizes[0]);
+    assert!(!reader.block_bytes(0).unwrap().is_empty());
+    assert_eq!(reader.cached_bytes(), sizes[0]);
+}
+
+#[test]
+fn consumed_blocks_are_released() {
+    let bytes = encoded_items_per_block();
+    let header = parse_header(&bytes).unwrap();
+
+    let mut reader = spect_x_reader(&bytes, &header);
+    reader.track_block_uses([0, 0, 1]);
+
+    let first = reader.block_bytes(0).unwrap().to_vec();
+    reader.consume(0);
+    assert_eq!(reader.cached_bytes(), first.len());
+
+    reader.consume(0);
+    assert_eq!(reader.cached_bytes(), 0);
+
+    let second = reader.block_bytes(1).unwrap().len();
+    assert_eq!(reader.cached_bytes(), second);
+    reader.consume(1);
+    assert_eq!(reader.cached_bytes(), 0);
+
+    assert_eq!(reader.block_bytes(0).unwrap(), first.as_slice());
+}
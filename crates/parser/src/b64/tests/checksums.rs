@@ -0,0 +1,140 @@
+use std::{fs, io::Cursor, path::PathBuf, sync::OnceLock};
+
+use crate::{
+    b64::{
+        B000Reader, BlockSize, EncodeOptions, decode, encode, encode_with,
+        utilities::{checksums::HDR_OFF_CHECKSUM_KIND, crc32c, parse_header},
+    },
+    mzml::structs::MzML,
+    utilities::test::mzml,
+};
+
+static MZML_CACHE: OnceLock<MzML> = OnceLock::new();
+static TINY_CACHE: OnceLock<MzML> = OnceLock::new();
+
+const PATH: &str = "data/mzml/test.mzML";
+const TINY_PATH: &str = "data/mzml/tiny.pwiz.mzML0.99.10.mzML";
+const GOLDEN_PATH: &str = "data/b64/test.b64";
+
+fn encoded() -> Vec<u8> {
+    let options = EncodeOptions {
+        block_size: BlockSize::Items(1),
+        ..EncodeOptions::default()
+    };
+    encode_with(mzml(&MZML_CACHE, PATH), &options).unwrap()
+}
+
+/// Offset of the first compressed byte of spectrum x block `block_id`.
+fn spect_x_block_start(bytes: &[u8], block_id: usize) -> usize {
+    let header = parse_header(bytes).unwrap();
+    let container = header.off_container_spect_x as usize;
+    let entry = container + block_id * 32;
+    let comp_off = u64::from_le_bytes(bytes[entry..entry + 8].try_into().unwrap());
+    container + header.block_count_spect_x as usize * 32 + comp_off as usize
+}
+
+#[test]
+fn crc32c_matches_reference_vectors() {
+    assert_eq!(crc32c(b""), 0);
+    assert_eq!(crc32c(b"123456789"), 0xE306_9283);
+    assert_eq!(crc32c(&[0u8; 32]), 0x8A91_36AA);
+    assert_eq!(crc32c(&[0xFFu8; 32]), 0x62A8_AB43);
+}
+
+#[test]
+fn encoded_files_verify() {
+    let bytes = encoded();
+    assert!(parse_header(&bytes).unwrap().has_checksums());
+    decode(&bytes).expect("decode failed");
+    B000Reader::new(Cursor::new(bytes)).expect("reader failed");
+}
+
+#[test]
+fn corrupt_block_is_named() {
+    let mut bytes = encoded();
+    let at = spect_x_block_start(&bytes, 1);
+    bytes[at] ^= 0x10;
+
+    let err = decode(&bytes).unwrap_err();
+    assert_eq!(err, "spectrum x container: checksum mismatch in block 1");
+
+    let mut reader = B000Reader::new(Cursor::new(bytes)).expect("reader failed");
+    reader.spectrum(0).expect("block 0 is intact");
+    assert_eq!(
+        reader.spectrum(1).unwrap_err(),
+        "spectrum x container: checksum mismatch in block 1"
+    );
+}
+
+#[test]
+fn corrupt_section_is_named() {
+    let mut bytes = encoded();
+    let header = parse_header(&bytes).unwrap();
+    bytes[header.off_spec_meta as usize + 3] ^= 0x01;
+
+    let err = decode(&bytes).unwrap_err();
+    assert!(
+        err.starts_with("checksum mismatch in spectrum metadata section (bytes "),
+        "{err}"
+    );
+    let err = B000Reader::new(Cursor::new(bytes)).err().unwrap();
+    assert!(
+        err.starts_with("checksum mismatch in spectrum metadata section"),
+        "{err}"
+    );
+}
+
+#[test]
+fn corrupt_header_is_named() {
+    let mut bytes = encoded();
+    bytes[112] ^= 0x01;
+
+    let err = decode(&bytes).unwrap_err();
+    assert_eq!(err, "checksum mismatch in header (bytes 0..192)");
+}
+
+#[test]
+fn cleared_checksum_kind_does_not_disable_checks() {
+    let mut bytes = encoded();
+    bytes[HDR_OFF_CHECKSUM_KIND] = 0;
+    let at = spect_x_block_start(&bytes, 1);
+    bytes[at] ^= 0x10;
+    assert!(!parse_header(&bytes).unwrap().has_checksums());
+
+    let err = decode(&bytes).unwrap_err();
+    assert_eq!(err, "checksum mismatch in header (bytes 0..192)");
+    let err = B000Reader::new(Cursor::new(bytes)).err().unwrap();
+    assert!(err.starts_with("checksum mismatch in header"), "{err}");
+}
+
+#[test]
+fn files_without_checksums_still_decode() {
+    let full = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join(GOLDEN_PATH);
+    let bytes = fs::read(&full).unwrap_or_else(|e| panic!("cannot read {full:?}: {e}"));
+    assert!(!parse_header(&bytes).unwrap().has_checksums());
+    decode(&bytes).expect("decode failed");
+}
+
+/// Every single-bit flip of a small file; decoding may fail but must not
+/// panic or abort.
+#[test]
+fn flipped_bits_are_errors_not_panics() {
+    let bytes = encode(mzml(&TINY_CACHE, TINY_PATH), 3, false);
+    assert!(parse_header(&bytes).unwrap().has_checksums());
+
+    for bit in 0..bytes.len() * 8 {
+        let mut corrupt = bytes.clone();
+        corrupt[bit / 8] ^= 1 << (bit % 8);
+
+        let _ = decode(&corrupt);
+        let Ok(mut reader) = B000Reader::new(Cursor::new(corrupt)) else {
+            continue;
+        };
+        for i in 0..reader.len() {
+            let _ = reader.spectrum(i);
+        }
+        for i in 0..reader.chromatogram_count() {
+            let _ = reader.chromatogram(i);
+        }
+    }
+}
@@ -0,0 +1,159 @@
+use std::{io::Cursor, sync::OnceLock};
+
+use crate::{
+    b64::{B000Reader, decode, encode, utilities::CvPrefixes},
+    mzml::structs::{CvParam, MzML},
+    utilities::test::mzml,
+};
+
+static MZML_CACHE: OnceLock<MzML> = OnceLock::new();
+
+const PATH: &str = "data/mzml/test.mzML";
+
+fn cv(cv_ref: &str, accession: &str, value: &str) -> CvParam {
+    CvParam {
+        cv_ref: Some(cv_ref.to_string()),
+        accession: Some(accession.to_string()),
+        name: accession.to_string(),
+        value: Some(value.to_string()),
+        ..Default::default()
+    }
+}
+
+fn with_unit(mut cv: CvParam, unit_cv_ref: &str, unit_accession: &str) -> CvParam {
+    cv.unit_cv_ref = Some(unit_cv_ref.to_string());
+    cv.unit_accession = Some(unit_accession.to_string());
+    cv
+}
+
+fn foreign_params() -> Vec<CvParam> {
+    vec![
+        cv("PRIDE", "PRIDE:0000219", "date of search"),
+        cv("BTO", "BTO:0000142", ""),
+        cv("NCBITaxon", "NCBITaxon:9606", "Homo sapiens"),
+        cv("XLMOD", "XLMOD:02001", ""),
+        cv("UNIMOD", "UNIMOD:35", ""),
+        cv("NCIT", "NCIT:C25330", ""),
+        cv("NCIT", "NCIT:25330", ""),
+        cv("PSI-MS", "MS:1000511", "2"),
+        cv("MS", "MS:0000511", ""),
+        cv("EFO", "EFO:no-number", ""),
+        with_unit(cv("PATO", "PATO:0000119", "12.5"), "UO", "UO:0000008"),
+        with_unit(cv("MS", "MS:1000504", "3"), "PATO", "PATO:0001470"),
+    ]
+}
+
+/// `(cvRef, accession, value, unitCvRef, unitAccession)`.
+type Key<'a> = [Option<&'a str>; 5];
+
+fn keys(cvs: &[CvParam]) -> Vec<Key<'_>> {
+    cvs.iter()
+        .map(|cv| {
+            [
+                cv.cv_ref.as_deref(),
+                cv.accession.as_deref(),
+                cv.value.as_deref().filter(|v| !v.is_empty()),
+                cv.unit_cv_ref.as_deref(),
+                cv.unit_accession.as_deref(),
+            ]
+        })
+        .collect()
+}
+
+fn with_foreign_params(mut mzml: MzML) -> MzML {
+    mzml.file_description
+        .file_content
+        .cv_params
+        .extend(foreign_params());
+    let spectrum = &mut mzml.run.spectrum_list.as_mut().unwrap().spectra[0];
+    spectrum.cv_params.extend(foreign_params());
+    spectrum
+        .binary_data_array_list
+        .as_mut()
+        .unwrap()
+        .binary_data_arrays[0]
+        .cv_params
+        .push(cv("PRIDE", "PRIDE:0000219", "array note"));
+    mzml
+}
+
+#[test]
+fn foreign_cv_prefixes_survive_encode_decode() {
+    let src = with_foreign_params(mzml(&MZML_CACHE, PATH).clone());
+    let expected = foreign_params();
+
+    for level in [0u8, 12] {
+        let decoded = decode(&encode(&src, level, false)).expect("decode failed");
+
+        let file_content = &decoded.file_description.file_content.cv_params;
+        assert_eq!(
+            keys(&file_content[file_content.len() - expected.len()..]),
+            keys(&expected)
+        );
+
+        let spectrum = &decoded.run.spectrum_list.as_ref().unwrap().spectra[0];
+        let cvs = &spectrum.cv_params;
+        assert_eq!(keys(&cvs[cvs.len() - expected.len()..]), keys(&expected));
+        assert!(
+            spectrum.user_params.iter().all(|up| !up.name.contains(':')),
+            "{:?}",
+            spectrum.user_params
+        );
+
+        let array = &spectrum
+            .binary_data_array_list
+            .as_ref()
+            .unwrap()
+            .binary_data_arrays[0];
+        assert_eq!(
+            keys(array.cv_params.last().map(std::slice::from_ref).unwrap()),
+            keys(&[cv("PRIDE", "PRIDE:0000219", "array note")])
+        );
+    }
+}
+
+#[test]
+fn reader_resolves_foreign_cv_prefixes() {
+    let src = with_foreign_params(mzml(&MZML_CACHE, PATH).clone());
+    let expected = foreign_params();
+    let bytes = encode(&src, 12, false);
+
+    let mut reader = B000Reader::new(Cursor::new(bytes)).unwrap();
+    let spectrum = reader.spectrum(0).unwrap();
+    let cvs = &spectrum.cv_params;
+    assert_eq!(keys(&cvs[cvs.len() - expected.len()..]), keys(&expected));
+}
+
+#[test]
+fn table_holds_only_formats_the_fixed_codes_miss() {
+    let mut table = CvPrefixes::default();
+    assert_eq!(
+        table.code_of(Some("MS"), Some("MS:1000511")),
+        (0, 1_000_511)
+    );
+    assert_eq!(table.code_of(None, Some("UO:0000008")), (1, 8));
+    assert_eq!(
+        table.code_of(Some("NCIT"), Some("NCIT:C25330")),
+        (2, 25_330)
+    );
+    assert!(table.is_empty());
+
+    assert_eq!(table.code_of(Some("BTO"), Some("BTO:0000142")), (6, 142));
+    assert_eq!(table.code_of(Some("BTO"), Some("BTO:0000089")), (6, 89));
+    assert_eq!(
+        table.code_of(Some("BTO"), Some("BTO:1000089")),
+        (7, 1_000_089)
+    );
+    assert_eq!(
+        table.code_of(Some("PSI-MS"), Some("MS:1000511")),
+        (8, 1_000_511)
+    );
+    assert_eq!(table.rows().len(), 3);
+
+    assert_eq!(table.accession(6, 42).as_deref(), Some("BTO:0000042"));
+    assert_eq!(table.accession(7, 42).as_deref(), Some("BTO:42"));
+    assert_eq!(table.accession(8, 1_000_511).as_deref(), Some("MS:1000511"));
+    assert_eq!(table.cv_ref(8).as_deref(), Some("PSI-MS"));
+    assert_eq!(table.cv_ref(6), None);
+    assert_eq!(table.accession(0, 511).as_deref(), Some("MS:1000511"));
+}
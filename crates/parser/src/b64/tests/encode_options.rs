@@ -0,0 +1,137 @@
+use std::sync::OnceLock;
+
+use crate::{
+    BinaryData,
+    b64::{
+        ArrayFilter, BlockSize, Codec, EncodeError, EncodeOptions, FloatPrecision, decode, encode,
+        encode_with, utilities::parse_header,
+    },
+    mzml::structs::MzML,
+    utilities::test::mzml,
+};
+
+static MZML_CACHE: OnceLock<MzML> = OnceLock::new();
+
+const PATH: &str = "data/mzml/test.mzML";
+
+fn spectrum_binaries(mzml: &MzML) -> Vec<Option<BinaryData>> {
+    mzml.run
+        .spectrum_list
+        .as_ref()
+        .unwrap()
+        .spectra
+        .iter()
+        .flat_map(|s| {
+            &s.binary_data_array_list
+                .as_ref()
+                .unwrap()
+                .binary_data_arrays
+        })
+        .map(|ba| ba.binary.clone())
+        .collect()
+}
+
+#[test]
+fn encode_matches_encode_with_legacy_options() {
+    let src = mzml(&MZML_CACHE, PATH);
+    for (level, f32_compress) in [(0u8, false), (12, false), (3, true)] {
+        let options = EncodeOptions::new(level, f32_compress);
+        assert_eq!(
+            encode(src, level, f32_compress),
+            encode_with(src, &options).unwrap(),
+            "level {level}, f32_compress {f32_compress}"
+        );
+    }
+}
+
+#[test]
+fn invalid_options_return_errors() {
+    let src = mzml(&MZML_CACHE, PATH);
+
+    let options = EncodeOptions {
+        compression_level: 23,
+        ..EncodeOptions::default()
+    };
+    assert_eq!(
+        encode_with(src, &options),
+        Err(EncodeError::InvalidCompressionLevel(23))
+    );
+
+    for block_size in [BlockSize::Bytes(0), BlockSize::Items(0)] {
+        let options = EncodeOptions {
+            block_size,
+            ..EncodeOptions::default()
+        };
+        assert_eq!(
+            encode_with(src, &options),
+            Err(EncodeError::InvalidBlockSize(block_size))
+        );
+    }
+}
+
+#[test]
+fn option_combinations_decode_losslessly() {
+    let src = mzml(&MZML_CACHE, PATH);
+    let expected = spectrum_binaries(src);
+
+    let combos = [
+        EncodeOptions {
+            codec: Codec::None,
+            ..EncodeOptions::default()
+        },
+        EncodeOptions {
+            array_filter: ArrayFilter::None,
+            compress_metadata: false,
+            ..EncodeOptions::default()
+        },
+        EncodeOptions {
+            block_size: BlockSize::Items(1),
+            ..EncodeOptions::default()
+        },
+        EncodeOptions {
+            compression_level: 0,
+            block_size: BlockSize::Bytes(4096),
+            ..EncodeOptions::default()
+        },
+    ];
+
+    for options in combos {
+        let bytes = encode_with(src, &options).expect("encode_with failed");
+        let decoded = decode(&bytes).expect("decode failed");
+        assert_eq!(spectrum_binaries(&decoded), expected, "{options:?}");
+    }
+}
+
+#[test]
+fn f32_precision_narrows_float_arrays() {
+    let src = mzml(&MZML_CACHE, PATH);
+    let options = EncodeOptions {
+        float_precision: FloatPrecision::F32,
+        ..EncodeOptions::default()
+    };
+
+    let decoded = decode(&encode_with(src, &options).unwrap()).expect("decode failed");
+    for binary in spectrum_binaries(&decoded) {
+        assert!(matches!(binary, Some(BinaryData::F32(_))));
+    }
+}
+
+#[test]
+fn block_size_is_recorded_in_header() {
+    let src = mzml(&MZML_CACHE, PATH);
+
+    let legacy = parse_header(&encode(src, 12, false)).unwrap();
+    assert_eq!(legacy.block_size(), Some(BlockSize::default()));
+    assert_eq!(legacy.block_count_spect_x, 1);
+
+    let options = EncodeOptions {
+        block_size: BlockSize::Items(1),
+        ..EncodeOptions::default()
+    };
+    let header = parse_header(&encode_with(src, &options).unwrap()).unwrap();
+    assert_eq!(header.block_size(), Some(BlockSize::Items(1)));
+    assert_eq!(header.block_count_spect_x, header.spectrum_count);
+    assert_eq!(header.block_count_spect_y, header.spectrum_count);
+    assert_eq!(header.block_count_chrom_x, header.chrom_count);
+    assert_eq!(header.block_count_chrom_y, header.chrom_count);
+}
@@ -0,0 +1,485 @@
+use std::{io::Cursor, sync::OnceLock};
+
+use crate::{
+    BinaryData, NumericType,
+    b64::{
+        B000Reader, BlockSize, EncodeOptions, decode, encode, encode_with,
+        utilities::{
+            SectionChecksums,
+            checksums::{CHECKSUM_FOOTER_SIZE, HDR_OFF_CHECKSUM_KIND},
+            parse_header,
+        },
+    },
+    mzml::{
+        bin_to_mzml::bin_to_mzml,
+        parse_mzml::parse_mzml,
+        structs::{BinaryDataArray, CvParam, MzML, UserParam},
+    },
+    utilities::test::mzml,
+};
+
+static MZML_CACHE: OnceLock<MzML> = OnceLock::new();
+
+const PATH: &str = "data/mzml/test.mzML";
+
+fn ms_cv(accession: &str, name: &str) -> CvParam {
+    CvParam {
+        cv_ref: Some("MS".to_string()),
+        accession: Some(accession.to_string()),
+        name: name.to_string(),
+        value: Some(String::new()),
+        unit_cv_ref: None,
+        unit_name: None,
+        unit_accession: None,
+    }
+}
+
+fn arrays_of_chromatogram(mzml: &MzML, idx: usize) -> &[BinaryDataArray] {
+    &mzml.run.chromatogram_list.as_ref().unwrap().chromatograms[idx]
+        .binary_data_array_list
+        .as_ref()
+        .unwrap()
+        .binary_data_arrays
+}
+
+fn arrays_of_spectrum(mzml: &MzML, idx: usize) -> &[BinaryDataArray] {
+    &mzml.run.spectrum_list.as_ref().unwrap().spectra[idx]
+        .binary_data_array_list
+        .as_ref()
+        .unwrap()
+        .binary_data_arrays
+}
+
+fn with_ion_mobility_array(mut mzml: MzML) -> MzML {
+    let bdal = mzml.run.spectrum_list.as_mut().unwrap().spectra[0]
+        .binary_data_array_list
+        .as_mut()
+        .unwrap();
+
+    let len = match bdal.binary_data_arrays[0].binary.as_ref().unwrap() {
+        BinaryData::F64(v) => v.len(),
+        BinaryData::F32(v) => v.len(),
+        other => panic!("unexpected m/z array {other:?}"),
+    };
+
+    bdal.binary_data_arrays.push(BinaryDataArray {
+        cv_params: vec![
+            ms_cv("MS:1000521", "32-bit float"),
+            ms_cv("MS:1000576", "no compression"),
+            ms_cv("MS:1002816", "mean ion mobility array"),
+        ],
+        numeric_type: Some(NumericType::Float32),
+        binary: Some(BinaryData::F32((0..len).map(|i| i as f32 * 0.5).collect())),
+        ..Default::default()
+    });
+    bdal.count = Some(bdal.binary_data_arrays.len());
+    mzml
+}
+
+#[test]
+fn chromatogram_non_standard_array_survives_encode_decode() {
+    let src = mzml(&MZML_CACHE, PATH);
+    let expected = arrays_of_chromatogram(src, 0)[2].binary.clone();
+    assert!(matches!(expected, Some(BinaryData::I64(_))));
+
+    for level in [0u8, 12] {
+        let decoded = decode(&encode(src, level, false)).expect("decode failed");
+        for idx in 0..2 {
+            let got = &arrays_of_chromatogram(&decoded, idx)[2];
+            assert_eq!(got.binary, expected, "level {level}, chromatogram {idx}");
+            assert_eq!(got.numeric_type, Some(NumericType::Int64));
+        }
+    }
+}
+
+#[test]
+fn spectrum_ion_mobility_array_survives_encode_decode() {
+    let src = with_ion_mobility_array(mzml(&MZML_CACHE, PATH).clone());
+    let expected = arrays_of_spectrum(&src, 0)[2].binary.clone();
+
+    let decoded = decode(&encode(&src, 12, true)).expect("decode failed");
+
+    let arrays = arrays_of_spectrum(&decoded, 0);
+    assert_eq!(arrays.len(), 3);
+    assert_eq!(arrays[2].binary, expected);
+    assert_eq!(arrays[2].numeric_type, Some(NumericType::Float32));
+    assert!(
+        arrays[2]
+            .cv_params
+            .iter()
+            .any(|cv| cv.accession.as_deref() == Some("MS:1002816"))
+    );
+
+    assert_eq!(arrays_of_spectrum(&decoded, 1).len(), 2);
+}
+
+#[test]
+fn decoded_auxiliary_arrays_write_back_to_mzml() {
+    let src = with_ion_mobility_array(mzml(&MZML_CACHE, PATH).clone());
+    let decoded = decode(&encode(&src, 3, false)).expect("decode failed");
+
+    let xml = bin_to_mzml(&decoded).expect("bin_to_mzml failed");
+    let reparsed = parse_mzml(xml.as_bytes(), false).expect("reparse failed");
+
+    assert_eq!(
+        arrays_of_chromatogram(&reparsed, 1)[2].binary,
+        arrays_of_chromatogram(&src, 1)[2].binary
+    );
+    assert_eq!(
+        arrays_of_spectrum(&reparsed, 0)[2].binary,
+        arrays_of_spectrum(&src, 0)[2].binary
+    );
+}
+
+/// `bytes` as written before checksums existed: no trailer, kind byte 0.
+fn without_checksums(mut bytes: Vec<u8>) -> Vec<u8> {
+    let footer = &bytes[bytes.len() - CHECKSUM_FOOTER_SIZE..];
+    let (off_trailer, _) = SectionChecksums::locate(footer).unwrap();
+    bytes.truncate(off_trailer as usize);
+    bytes[HDR_OFF_CHECKSUM_KIND] = 0;
+    bytes
+}
+
+#[test]
+fn inflated_aux_block_count_is_an_error() {
+    let src = with_ion_mobility_array(mzml(&MZML_CACHE, PATH).clone());
+    let mut bytes = without_checksums(encode(&src, 3, false));
+    decode(&bytes).expect("decode failed");
+
+    // aux header, then the first container entry: kind (8 bytes), block_count.
+    let at = parse_header(&bytes).unwrap().off_aux_arrays as usize + 16 + 8;
+    bytes[at..at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+
+    let err = decode(&bytes).unwrap_err();
+    assert!(err.contains("container too small"), "{err}");
+    let err = B000Reader::new(Cursor::new(bytes)).err().unwrap();
+    assert!(err.contains("container too small"), "{err}");
+}
+
+fn with_user_params(mut mzml: MzML) -> MzML {
+    let spectrum = &mut mzml.run.spectrum_list.as_mut().unwrap().spectra[0];
+    spectrum.user_params.push(UserParam {
+        name: "collision energy ramp".to_string(),
+        r#type: Some("xsd:double".to_string()),
+        unit_accession: Some("UO:0000266".to_string()),
+        unit_cv_ref: Some("UO".to_string()),
+        unit_name: Some("electronvolt".to_string()),
+        value: Some("0.10".to_string()),
+    });
+    spectrum.user_params.push(UserParam {
+        name: "flag".to_string(),
+        value: Some(String::new()),
+        ..Default::default()
+    });
+
+    spectrum
+        .binary_data_array_list
+        .as_mut()
+        .unwrap()
+        .binary_data_arrays[0]
+        .user_params
+        .push(UserParam {
+            name: "calibrated".to_string(),
+            r#type: Some("xsd:boolean".to_string()),
+            value: Some("true".to_string()),
+            ..Default::default()
+        });
+    mzml
+}
+
+fn narrow_to_f32(ba: &mut BinaryDataArray) {
+    let values: Vec<f32> = match ba.binary.as_ref().unwrap() {
+        BinaryData::F64(v) => v.iter().map(|&x| x as f32).collect(),
+        other => panic!("expected f64 array, got {other:?}"),
+    };
+    for cv in &mut ba.cv_params {
+        if cv.accession.as_deref() == Some("MS:1000523") {
+            *cv = ms_cv("MS:1000521", "32-bit float");
+        }
+    }
+    ba.numeric_type = Some(NumericType::Float32);
+    ba.binary = Some(BinaryData::F32(values));
+}
+
+fn widen_to_f64(ba: &mut BinaryDataArray) {
+    let values: Vec<f64> = match ba.binary.as_ref().unwrap() {
+        BinaryData::F32(v) => v.iter().map(|&x| x as f64).collect(),
+        other => panic!("expected f32 array, got {other:?}"),
+    };
+    for cv in &mut ba.cv_params {
+        if cv.accession.as_deref() == Some("MS:1000521") {
+            *cv = ms_cv("MS:1000523", "64-bit float");
+        }
+    }
+    ba.numeric_type = Some(NumericType::Float64);
+    ba.binary = Some(BinaryData::F64(values));
+}
+
+fn with_mixed_widths(mut mzml: MzML) -> MzML {
+    let spectra = &mut mzml.run.spectrum_list.as_mut().unwrap().spectra;
+    narrow_to_f32(
+        &mut spectra[1]
+            .binary_data_array_list
+            .as_mut()
+            .unwrap()
+            .binary_data_arrays[0],
+    );
+
+    let chromatograms = &mut mzml.run.chromatogram_list.as_mut().unwrap().chromatograms;
+    widen_to_f64(
+        &mut chromatograms[0]
+            .binary_data_array_list
+            .as_mut()
+            .unwrap()
+            .binary_data_arrays[1],
+    );
+    mzml
+}
+
+#[test]
+fn mixed_float_widths_survive_encode_decode() {
+    let src = with_mixed_widths(mzml(&MZML_CACHE, PATH).clone());
+
+    for level in [0u8, 12] {
+        let decoded = decode(&encode(&src, level, false)).expect("decode failed");
+
+        for idx in 0..2 {
+            let got = &arrays_of_spectrum(&decoded, idx)[0];
+            let want = &arrays_of_spectrum(&src, idx)[0];
+            assert_eq!(got.binary, want.binary, "level {level}, spectrum {idx}");
+            assert_eq!(got.numeric_type, want.numeric_type);
+
+            let got = &arrays_of_chromatogram(&decoded, idx)[1];
+            let want = &arrays_of_chromatogram(&src, idx)[1];
+            assert_eq!(got.binary, want.binary, "level {level}, chromatogram {idx}");
+            assert_eq!(got.numeric_type, want.numeric_type);
+        }
+    }
+}
+
+#[test]
+fn alternating_float_widths_share_blocks() {
+    let mut src = mzml(&MZML_CACHE, PATH).clone();
+    let list = src.run.spectrum_list.as_mut().unwrap();
+    let first = list.spectra[0].clone();
+    list.spectra = (0..8)
+        .map(|i| {
+            let mut s = first.clone();
+            s.index = Some(i);
+            s.id = format!("scan={i}");
+            if i % 2 == 1 {
+                narrow_to_f32(
+                    &mut s
+                        .binary_data_array_list
+                        .as_mut()
+                        .unwrap()
+                        .binary_data_arrays[0],
+                );
+            }
+            s
+        })
+        .collect();
+    list.count = Some(8);
+
+    let options = EncodeOptions {
+        block_size: BlockSize::Items(4),
+        ..EncodeOptions::default()
+    };
+    let bytes = encode_with(&src, &options).unwrap();
+    assert_eq!(parse_header(&bytes).unwrap().block_count_spect_x, 2);
+
+    let decoded = decode(&bytes).expect("decode failed");
+    for idx in 0..8 {
+        let got = &arrays_of_spectrum(&decoded, idx)[0];
+        let want = &arrays_of_spectrum(&src, idx)[0];
+        assert_eq!(got.binary, want.binary, "spectrum {idx}");
+    }
+    let mut reader = B000Reader::new(Cursor::new(bytes)).unwrap();
+    for idx in (0..8).rev() {
+        let got = reader.spectrum(idx).unwrap();
+        let got = &got.binary_data_array_list.unwrap().binary_data_arrays[0];
+        assert_eq!(
+            got.binary,
+            arrays_of_spectrum(&src, idx)[0].binary,
+            "spectrum {idx}"
+        );
+    }
+}
+
+fn to_integer(ba: &mut BinaryDataArray, numeric_type: NumericType) {
+    let values: Vec<f64> = match ba.binary.as_ref().unwrap() {
+        BinaryData::F64(v) => v.clone(),
+        BinaryData::F32(v) => v.iter().map(|&x| x as f64).collect(),
+        other => panic!("expected float array, got {other:?}"),
+    };
+    let (accession, name, binary) = match numeric_type {
+        NumericType::Int16 => (
+            "MS:1000518",
+            "16-bit integer",
+            BinaryData::I16(values.iter().map(|&v| v as i16).collect()),
+        ),
+        NumericType::Int32 => (
+            "MS:1000519",
+            "32-bit integer",
+            BinaryData::I32(values.iter().map(|&v| v as i32).collect()),
+        ),
+        _ => (
+            "MS:1000522",
+            "64-bit integer",
+            BinaryData::I64(values.iter().map(|&v| v as i64).collect()),
+        ),
+    };
+    for cv in &mut ba.cv_params {
+        if matches!(cv.accession.as_deref(), Some("MS:1000521" | "MS:1000523")) {
+            *cv = ms_cv(accession, name);
+        }
+    }
+    ba.numeric_type = Some(numeric_type);
+    ba.binary = Some(binary);
+}
+
+fn with_integer_xy_arrays(mut mzml: MzML) -> MzML {
+    let spectra = &mut mzml.run.spectrum_list.as_mut().unwrap().spectra;
+    to_integer(
+        &mut spectra[0]
+            .binary_data_array_list
+            .as_mut()
+            .unwrap()
+            .binary_data_arrays[1],
+        NumericType::Int32,
+    );
+    to_integer(
+        &mut spectra[1]
+            .binary_data_array_list
+            .as_mut()
+            .unwrap()
+            .binary_data_arrays[1],
+        NumericType::Int16,
+    );
+
+    let chromatograms = &mut mzml.run.chromatogram_list.as_mut().unwrap().chromatograms;
+    to_integer(
+        &mut chromatograms[1]
+            .binary_data_array_list
+            .as_mut()
+            .unwrap()
+            .binary_data_arrays[1],
+        NumericType::Int64,
+    );
+    mzml
+}
+
+#[test]
+fn integer_xy_arrays_survive_encode_decode() {
+    let src = with_integer_xy_arrays(mzml(&MZML_CACHE, PATH).clone());
+
+    for (level, f32_compress) in [(0u8, false), (12, false), (12, true)] {
+        let decoded = decode(&encode(&src, level, f32_compress)).expect("decode failed");
+
+        for idx in 0..2 {
+            let got = &arrays_of_spectrum(&decoded, idx)[1];
+            let want = &arrays_of_spectrum(&src, idx)[1];
+            assert_eq!(got.binary, want.binary, "level {level}, spectrum {idx}");
+            assert_eq!(got.numeric_type, want.numeric_type);
+            assert!(
+                !got.cv_params
+                    .iter()
+                    .any(|cv| cv.accession.as_deref() == Some("MS:1000521"))
+            );
+        }
+
+        let got = &arrays_of_chromatogram(&decoded, 1)[1];
+        let want = &arrays_of_chromatogram(&src, 1)[1];
+        assert_eq!(got.binary, want.binary, "level {level}, chromatogram 1");
+        assert_eq!(got.numeric_type, Some(NumericType::Int64));
+    }
+}
+
+fn user_param_text(ups: &[UserParam]) -> String {
+    format!("{ups:?}")
+}
+
+#[test]
+fn user_params_survive_encode_decode() {
+    let src = with_user_params(mzml(&MZML_CACHE, PATH).clone());
+    let decoded = decode(&encode(&src, 12, false)).expect("decode failed");
+
+    let software = &decoded.software_list.as_ref().unwrap().software;
+    let baf2sql = software.iter().find(|s| s.id == "BAF2SQL").unwrap();
+    assert_eq!(baf2sql.user_param.len(), 1);
+    assert_eq!(baf2sql.user_param[0].name, "software name");
+    assert_eq!(baf2sql.user_param[0].value.as_deref(), Some("BAF2SQL"));
+
+    let src_spectrum = &src.run.spectrum_list.as_ref().unwrap().spectra[0];
+    let got_spectrum = &decoded.run.spectrum_list.as_ref().unwrap().spectra[0];
+    assert_eq!(
+        user_param_text(&got_spectrum.user_params),
+        user_param_text(&src_spectrum.user_params)
+    );
+
+    assert_eq!(
+        user_param_text(&arrays_of_spectrum(&decoded, 0)[0].user_params),
+        user_param_text(&arrays_of_spectrum(&src, 0)[0].user_params)
+    );
+    assert!(arrays_of_spectrum(&decoded, 1)[0].user_params.is_empty());
+}
+
+#[test]
+fn decoded_user_params_write_back_to_mzml() {
+    let src = with_user_params(mzml(&MZML_CACHE, PATH).clone());
+    let decoded = decode(&encode(&src, 3, false)).expect("decode failed");
+
+    let xml = bin_to_mzml(&decoded).expect("bin_to_mzml failed");
+    assert!(xml.contains(r#"<userParam name="software name" value="BAF2SQL"/>"#));
+
+    let reparsed = parse_mzml(xml.as_bytes(), false).expect("reparse failed");
+    assert_eq!(
+        user_param_text(&reparsed.run.spectrum_list.as_ref().unwrap().spectra[0].user_params),
+        user_param_text(&src.run.spectrum_list.as_ref().unwrap().spectra[0].user_params)
+    );
+}
+
+#[test]
+fn numeric_looking_cv_values_keep_their_text() {
+    let values = [
+        ("MS:1000504", "1.0"),
+        ("MS:1000505", "0005"),
+        ("MS:1000285", "1e3"),
+        ("MS:1000528", "Infinity"),
+        ("MS:1000527", "-0.50"),
+        ("MS:1000529", "023433"),
+        ("MS:1000796", "42"),
+        ("MS:1000797", "445.1"),
+    ];
+
+    let mut src = mzml(&MZML_CACHE, PATH).clone();
+    let spectrum = &mut src.run.spectrum_list.as_mut().unwrap().spectra[0];
+    for (accession, value) in values {
+        let mut cv = ms_cv(accession, accession);
+        cv.value = Some(value.to_string());
+        spectrum.cv_params.push(cv);
+    }
+
+    let decoded = decode(&encode(&src, 3, false)).expect("decode failed");
+    let cv_params = &decoded.run.spectrum_list.as_ref().unwrap().spectra[0].cv_params;
+    for (accession, value) in values {
+        let cv = cv_params
+            .iter()
+            .rfind(|cv| cv.accession.as_deref() == Some(accession))
+            .unwrap_or_else(|| panic!("{accession} missing"));
+        assert_eq!(cv.value.as_deref(), Some(value), "{accession}");
+    }
+}
+
+#[test]
+fn numeric_looking_attributes_keep_their_text() {
+    let mut src = mzml(&MZML_CACHE, PATH).clone();
+    let spectrum = &mut src.run.spectrum_list.as_mut().unwrap().spectra[0];
+    spectrum.spot_id = Some("1.0000000001".to_string());
+    spectrum.native_id = Some("12345678901".to_string());
+
+    let decoded = decode(&encode(&src, 3, false)).expect("decode failed");
+    let spectrum = &decoded.run.spectrum_list.as_ref().unwrap().spectra[0];
+    assert_eq!(spectrum.spot_id.as_deref(), Some("1.0000000001"));
+    assert_eq!(spectrum.native_id.as_deref(), Some("12345678901"));
+}
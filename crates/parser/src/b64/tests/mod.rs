@@ -1,3 +1,9 @@
+mod block_cache;
+mod checksums;
+mod cv_prefixes;
+mod encode_options;
+mod encode_roundtrip;
+mod reader;
 mod test_mzml_b64;
 mod tiny_msdata_mzml0_99_10_b64;
 mod tiny_msdata_mzml0_99_9_b64;
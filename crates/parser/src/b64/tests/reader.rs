@@ -0,0 +1,188 @@
+use std::{
+    io::{Cursor, Read, Seek, SeekFrom},
+    sync::OnceLock,
+};
+
+use crate::{
+    BinaryData, NumericType,
+    b64::{B000Reader, BlockSize, EncodeOptions, decode, encode_with},
+    mzml::structs::{BinaryDataArray, CvParam, MzML},
+    utilities::test::mzml,
+};
+
+static MZML_CACHE: OnceLock<MzML> = OnceLock::new();
+
+const PATH: &str = "data/mzml/test.mzML";
+
+fn with_ion_mobility_array(mut mzml: MzML) -> MzML {
+    let bdal = mzml.run.spectrum_list.as_mut().unwrap().spectra[1]
+        .binary_data_array_list
+        .as_mut()
+        .unwrap();
+
+    let len = match bdal.binary_data_arrays[0].binary.as_ref().unwrap() {
+        BinaryData::F64(v) => v.len(),
+        BinaryData::F32(v) => v.len(),
+        other => panic!("unexpected m/z array {other:?}"),
+    };
+
+    bdal.binary_data_arrays.push(BinaryDataArray {
+        cv_params: vec![CvParam {
+            cv_ref: Some("MS".to_string()),
+            accession: Some("MS:1002816".to_string()),
+            name: "mean ion mobility array".to_string(),
+            value: Some(String::new()),
+            ..Default::default()
+        }],
+        numeric_type: Some(NumericType::Float32),
+        binary: Some(BinaryData::F32((0..len).map(|i| i as f32 * 0.5).collect())),
+        ..Default::default()
+    });
+    bdal.count = Some(bdal.binary_data_arrays.len());
+    mzml
+}
+
+fn encoded(block_size: BlockSize) -> Vec<u8> {
+    let options = EncodeOptions {
+        block_size,
+        ..EncodeOptions::default()
+    };
+    encode_with(
+        &with_ion_mobility_array(mzml(&MZML_CACHE, PATH).clone()),
+        &options,
+    )
+    .unwrap()
+}
+
+struct CountingReader {
+    inner: Cursor<Vec<u8>>,
+    bytes_read: u64,
+    end_seeks: u32,
+}
+
+impl Read for CountingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.bytes_read += n as u64;
+        Ok(n)
+    }
+}
+
+impl Seek for CountingReader {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        if matches!(pos, SeekFrom::End(_)) {
+            self.end_seeks += 1;
+        }
+        self.inner.seek(pos)
+    }
+}
+
+#[test]
+fn reader_items_match_full_decode() {
+    for block_size in [BlockSize::default(), BlockSize::Items(1)] {
+        let bytes = encoded(block_size);
+        let full = decode(&bytes).unwrap();
+        let mut reader = B000Reader::new(Cursor::new(bytes)).unwrap();
+
+        let spectra = &full.run.spectrum_list.as_ref().unwrap().spectra;
+        assert_eq!(reader.len(), spectra.len());
+        for (i, expected) in spectra.iter().enumerate().rev() {
+            let got = reader.spectrum(i).unwrap();
+            assert_eq!(format!("{got:?}"), format!("{expected:?}"), "spectrum {i}");
+
+            let arrays = reader.spectrum_arrays(i).unwrap();
+            assert_eq!(
+                format!("{arrays:?}"),
+                format!("{:?}", expected.binary_data_array_list.as_ref().unwrap()),
+                "spectrum arrays {i}"
+            );
+        }
+
+        let chromatograms = &full.run.chromatogram_list.as_ref().unwrap().chromatograms;
+        assert_eq!(reader.chromatogram_count(), chromatograms.len());
+        for (i, expected) in chromatograms.iter().enumerate() {
+            let got = reader.chromatogram(i).unwrap();
+            assert_eq!(
+                format!("{got:?}"),
+                format!("{expected:?}"),
+                "chromatogram {i}"
+            );
+        }
+    }
+}
+
+#[test]
+fn reader_fetches_only_needed_blocks() {
+    let bytes = encoded(BlockSize::Items(1));
+    let file_len = bytes.len() as u64;
+    let mut reader = B000Reader::new(CountingReader {
+        inner: Cursor::new(bytes),
+        bytes_read: 0,
+        end_seeks: 0,
+    })
+    .unwrap();
+
+    let header = reader.header();
+    let containers = header.size_container_spect_x + header.size_container_spect_y;
+    let opened = reader.get_ref().bytes_read;
+    assert!(opened < file_len);
+
+    let last = reader.len() - 1;
+    reader.spectrum_arrays(last).unwrap();
+    let fetched = reader.get_ref().bytes_read - opened;
+    assert!(fetched > 0);
+    assert!(
+        fetched < containers,
+        "fetched {fetched} of {containers} container bytes"
+    );
+
+    reader.spectrum_arrays(last).unwrap();
+    assert_eq!(reader.get_ref().bytes_read - opened, fetched);
+}
+
+#[test]
+fn reader_reads_stream_length_once() {
+    let mut reader = B000Reader::new(CountingReader {
+        inner: Cursor::new(encoded(BlockSize::Items(1))),
+        bytes_read: 0,
+        end_seeks: 0,
+    })
+    .unwrap();
+    assert_eq!(reader.get_ref().end_seeks, 1);
+
+    for i in 0..reader.len() {
+        reader.spectrum(i).unwrap();
+    }
+    for i in 0..reader.chromatogram_count() {
+        reader.chromatogram(i).unwrap();
+    }
+    assert_eq!(reader.get_ref().end_seeks, 1);
+}
+
+#[test]
+fn reader_cache_stays_within_budget() {
+    let mut reader = B000Reader::new(Cursor::new(encoded(BlockSize::Items(1)))).unwrap();
+    let header = reader.header();
+    let largest_block = (header.size_container_spect_x + header.size_container_spect_y) as usize;
+
+    for i in 0..reader.len() {
+        reader.spectrum(i).unwrap();
+    }
+    let unbounded = reader.cached_bytes();
+
+    reader.set_cache_budget(0);
+    for i in 0..reader.len() {
+        reader.spectrum(i).unwrap();
+        assert!(reader.cached_bytes() <= largest_block);
+    }
+    assert!(reader.cached_bytes() < unbounded);
+}
+
+#[test]
+fn reader_rejects_out_of_range_items() {
+    let mut reader = B000Reader::new(Cursor::new(encoded(BlockSize::default()))).unwrap();
+    let n = reader.len();
+    assert!(reader.spectrum(n).is_err());
+    assert!(reader.spectrum_arrays(n).is_err());
+    assert!(reader.chromatogram(reader.chromatogram_count()).is_err());
+}
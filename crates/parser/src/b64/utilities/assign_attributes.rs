@@ -47,14 +47,15 @@ where
             Value::Null => None,
             Value::String(s) => Some(MetadatumValue::Text(s.clone())),
             Value::Number(n) => {
+                // Integers beyond 2^53 are not exact in f64, so keep their text.
                 if let Some(u) = n.as_u64() {
                     if u > (1u64 << 53) {
-                        panic!("numeric attribute too large for lossless f64: {u}");
+                        return Some(MetadatumValue::Text(u.to_string()));
                     }
                     Some(MetadatumValue::Number(u as f64))
                 } else if let Some(i) = n.as_i64() {
                     if (i.unsigned_abs() as u64) > (1u64 << 53) {
-                        panic!("numeric attribute too large for lossless f64: {i}");
+                        return Some(MetadatumValue::Text(i.to_string()));
                     }
                     Some(MetadatumValue::Number(i as f64))
                 } else if let Some(f) = n.as_f64() {
@@ -271,6 +272,8 @@ where
             tag_id,
             accession: Some(accession),
             unit_accession: None,
+            cv_ref: None,
+            unit_cv_ref: None,
             value,
         });
         item_index += 1;
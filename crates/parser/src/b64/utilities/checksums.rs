@@ -0,0 +1,281 @@
+use crate::b64::utilities::Header;
+
+/// Header byte holding the checksum kind; 0 means the file has none.
+pub const HDR_OFF_CHECKSUM_KIND: usize = 6;
+pub const CHECKSUM_KIND_CRC32C: u8 = 1;
+
+/// Block directory byte 25, bit 0: bytes 28..32 hold the CRC-32C of the
+/// block as stored.
+pub const BLOCK_FLAG_CRC32C: u8 = 1;
+
+pub const CHECKSUM_ENTRY_SIZE: usize = 24;
+pub const CHECKSUM_FOOTER_SIZE: usize = 16;
+
+const CRC32C_POLY: u32 = 0x82F6_3B78;
+
+const CRC32C_TABLES: [[u32; 256]; 8] = crc32c_tables();
+
+const fn crc32c_tables() -> [[u32; 256]; 8] {
+    let mut tables = [[0u32; 256]; 8];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ CRC32C_POLY
+            } else {
+                crc >> 1
+            };
+            k += 1;
+        }
+        tables[0][i] = crc;
+        i += 1;
+    }
+
+    let mut t = 1;
+    while t < 8 {
+        let mut i = 0;
+        while i < 256 {
+            let prev = tables[t - 1][i];
+            tables[t][i] = (prev >> 8) ^ tables[0][(prev & 0xFF) as usize];
+            i += 1;
+        }
+        t += 1;
+    }
+    tables
+}
+
+/// CRC-32C (Castagnoli) of `bytes`.
+pub fn crc32c(bytes: &[u8]) -> u32 {
+    let t = &CRC32C_TABLES;
+    let mut crc = !0u32;
+
+    let mut chunks = bytes.chunks_exact(8);
+    for c in &mut chunks {
+        let lo = crc ^ u32::from_le_bytes([c[0], c[1], c[2], c[3]]);
+        crc = t[7][(lo & 0xFF) as usize]
+            ^ t[6][((lo >> 8) & 0xFF) as usize]
+            ^ t[5][((lo >> 16) & 0xFF) as usize]
+            ^ t[4][(lo >> 24) as usize]
+            ^ t[3][c[4] as usize]
+            ^ t[2][c[5] as usize]
+            ^ t[1][c[6] as usize]
+            ^ t[0][c[7] as usize];
+    }
+    for &b in chunks.remainder() {
+        crc = (crc >> 8) ^ t[0][((crc ^ b as u32) & 0xFF) as usize];
+    }
+    !crc
+}
+
+/// File region covered by a checksum trailer entry.
+#[repr(u8)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Header = 0,
+    SpectrumIndex = 1,
+    ChromIndex = 2,
+    SpectrumMeta = 3,
+    ChromMeta = 4,
+    GlobalMeta = 5,
+    SpectXDirectory = 6,
+    SpectYDirectory = 7,
+    ChromXDirectory = 8,
+    ChromYDirectory = 9,
+    AuxLayout = 10,
+    AuxDirectory = 11,
+}
+
+impl Section {
+    #[inline]
+    fn from_u8(v: u8) -> Option<Self> {
+        Some(match v {
+            0 => Self::Header,
+            1 => Self::SpectrumIndex,
+            2 => Self::ChromIndex,
+            3 => Self::SpectrumMeta,
+            4 => Self::ChromMeta,
+            5 => Self::GlobalMeta,
+            6 => Self::SpectXDirectory,
+            7 => Self::SpectYDirectory,
+            8 => Self::ChromXDirectory,
+            9 => Self::ChromYDirectory,
+            10 => Self::AuxLayout,
+            11 => Self::AuxDirectory,
+            _ => return None,
+        })
+    }
+
+    #[inline]
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::Header => "header",
+            Self::SpectrumIndex => "spectrum index",
+            Self::ChromIndex => "chromatogram index",
+            Self::SpectrumMeta => "spectrum metadata section",
+            Self::ChromMeta => "chromatogram metadata section",
+            Self::GlobalMeta => "global metadata section",
+            Self::SpectXDirectory => "spectrum x block directory",
+            Self::SpectYDirectory => "spectrum y block directory",
+            Self::ChromXDirectory => "chromatogram x block directory",
+            Self::ChromYDirectory => "chromatogram y block directory",
+            Self::AuxLayout => "aux array layout",
+            Self::AuxDirectory => "aux block directory",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SectionChecksum {
+    section: Section,
+    crc: u32,
+    off: u64,
+    len: u64,
+}
+
+/// Checksum trailer: one CRC-32C per file section, stored after the last
+/// section and located through the footer at the end of the file.
+#[derive(Debug, Clone, Default)]
+pub struct SectionChecksums {
+    entries: Vec<SectionChecksum>,
+}
+
+impl SectionChecksums {
+    /// Records the checksum of `bytes`, stored at `off`; empty sections are
+    /// skipped.
+    #[inline]
+    pub fn push(&mut self, section: Section, off: u64, bytes: &[u8]) {
+        if bytes.is_empty() {
+            return;
+        }
+        self.entries.push(SectionChecksum {
+            section,
+            crc: crc32c(bytes),
+            off,
+            len: bytes.len() as u64,
+        });
+    }
+
+    /// Appends the trailer and its footer to `output`, 8-byte aligned.
+    pub fn write(&self, output: &mut Vec<u8>) {
+        let aligned = output.len().div_ceil(8) * 8;
+        output.resize(aligned, 0);
+        let off_trailer = output.len() as u64;
+
+        for e in &self.entries {
+            output.push(e.section as u8);
+            output.extend_from_slice(&[0u8; 3]);
+            output.extend_from_slice(&e.crc.to_le_bytes());
+            output.extend_from_slice(&e.off.to_le_bytes());
+            output.extend_from_slice(&e.len.to_le_bytes());
+        }
+        let table_crc = crc32c(&output[aligned..]);
+
+        output.extend_from_slice(&off_trailer.to_le_bytes());
+        output.extend_from_slice(&(self.entries.len() as u32).to_le_bytes());
+        output.extend_from_slice(&table_crc.to_le_bytes());
+    }
+
+    /// Offset and byte length of the trailer entries named by `footer`.
+    pub fn locate(footer: &[u8]) -> Result<(u64, usize), String> {
+        if footer.len() != CHECKSUM_FOOTER_SIZE {
+            return Err("checksums: bad footer size".to_string());
+        }
+        let off = u64::from_le_bytes(footer[0..8].try_into().unwrap());
+        let count = u32::from_le_bytes(footer[8..12].try_into().unwrap()) as usize;
+        let len = count
+            .checked_mul(CHECKSUM_ENTRY_SIZE)
+            .ok_or_else(|| "checksums: trailer size overflow".to_string())?;
+        Ok((off, len))
+    }
+
+    /// Parses the trailer `entries` and checks them against the CRC in
+    /// `footer`.
+    pub fn parse(entries: &[u8], footer: &[u8]) -> Result<Self, String> {
+        let (_, len) = Self::locate(footer)?;
+        if entries.len() != len {
+            return Err("checksums: trailer out of bounds".to_string());
+        }
+        let table_crc = u32::from_le_bytes(footer[12..16].try_into().unwrap());
+        if crc32c(entries) != table_crc {
+            return Err("checksum mismatch in checksum trailer".to_string());
+        }
+
+        let mut out = Vec::with_capacity(len / CHECKSUM_ENTRY_SIZE);
+        for raw in entries.chunks_exact(CHECKSUM_ENTRY_SIZE) {
+            let section = Section::from_u8(raw[0])
+                .ok_or_else(|| format!("checksums: unknown section id {}", raw[0]))?;
+            out.push(SectionChecksum {
+                section,
+                crc: u32::from_le_bytes(raw[4..8].try_into().unwrap()),
+                off: u64::from_le_bytes(raw[8..16].try_into().unwrap()),
+                len: u64::from_le_bytes(raw[16..24].try_into().unwrap()),
+            });
+        }
+        Ok(Self { entries: out })
+    }
+
+    /// Trailer of a whole file held in memory. A valid footer is used even
+    /// when `header` does not flag checksums; otherwise the trailer is empty
+    /// for such files.
+    pub fn from_file(bytes: &[u8], header: &Header) -> Result<Self, String> {
+        let found = Self::read_footer(bytes);
+        if found.is_err() && !header.has_checksums() {
+            return Ok(Self::default());
+        }
+        found
+    }
+
+    fn read_footer(bytes: &[u8]) -> Result<Self, String> {
+        let footer_off = bytes
+            .len()
+            .checked_sub(CHECKSUM_FOOTER_SIZE)
+            .ok_or_else(|| "checksums: file too small for footer".to_string())?;
+        let footer = &bytes[footer_off..];
+        let (off, len) = Self::locate(footer)?;
+        let entries = usize::try_from(off)
+            .ok()
+            .and_then(|o| bytes.get(o..o.checked_add(len)?))
+            .ok_or_else(|| "checksums: trailer out of bounds".to_string())?;
+        Self::parse(entries, footer)
+    }
+
+    /// Checks every section against `bytes`, the whole file.
+    pub fn verify_all(&self, bytes: &[u8]) -> Result<(), String> {
+        for e in &self.entries {
+            let section = usize::try_from(e.off)
+                .ok()
+                .zip(usize::try_from(e.len).ok())
+                .and_then(|(o, l)| bytes.get(o..o.checked_add(l)?));
+            check(e, section)?;
+        }
+        Ok(())
+    }
+
+    /// Checks the `section` stored at `off` against `bytes`, read from `off`
+    /// on; sections the trailer does not list pass.
+    pub fn verify(&self, section: Section, off: u64, bytes: &[u8]) -> Result<(), String> {
+        match self
+            .entries
+            .iter()
+            .find(|e| e.section == section && e.off == off)
+        {
+            Some(e) => check(e, usize::try_from(e.len).ok().and_then(|l| bytes.get(..l))),
+            None => Ok(()),
+        }
+    }
+}
+
+#[inline]
+fn check(e: &SectionChecksum, bytes: Option<&[u8]>) -> Result<(), String> {
+    let range = format!("bytes {}..{}", e.off, e.off.saturating_add(e.len));
+    match bytes {
+        Some(b) if crc32c(b) == e.crc => Ok(()),
+        Some(_) => Err(format!(
+            "checksum mismatch in {} ({range})",
+            e.section.name()
+        )),
+        None => Err(format!("{} out of bounds ({range})", e.section.name())),
+    }
+}
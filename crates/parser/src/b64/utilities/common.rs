@@ -4,10 +4,13 @@ use std::{
 };
 
 use crate::{
-    BinaryData, BinaryDataArray, BinaryDataArrayList,
+    BinaryData, BinaryDataArray, BinaryDataArrayList, UserParam,
     decode::{Metadatum, MetadatumValue},
     mzml::{
-        attr_meta::CV_REF_ATTR,
+        attr_meta::{
+            ACC_USER_PARAM_NAME, ACC_USER_PARAM_TYPE, ACC_USER_PARAM_UNIT_NAME,
+            ACC_USER_PARAM_VALUE, CV_REF_ATTR,
+        },
         schema::{SchemaNode, SchemaTree as Schema, TagId},
     },
 };
@@ -156,11 +159,6 @@ pub fn value_to_opt_string(v: &MetadatumValue) -> Option<String> {
     }
 }
 
-#[inline]
-pub fn is_cv_prefix(p: &str) -> bool {
-    matches!(p, "MS" | "UO" | "NCIT" | "PEFF")
-}
-
 #[inline]
 pub fn unit_cv_ref(unit_accession: Option<&str>) -> Option<String> {
     unit_accession
@@ -168,8 +166,44 @@ pub fn unit_cv_ref(unit_accession: Option<&str>) -> Option<String> {
         .map(|(pref, _)| pref.to_owned())
 }
 
+/// <userParam>
 #[inline]
-fn b000_tail(acc: &str) -> Option<u32> {
+pub fn push_user_param_field(user_params: &mut Vec<UserParam>, m: &Metadatum) {
+    let Some(tail) = m
+        .accession
+        .as_deref()
+        .and_then(|a| a.split_once(':'))
+        .and_then(|(_, t)| t.parse::<u32>().ok())
+    else {
+        return;
+    };
+
+    if tail == ACC_USER_PARAM_NAME {
+        user_params.push(UserParam {
+            name: value_to_opt_string(&m.value).unwrap_or_default(),
+            r#type: None,
+            unit_accession: m.unit_accession.clone(),
+            unit_cv_ref: unit_cv_ref(m.unit_accession.as_deref()),
+            unit_name: None,
+            value: None,
+        });
+        return;
+    }
+
+    let Some(up) = user_params.last_mut() else {
+        return;
+    };
+
+    match tail {
+        ACC_USER_PARAM_TYPE => up.r#type = value_to_opt_string(&m.value),
+        ACC_USER_PARAM_VALUE => up.value = Some(value_to_opt_string(&m.value).unwrap_or_default()),
+        ACC_USER_PARAM_UNIT_NAME => up.unit_name = value_to_opt_string(&m.value),
+        _ => {}
+    }
+}
+
+#[inline]
+pub fn b000_tail(acc: &str) -> Option<u32> {
     let (pref, tail) = acc.split_once(':')?;
     if pref != CV_REF_ATTR {
         return None;
@@ -215,14 +249,7 @@ pub fn get_attr_text(rows: &[&Metadatum], accession_tail: u32) -> Option<String>
 
         return match &m.value {
             MetadatumValue::Text(s) => Some(s.clone()),
-            MetadatumValue::Number(n) => Some({
-                let r = n.round();
-                if (*n - r).abs() < 1e-9 {
-                    format!("{}", r as i64)
-                } else {
-                    format!("{}", n)
-                }
-            }),
+            MetadatumValue::Number(n) => Some(n.to_string()),
             MetadatumValue::Empty => None,
         };
     }
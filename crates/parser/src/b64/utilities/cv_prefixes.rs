@@ -0,0 +1,157 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    b64::utilities::common::{b000_tail, parse_accession_tail},
+    decode::{Metadatum, MetadatumValue},
+    mzml::{
+        attr_meta::{
+            ACC_ATTR_CV_PREFIX, CV_CODE_B000, CV_CODE_TABLE_FIRST, CV_CODE_UNKNOWN,
+            CV_CODE_USER_PARAM, attr_cv_param, cv_ref_code_from_str, format_accession,
+        },
+        structs::CvParam,
+    },
+};
+
+/// How accessions stored under `code` are written back.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct CvPrefix {
+    code: u8,
+    /// `cvRef`, when it is not the accession prefix.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    cv_ref: Option<String>,
+    /// Accession text ahead of the trailing number, e.g. `NCIT:C` or `BTO:`.
+    stem: String,
+    /// Zero-padded width of the number (0 for none); `None` when the
+    /// accession has no trailing number.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    width: Option<usize>,
+}
+
+impl CvPrefix {
+    #[inline]
+    fn accession(&self, tail: u32) -> String {
+        match self.width {
+            Some(w) => format!("{}{tail:0w$}", self.stem),
+            None => self.stem.clone(),
+        }
+    }
+}
+
+/// Per-file table of the accession formats the fixed codes of
+/// [`cv_ref_code_from_str`] cannot reproduce, stored as
+/// [`ACC_ATTR_CV_PREFIX`] rows in the global metadata.
+#[derive(Debug, Clone, Default)]
+pub struct CvPrefixes {
+    entries: Vec<CvPrefix>,
+}
+
+impl CvPrefixes {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Ref code and accession number for `accession`, adding a table entry
+    /// when no fixed code writes it back as given.
+    pub fn code_of(&mut self, cv_ref: Option<&str>, accession: Option<&str>) -> (u8, u32) {
+        let prefix = accession.and_then(|a| a.split_once(':')).map(|(p, _)| p);
+        let code = cv_ref_code_from_str(prefix.or(cv_ref));
+        let tail = parse_accession_tail(accession);
+
+        let Some(acc) = accession else {
+            return (code, tail);
+        };
+        if code == CV_CODE_B000 || code == CV_CODE_USER_PARAM {
+            return (code, tail);
+        }
+        if code != CV_CODE_UNKNOWN
+            && cv_ref.is_none_or(|r| Some(r) == prefix)
+            && format_accession(code, tail).as_deref() == Some(acc)
+        {
+            return (code, tail);
+        }
+
+        self.table_code(cv_ref.filter(|&r| Some(r) != prefix), acc)
+            .unwrap_or((code, tail))
+    }
+
+    fn table_code(&mut self, cv_ref: Option<&str>, acc: &str) -> Option<(u8, u32)> {
+        let stem = acc.trim_end_matches(|c: char| c.is_ascii_digit());
+        let digits = &acc[stem.len()..];
+        let (tail, width) = if digits.is_empty() {
+            (0, None)
+        } else {
+            let width = if digits.starts_with('0') {
+                digits.len()
+            } else {
+                0
+            };
+            (digits.parse::<u32>().ok()?, Some(width))
+        };
+
+        if let Some(e) = self
+            .entries
+            .iter()
+            .find(|e| e.cv_ref.as_deref() == cv_ref && e.stem == stem && e.width == width)
+        {
+            return Some((e.code, tail));
+        }
+
+        let code = u8::try_from(self.entries.len())
+            .ok()
+            .and_then(|n| CV_CODE_TABLE_FIRST.checked_add(n))
+            .filter(|&c| c != CV_CODE_UNKNOWN)?;
+        self.entries.push(CvPrefix {
+            code,
+            cv_ref: cv_ref.map(str::to_owned),
+            stem: stem.to_owned(),
+            width,
+        });
+        Some((code, tail))
+    }
+
+    #[inline]
+    fn entry(&self, code: u8) -> Option<&CvPrefix> {
+        if code < CV_CODE_TABLE_FIRST || code == CV_CODE_UNKNOWN {
+            return None;
+        }
+        self.entries.iter().find(|e| e.code == code)
+    }
+
+    /// Accession stored as `code` and `tail`.
+    #[inline]
+    pub fn accession(&self, code: u8, tail: u32) -> Option<String> {
+        match self.entry(code) {
+            Some(e) => Some(e.accession(tail)),
+            None => format_accession(code, tail),
+        }
+    }
+
+    /// `cvRef` of `code` when it is not the accession prefix.
+    #[inline]
+    pub fn cv_ref(&self, code: u8) -> Option<String> {
+        self.entry(code).and_then(|e| e.cv_ref.clone())
+    }
+
+    /// One [`ACC_ATTR_CV_PREFIX`] row per entry.
+    pub fn rows(&self) -> Vec<CvParam> {
+        self.entries
+            .iter()
+            .filter_map(|e| serde_json::to_string(e).ok())
+            .map(|json| attr_cv_param(ACC_ATTR_CV_PREFIX, &json))
+            .collect()
+    }
+
+    /// Table stored in the [`ACC_ATTR_CV_PREFIX`] rows among `metadata`.
+    pub fn from_metadata(metadata: &[Metadatum]) -> Self {
+        let entries = metadata
+            .iter()
+            .filter(|m| m.accession.as_deref().and_then(b000_tail) == Some(ACC_ATTR_CV_PREFIX))
+            .filter_map(|m| match &m.value {
+                MetadatumValue::Text(json) => serde_json::from_str(json).ok(),
+                _ => None,
+            })
+            .collect();
+        Self { entries }
+    }
+}
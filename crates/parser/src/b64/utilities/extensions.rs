@@ -0,0 +1,248 @@
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    b64::utilities::common::b000_tail,
+    decode::{Metadatum, MetadatumValue},
+    mzml::{
+        attr_meta::{ACC_ATTR_EXTENSIONS, attr_cv_param},
+        structs::*,
+    },
+};
+
+/// Element structs carrying [`Extensions`], addressed by paths of mzML
+/// element names such as `scanList/scan[0]/scanWindowList`.
+///
+/// Spectra and chromatograms are walked from the item itself, not from
+/// [`MzML`], so their extensions are stored with the item.
+pub trait Extended {
+    /// Calls `f` with the path, relative to `self`, of every element under
+    /// `self` (itself included) whose extensions are not empty.
+    fn visit_extensions(&self, path: &mut String, f: &mut dyn FnMut(&str, &Extensions));
+
+    /// Extensions of the element at `segments`; optional elements on the way
+    /// are created, out-of-range list indexes give `None`.
+    fn extensions_at(&mut self, segments: &[&str]) -> Option<&mut Extensions>;
+}
+
+/// Value of an [`ACC_ATTR_EXTENSIONS`] row, as JSON.
+#[derive(Serialize, Deserialize)]
+struct ExtensionsRow {
+    path: String,
+    #[serde(flatten)]
+    extensions: Extensions,
+}
+
+/// One [`ACC_ATTR_EXTENSIONS`] row per element under `root` with extensions.
+pub fn extension_rows<T: Extended>(root: &T) -> Vec<CvParam> {
+    let mut rows = Vec::new();
+    root.visit_extensions(&mut String::new(), &mut |path, ext| {
+        let row = ExtensionsRow {
+            path: path.to_string(),
+            extensions: ext.clone(),
+        };
+        if let Ok(json) = serde_json::to_string(&row) {
+            rows.push(attr_cv_param(ACC_ATTR_EXTENSIONS, &json));
+        }
+    });
+    rows
+}
+
+/// Puts the extensions of the [`ACC_ATTR_EXTENSIONS`] rows among `metadata`
+/// back under `root`; rows whose element no longer exists are dropped.
+pub fn apply_extension_rows<T: Extended>(root: &mut T, metadata: &[&Metadatum]) {
+    for m in metadata {
+        if m.accession.as_deref().and_then(b000_tail) != Some(ACC_ATTR_EXTENSIONS) {
+            continue;
+        }
+        let MetadatumValue::Text(json) = &m.value else {
+            continue;
+        };
+        let Ok(row) = serde_json::from_str::<ExtensionsRow>(json) else {
+            continue;
+        };
+        let segments: Vec<&str> = row.path.split('/').filter(|s| !s.is_empty()).collect();
+        if let Some(slot) = root.extensions_at(&segments) {
+            *slot = row.extensions;
+        }
+    }
+}
+
+/// Runs `visit` with `name` (and `[index]`) appended to `path`.
+#[inline]
+fn descend(path: &mut String, name: &str, index: Option<usize>, visit: impl FnOnce(&mut String)) {
+    let len = path.len();
+    if !path.is_empty() {
+        path.push('/');
+    }
+    path.push_str(name);
+    if let Some(i) = index {
+        path.push_str(&format!("[{i}]"));
+    }
+    visit(path);
+    path.truncate(len);
+}
+
+/// `name` or `name[index]`.
+#[inline]
+fn split_segment(segment: &str) -> Option<(&str, Option<usize>)> {
+    match segment.strip_suffix(']') {
+        Some(s) => {
+            let (name, index) = s.split_once('[')?;
+            Some((name, Some(index.parse().ok()?)))
+        }
+        None => Some((segment, None)),
+    }
+}
+
+macro_rules! extended {
+    ($ty:ty { $($kind:ident $field:ident => $name:literal),* $(,)? }) => {
+        impl Extended for $ty {
+            fn visit_extensions(
+                &self,
+                path: &mut String,
+                f: &mut dyn FnMut(&str, &Extensions),
+            ) {
+                if !self.extensions.is_empty() {
+                    f(path, &self.extensions);
+                }
+                $(extended!(@visit $kind self.$field, $name, path, f);)*
+            }
+
+            #[allow(unused_variables)]
+            fn extensions_at(&mut self, segments: &[&str]) -> Option<&mut Extensions> {
+                let Some((first, rest)) = segments.split_first() else {
+                    return Some(&mut self.extensions);
+                };
+                let (name, index) = split_segment(first)?;
+                match name {
+                    $($name => extended!(@at $kind self.$field, index, rest),)*
+                    _ => None,
+                }
+            }
+        }
+    };
+    (@visit one $v:expr, $name:expr, $path:ident, $f:ident) => {
+        descend($path, $name, None, |p| $v.visit_extensions(p, $f))
+    };
+    (@visit opt $v:expr, $name:expr, $path:ident, $f:ident) => {
+        if let Some(v) = &$v {
+            descend($path, $name, None, |p| v.visit_extensions(p, $f))
+        }
+    };
+    (@visit vec $v:expr, $name:expr, $path:ident, $f:ident) => {
+        for (i, v) in $v.iter().enumerate() {
+            descend($path, $name, Some(i), |p| v.visit_extensions(p, $f))
+        }
+    };
+    (@at one $v:expr, $index:ident, $rest:ident) => {
+        $v.extensions_at($rest)
+    };
+    (@at opt $v:expr, $index:ident, $rest:ident) => {
+        $v.get_or_insert_with(Default::default).extensions_at($rest)
+    };
+    (@at vec $v:expr, $index:ident, $rest:ident) => {
+        $v.get_mut($index?)?.extensions_at($rest)
+    };
+}
+
+extended!(MzML {
+    opt cv_list => "cvList",
+    one file_description => "fileDescription",
+    opt referenceable_param_group_list => "referenceableParamGroupList",
+    opt sample_list => "sampleList",
+    opt instrument_list => "instrumentConfigurationList",
+    opt software_list => "softwareList",
+    opt data_processing_list => "dataProcessingList",
+    opt scan_settings_list => "scanSettingsList",
+    one run => "run",
+});
+extended!(CvList {});
+extended!(FileDescription {
+    one file_content => "fileContent",
+    one source_file_list => "sourceFileList",
+    vec contacts => "contact",
+});
+extended!(FileContent {});
+extended!(SourceFileList { vec source_file => "sourceFile" });
+extended!(SourceFile {});
+extended!(Contact {});
+extended!(ReferenceableParamGroupList {
+    vec referenceable_param_groups => "referenceableParamGroup"
+});
+extended!(ReferenceableParamGroup {});
+extended!(SampleList { vec samples => "sample" });
+extended!(Sample {});
+extended!(InstrumentList { vec instrument => "instrumentConfiguration" });
+extended!(Instrument { opt component_list => "componentList" });
+extended!(ComponentList {
+    vec source => "source",
+    vec analyzer => "analyzer",
+    vec detector => "detector",
+});
+extended!(Source {});
+extended!(Analyzer {});
+extended!(Detector {});
+extended!(SoftwareList { vec software => "software" });
+extended!(Software {});
+extended!(DataProcessingList {
+    vec data_processing => "dataProcessing"
+});
+extended!(DataProcessing {
+    vec processing_method => "processingMethod"
+});
+extended!(ProcessingMethod {});
+extended!(ScanSettingsList { vec scan_settings => "scanSettings" });
+extended!(ScanSettings {
+    opt source_file_ref_list => "sourceFileRefList",
+    opt target_list => "targetList",
+});
+extended!(SourceFileRefList {});
+extended!(TargetList { vec targets => "target" });
+extended!(Target {});
+extended!(Run {
+    opt source_file_ref_list => "sourceFileRefList",
+    opt spectrum_list => "spectrumList",
+    opt chromatogram_list => "chromatogramList",
+});
+// The items themselves are walked one by one.
+extended!(SpectrumList {});
+extended!(ChromatogramList {});
+extended!(Spectrum {
+    opt spectrum_description => "spectrumDescription",
+    opt scan_list => "scanList",
+    opt precursor_list => "precursorList",
+    opt product_list => "productList",
+    opt binary_data_array_list => "binaryDataArrayList",
+});
+extended!(SpectrumDescription {
+    opt scan_list => "scanList",
+    opt precursor_list => "precursorList",
+    opt product_list => "productList",
+});
+extended!(ScanList { vec scans => "scan" });
+extended!(Scan { opt scan_window_list => "scanWindowList" });
+extended!(ScanWindowList { vec scan_windows => "scanWindow" });
+extended!(ScanWindow {});
+extended!(PrecursorList { vec precursors => "precursor" });
+extended!(Precursor {
+    opt isolation_window => "isolationWindow",
+    opt selected_ion_list => "selectedIonList",
+    opt activation => "activation",
+});
+extended!(IsolationWindow {});
+extended!(SelectedIonList { vec selected_ions => "selectedIon" });
+extended!(SelectedIon {});
+extended!(Activation {});
+extended!(ProductList { vec products => "product" });
+extended!(Product {
+    opt isolation_window => "isolationWindow"
+});
+extended!(BinaryDataArrayList {
+    vec binary_data_arrays => "binaryDataArray"
+});
+extended!(BinaryDataArray {});
+extended!(Chromatogram {
+    opt precursor => "precursor",
+    opt product => "product",
+    opt binary_data_array_list => "binaryDataArrayList",
+});
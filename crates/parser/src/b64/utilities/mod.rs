@@ -1,8 +1,12 @@
 pub mod parse_header;
 pub use parse_header::{Header, parse_header};
+pub mod checksums;
 pub mod common;
+pub use checksums::{Section, SectionChecksums, crc32c};
 pub mod parse_metadata;
-pub use parse_metadata::parse_metadata;
+pub use parse_metadata::{MetadataLayout, parse_metadata, parse_metadata_with};
+pub mod cv_prefixes;
+pub use cv_prefixes::CvPrefixes;
 pub mod parse_binary_array_list;
 pub use parse_binary_array_list::parse_binary_data_array_list;
 pub mod parse_cv_and_user_params;
@@ -15,9 +19,13 @@ pub use parse_precursor_list::parse_precursor_list;
 pub mod parse_product_list;
 pub use parse_product_list::parse_product_list;
 pub mod parse_spectrum_list;
-pub use parse_spectrum_list::parse_spectrum_list;
+pub use parse_spectrum_list::{
+    parse_spectrum_item, parse_spectrum_list, parse_spectrum_list_defaults,
+};
 pub mod parse_chromatogram_list;
-pub use parse_chromatogram_list::parse_chromatogram_list;
+pub use parse_chromatogram_list::{
+    parse_chromatogram_item, parse_chromatogram_list, parse_chromatogram_list_defaults,
+};
 pub mod assign_attributes;
 pub use assign_attributes::assign_attributes;
 pub mod parse_file_description;
@@ -37,6 +45,8 @@ pub mod parse_scan_settings_list;
 pub use parse_scan_settings_list::parse_scan_settings_list;
 pub mod parse_cv_list;
 pub use parse_cv_list::parse_cv_list;
+pub mod extensions;
+pub use extensions::{Extended, apply_extension_rows, extension_rows};
 
 #[cfg(test)]
 mod tests;
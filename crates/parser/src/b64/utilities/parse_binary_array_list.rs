@@ -1,11 +1,11 @@
 use crate::{
-    BinaryDataArray, BinaryDataArrayList, CvParam, NumericType, UserParam,
-    b64::utilities::common::{is_cv_prefix, unit_cv_ref, value_to_opt_string},
+    BinaryDataArray, BinaryDataArrayList, CvParam, NumericType,
+    b64::utilities::common::{push_user_param_field, unit_cv_ref, value_to_opt_string},
     decode::{Metadatum, MetadatumValue},
     mzml::{
         attr_meta::{
             ACC_ATTR_ARRAY_LENGTH, ACC_ATTR_COUNT, ACC_ATTR_DATA_PROCESSING_REF,
-            ACC_ATTR_DEFAULT_ARRAY_LENGTH, ACC_ATTR_ENCODED_LENGTH, CV_REF_ATTR,
+            ACC_ATTR_DEFAULT_ARRAY_LENGTH, ACC_ATTR_ENCODED_LENGTH, CV_REF_ATTR, CV_REF_USER_PARAM,
         },
         cv_table,
         schema::TagId,
@@ -67,6 +67,7 @@ pub fn parse_binary_data_array_list(metadata: &[&Metadatum]) -> Option<BinaryDat
         return Some(BinaryDataArrayList {
             count: count.or(Some(0)),
             binary_data_arrays: Vec::new(),
+            ..Default::default()
         });
     }
 
@@ -86,6 +87,7 @@ pub fn parse_binary_data_array_list(metadata: &[&Metadatum]) -> Option<BinaryDat
     Some(BinaryDataArrayList {
         count: count.or(Some(binary_data_arrays.len())),
         binary_data_arrays,
+        ..Default::default()
     })
 }
 
@@ -101,6 +103,7 @@ fn new_binary_data_array() -> BinaryDataArray {
         user_params: Vec::with_capacity(2),
         numeric_type: None,
         binary: None,
+        ..Default::default()
     }
 }
 
@@ -109,11 +112,12 @@ fn apply_binary_data_array_metadatum(out: &mut BinaryDataArray, m: &Metadatum) {
     let Some(acc) = m.accession.as_deref() else {
         return;
     };
-    let Some((prefix, tail)) = acc.split_once(':') else {
-        return;
+    let (prefix, tail) = match acc.split_once(':') {
+        Some((p, t)) => (Some(p), t),
+        None => (None, acc),
     };
 
-    if prefix == CV_REF_ATTR {
+    if prefix == Some(CV_REF_ATTR) {
         let Ok(tail_u32) = tail.parse::<u32>() else {
             return;
         };
@@ -126,63 +130,59 @@ fn apply_binary_data_array_metadatum(out: &mut BinaryDataArray, m: &Metadatum) {
         return;
     }
 
+    if prefix == Some(CV_REF_USER_PARAM) {
+        push_user_param_field(&mut out.user_params, m);
+        return;
+    }
+
     let value = value_to_opt_string(&m.value);
     let unit_accession_str = m.unit_accession.as_deref();
-    let unit_cv_ref = unit_cv_ref(unit_accession_str);
-
-    if is_cv_prefix(prefix) {
-        if prefix == "MS" {
-            let new_ty = match tail {
-                "1000519" => Some(NumericType::Int32),
-                "1000521" => Some(NumericType::Float32),
-                "1000522" => Some(NumericType::Int64),
-                "1000523" => Some(NumericType::Float64),
-                _ => None,
-            };
+    let unit_cv_ref = m
+        .unit_cv_ref
+        .clone()
+        .or_else(|| unit_cv_ref(unit_accession_str));
+
+    if prefix == Some("MS") {
+        let new_ty = match tail {
+            "1000519" => Some(NumericType::Int32),
+            "1000521" => Some(NumericType::Float32),
+            "1000522" => Some(NumericType::Int64),
+            "1000523" => Some(NumericType::Float64),
+            _ => None,
+        };
 
-            if let Some(nt) = new_ty {
-                out.numeric_type = match out.numeric_type {
-                    None => Some(nt),
-                    Some(cur) if cur == nt => Some(cur),
-                    Some(_) => None,
-                };
-            }
+        if let Some(nt) = new_ty {
+            out.numeric_type = match out.numeric_type {
+                None => Some(nt),
+                Some(cur) if cur == nt => Some(cur),
+                Some(_) => None,
+            };
         }
+    }
 
-        let unit_name = if let Some(ua) = unit_accession_str {
-            cv_table::get(ua)
-                .and_then(|v| v.as_str())
-                .map(str::to_owned)
-        } else {
-            None
-        };
-
-        let name = cv_table::get(acc)
+    let unit_name = if let Some(ua) = unit_accession_str {
+        cv_table::get(ua)
             .and_then(|v| v.as_str())
-            .unwrap_or(acc)
-            .to_owned();
-
-        let unit_accession = m.unit_accession.clone();
-        out.cv_params.push(CvParam {
-            cv_ref: Some(prefix.to_owned()),
-            accession: Some(acc.to_owned()),
-            name,
-            value,
-            unit_cv_ref,
-            unit_name,
-            unit_accession,
-        });
+            .map(str::to_owned)
     } else {
-        let unit_accession = m.unit_accession.clone();
-        out.user_params.push(UserParam {
-            name: acc.to_string(),
-            r#type: None,
-            unit_accession,
-            unit_cv_ref,
-            unit_name: None,
-            value,
-        });
-    }
+        None
+    };
+
+    let name = cv_table::get(acc)
+        .and_then(|v| v.as_str())
+        .unwrap_or(acc)
+        .to_owned();
+
+    let unit_accession = m.unit_accession.clone();
+    out.cv_params.push(CvParam {
+        cv_ref: m.cv_ref.clone().or_else(|| prefix.map(str::to_owned)),
+        accession: Some(acc.to_owned()),
+        name,
+        value,
+        unit_cv_ref,
+        unit_name,
+        unit_accession,
+    });
 }
 
 #[inline]
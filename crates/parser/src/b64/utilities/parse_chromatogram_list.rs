@@ -3,6 +3,7 @@ use std::collections::{HashMap, HashSet};
 use crate::{
     Chromatogram, ChromatogramList,
     b64::utilities::{
+        apply_extension_rows,
         common::{
             ChildIndex, get_attr_text, get_attr_u32, ordered_unique_owner_ids, xy_lengths_from_bdal,
         },
@@ -161,9 +162,66 @@ pub fn parse_chromatogram_list(
         count: count_attr.or(Some(chromatograms.len())),
         default_data_processing_ref,
         chromatograms,
+        ..Default::default()
     })
 }
 
+/// `<chromatogramList>` default `dataProcessingRef`.
+#[inline]
+pub fn parse_chromatogram_list_defaults(metadata: &[&Metadatum]) -> Option<String> {
+    let chromatogram_list_rows: Vec<&Metadatum> = metadata
+        .iter()
+        .copied()
+        .filter(|m| m.tag_id == TagId::ChromatogramList)
+        .collect();
+    get_attr_text(
+        &chromatogram_list_rows,
+        ACC_ATTR_DEFAULT_DATA_PROCESSING_REF,
+    )
+}
+
+/// <chromatogram> from the rows of a single item.
+#[inline]
+pub fn parse_chromatogram_item(
+    metadata: &[&Metadatum],
+    fallback_index: u32,
+    default_data_processing_ref: Option<&str>,
+) -> Option<Chromatogram> {
+    let chromatogram_id = metadata
+        .iter()
+        .find(|m| m.tag_id == TagId::Chromatogram)
+        .map(|m| m.owner_id)?;
+
+    let child_index = ChildIndex::new_from_refs(metadata);
+    let has_other_root = metadata
+        .iter()
+        .any(|m| m.tag_id == TagId::Chromatogram && m.owner_id != chromatogram_id);
+
+    if has_other_root {
+        let keep = subtree_owner_ids(chromatogram_id, &child_index);
+        let scoped: Vec<&Metadatum> = metadata
+            .iter()
+            .copied()
+            .filter(|m| keep.contains(&m.owner_id))
+            .collect();
+        return Some(parse_chromatogram(
+            &scoped,
+            chromatogram_id,
+            &child_index,
+            fallback_index,
+            default_data_processing_ref,
+        ));
+    }
+
+    Some(parse_chromatogram(
+        metadata,
+        chromatogram_id,
+        &child_index,
+        fallback_index,
+        default_data_processing_ref,
+    ))
+}
+
 /// <chromatogram>
 #[inline]
 fn parse_chromatogram(
@@ -211,7 +269,7 @@ fn parse_chromatogram(
     let precursor = parse_precursor_for_chromatogram(metadata, chromatogram_id, child_index);
     let product = parse_product_for_chromatogram(metadata, chromatogram_id, child_index);
 
-    Chromatogram {
+    let mut chromatogram = Chromatogram {
         id,
         native_id,
         index,
@@ -223,7 +281,10 @@ fn parse_chromatogram(
         precursor,
         product,
         binary_data_array_list,
-    }
+        ..Default::default()
+    };
+    apply_extension_rows(&mut chromatogram, &chromatogram_rows);
+    chromatogram
 }
 
 /// <precursor>
@@ -253,6 +314,7 @@ fn parse_precursor_for_chromatogram(
         isolation_window: parse_isolation_window(metadata, precursor_id, child_index),
         selected_ion_list: parse_selected_ion_list(metadata, precursor_id, child_index),
         activation: parse_activation(metadata, precursor_id, child_index),
+        ..Default::default()
     })
 }
 
@@ -270,6 +332,7 @@ fn parse_product_for_chromatogram(
         source_file_ref: None,
         external_spectrum_id: None,
         isolation_window: parse_isolation_window(metadata, product_id, child_index),
+        ..Default::default()
     })
 }
 
@@ -297,6 +360,7 @@ fn parse_isolation_window(
         referenceable_param_group_refs: Vec::new(),
         cv_params,
         user_params,
+        ..Default::default()
     })
 }
 
@@ -343,12 +407,14 @@ fn parse_selected_ion_list(
             referenceable_param_group_refs: Vec::new(),
             cv_params,
             user_params,
+            ..Default::default()
         });
     }
 
     Some(SelectedIonList {
         count: count_attr.or(Some(selected_ions.len())),
         selected_ions,
+        ..Default::default()
     })
 }
 
@@ -376,6 +442,7 @@ fn parse_activation(
         referenceable_param_group_refs: Vec::new(),
         cv_params,
         user_params,
+        ..Default::default()
     })
 }
 
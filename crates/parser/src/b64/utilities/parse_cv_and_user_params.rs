@@ -1,8 +1,11 @@
 use crate::{
     CvParam, UserParam,
-    b64::utilities::common::{OwnerRows, is_cv_prefix, unit_cv_ref, value_to_opt_string},
+    b64::utilities::common::{OwnerRows, push_user_param_field, unit_cv_ref, value_to_opt_string},
     decode::Metadatum,
-    mzml::{attr_meta::CV_REF_ATTR, cv_table},
+    mzml::{
+        attr_meta::{CV_REF_ATTR, CV_REF_USER_PARAM},
+        cv_table,
+    },
 };
 
 #[inline]
@@ -40,18 +43,23 @@ pub fn parse_cv_and_user_params(metadata: &[&Metadatum]) -> (Vec<CvParam>, Vec<U
         let Some(acc) = m.accession.as_deref() else {
             continue;
         };
-        let Some((prefix, _)) = acc.split_once(':') else {
-            continue;
-        };
+        let prefix = acc.split_once(':').map(|(p, _)| p);
 
-        if prefix == CV_REF_ATTR {
+        if prefix == Some(CV_REF_ATTR) {
+            continue;
+        }
+        if prefix == Some(CV_REF_USER_PARAM) {
+            push_user_param_field(&mut user_params, m);
             continue;
         }
 
         let value = value_to_opt_string(&m.value);
 
         let unit_accession_str = m.unit_accession.as_deref();
-        let unit_cv_ref = unit_cv_ref(unit_accession_str);
+        let unit_cv_ref = m
+            .unit_cv_ref
+            .clone()
+            .or_else(|| unit_cv_ref(unit_accession_str));
 
         let name = cv_table::get(acc)
             .and_then(|v| v.as_str())
@@ -62,28 +70,15 @@ pub fn parse_cv_and_user_params(metadata: &[&Metadatum]) -> (Vec<CvParam>, Vec<U
             .and_then(|ua| cv_table::get(ua).and_then(|v| v.as_str()))
             .map(str::to_owned);
 
-        let unit_accession = m.unit_accession.clone();
-
-        if is_cv_prefix(prefix) {
-            cv_params.push(CvParam {
-                cv_ref: Some(prefix.to_owned()),
-                accession: Some(acc.to_owned()),
-                name,
-                value,
-                unit_cv_ref,
-                unit_name,
-                unit_accession,
-            });
-        } else {
-            user_params.push(UserParam {
-                name,
-                r#type: None,
-                unit_accession,
-                unit_cv_ref,
-                unit_name: None, // keep existing behavior (don’t populate unit_name for user params)
-                value,
-            });
-        }
+        cv_params.push(CvParam {
+            cv_ref: m.cv_ref.clone().or_else(|| prefix.map(str::to_owned)),
+            accession: Some(acc.to_owned()),
+            name,
+            value,
+            unit_cv_ref,
+            unit_name,
+            unit_accession: m.unit_accession.clone(),
+        });
     }
 
     (cv_params, user_params)
@@ -62,6 +62,7 @@ pub fn parse_cv_list(metadata: &[&Metadatum], child_index: &ChildIndex) -> Optio
     Some(CvList {
         count: Some(cv.len()),
         cv,
+        ..Default::default()
     })
 }
 
@@ -70,6 +70,7 @@ pub fn parse_data_processing_list(
     Some(DataProcessingList {
         count: Some(data_processing.len()),
         data_processing,
+        ..Default::default()
     })
 }
 
@@ -91,6 +92,7 @@ fn parse_data_processing(
         id,
         software_ref,
         processing_method,
+        ..Default::default()
     }
 }
 
@@ -137,6 +139,7 @@ fn parse_processing_method(ctx: &ParseCtx<'_>, processing_method_id: u32) -> Pro
         referenceable_param_group_ref,
         cv_param,
         user_param,
+        ..Default::default()
     }
 }
 
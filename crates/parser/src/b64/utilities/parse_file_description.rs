@@ -93,6 +93,7 @@ pub fn parse_file_description(
         file_content,
         source_file_list,
         contacts,
+        ..Default::default()
     })
 }
 
@@ -121,6 +122,7 @@ fn parse_file_content(ctx: &ParseCtx<'_>, file_desc_id: u32) -> FileContent {
         referenceable_param_group_refs: Vec::<ReferenceableParamGroupRef>::new(),
         cv_params,
         user_params,
+        ..Default::default()
     }
 }
 
@@ -133,6 +135,7 @@ fn parse_source_file_list(ctx: &ParseCtx<'_>, file_desc_id: u32) -> SourceFileLi
         return SourceFileList {
             count: Some(0),
             source_file: Vec::new(),
+            ..Default::default()
         };
     }
 
@@ -144,6 +147,7 @@ fn parse_source_file_list(ctx: &ParseCtx<'_>, file_desc_id: u32) -> SourceFileLi
         return SourceFileList {
             count: count_attr.or(Some(0)),
             source_file: Vec::new(),
+            ..Default::default()
         };
     }
 
@@ -155,6 +159,7 @@ fn parse_source_file_list(ctx: &ParseCtx<'_>, file_desc_id: u32) -> SourceFileLi
     SourceFileList {
         count: count_attr.or(Some(source_file.len())),
         source_file,
+        ..Default::default()
     }
 }
 
@@ -185,6 +190,7 @@ fn parse_source_file(ctx: &ParseCtx<'_>, source_file_id: u32) -> SourceFile {
         referenceable_param_group_ref: Vec::<ReferenceableParamGroupRef>::new(),
         cv_param,
         user_param,
+        ..Default::default()
     }
 }
 
@@ -223,5 +229,6 @@ fn parse_contact(ctx: &ParseCtx<'_>, contact_id: u32) -> Contact {
         referenceable_param_group_refs: Vec::<ReferenceableParamGroupRef>::new(),
         cv_params,
         user_params,
+        ..Default::default()
     }
 }
@@ -1,7 +1,10 @@
 use crate::{
     b64::{
         encode::{HDR_CODEC_MASK, HDR_CODEC_ZSTD},
-        utilities::{common::decompress_zstd_allow_aligned_padding, parse_metadata},
+        utilities::{
+            MetadataLayout, common::decompress_zstd_allow_aligned_padding, cv_prefixes::CvPrefixes,
+            parse_metadata_with,
+        },
     },
     decode::Metadatum,
 };
@@ -29,10 +32,35 @@ pub fn parse_global_metadata(
     compressed: bool,
     reserved_flags: u8,
 ) -> Result<Vec<Metadatum>, String> {
-    let codec_id = reserved_flags & HDR_CODEC_MASK;
+    let no_prefixes = CvPrefixes::default();
+    let mut layout = MetadataLayout {
+        item_count,
+        meta_count,
+        num_count,
+        str_count,
+        compressed,
+        reserved_flags,
+        cv_prefixes: &no_prefixes,
+    };
+    let meta = parse_global_metadata_with(bytes, &layout)?;
+
+    let cv_prefixes = CvPrefixes::from_metadata(&meta);
+    if cv_prefixes.is_empty() {
+        return Ok(meta);
+    }
+    layout.cv_prefixes = &cv_prefixes;
+    parse_global_metadata_with(bytes, &layout)
+}
+
+/// [`parse_global_metadata`] for the section described by `layout`.
+fn parse_global_metadata_with(
+    bytes: &[u8],
+    layout: &MetadataLayout,
+) -> Result<Vec<Metadatum>, String> {
+    let codec_id = layout.reserved_flags & HDR_CODEC_MASK;
 
     let owned;
-    let bytes = if compressed {
+    let bytes = if layout.compressed {
         if codec_id != HDR_CODEC_ZSTD {
             return Err(format!("unsupported metadata codec_id={codec_id}"));
         }
@@ -97,16 +125,15 @@ pub fn parse_global_metadata(
     let item_count = if derived_item_count != 0 {
         derived_item_count
     } else {
-        item_count
+        layout.item_count
     };
 
-    parse_metadata(
+    parse_metadata_with(
         &bytes[header_size..],
-        item_count,
-        meta_count,
-        num_count,
-        str_count,
-        false,
-        reserved_flags,
+        &MetadataLayout {
+            item_count,
+            compressed: false,
+            ..*layout
+        },
     )
 }
@@ -1,3 +1,5 @@
+use crate::b64::{encode::BlockSize, utilities::checksums::CHECKSUM_KIND_CRC32C};
+
 const HDR_FLAG_GLOBAL_META_COMP: u8 = 1 << 6;
 const HEADER_SIZE: usize = 192;
 
@@ -10,7 +12,9 @@ pub fn parse_header(bytes: &[u8]) -> Result<Header, String> {
 
     let file_signature = r.read_arr::<4>("file_signature")?;
     let endianness_flag = r.read_u8("endianness_flag")?;
-    let reserved_alignment = r.read_arr::<3>("reserved_alignment")?;
+    let reserved_alignment = r.read_u8("reserved_alignment")?;
+    let checksum_kind = r.read_u8("checksum_kind")?;
+    let reserved = r.read_u8("reserved")?;
 
     if &file_signature != b"B000" {
         return Err("header: invalid file_signature (expected \"B000\")".into());
@@ -61,7 +65,9 @@ pub fn parse_header(bytes: &[u8]) -> Result<Header, String> {
     let spect_y_format = r.read_u8("spect_y_format")?;
     let compression_level = r.read_u8("compression_level")?;
     let array_filter = r.read_u8("array_filter")?;
-    let reserved = r.read_arr::<13>("reserved")?;
+    let block_size_mode = r.read_u8("block_size_mode")?;
+    let block_size_value = r.read_u32_le("block_size_value")?;
+    let off_aux_arrays = r.read_u64_le("off_aux_arrays")?;
 
     let _ = reserved_flags & HDR_FLAG_GLOBAL_META_COMP;
 
@@ -69,6 +75,8 @@ pub fn parse_header(bytes: &[u8]) -> Result<Header, String> {
         file_signature,
         endianness_flag,
         reserved_alignment,
+        checksum_kind,
+        reserved,
 
         off_spec_index,
         off_chrom_index,
@@ -113,7 +121,9 @@ pub fn parse_header(bytes: &[u8]) -> Result<Header, String> {
         compression_level,
         array_filter,
 
-        reserved,
+        block_size_mode,
+        block_size_value,
+        off_aux_arrays,
     })
 }
 
@@ -121,7 +131,9 @@ pub fn parse_header(bytes: &[u8]) -> Result<Header, String> {
 pub struct Header {
     pub file_signature: [u8; 4],
     pub endianness_flag: u8,
-    pub reserved_alignment: [u8; 3],
+    pub reserved_alignment: u8,
+    pub checksum_kind: u8,
+    pub reserved: u8,
 
     pub off_spec_index: u64,
     pub off_chrom_index: u64,
@@ -166,7 +178,23 @@ pub struct Header {
     pub compression_level: u8,
     pub array_filter: u8,
 
-    pub reserved: [u8; 13],
+    pub block_size_mode: u8,
+    pub block_size_value: u32,
+    pub off_aux_arrays: u64,
+}
+
+impl Header {
+    /// Block sizing the file was written with; `None` for files that predate it.
+    #[inline]
+    pub fn block_size(&self) -> Option<BlockSize> {
+        BlockSize::from_header(self.block_size_mode, self.block_size_value)
+    }
+
+    /// Whether blocks and sections carry CRC-32C checksums.
+    #[inline]
+    pub fn has_checksums(&self) -> bool {
+        self.checksum_kind == CHECKSUM_KIND_CRC32C
+    }
 }
 
 struct Reader<'a> {
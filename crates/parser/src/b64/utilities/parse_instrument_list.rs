@@ -69,6 +69,7 @@ pub fn parse_instrument_list(
     Some(InstrumentList {
         count: Some(instrument.len()),
         instrument,
+        ..Default::default()
     })
 }
 
@@ -109,6 +110,7 @@ fn parse_instrument(ctx: &ParseCtx<'_>, instrument_id: u32) -> Instrument {
         referenceable_param_group_ref,
         component_list,
         software_ref,
+        ..Default::default()
     }
 }
 
@@ -162,6 +164,7 @@ fn parse_component_list(ctx: &ParseCtx<'_>, instrument_id: u32) -> Option<Compon
         source,
         analyzer,
         detector,
+        ..Default::default()
     })
 }
 
@@ -191,6 +194,7 @@ fn parse_source(ctx: &ParseCtx<'_>, source_id: u32) -> Source {
         referenceable_param_group_ref,
         cv_param,
         user_param,
+        ..Default::default()
     }
 }
 
@@ -220,6 +224,7 @@ fn parse_analyzer(ctx: &ParseCtx<'_>, analyzer_id: u32) -> Analyzer {
         referenceable_param_group_ref,
         cv_param,
         user_param,
+        ..Default::default()
     }
 }
 
@@ -249,6 +254,7 @@ fn parse_detector(ctx: &ParseCtx<'_>, detector_id: u32) -> Detector {
         referenceable_param_group_ref,
         cv_param,
         user_param,
+        ..Default::default()
     }
 }
 
@@ -1,7 +1,7 @@
 use crate::{
-    b64::utilities::common::*,
+    b64::utilities::{common::*, cv_prefixes::CvPrefixes},
     decode::{Metadatum, MetadatumValue},
-    mzml::{attr_meta::format_accession, schema::TagId},
+    mzml::schema::TagId,
 };
 
 const HDR_CODEC_MASK: u8 = 0x0F;
@@ -16,6 +16,47 @@ pub fn parse_metadata(
     compressed: bool,
     reserved_flags: u8,
 ) -> Result<Vec<Metadatum>, String> {
+    parse_metadata_with(
+        bytes,
+        &MetadataLayout {
+            item_count,
+            meta_count,
+            num_count,
+            str_count,
+            compressed,
+            reserved_flags,
+            cv_prefixes: &CvPrefixes::default(),
+        },
+    )
+}
+
+/// Header counts and flags of a metadata section, with the CV prefix table
+/// its table ref codes resolve through.
+#[derive(Debug, Clone, Copy)]
+pub struct MetadataLayout<'a> {
+    pub item_count: u32,
+    pub meta_count: u32,
+    pub num_count: u32,
+    pub str_count: u32,
+    pub compressed: bool,
+    pub reserved_flags: u8,
+    pub cv_prefixes: &'a CvPrefixes,
+}
+
+/// [`parse_metadata`] for the section described by `layout`.
+pub fn parse_metadata_with(
+    bytes: &[u8],
+    layout: &MetadataLayout,
+) -> Result<Vec<Metadatum>, String> {
+    let MetadataLayout {
+        item_count,
+        meta_count,
+        num_count,
+        str_count,
+        compressed,
+        reserved_flags,
+        cv_prefixes,
+    } = *layout;
     let codec_id = reserved_flags & HDR_CODEC_MASK;
 
     let owned;
@@ -112,8 +153,8 @@ pub fn parse_metadata(
                 _ => MetadatumValue::Empty,
             };
 
-            let accession = format_accession(mri[j], man[j]);
-            let unit_accession = format_accession(muri[j], muan[j]);
+            let accession = cv_prefixes.accession(mri[j], man[j]);
+            let unit_accession = cv_prefixes.accession(muri[j], muan[j]);
 
             out.push(Metadatum {
                 item_index: item_index as u32,
@@ -122,6 +163,8 @@ pub fn parse_metadata(
                 tag_id,
                 accession,
                 unit_accession,
+                cv_ref: cv_prefixes.cv_ref(mri[j]),
+                unit_cv_ref: cv_prefixes.cv_ref(muri[j]),
                 value,
             });
         }
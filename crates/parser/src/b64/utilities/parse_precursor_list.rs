@@ -130,6 +130,7 @@ pub fn parse_precursor_list(
         cv_params: Vec::new(),
         user_params: Vec::new(),
         precursors,
+        ..Default::default()
     })
 }
 
@@ -154,6 +155,7 @@ fn parse_precursor(ctx: &ParseCtx<'_>, precursor_id: u32) -> Precursor {
         isolation_window,
         selected_ion_list,
         activation,
+        ..Default::default()
     }
 }
 
@@ -209,6 +211,7 @@ fn parse_isolation_window(
         referenceable_param_group_refs: Vec::new(),
         cv_params,
         user_params,
+        ..Default::default()
     })
 }
 
@@ -258,12 +261,14 @@ fn parse_selected_ion_list(
             referenceable_param_group_refs: Vec::new(),
             cv_params,
             user_params,
+            ..Default::default()
         });
     }
 
     Some(SelectedIonList {
         count: Some(selected_ions.len()),
         selected_ions,
+        ..Default::default()
     })
 }
 
@@ -314,5 +319,6 @@ fn parse_activation(
         referenceable_param_group_refs: Vec::new(),
         cv_params,
         user_params,
+        ..Default::default()
     })
 }
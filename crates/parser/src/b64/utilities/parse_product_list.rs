@@ -59,6 +59,7 @@ pub fn parse_product_list(
     Some(ProductList {
         count: Some(products.len()),
         products,
+        ..Default::default()
     })
 }
 
@@ -88,6 +89,7 @@ fn parse_product(ctx: &ParseCtx<'_>, product_id: u32) -> Product {
             product_id,
             product_parent,
         ),
+        ..Default::default()
     }
 }
 
@@ -119,6 +121,7 @@ fn parse_isolation_window(
         referenceable_param_group_refs: Vec::new(),
         cv_params,
         user_params,
+        ..Default::default()
     })
 }
 
@@ -70,6 +70,7 @@ pub fn parse_referenceable_param_group_list(
     Some(ReferenceableParamGroupList {
         count,
         referenceable_param_groups,
+        ..Default::default()
     })
 }
 
@@ -95,5 +96,6 @@ fn parse_referenceable_param_group(ctx: &ParseCtx<'_>, group_id: u32) -> Referen
         id,
         cv_params,
         user_params,
+        ..Default::default()
     }
 }
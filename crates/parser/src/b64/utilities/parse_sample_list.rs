@@ -1,8 +1,12 @@
 use std::collections::HashMap;
 
 use crate::{
-    b64::utilities::common::{
-        ChildIndex, OwnerRows, ParseCtx, b000_attr_text, ids_for_parent, rows_for_owner,
+    b64::utilities::{
+        common::{
+            ChildIndex, OwnerRows, ParseCtx, b000_attr_text, child_params_for_parent,
+            ids_for_parent, rows_for_owner,
+        },
+        parse_cv_and_user_params,
     },
     decode::Metadatum,
     mzml::{
@@ -59,6 +63,7 @@ pub fn parse_sample_list(metadata: &[&Metadatum], child_index: &ChildIndex) -> O
     Some(SampleList {
         count: Some(samples.len() as u32),
         samples,
+        ..Default::default()
     })
 }
 
@@ -70,21 +75,31 @@ fn parse_sample(ctx: &ParseCtx<'_>, sample_id: u32) -> Sample {
     let id = b000_attr_text(rows, ACC_ATTR_ID).unwrap_or_default();
     let name = b000_attr_text(rows, ACC_ATTR_NAME).unwrap_or_default();
 
-    let mut referenceable_param_group_ref = None;
-
-    let ref_ids = ids_for_parent(ctx, sample_id, TagId::ReferenceableParamGroupRef);
-    if let Some(&ref_id) = ref_ids.first() {
+    let mut referenceable_param_group_ref = Vec::new();
+    for ref_id in ids_for_parent(ctx, sample_id, TagId::ReferenceableParamGroupRef) {
         let ref_rows = rows_for_owner(ctx.owner_rows, ref_id);
-        if let Some(r) = b000_attr_text(ref_rows, ACC_ATTR_REF) {
-            if !r.is_empty() {
-                referenceable_param_group_ref = Some(ReferenceableParamGroupRef { r#ref: r });
-            }
+        if let Some(r) = b000_attr_text(ref_rows, ACC_ATTR_REF).filter(|r| !r.is_empty()) {
+            referenceable_param_group_ref.push(ReferenceableParamGroupRef { r#ref: r });
         }
     }
 
+    let child_meta = child_params_for_parent(ctx.owner_rows, ctx.child_index, sample_id);
+
+    let (cv_param, user_param) = if child_meta.is_empty() {
+        parse_cv_and_user_params(rows)
+    } else {
+        let mut params_meta = Vec::with_capacity(rows.len() + child_meta.len());
+        params_meta.extend_from_slice(rows);
+        params_meta.extend(child_meta);
+        parse_cv_and_user_params(&params_meta)
+    };
+
     Sample {
         id,
         name,
         referenceable_param_group_ref,
+        cv_param,
+        user_param,
+        ..Default::default()
     }
 }
@@ -84,6 +84,7 @@ pub fn parse_scan_list(metadata: &[&Metadatum], child_index: &ChildIndex) -> Opt
         cv_params,
         user_params,
         scans,
+        ..Default::default()
     })
 }
 
@@ -101,6 +102,7 @@ fn parse_scan(scan_rows: &[&Metadatum], scan_window_list: Option<ScanWindowList>
         cv_params,
         user_params,
         scan_window_list,
+        ..Default::default()
     }
 }
 
@@ -123,6 +125,7 @@ fn parse_scan_window_list(
     Some(ScanWindowList {
         count: Some(scan_windows.len()),
         scan_windows,
+        ..Default::default()
     })
 }
 
@@ -73,6 +73,7 @@ pub fn parse_scan_settings_list(
     Some(ScanSettingsList {
         count: Some(scan_settings.len()),
         scan_settings,
+        ..Default::default()
     })
 }
 
@@ -110,6 +111,7 @@ fn parse_scan_settings(ctx: &ParseCtx<'_>, scan_settings_id: u32) -> ScanSetting
         user_params,
         source_file_ref_list,
         target_list,
+        ..Default::default()
     }
 }
 
@@ -139,6 +141,7 @@ fn parse_source_file_ref_list(
                 return Some(SourceFileRefList {
                     count: Some(source_file_refs.len()),
                     source_file_refs,
+                    ..Default::default()
                 });
             }
         }
@@ -165,6 +168,7 @@ fn parse_source_file_ref_list(
     (!source_file_refs.is_empty()).then(|| SourceFileRefList {
         count: Some(source_file_refs.len()),
         source_file_refs,
+        ..Default::default()
     })
 }
 
@@ -191,6 +195,7 @@ fn parse_target_list(ctx: &ParseCtx<'_>, scan_settings_id: u32) -> Option<Target
         return Some(TargetList {
             count: Some(targets.len()),
             targets,
+            ..Default::default()
         });
     }
 
@@ -237,6 +242,7 @@ fn parse_target_list(ctx: &ParseCtx<'_>, scan_settings_id: u32) -> Option<Target
                 referenceable_param_group_refs: Vec::new(),
                 cv_params: cur,
                 user_params: Vec::new(),
+                ..Default::default()
             });
             cur = Vec::new();
         }
@@ -249,12 +255,14 @@ fn parse_target_list(ctx: &ParseCtx<'_>, scan_settings_id: u32) -> Option<Target
             referenceable_param_group_refs: Vec::new(),
             cv_params: cur,
             user_params: Vec::new(),
+            ..Default::default()
         });
     }
 
     (!targets.is_empty()).then(|| TargetList {
         count: Some(targets.len()),
         targets,
+        ..Default::default()
     })
 }
 
@@ -280,6 +288,7 @@ fn parse_target(ctx: &ParseCtx<'_>, target_id: u32) -> Target {
         referenceable_param_group_refs,
         cv_params,
         user_params,
+        ..Default::default()
     }
 }
 
@@ -67,6 +67,7 @@ pub fn parse_software_list(
     Some(SoftwareList {
         count: Some(software.len()),
         software,
+        ..Default::default()
     })
 }
 
@@ -82,7 +83,7 @@ fn parse_software(ctx: &ParseCtx<'_>, software_id: u32) -> Software {
 
     let child_meta = child_params_for_parent(ctx.owner_rows, ctx.child_index, software_id);
 
-    let (cv_param, _) = if child_meta.is_empty() {
+    let (cv_param, user_param) = if child_meta.is_empty() {
         parse_cv_and_user_params(rows)
     } else {
         let mut params_meta = Vec::with_capacity(rows.len() + child_meta.len());
@@ -96,6 +97,8 @@ fn parse_software(ctx: &ParseCtx<'_>, software_id: u32) -> Software {
         version,
         software_param,
         cv_param,
+        user_param,
+        ..Default::default()
     }
 }
 
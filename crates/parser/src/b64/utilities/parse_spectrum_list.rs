@@ -5,6 +5,7 @@ use crate::{
     b64::{
         decode::Metadatum,
         utilities::{
+            apply_extension_rows,
             common::{ChildIndex, get_attr_text, get_attr_u32, xy_lengths_from_bdal},
             parse_binary_data_array_list, parse_cv_and_user_params, parse_precursor_list,
             parse_product_list, parse_scan_list,
@@ -41,10 +42,7 @@ pub fn parse_spectrum_list(
     }
 
     let default_data_processing_ref =
-        get_attr_text(&spectrum_list_rows, ACC_ATTR_DEFAULT_DATA_PROCESSING_REF)
-            .or_else(|| get_attr_text(&spectrum_list_rows, ACC_ATTR_DATA_PROCESSING_REF))
-            .or_else(|| get_attr_text(metadata, ACC_ATTR_DEFAULT_DATA_PROCESSING_REF))
-            .or_else(|| get_attr_text(metadata, ACC_ATTR_DATA_PROCESSING_REF));
+        spectrum_list_default_data_processing_ref(metadata, &spectrum_list_rows);
 
     let count_attr = get_attr_u32(&spectrum_list_rows, ACC_ATTR_COUNT)
         .or_else(|| get_attr_u32(metadata, ACC_ATTR_COUNT))
@@ -160,9 +158,54 @@ pub fn parse_spectrum_list(
         count: count_attr.or(Some(spectra.len())),
         default_data_processing_ref,
         spectra,
+        ..Default::default()
     })
 }
 
+/// Default `dataProcessingRef` inherited by every `<spectrum>` of the list.
+#[inline]
+fn spectrum_list_default_data_processing_ref(
+    metadata: &[&Metadatum],
+    spectrum_list_rows: &[&Metadatum],
+) -> Option<String> {
+    get_attr_text(spectrum_list_rows, ACC_ATTR_DEFAULT_DATA_PROCESSING_REF)
+        .or_else(|| get_attr_text(spectrum_list_rows, ACC_ATTR_DATA_PROCESSING_REF))
+        .or_else(|| get_attr_text(metadata, ACC_ATTR_DEFAULT_DATA_PROCESSING_REF))
+        .or_else(|| get_attr_text(metadata, ACC_ATTR_DATA_PROCESSING_REF))
+}
+
+/// `<spectrumList>` attributes shared by all spectra: the default
+/// `dataProcessingRef`, resolved the same way as in [`parse_spectrum_list`].
+#[inline]
+pub fn parse_spectrum_list_defaults(metadata: &[&Metadatum]) -> Option<String> {
+    let spectrum_list_rows: Vec<&Metadatum> = metadata
+        .iter()
+        .copied()
+        .filter(|m| m.tag_id == TagId::SpectrumList)
+        .collect();
+    spectrum_list_default_data_processing_ref(metadata, &spectrum_list_rows)
+}
+
+/// <spectrum> from the rows of a single item.
+#[inline]
+pub fn parse_spectrum_item(
+    metadata: &[&Metadatum],
+    fallback_index: u32,
+    default_data_processing_ref: Option<&str>,
+) -> Option<Spectrum> {
+    if !metadata.iter().any(|m| m.tag_id == TagId::Spectrum) {
+        return None;
+    }
+
+    let child_index = ChildIndex::new_from_refs(metadata);
+    Some(parse_spectrum(
+        metadata,
+        fallback_index,
+        default_data_processing_ref,
+        &child_index,
+    ))
+}
+
 #[inline]
 fn parse_spectrum(
     metadata: &[&Metadatum],
@@ -230,7 +273,7 @@ fn parse_spectrum(
     let default_array_length = default_array_length_attr.or(x_len).or(y_len).or(Some(0));
     let spectrum_description = parse_spectrum_description(metadata, child_index);
 
-    Spectrum {
+    let mut spectrum = Spectrum {
         id,
         index,
         scan_number,
@@ -248,7 +291,10 @@ fn parse_spectrum(
         precursor_list,
         product_list,
         binary_data_array_list,
-    }
+        ..Default::default()
+    };
+    apply_extension_rows(&mut spectrum, &spectrum_rows);
+    spectrum
 }
 
 #[inline]
@@ -307,6 +353,7 @@ fn parse_spectrum_description(
         scan_list,
         precursor_list,
         product_list,
+        ..Default::default()
     })
 }
 
@@ -16,7 +16,9 @@ fn check_header() {
 
     assert_eq!(header.file_signature, [66, 48, 48, 48]);
     assert_eq!(header.endianness_flag, 0);
-    assert_eq!(header.reserved_alignment, [1, 0, 0]);
+    assert_eq!(header.reserved_alignment, 1);
+    assert_eq!(header.checksum_kind, 0);
+    assert_eq!(header.reserved, 0);
 
     assert_eq!(header.off_spec_index, 192);
     assert_eq!(header.off_chrom_index, 256);
@@ -64,7 +66,10 @@ fn check_header() {
     assert_eq!(header.compression_level, 12);
     assert_eq!(header.array_filter, 1);
 
-    assert_eq!(header.reserved, [0; 13]);
+    assert_eq!(header.block_size_mode, 0);
+    assert_eq!(header.block_size_value, 0);
+    assert_eq!(header.block_size(), None);
+    assert_eq!(header.off_aux_arrays, 0);
 
     let len = bytes.len() as u64;
     for &off in &[
@@ -1,5 +1,12 @@
 pub mod mzml;
-pub use mzml::{bin_to_mzml, parse_mzml, structs::*};
+pub use mzml::{
+    Conversion, GzipReader, IndexIssue, IndexReport, IndexedMzML, MaybeGzip, MzMLReader,
+    ParseError, ParseOptions, Recovery, WriteOptions, bin_to_mzml, bin_to_mzml_with, check_index,
+    parse_mzml, parse_mzml_lenient, parse_mzml_with, reindex, structs::*, verify_file_checksum,
+};
 pub mod b64;
-pub use b64::{decode, encode, utilities::Header};
+pub use b64::{
+    ArrayFilter, B000Reader, BlockSize, Codec, EncodeError, EncodeOptions, FloatPrecision, decode,
+    encode, encode_with, utilities::Header,
+};
 pub mod utilities;
@@ -1,12 +1,16 @@
 use crate::mzml::structs::CvParam;
 
 pub const CV_REF_ATTR: &str = "B000";
+pub const CV_REF_USER_PARAM: &str = "USER";
 
 pub const CV_CODE_MS: u8 = 0;
 pub const CV_CODE_UO: u8 = 1;
 pub const CV_CODE_NCIT: u8 = 2;
 pub const CV_CODE_PEFF: u8 = 3;
 pub const CV_CODE_B000: u8 = 4;
+pub const CV_CODE_USER_PARAM: u8 = 5;
+/// First code of the per-file CV prefix table; codes up to 254 follow.
+pub const CV_CODE_TABLE_FIRST: u8 = 6;
 pub const CV_CODE_UNKNOWN: u8 = 255;
 
 /// Encode: string -> code
@@ -18,6 +22,7 @@ pub fn cv_ref_code_from_str(cv_ref: Option<&str>) -> u8 {
         Some("NCIT") => CV_CODE_NCIT,
         Some("PEFF") => CV_CODE_PEFF,
         Some(CV_REF_ATTR) => CV_CODE_B000,
+        Some(CV_REF_USER_PARAM) => CV_CODE_USER_PARAM,
         _ => CV_CODE_UNKNOWN,
     }
 }
@@ -30,7 +35,8 @@ pub fn cv_ref_prefix_from_code(code: u8) -> Option<&'static str> {
         CV_CODE_UO => Some("UO"),
         CV_CODE_NCIT => Some("NCIT"),
         CV_CODE_PEFF => Some("PEFF"),
-        CV_CODE_B000 => Some(CV_REF_ATTR), // "B000"
+        CV_CODE_B000 => Some(CV_REF_ATTR),             // "B000"
+        CV_CODE_USER_PARAM => Some(CV_REF_USER_PARAM), // "USER"
         _ => None,
     }
 }
@@ -73,6 +79,8 @@ pub const ACC_ATTR_CV_FULL_NAME: u32 = 9_900_002;
 pub const ACC_ATTR_CV_VERSION: u32 = 9_900_003;
 pub const ACC_ATTR_CV_URI: u32 = 9_900_004;
 pub const ACC_ATTR_LABEL: u32 = 9_910_020;
+pub const ACC_ATTR_EXTENSIONS: u32 = 9_910_021;
+pub const ACC_ATTR_CV_PREFIX: u32 = 9_910_022;
 
 pub const ACC_ATTR_START_TIME_STAMP: u32 = 9_910_005;
 pub const ACC_ATTR_DEFAULT_INSTRUMENT_CONFIGURATION_REF: u32 = 9_910_006;
@@ -94,6 +102,12 @@ pub const ACC_ATTR_INSTRUMENT_CONFIGURATION_REF: u32 = 9_910_017;
 pub const ACC_ATTR_SOFTWARE_REF: u32 = 9_910_018;
 pub const ACC_ATTR_VERSION: u32 = 9_910_019;
 
+// <userParam> fields, stored as "USER:<tail>" rows on the owning element
+pub const ACC_USER_PARAM_NAME: u32 = 1;
+pub const ACC_USER_PARAM_TYPE: u32 = 2;
+pub const ACC_USER_PARAM_VALUE: u32 = 3;
+pub const ACC_USER_PARAM_UNIT_NAME: u32 = 4;
+
 // Numbers
 pub const ACC_ATTR_COUNT: u32 = 9_910_100;
 pub const ACC_ATTR_ORDER: u32 = 9_910_101;
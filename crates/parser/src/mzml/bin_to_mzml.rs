@@ -5,6 +5,10 @@ use miniz_oxide::deflate::compress_to_vec_zlib;
 use quick_xml::Writer;
 use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
 
+use crate::mzml::checksum::sha1_hex;
+use crate::mzml::numpress::Numpress;
+use crate::mzml::parse_mzml::IndexOffset;
+use crate::mzml::provenance::{Conversion, with_provenance};
 use crate::mzml::structs::*;
 
 #[derive(Default)]
@@ -46,12 +50,36 @@ fn write_start_capture_offset(
     Ok((before + rel) as u64)
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WriteOptions {
+    /// Append octo's `<software>` and a "Conversion to mzML"
+    /// `<dataProcessing>` step.
+    pub provenance: bool,
+}
+
+impl Default for WriteOptions {
+    fn default() -> Self {
+        Self { provenance: true }
+    }
+}
+
 pub fn bin_to_mzml(mzml: &MzML) -> Result<String, String> {
-    let bytes = convert_bin_to_mzml_bytes(mzml)?;
+    bin_to_mzml_with(mzml, &WriteOptions::default())
+}
+
+pub fn bin_to_mzml_with(mzml: &MzML, options: &WriteOptions) -> Result<String, String> {
+    let bytes = convert_bin_to_mzml_bytes_with(mzml, options)?;
     String::from_utf8(bytes).map_err(|e| e.to_string())
 }
 
 pub fn convert_bin_to_mzml_bytes(mzml: &MzML) -> Result<Vec<u8>, String> {
+    convert_bin_to_mzml_bytes_with(mzml, &WriteOptions::default())
+}
+
+pub fn convert_bin_to_mzml_bytes_with(
+    mzml: &MzML,
+    options: &WriteOptions,
+) -> Result<Vec<u8>, String> {
     let mut writer = Writer::new_with_indent(Vec::new(), b' ', 2);
 
     writer
@@ -78,6 +106,7 @@ pub fn convert_bin_to_mzml_bytes(mzml: &MzML) -> Result<Vec<u8>, String> {
     ));
     mzml_tag.push_attribute(("id", mzml.run.id.as_str()));
     mzml_tag.push_attribute(("version", "1.1.0"));
+    push_extension_attributes(&mut mzml_tag, &mzml.extensions);
 
     writer
         .write_event(Event::Start(mzml_tag))
@@ -103,37 +132,86 @@ pub fn convert_bin_to_mzml_bytes(mzml: &MzML) -> Result<Vec<u8>, String> {
     if let Some(il) = &mzml.instrument_list {
         write_instrument_list(&mut writer, il)?;
     }
-    if let Some(sw) = &mzml.software_list {
+    let provenance = options.provenance.then(|| {
+        with_provenance(
+            mzml.software_list.as_ref(),
+            mzml.data_processing_list.as_ref(),
+            Conversion::ToMzML,
+        )
+    });
+    let (software_list, data_processing_list) = match &provenance {
+        Some((sw, dpl)) => (Some(sw), Some(dpl)),
+        None => (
+            mzml.software_list.as_ref(),
+            mzml.data_processing_list.as_ref(),
+        ),
+    };
+
+    if let Some(sw) = software_list {
         write_software_list(&mut writer, sw)?;
     }
-    if let Some(dpl) = &mzml.data_processing_list {
+    if let Some(dpl) = data_processing_list {
         write_data_processing_list(&mut writer, dpl)?;
     }
     if let Some(ssl) = &mzml.scan_settings_list {
         write_scan_settings_list(&mut writer, ssl)?;
     }
 
-    let fallback_default_dp = mzml
-        .data_processing_list
-        .as_ref()
+    let fallback_default_dp = data_processing_list
         .and_then(|dpl| dpl.data_processing.first())
         .map(|dp| dp.id.as_str());
 
     let mut idx = IndexAcc::default();
     write_run(&mut writer, &mzml.run, fallback_default_dp, &mut idx)?;
+    write_extension_elements(&mut writer, &mzml.extensions)?;
 
     writer
         .write_event(Event::End(BytesEnd::new("mzML")))
         .map_err(|e| e.to_string())?;
 
-    let index_list_offset = write_index_list_with_offset(&mut writer, &idx)?;
-    write_index_list_offset(&mut writer, index_list_offset)?;
+    write_index_tail(&mut writer, &idx)?;
+
+    Ok(writer.into_inner())
+}
+
+/// `doc`, an `<indexedmzML>` document written up to `</mzML>`, completed with
+/// an index of the given offsets.
+pub(crate) fn append_index(
+    mut doc: Vec<u8>,
+    spectrum: &[IndexOffset],
+    chromatogram: &[IndexOffset],
+) -> Result<Vec<u8>, String> {
+    let acc = |offsets: &[IndexOffset]| {
+        offsets
+            .iter()
+            .map(|o| IndexOffsetAcc {
+                id_ref: o.id_ref.clone().unwrap_or_default(),
+                offset: o.offset,
+            })
+            .collect()
+    };
+    let idx = IndexAcc {
+        spectrum: acc(spectrum),
+        chromatogram: acc(chromatogram),
+    };
+
+    doc.push(b'\n');
+    let mut writer = Writer::new_with_indent(doc, b' ', 2);
+    write_index_tail(&mut writer, &idx)?;
+    writer.get_mut().push(b'\n');
+    Ok(writer.into_inner())
+}
+
+/// <indexList>, <indexListOffset> and <fileChecksum>, then `</indexedmzML>`.
+fn write_index_tail(writer: &mut Writer<Vec<u8>>, idx: &IndexAcc) -> Result<(), String> {
+    let index_list_offset = write_index_list_with_offset(writer, idx)?;
+    write_index_list_offset(writer, index_list_offset)?;
+    write_file_checksum(writer)?;
 
     writer
         .write_event(Event::End(BytesEnd::new("indexedmzML")))
         .map_err(|e| e.to_string())?;
-
-    Ok(writer.into_inner())
+    Ok(())
 }
 
 fn default_cv_list() -> CvList {
@@ -161,6 +239,7 @@ fn default_cv_list() -> CvList {
                 ),
             },
         ],
+        ..Default::default()
     }
 }
 
@@ -169,6 +248,7 @@ pub fn write_cv_list(writer: &mut Writer<Vec<u8>>, cvl: &CvList) -> Result<(), S
     let mut tag = BytesStart::new("cvList");
     let count_s = count.to_string();
     tag.push_attribute(("count", count_s.as_str()));
+    push_extension_attributes(&mut tag, &cvl.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -192,6 +272,7 @@ pub fn write_cv_list(writer: &mut Writer<Vec<u8>>, cvl: &CvList) -> Result<(), S
             .map_err(|e| e.to_string())?;
     }
 
+    write_extension_elements(writer, &cvl.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("cvList")))
         .map_err(|e| e.to_string())?;
@@ -203,17 +284,21 @@ fn write_file_description(
     fd: &FileDescription,
 ) -> Result<(), String> {
     writer
-        .write_event(Event::Start(BytesStart::new("fileDescription")))
+        .write_event(Event::Start(start_tag("fileDescription", &fd.extensions)))
         .map_err(|e| e.to_string())?;
 
     writer
-        .write_event(Event::Start(BytesStart::new("fileContent")))
+        .write_event(Event::Start(start_tag(
+            "fileContent",
+            &fd.file_content.extensions,
+        )))
         .map_err(|e| e.to_string())?;
 
     write_referenceable_param_group_refs(writer, &fd.file_content.referenceable_param_group_refs)?;
     write_cv_params(writer, &fd.file_content.cv_params)?;
     write_user_params(writer, &fd.file_content.user_params)?;
 
+    write_extension_elements(writer, &fd.file_content.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("fileContent")))
         .map_err(|e| e.to_string())?;
@@ -222,16 +307,18 @@ fn write_file_description(
 
     for c in &fd.contacts {
         writer
-            .write_event(Event::Start(BytesStart::new("contact")))
+            .write_event(Event::Start(start_tag("contact", &c.extensions)))
             .map_err(|e| e.to_string())?;
         write_referenceable_param_group_refs(writer, &c.referenceable_param_group_refs)?;
         write_cv_params(writer, &c.cv_params)?;
         write_user_params(writer, &c.user_params)?;
+        write_extension_elements(writer, &c.extensions)?;
         writer
             .write_event(Event::End(BytesEnd::new("contact")))
             .map_err(|e| e.to_string())?;
     }
 
+    write_extension_elements(writer, &fd.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("fileDescription")))
         .map_err(|e| e.to_string())?;
@@ -246,6 +333,7 @@ fn write_source_file_list(
     let mut tag = BytesStart::new("sourceFileList");
     let count_s = count.to_string();
     tag.push_attribute(("count", count_s.as_str()));
+    push_extension_attributes(&mut tag, &sfl.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -260,6 +348,7 @@ fn write_source_file_list(
         if !sf.location.is_empty() {
             sf_tag.push_attribute(("location", sf.location.as_str()));
         }
+        push_extension_attributes(&mut sf_tag, &sf.extensions);
 
         writer
             .write_event(Event::Start(sf_tag))
@@ -269,11 +358,13 @@ fn write_source_file_list(
         write_cv_params(writer, &sf.cv_param)?;
         write_user_params(writer, &sf.user_param)?;
 
+        write_extension_elements(writer, &sf.extensions)?;
         writer
             .write_event(Event::End(BytesEnd::new("sourceFile")))
             .map_err(|e| e.to_string())?;
     }
 
+    write_extension_elements(writer, &sfl.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("sourceFileList")))
         .map_err(|e| e.to_string())?;
@@ -288,6 +379,7 @@ fn write_referenceable_param_group_list(
     let mut tag = BytesStart::new("referenceableParamGroupList");
     let count_s = count.to_string();
     tag.push_attribute(("count", count_s.as_str()));
+    push_extension_attributes(&mut tag, &list.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -296,6 +388,7 @@ fn write_referenceable_param_group_list(
     for g in &list.referenceable_param_groups {
         let mut g_tag = BytesStart::new("referenceableParamGroup");
         g_tag.push_attribute(("id", g.id.as_str()));
+        push_extension_attributes(&mut g_tag, &g.extensions);
         writer
             .write_event(Event::Start(g_tag))
             .map_err(|e| e.to_string())?;
@@ -303,11 +396,13 @@ fn write_referenceable_param_group_list(
         write_cv_params(writer, &g.cv_params)?;
         write_user_params(writer, &g.user_params)?;
 
+        write_extension_elements(writer, &g.extensions)?;
         writer
             .write_event(Event::End(BytesEnd::new("referenceableParamGroup")))
             .map_err(|e| e.to_string())?;
     }
 
+    write_extension_elements(writer, &list.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("referenceableParamGroupList")))
         .map_err(|e| e.to_string())?;
@@ -319,6 +414,7 @@ fn write_sample_list(writer: &mut Writer<Vec<u8>>, list: &SampleList) -> Result<
     let mut tag = BytesStart::new("sampleList");
     let count_s = count.to_string();
     tag.push_attribute(("count", count_s.as_str()));
+    push_extension_attributes(&mut tag, &list.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -330,19 +426,22 @@ fn write_sample_list(writer: &mut Writer<Vec<u8>>, list: &SampleList) -> Result<
         if !s.name.is_empty() {
             s_tag.push_attribute(("name", s.name.as_str()));
         }
+        push_extension_attributes(&mut s_tag, &s.extensions);
         writer
             .write_event(Event::Start(s_tag))
             .map_err(|e| e.to_string())?;
 
-        if let Some(r) = &s.referenceable_param_group_ref {
-            write_referenceable_param_group_ref(writer, r)?;
-        }
+        write_referenceable_param_group_refs(writer, &s.referenceable_param_group_ref)?;
+        write_cv_params(writer, &s.cv_param)?;
+        write_user_params(writer, &s.user_param)?;
 
+        write_extension_elements(writer, &s.extensions)?;
         writer
             .write_event(Event::End(BytesEnd::new("sample")))
             .map_err(|e| e.to_string())?;
     }
 
+    write_extension_elements(writer, &list.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("sampleList")))
         .map_err(|e| e.to_string())?;
@@ -357,6 +456,7 @@ fn write_instrument_list(
     let mut tag = BytesStart::new("instrumentConfigurationList");
     let count_s = count.to_string();
     tag.push_attribute(("count", count_s.as_str()));
+    push_extension_attributes(&mut tag, &list.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -370,6 +470,7 @@ fn write_instrument_list(
                 ic_tag.push_attribute(("scanSettingsRef", v));
             }
         }
+        push_extension_attributes(&mut ic_tag, &ic.extensions);
 
         writer
             .write_event(Event::Start(ic_tag))
@@ -427,11 +528,13 @@ fn write_instrument_list(
                 .map_err(|e| e.to_string())?;
         }
 
+        write_extension_elements(writer, &ic.extensions)?;
         writer
             .write_event(Event::End(BytesEnd::new("instrumentConfiguration")))
             .map_err(|e| e.to_string())?;
     }
 
+    write_extension_elements(writer, &list.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("instrumentConfigurationList")))
         .map_err(|e| e.to_string())?;
@@ -445,6 +548,7 @@ fn write_component_list(writer: &mut Writer<Vec<u8>>, cl: &ComponentList) -> Res
     let mut tag = BytesStart::new("componentList");
     let count_s = count.to_string();
     tag.push_attribute(("count", count_s.as_str()));
+    push_extension_attributes(&mut tag, &cl.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -458,6 +562,7 @@ fn write_component_list(writer: &mut Writer<Vec<u8>>, cl: &ComponentList) -> Res
             &s.referenceable_param_group_ref,
             &s.cv_param,
             &s.user_param,
+            &s.extensions,
         )?;
     }
     for a in &cl.analyzer {
@@ -468,6 +573,7 @@ fn write_component_list(writer: &mut Writer<Vec<u8>>, cl: &ComponentList) -> Res
             &a.referenceable_param_group_ref,
             &a.cv_param,
             &a.user_param,
+            &a.extensions,
         )?;
     }
     for d in &cl.detector {
@@ -478,9 +584,11 @@ fn write_component_list(writer: &mut Writer<Vec<u8>>, cl: &ComponentList) -> Res
             &d.referenceable_param_group_ref,
             &d.cv_param,
             &d.user_param,
+            &d.extensions,
         )?;
     }
 
+    write_extension_elements(writer, &cl.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("componentList")))
         .map_err(|e| e.to_string())?;
@@ -497,7 +605,15 @@ fn write_component_list_fallback_from_instrument_cv(
         .write_event(Event::Start(tag))
         .map_err(|e| e.to_string())?;
 
-    write_component(writer, "source", Some(1), &[], &[], &[])?;
+    write_component(
+        writer,
+        "source",
+        Some(1),
+        &[],
+        &[],
+        &[],
+        &Extensions::default(),
+    )?;
     for p in params {
         let acc = p.accession.as_deref().unwrap_or("");
         if matches!(acc, "MS:1000073" | "MS:1000057") {
@@ -508,7 +624,15 @@ fn write_component_list_fallback_from_instrument_cv(
         .write_event(Event::End(BytesEnd::new("source")))
         .map_err(|e| e.to_string())?;
 
-    write_component(writer, "analyzer", Some(2), &[], &[], &[])?;
+    write_component(
+        writer,
+        "analyzer",
+        Some(2),
+        &[],
+        &[],
+        &[],
+        &Extensions::default(),
+    )?;
     for p in params {
         let acc = p.accession.as_deref().unwrap_or("");
         if matches!(acc, "MS:1000081" | "MS:1000084") {
@@ -519,7 +643,15 @@ fn write_component_list_fallback_from_instrument_cv(
         .write_event(Event::End(BytesEnd::new("analyzer")))
         .map_err(|e| e.to_string())?;
 
-    write_component(writer, "detector", Some(3), &[], &[], &[])?;
+    write_component(
+        writer,
+        "detector",
+        Some(3),
+        &[],
+        &[],
+        &[],
+        &Extensions::default(),
+    )?;
     for p in params {
         let acc = p.accession.as_deref().unwrap_or("");
         if matches!(acc, "MS:1000114" | "MS:1000116") {
@@ -543,12 +675,14 @@ fn write_component(
     refs: &[ReferenceableParamGroupRef],
     cvs: &[CvParam],
     ups: &[UserParam],
+    ext: &Extensions,
 ) -> Result<(), String> {
     let mut tag = BytesStart::new(name);
     if let Some(o) = order {
         let o_s = o.to_string();
         tag.push_attribute(("order", o_s.as_str()));
     }
+    push_extension_attributes(&mut tag, ext);
 
     writer
         .write_event(Event::Start(tag))
@@ -557,6 +691,7 @@ fn write_component(
     write_referenceable_param_group_refs(writer, refs)?;
     write_cv_params(writer, cvs)?;
     write_user_params(writer, ups)?;
+    write_extension_elements(writer, ext)?;
 
     writer
         .write_event(Event::End(BytesEnd::new(name)))
@@ -569,6 +704,7 @@ fn write_software_list(writer: &mut Writer<Vec<u8>>, list: &SoftwareList) -> Res
     let mut tag = BytesStart::new("softwareList");
     let count_s = count.to_string();
     tag.push_attribute(("count", count_s.as_str()));
+    push_extension_attributes(&mut tag, &list.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -580,6 +716,7 @@ fn write_software_list(writer: &mut Writer<Vec<u8>>, list: &SoftwareList) -> Res
         if let Some(v) = &sw.version {
             sw_tag.push_attribute(("version", v.as_str()));
         }
+        push_extension_attributes(&mut sw_tag, &sw.extensions);
 
         writer
             .write_event(Event::Start(sw_tag))
@@ -600,13 +737,17 @@ fn write_software_list(writer: &mut Writer<Vec<u8>>, list: &SoftwareList) -> Res
                 .map_err(|e| e.to_string())?;
         }
 
+        write_referenceable_param_group_refs(writer, &sw.referenceable_param_group_ref)?;
         write_cv_params(writer, &sw.cv_param)?;
+        write_user_params(writer, &sw.user_param)?;
 
+        write_extension_elements(writer, &sw.extensions)?;
         writer
             .write_event(Event::End(BytesEnd::new("software")))
             .map_err(|e| e.to_string())?;
     }
 
+    write_extension_elements(writer, &list.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("softwareList")))
         .map_err(|e| e.to_string())?;
@@ -621,6 +762,7 @@ fn write_data_processing_list(
     let mut tag = BytesStart::new("dataProcessingList");
     let count_s = count.to_string();
     tag.push_attribute(("count", count_s.as_str()));
+    push_extension_attributes(&mut tag, &list.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -632,6 +774,7 @@ fn write_data_processing_list(
         if let Some(sw) = nonempty(dp.software_ref.as_deref()) {
             dp_tag.push_attribute(("softwareRef", sw));
         }
+        push_extension_attributes(&mut dp_tag, &dp.extensions);
 
         writer
             .write_event(Event::Start(dp_tag))
@@ -646,6 +789,7 @@ fn write_data_processing_list(
             if let Some(sw) = nonempty(m.software_ref.as_deref()) {
                 pm.push_attribute(("softwareRef", sw));
             }
+            push_extension_attributes(&mut pm, &m.extensions);
 
             writer
                 .write_event(Event::Start(pm))
@@ -655,16 +799,19 @@ fn write_data_processing_list(
             write_cv_params(writer, &m.cv_param)?;
             write_user_params(writer, &m.user_param)?;
 
+            write_extension_elements(writer, &m.extensions)?;
             writer
                 .write_event(Event::End(BytesEnd::new("processingMethod")))
                 .map_err(|e| e.to_string())?;
         }
 
+        write_extension_elements(writer, &dp.extensions)?;
         writer
             .write_event(Event::End(BytesEnd::new("dataProcessing")))
             .map_err(|e| e.to_string())?;
     }
 
+    write_extension_elements(writer, &list.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("dataProcessingList")))
         .map_err(|e| e.to_string())?;
@@ -679,6 +826,7 @@ fn write_scan_settings_list(
     let mut tag = BytesStart::new("scanSettingsList");
     let count_s = count.to_string();
     tag.push_attribute(("count", count_s.as_str()));
+    push_extension_attributes(&mut tag, &list.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -696,6 +844,7 @@ fn write_scan_settings_list(
                 ss_tag.push_attribute(("instrumentConfigurationRef", r));
             }
         }
+        push_extension_attributes(&mut ss_tag, &ss.extensions);
 
         writer
             .write_event(Event::Start(ss_tag))
@@ -712,11 +861,13 @@ fn write_scan_settings_list(
             write_target_list(writer, tl)?;
         }
 
+        write_extension_elements(writer, &ss.extensions)?;
         writer
             .write_event(Event::End(BytesEnd::new("scanSettings")))
             .map_err(|e| e.to_string())?;
     }
 
+    write_extension_elements(writer, &list.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("scanSettingsList")))
         .map_err(|e| e.to_string())?;
@@ -743,6 +894,7 @@ fn write_run(
     if let Some(samp) = nonempty(run.sample_ref.as_deref()) {
         run_tag.push_attribute(("sampleRef", samp));
     }
+    push_extension_attributes(&mut run_tag, &run.extensions);
 
     writer
         .write_event(Event::Start(run_tag))
@@ -762,6 +914,7 @@ fn write_run(
         write_chromatogram_list(writer, cl, fallback_default_dp, idx)?;
     }
 
+    write_extension_elements(writer, &run.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("run")))
         .map_err(|e| e.to_string())?;
@@ -833,6 +986,7 @@ fn write_spectrum_list(
     if let Some(dp) = nonempty(dpr) {
         tag.push_attribute(("defaultDataProcessingRef", dp));
     }
+    push_extension_attributes(&mut tag, &list.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -842,6 +996,7 @@ fn write_spectrum_list(
         write_spectrum(writer, s, fallback_default_dp, idx)?;
     }
 
+    write_extension_elements(writer, &list.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("spectrumList")))
         .map_err(|e| e.to_string())?;
@@ -897,6 +1052,7 @@ fn write_spectrum(
     if let Some(v) = nonempty(s.spot_id.as_deref()) {
         tag.push_attribute(("spotID", v));
     }
+    push_extension_attributes(&mut tag, &s.extensions);
 
     let off = write_start_capture_offset(writer, tag)?;
     idx.spectrum.push(IndexOffsetAcc {
@@ -944,6 +1100,7 @@ fn write_spectrum(
         write_binary_data_array_list(writer, bdal, fallback_default_dp)?;
     }
 
+    write_extension_elements(writer, &s.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("spectrum")))
         .map_err(|e| e.to_string())?;
@@ -955,7 +1112,10 @@ fn write_spectrum_description(
     sd: &SpectrumDescription,
 ) -> Result<(), String> {
     writer
-        .write_event(Event::Start(BytesStart::new("spectrumDescription")))
+        .write_event(Event::Start(start_tag(
+            "spectrumDescription",
+            &sd.extensions,
+        )))
         .map_err(|e| e.to_string())?;
 
     write_referenceable_param_group_refs(writer, &sd.referenceable_param_group_refs)?;
@@ -972,6 +1132,7 @@ fn write_spectrum_description(
         write_product_list(writer, pr)?;
     }
 
+    write_extension_elements(writer, &sd.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("spectrumDescription")))
         .map_err(|e| e.to_string())?;
@@ -983,6 +1144,7 @@ fn write_scan_list(writer: &mut Writer<Vec<u8>>, list: &ScanList) -> Result<(),
     let mut tag = BytesStart::new("scanList");
     let count_s = count.to_string();
     tag.push_attribute(("count", count_s.as_str()));
+    push_extension_attributes(&mut tag, &list.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -1006,6 +1168,7 @@ fn write_scan_list(writer: &mut Writer<Vec<u8>>, list: &ScanList) -> Result<(),
         if let Some(v) = nonempty(s.spectrum_ref.as_deref()) {
             st.push_attribute(("spectrumRef", v));
         }
+        push_extension_attributes(&mut st, &s.extensions);
 
         writer
             .write_event(Event::Start(st))
@@ -1067,11 +1230,13 @@ fn write_scan_list(writer: &mut Writer<Vec<u8>>, list: &ScanList) -> Result<(),
             write_scan_window_list(writer, swl)?;
         }
 
+        write_extension_elements(writer, &s.extensions)?;
         writer
             .write_event(Event::End(BytesEnd::new("scan")))
             .map_err(|e| e.to_string())?;
     }
 
+    write_extension_elements(writer, &list.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("scanList")))
         .map_err(|e| e.to_string())?;
@@ -1086,6 +1251,7 @@ fn write_scan_window_list(
     let mut tag = BytesStart::new("scanWindowList");
     let count_s = count.to_string();
     tag.push_attribute(("count", count_s.as_str()));
+    push_extension_attributes(&mut tag, &list.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -1093,15 +1259,17 @@ fn write_scan_window_list(
 
     for w in &list.scan_windows {
         writer
-            .write_event(Event::Start(BytesStart::new("scanWindow")))
+            .write_event(Event::Start(start_tag("scanWindow", &w.extensions)))
             .map_err(|e| e.to_string())?;
         write_cv_params(writer, &w.cv_params)?;
         write_user_params(writer, &w.user_params)?;
+        write_extension_elements(writer, &w.extensions)?;
         writer
             .write_event(Event::End(BytesEnd::new("scanWindow")))
             .map_err(|e| e.to_string())?;
     }
 
+    write_extension_elements(writer, &list.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("scanWindowList")))
         .map_err(|e| e.to_string())?;
@@ -1113,6 +1281,7 @@ fn write_precursor_list(writer: &mut Writer<Vec<u8>>, list: &PrecursorList) -> R
     let mut tag = BytesStart::new("precursorList");
     let count_s = count.to_string();
     tag.push_attribute(("count", count_s.as_str()));
+    push_extension_attributes(&mut tag, &list.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -1122,6 +1291,7 @@ fn write_precursor_list(writer: &mut Writer<Vec<u8>>, list: &PrecursorList) -> R
         write_precursor(writer, p)?;
     }
 
+    write_extension_elements(writer, &list.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("precursorList")))
         .map_err(|e| e.to_string())?;
@@ -1139,6 +1309,7 @@ fn write_precursor(writer: &mut Writer<Vec<u8>>, p: &Precursor) -> Result<(), St
     if let Some(v) = nonempty(p.external_spectrum_id.as_deref()) {
         pt.push_attribute(("externalSpectrumID", v));
     }
+    push_extension_attributes(&mut pt, &p.extensions);
 
     writer
         .write_event(Event::Start(pt))
@@ -1151,6 +1322,7 @@ fn write_precursor(writer: &mut Writer<Vec<u8>>, p: &Precursor) -> Result<(), St
             &iw.referenceable_param_group_refs,
             &iw.cv_params,
             &iw.user_params,
+            &iw.extensions,
         )?;
     }
     if let Some(sil) = &p.selected_ion_list {
@@ -1163,9 +1335,11 @@ fn write_precursor(writer: &mut Writer<Vec<u8>>, p: &Precursor) -> Result<(), St
             &act.referenceable_param_group_refs,
             &act.cv_params,
             &act.user_params,
+            &act.extensions,
         )?;
     }
 
+    write_extension_elements(writer, &p.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("precursor")))
         .map_err(|e| e.to_string())?;
@@ -1180,6 +1354,7 @@ fn write_selected_ion_list(
     let mut tag = BytesStart::new("selectedIonList");
     let count_s = count.to_string();
     tag.push_attribute(("count", count_s.as_str()));
+    push_extension_attributes(&mut tag, &list.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -1187,18 +1362,20 @@ fn write_selected_ion_list(
 
     for si in &list.selected_ions {
         writer
-            .write_event(Event::Start(BytesStart::new("selectedIon")))
+            .write_event(Event::Start(start_tag("selectedIon", &si.extensions)))
             .map_err(|e| e.to_string())?;
 
         write_referenceable_param_group_refs(writer, &si.referenceable_param_group_refs)?;
         write_cv_params(writer, &si.cv_params)?;
         write_user_params(writer, &si.user_params)?;
 
+        write_extension_elements(writer, &si.extensions)?;
         writer
             .write_event(Event::End(BytesEnd::new("selectedIon")))
             .map_err(|e| e.to_string())?;
     }
 
+    write_extension_elements(writer, &list.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("selectedIonList")))
         .map_err(|e| e.to_string())?;
@@ -1210,6 +1387,7 @@ fn write_product_list(writer: &mut Writer<Vec<u8>>, list: &ProductList) -> Resul
     let mut tag = BytesStart::new("productList");
     let count_s = count.to_string();
     tag.push_attribute(("count", count_s.as_str()));
+    push_extension_attributes(&mut tag, &list.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -1219,6 +1397,7 @@ fn write_product_list(writer: &mut Writer<Vec<u8>>, list: &ProductList) -> Resul
         write_product(writer, p)?;
     }
 
+    write_extension_elements(writer, &list.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("productList")))
         .map_err(|e| e.to_string())?;
@@ -1236,6 +1415,7 @@ fn write_product(writer: &mut Writer<Vec<u8>>, p: &Product) -> Result<(), String
     if let Some(v) = nonempty(p.external_spectrum_id.as_deref()) {
         pt.push_attribute(("externalSpectrumID", v));
     }
+    push_extension_attributes(&mut pt, &p.extensions);
 
     writer
         .write_event(Event::Start(pt))
@@ -1248,9 +1428,11 @@ fn write_product(writer: &mut Writer<Vec<u8>>, p: &Product) -> Result<(), String
             &iw.referenceable_param_group_refs,
             &iw.cv_params,
             &iw.user_params,
+            &iw.extensions,
         )?;
     }
 
+    write_extension_elements(writer, &p.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("product")))
         .map_err(|e| e.to_string())?;
@@ -1272,6 +1454,7 @@ fn write_chromatogram_list(
     if let Some(dp) = nonempty(dpr) {
         tag.push_attribute(("defaultDataProcessingRef", dp));
     }
+    push_extension_attributes(&mut tag, &list.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -1281,6 +1464,7 @@ fn write_chromatogram_list(
         write_chromatogram(writer, c, fallback_default_dp, idx)?;
     }
 
+    write_extension_elements(writer, &list.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("chromatogramList")))
         .map_err(|e| e.to_string())?;
@@ -1319,6 +1503,7 @@ fn write_chromatogram(
     if let Some(v) = nonempty(dpr) {
         tag.push_attribute(("dataProcessingRef", v));
     }
+    push_extension_attributes(&mut tag, &c.extensions);
 
     let off = write_start_capture_offset(writer, tag)?;
     idx.chromatogram.push(IndexOffsetAcc {
@@ -1341,6 +1526,7 @@ fn write_chromatogram(
         write_binary_data_array_list(writer, bdal, fallback_default_dp)?;
     }
 
+    write_extension_elements(writer, &c.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("chromatogram")))
         .map_err(|e| e.to_string())?;
@@ -1357,6 +1543,7 @@ fn write_binary_data_array_list(
     let mut tag = BytesStart::new("binaryDataArrayList");
     let count_s = count.to_string();
     tag.push_attribute(("count", count_s.as_str()));
+    push_extension_attributes(&mut tag, &list.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -1366,6 +1553,7 @@ fn write_binary_data_array_list(
         write_binary_data_array(writer, bda, fallback_default_dp)?;
     }
 
+    write_extension_elements(writer, &list.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("binaryDataArrayList")))
         .map_err(|e| e.to_string())?;
@@ -1386,6 +1574,10 @@ fn write_binary_data_array(
 
     let cv_has_zlib = has_accession("MS:1000574"); // zlib compression
     let cv_has_no_comp = has_accession("MS:1000576"); // no compression
+    let numpress = bda
+        .cv_params
+        .iter()
+        .find_map(|p| p.accession.as_deref().and_then(Numpress::from_accession));
 
     let cv_has_f64 = has_accession("MS:1000523"); // 64-bit float
     let cv_has_f32 = has_accession("MS:1000521"); // 32-bit float
@@ -1449,12 +1641,18 @@ fn write_binary_data_array(
         }
     };
 
-    if !cv_has_zlib && !cv_has_no_comp {
+    if !cv_has_zlib && !cv_has_no_comp && numpress.is_none() {
         return Err(
             "binaryDataArray missing compression cvParam (MS:1000576 or MS:1000574)".into(),
         );
     }
-    if cv_has_zlib && !raw_bytes.is_empty() {
+    if let Some((codec, _)) = numpress
+        && array_len > 0
+    {
+        raw_bytes = codec.encode(&binary_to_f64(binary))?;
+    }
+    let zlib = cv_has_zlib || numpress.is_some_and(|(_, zlib)| zlib);
+    if zlib && !raw_bytes.is_empty() {
         raw_bytes = compress_to_vec_zlib(&raw_bytes, 6);
     }
 
@@ -1500,6 +1698,7 @@ fn write_binary_data_array(
             tag.push_attribute(("dataProcessingRef", dp));
         }
     }
+    push_extension_attributes(&mut tag, &bda.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -1515,6 +1714,7 @@ fn write_binary_data_array(
 
     write_cv_params(writer, &bda.cv_params)?;
     write_user_params(writer, &bda.user_params)?;
+    write_extension_elements(writer, &bda.extensions)?;
 
     writer
         .write_event(Event::Start(BytesStart::new("binary")))
@@ -1534,11 +1734,23 @@ fn write_binary_data_array(
     Ok(())
 }
 
+/// Values of `binary` widened to doubles, as Numpress expects.
+fn binary_to_f64(binary: &BinaryData) -> Vec<f64> {
+    match binary {
+        BinaryData::F64(v) => v.clone(),
+        BinaryData::F32(v) => v.iter().map(|&x| x as f64).collect(),
+        BinaryData::I64(v) => v.iter().map(|&x| x as f64).collect(),
+        BinaryData::I32(v) => v.iter().map(|&x| x as f64).collect(),
+        BinaryData::I16(v) => v.iter().map(|&x| x as f64).collect(),
+    }
+}
+
 fn write_target_list(writer: &mut Writer<Vec<u8>>, list: &TargetList) -> Result<(), String> {
     let count = list.count.unwrap_or(list.targets.len());
     let mut tag = BytesStart::new("targetList");
     let count_s = count.to_string();
     tag.push_attribute(("count", count_s.as_str()));
+    push_extension_attributes(&mut tag, &list.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -1546,18 +1758,20 @@ fn write_target_list(writer: &mut Writer<Vec<u8>>, list: &TargetList) -> Result<
 
     for t in &list.targets {
         writer
-            .write_event(Event::Start(BytesStart::new("target")))
+            .write_event(Event::Start(start_tag("target", &t.extensions)))
             .map_err(|e| e.to_string())?;
 
         write_referenceable_param_group_refs(writer, &t.referenceable_param_group_refs)?;
         write_cv_params(writer, &t.cv_params)?;
         write_user_params(writer, &t.user_params)?;
 
+        write_extension_elements(writer, &t.extensions)?;
         writer
             .write_event(Event::End(BytesEnd::new("target")))
             .map_err(|e| e.to_string())?;
     }
 
+    write_extension_elements(writer, &list.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("targetList")))
         .map_err(|e| e.to_string())?;
@@ -1572,6 +1786,7 @@ fn write_source_file_ref_list(
     let mut tag = BytesStart::new("sourceFileRefList");
     let count_s = count.to_string();
     tag.push_attribute(("count", count_s.as_str()));
+    push_extension_attributes(&mut tag, &list.extensions);
 
     writer
         .write_event(Event::Start(tag))
@@ -1585,12 +1800,36 @@ fn write_source_file_ref_list(
             .map_err(|e| e.to_string())?;
     }
 
+    write_extension_elements(writer, &list.extensions)?;
     writer
         .write_event(Event::End(BytesEnd::new("sourceFileRefList")))
         .map_err(|e| e.to_string())?;
     Ok(())
 }
 
+/// `name` start tag carrying the attributes of `ext`.
+fn start_tag<'a>(name: &'a str, ext: &Extensions) -> BytesStart<'a> {
+    let mut tag = BytesStart::new(name);
+    push_extension_attributes(&mut tag, ext);
+    tag
+}
+
+#[inline]
+fn push_extension_attributes(tag: &mut BytesStart, ext: &Extensions) {
+    for (k, v) in &ext.attributes {
+        tag.push_attribute((k.as_str(), v.as_str()));
+    }
+}
+
+/// Writes the elements of `ext` as is, each on its own line.
+fn write_extension_elements(writer: &mut Writer<Vec<u8>>, ext: &Extensions) -> Result<(), String> {
+    for element in &ext.elements {
+        writer.write_indent().map_err(|e| e.to_string())?;
+        writer.get_mut().extend_from_slice(element.as_bytes());
+    }
+    Ok(())
+}
+
 #[inline]
 fn write_referenceable_param_group_refs(
     writer: &mut Writer<Vec<u8>>,
@@ -1691,14 +1930,16 @@ fn write_cv_container(
     refs: &[ReferenceableParamGroupRef],
     cvs: &[CvParam],
     ups: &[UserParam],
+    ext: &Extensions,
 ) -> Result<(), String> {
     writer
-        .write_event(Event::Start(BytesStart::new(tag_name)))
+        .write_event(Event::Start(start_tag(tag_name, ext)))
         .map_err(|e| e.to_string())?;
 
     write_referenceable_param_group_refs(writer, refs)?;
     write_cv_params(writer, cvs)?;
     write_user_params(writer, ups)?;
+    write_extension_elements(writer, ext)?;
 
     writer
         .write_event(Event::End(BytesEnd::new(tag_name)))
@@ -1844,6 +2085,23 @@ fn write_index(
     Ok(())
 }
 
+/// <fileChecksum>: SHA-1 of everything written up to and including its start tag.
+fn write_file_checksum(writer: &mut Writer<Vec<u8>>) -> Result<(), String> {
+    writer
+        .write_event(Event::Start(BytesStart::new("fileChecksum")))
+        .map_err(|e| e.to_string())?;
+
+    let s = sha1_hex(writer.get_ref());
+    writer
+        .write_event(Event::Text(BytesText::new(s.as_str())))
+        .map_err(|e| e.to_string())?;
+
+    writer
+        .write_event(Event::End(BytesEnd::new("fileChecksum")))
+        .map_err(|e| e.to_string())?;
+    Ok(())
+}
+
 fn write_index_list_offset(writer: &mut Writer<Vec<u8>>, off: u64) -> Result<(), String> {
     writer
         .write_event(Event::Start(BytesStart::new("indexListOffset")))
@@ -0,0 +1,214 @@
+use std::{
+    panic,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use crate::mzml::{
+    error::Cause,
+    parse_mzml::{BinaryArrayFlags, binary_array_flags, decode_payload},
+    structs::{BinaryData, BinaryDataArray, Chromatogram, Spectrum},
+};
+
+/// Pending base64 text after which the collected payloads are decoded, so a
+/// large list is not held twice in memory.
+const FLUSH_BYTES: usize = 64 * 1024 * 1024;
+
+/// Where `<binary>` payloads go while the XML around them is parsed.
+pub(crate) enum Binaries {
+    /// Decoded on the spot.
+    Inline,
+    /// Collected for the items of one list and decoded on `threads` workers.
+    Deferred {
+        threads: usize,
+        payloads: Vec<Payload>,
+        pending_bytes: usize,
+        item: usize,
+        array: usize,
+    },
+}
+
+pub(crate) struct Payload {
+    item: usize,
+    array: usize,
+    /// End of `</binary>`, for errors.
+    offset: u64,
+    flags: BinaryArrayFlags,
+    array_length: Option<usize>,
+    text: String,
+}
+
+impl Payload {
+    #[inline]
+    fn decode(&self) -> Result<Option<BinaryData>, Cause> {
+        decode_payload(&self.text, self.flags, self.array_length)
+    }
+}
+
+/// Item of a list whose arrays receive the decoded payloads.
+pub(crate) trait WithArrays {
+    fn arrays_mut(&mut self) -> &mut [BinaryDataArray];
+}
+
+impl WithArrays for Spectrum {
+    #[inline]
+    fn arrays_mut(&mut self) -> &mut [BinaryDataArray] {
+        self.binary_data_array_list
+            .as_mut()
+            .map_or(&mut [], |l| &mut l.binary_data_arrays[..])
+    }
+}
+
+impl WithArrays for Chromatogram {
+    #[inline]
+    fn arrays_mut(&mut self) -> &mut [BinaryDataArray] {
+        self.binary_data_array_list
+            .as_mut()
+            .map_or(&mut [], |l| &mut l.binary_data_arrays[..])
+    }
+}
+
+impl Binaries {
+    /// Inline for a single thread, deferred otherwise.
+    pub(crate) fn new(threads: usize) -> Self {
+        if threads <= 1 {
+            return Binaries::Inline;
+        }
+        Binaries::Deferred {
+            threads,
+            payloads: Vec::new(),
+            pending_bytes: 0,
+            item: 0,
+            array: 0,
+        }
+    }
+
+    /// The arrays that follow belong to the item at `index` in its list.
+    #[inline]
+    pub(crate) fn start_item(&mut self, index: usize) {
+        if let Binaries::Deferred { item, array, .. } = self {
+            *item = index;
+            *array = 0;
+        }
+    }
+
+    /// The current `<binaryDataArray>` is complete.
+    #[inline]
+    pub(crate) fn end_array(&mut self) {
+        if let Binaries::Deferred { array, .. } = self {
+            *array += 1;
+        }
+    }
+
+    /// `<binary>` text of `a`, read up to `offset`.
+    pub(crate) fn push(
+        &mut self,
+        a: &mut BinaryDataArray,
+        text: String,
+        offset: u64,
+    ) -> Result<(), Cause> {
+        let flags = binary_array_flags(a)?;
+        a.numeric_type = Some(flags.numeric_type);
+
+        match self {
+            Binaries::Inline => a.binary = decode_payload(&text, flags, a.array_length)?,
+            Binaries::Deferred {
+                payloads,
+                pending_bytes,
+                item,
+                array,
+                ..
+            } => {
+                *pending_bytes += text.len();
+                payloads.push(Payload {
+                    item: *item,
+                    array: *array,
+                    offset,
+                    flags,
+                    array_length: a.array_length,
+                    text,
+                });
+            }
+        }
+        Ok(())
+    }
+
+    /// Decodes the collected payloads once they are large enough.
+    #[inline]
+    pub(crate) fn flush_if_full<T: WithArrays>(&mut self, items: &mut [T]) -> Result<(), Cause> {
+        match self {
+            Binaries::Deferred { pending_bytes, .. } if *pending_bytes >= FLUSH_BYTES => {
+                self.flush(items)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Decodes the collected payloads into `items`. The error of the first
+    /// payload, in document order, that fails is returned.
+    pub(crate) fn flush<T: WithArrays>(&mut self, items: &mut [T]) -> Result<(), Cause> {
+        let Binaries::Deferred {
+            threads,
+            payloads,
+            pending_bytes,
+            ..
+        } = self
+        else {
+            return Ok(());
+        };
+        let payloads = std::mem::take(payloads);
+        *pending_bytes = 0;
+
+        for (p, decoded) in payloads.iter().zip(decode_all(&payloads, *threads)) {
+            let binary = decoded.map_err(|cause| Cause::At(Box::new(cause), p.offset))?;
+            let target = items
+                .get_mut(p.item)
+                .and_then(|it| it.arrays_mut().get_mut(p.array));
+            if let Some(a) = target {
+                a.binary = binary;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Decodes `payloads` on up to `threads` workers, keeping their order.
+fn decode_all(payloads: &[Payload], threads: usize) -> Vec<Result<Option<BinaryData>, Cause>> {
+    let workers = threads.min(payloads.len());
+    if workers <= 1 {
+        return payloads.iter().map(Payload::decode).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let mut results: Vec<Option<Result<Option<BinaryData>, Cause>>> =
+        (0..payloads.len()).map(|_| None).collect();
+
+    thread::scope(|s| {
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                s.spawn(|| {
+                    let mut out = Vec::new();
+                    loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(p) = payloads.get(i) else {
+                            return out;
+                        };
+                        out.push((i, p.decode()));
+                    }
+                })
+            })
+            .collect();
+
+        for h in handles {
+            let decoded = h.join().unwrap_or_else(|e| panic::resume_unwind(e));
+            for (i, r) in decoded {
+                results[i] = Some(r);
+            }
+        }
+    });
+
+    results
+        .into_iter()
+        .map(|r| r.expect("every payload is decoded"))
+        .collect()
+}
@@ -0,0 +1,168 @@
+use crate::mzml::{
+    error::{Cause, ParseError},
+    gzip::maybe_gunzip,
+    indexed::scan_tags,
+};
+
+/// Bytes at the end of the document searched for `<fileChecksum>`.
+const TAIL_LEN: usize = 4096;
+
+/// SHA-1, as used by the indexedmzML `<fileChecksum>`.
+#[derive(Clone)]
+pub(crate) struct Sha1 {
+    state: [u32; 5],
+    block: [u8; 64],
+    filled: usize,
+    len: u64,
+}
+
+impl Default for Sha1 {
+    fn default() -> Self {
+        Self {
+            state: [
+                0x6745_2301,
+                0xEFCD_AB89,
+                0x98BA_DCFE,
+                0x1032_5476,
+                0xC3D2_E1F0,
+            ],
+            block: [0; 64],
+            filled: 0,
+            len: 0,
+        }
+    }
+}
+
+impl Sha1 {
+    pub(crate) fn update(&mut self, mut bytes: &[u8]) {
+        self.len = self.len.wrapping_add(bytes.len() as u64);
+
+        if self.filled > 0 {
+            let n = (64 - self.filled).min(bytes.len());
+            self.block[self.filled..self.filled + n].copy_from_slice(&bytes[..n]);
+            self.filled += n;
+            bytes = &bytes[n..];
+            if self.filled < 64 {
+                return;
+            }
+            let block = self.block;
+            self.compress(&block);
+            self.filled = 0;
+        }
+
+        let mut chunks = bytes.chunks_exact(64);
+        for chunk in &mut chunks {
+            self.compress(chunk.try_into().expect("64-byte chunk"));
+        }
+        let rest = chunks.remainder();
+        self.block[..rest.len()].copy_from_slice(rest);
+        self.filled = rest.len();
+    }
+
+    pub(crate) fn finish(mut self) -> [u8; 20] {
+        let bits = self.len.wrapping_mul(8);
+        self.update(&[0x80]);
+        while self.filled != 56 {
+            self.update(&[0]);
+        }
+        self.update(&bits.to_be_bytes());
+
+        let mut out = [0u8; 20];
+        for (o, s) in out.chunks_exact_mut(4).zip(self.state) {
+            o.copy_from_slice(&s.to_be_bytes());
+        }
+        out
+    }
+
+    fn compress(&mut self, block: &[u8; 64]) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes([word[0], word[1], word[2], word[3]]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+
+        let [mut a, mut b, mut c, mut d, mut e] = self.state;
+        for (i, &wi) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..20 => ((b & c) | (!b & d), 0x5A82_7999),
+                20..40 => (b ^ c ^ d, 0x6ED9_EBA1),
+                40..60 => ((b & c) | (b & d) | (c & d), 0x8F1B_BCDC),
+                _ => (b ^ c ^ d, 0xCA62_C1D6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(wi);
+            e = d;
+            d = c;
+            c = b.rotate_left(30);
+            b = a;
+            a = t;
+        }
+
+        for (s, v) in self.state.iter_mut().zip([a, b, c, d, e]) {
+            *s = s.wrapping_add(v);
+        }
+    }
+}
+
+/// Lowercase hex SHA-1 of `bytes`.
+pub fn sha1_hex(bytes: &[u8]) -> String {
+    let mut h = Sha1::default();
+    h.update(bytes);
+    h.finish().iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Where the `<fileChecksum>` of an indexedmzML document sits.
+pub(crate) struct FileChecksum<'a> {
+    /// One past the `>` of `<fileChecksum>`: the hash covers `bytes[..prefix_end]`.
+    pub(crate) prefix_end: usize,
+    /// The recorded value, trimmed.
+    pub(crate) value: &'a str,
+}
+
+/// The last `<fileChecksum>` near the end of `bytes`.
+pub(crate) fn find_file_checksum(bytes: &[u8]) -> Option<FileChecksum<'_>> {
+    let tail_start = bytes.len().saturating_sub(TAIL_LEN);
+    let tail = &bytes[tail_start..];
+    let tag = scan_tags(tail)
+        .filter(|t| !t.closing && t.name == b"fileChecksum" && !tail[..t.end].ends_with(b"/>"))
+        .last()?;
+    let rest = &tail[tag.end..];
+    let text = &rest[..rest.iter().position(|&b| b == b'<')?];
+    Some(FileChecksum {
+        prefix_end: tail_start + tag.end,
+        value: std::str::from_utf8(text).ok()?.trim(),
+    })
+}
+
+/// Checks the indexedmzML `<fileChecksum>` of `bytes` (gzip-compressed input
+/// is inflated first).
+///
+/// Returns `Ok(false)` when the document carries no checksum, and an
+/// [`ParseError::Invalid`] error when it does not match the SHA-1 of the
+/// document up to and including `<fileChecksum>`.
+pub fn verify_file_checksum(bytes: &[u8]) -> Result<bool, ParseError> {
+    let bytes = maybe_gunzip(bytes).map_err(|e| Cause::Io(e).at(0, String::new()))?;
+    check_file_checksum(&bytes)
+}
+
+/// [`verify_file_checksum`] of an inflated document.
+pub(crate) fn check_file_checksum(bytes: &[u8]) -> Result<bool, ParseError> {
+    let Some(found) = find_file_checksum(bytes) else {
+        return Ok(false);
+    };
+    let computed = sha1_hex(&bytes[..found.prefix_end]);
+    if !computed.eq_ignore_ascii_case(found.value) {
+        return Err(Cause::Invalid(format!(
+            "SHA-1 mismatch: file says {}, content is {computed}",
+            found.value
+        ))
+        .at(found.prefix_end as u64, "fileChecksum".to_string()));
+    }
+    Ok(true)
+}
@@ -0,0 +1,243 @@
+use std::{fmt, io::BufRead};
+
+use quick_xml::{Reader, events::Event};
+
+use crate::mzml::parse_mzml::{get_attr, local_name};
+
+/// Failure while parsing mzML, with where it happened.
+///
+/// `offset` is the byte position in the (decompressed) document at which the
+/// problem was detected. `path` names the elements open at that point, from
+/// `<run>` down; children of list elements carry their `id`, or their
+/// 1-based position when they have none, e.g.
+/// `run/spectrumList/spectrum[id=scan=3]/binaryDataArrayList/binaryDataArray[2]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// The document is not well-formed XML.
+    Xml {
+        offset: u64,
+        path: String,
+        message: String,
+    },
+    /// A `<binary>` payload does not decode (base64, zlib or Numpress).
+    Binary {
+        offset: u64,
+        path: String,
+        message: String,
+    },
+    /// Well-formed content that cannot be interpreted, e.g. conflicting
+    /// cvParams.
+    Invalid {
+        offset: u64,
+        path: String,
+        message: String,
+    },
+    /// The input could not be read or decompressed.
+    Io {
+        offset: u64,
+        path: String,
+        message: String,
+    },
+}
+
+impl ParseError {
+    #[inline]
+    pub fn offset(&self) -> u64 {
+        match self {
+            ParseError::Xml { offset, .. }
+            | ParseError::Binary { offset, .. }
+            | ParseError::Invalid { offset, .. }
+            | ParseError::Io { offset, .. } => *offset,
+        }
+    }
+
+    #[inline]
+    pub fn path(&self) -> &str {
+        match self {
+            ParseError::Xml { path, .. }
+            | ParseError::Binary { path, .. }
+            | ParseError::Invalid { path, .. }
+            | ParseError::Io { path, .. } => path,
+        }
+    }
+
+    #[inline]
+    pub fn message(&self) -> &str {
+        match self {
+            ParseError::Xml { message, .. }
+            | ParseError::Binary { message, .. }
+            | ParseError::Invalid { message, .. }
+            | ParseError::Io { message, .. } => message,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let what = match self {
+            ParseError::Xml { .. } => "malformed XML",
+            ParseError::Binary { .. } => "bad binary data",
+            ParseError::Invalid { .. } => "invalid content",
+            ParseError::Io { .. } => "read failed",
+        };
+        write!(f, "{what}: {} at byte {}", self.message(), self.offset())?;
+        if !self.path().is_empty() {
+            write!(f, " in {}", self.path())?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+/// Cause of a failure inside the parser, before its location is known.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Cause {
+    Xml(String),
+    Binary(String),
+    Invalid(String),
+    Io(String),
+    /// Detected at a known offset rather than where the reader stopped, e.g.
+    /// a payload decoded after the XML around it was read.
+    At(Box<Cause>, u64),
+}
+
+impl Cause {
+    /// Byte offset of this cause, given the reader that was parsing.
+    pub(crate) fn offset<R>(&self, reader: &Reader<R>) -> u64 {
+        match self {
+            Cause::At(_, offset) => *offset,
+            Cause::Xml(_) if reader.error_position() > 0 => reader.error_position(),
+            _ => reader.buffer_position(),
+        }
+    }
+
+    pub(crate) fn at(self, offset: u64, path: String) -> ParseError {
+        match self {
+            Cause::At(cause, _) => cause.at(offset, path),
+            Cause::Xml(message) => ParseError::Xml {
+                offset,
+                path,
+                message,
+            },
+            Cause::Binary(message) => ParseError::Binary {
+                offset,
+                path,
+                message,
+            },
+            Cause::Invalid(message) => ParseError::Invalid {
+                offset,
+                path,
+                message,
+            },
+            Cause::Io(message) => ParseError::Io {
+                offset,
+                path,
+                message,
+            },
+        }
+    }
+}
+
+impl From<quick_xml::Error> for Cause {
+    #[inline]
+    fn from(e: quick_xml::Error) -> Self {
+        Cause::Xml(e.to_string())
+    }
+}
+
+impl From<String> for Cause {
+    #[inline]
+    fn from(message: String) -> Self {
+        Cause::Invalid(message)
+    }
+}
+
+impl From<&str> for Cause {
+    #[inline]
+    fn from(message: &str) -> Self {
+        Cause::Invalid(message.to_string())
+    }
+}
+
+struct Open {
+    label: String,
+    is_list: bool,
+    children: Vec<(Vec<u8>, usize)>,
+}
+
+impl Open {
+    fn new(name: &[u8], label: String) -> Self {
+        Self {
+            is_list: name.ends_with(b"List"),
+            label,
+            children: Vec::new(),
+        }
+    }
+
+    /// 1-based position of the next child called `name`.
+    fn next_child(&mut self, name: &[u8]) -> usize {
+        match self.children.iter_mut().find(|(n, _)| n == name) {
+            Some((_, count)) => {
+                *count += 1;
+                *count
+            }
+            None => {
+                self.children.push((name.to_vec(), 1));
+                1
+            }
+        }
+    }
+}
+
+/// Path of the elements still open at the end of `source`, below the
+/// `ancestors` already known to enclose them.
+pub(crate) fn element_path<R: BufRead>(source: R, ancestors: &[&str]) -> String {
+    let mut stack: Vec<Open> = ancestors
+        .iter()
+        .map(|a| Open::new(a.as_bytes(), a.to_string()))
+        .collect();
+
+    let mut reader = Reader::from_reader(source);
+    reader.config_mut().check_end_names = false;
+    let mut buf = Vec::new();
+
+    loop {
+        match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) => {
+                let name = local_name(e.name().as_ref()).to_vec();
+                let text = String::from_utf8_lossy(&name).into_owned();
+                let label = match stack.last_mut() {
+                    Some(parent) if parent.is_list => {
+                        let n = parent.next_child(&name);
+                        match get_attr(&e, b"id") {
+                            Some(id) => format!("{text}[id={id}]"),
+                            None => format!("{text}[{n}]"),
+                        }
+                    }
+                    _ => text,
+                };
+                stack.push(Open::new(&name, label));
+            }
+            Ok(Event::Empty(e)) => {
+                let name = local_name(e.name().as_ref()).to_vec();
+                if let Some(parent) = stack.last_mut() {
+                    parent.next_child(&name);
+                }
+            }
+            Ok(Event::End(_)) => {
+                stack.pop();
+            }
+            Ok(Event::Eof) | Err(_) => break,
+            Ok(_) => {}
+        }
+        buf.clear();
+    }
+
+    stack
+        .iter()
+        .map(|o| o.label.as_str())
+        .filter(|l| !matches!(*l, "mzML" | "indexedmzML"))
+        .collect::<Vec<_>>()
+        .join("/")
+}
@@ -0,0 +1,296 @@
+use std::{
+    borrow::Cow,
+    io::{self, BufRead, Read},
+};
+
+use miniz_oxide::{
+    DataFormat, MZFlush, MZStatus,
+    inflate::stream::{InflateState, inflate},
+};
+
+const GZIP_MAGIC: [u8; 2] = [0x1f, 0x8b];
+const CM_DEFLATE: u8 = 8;
+const OUT_BUF_LEN: usize = 64 * 1024;
+
+const FHCRC: u8 = 0x02;
+const FEXTRA: u8 = 0x04;
+const FNAME: u8 = 0x08;
+const FCOMMENT: u8 = 0x10;
+
+/// `true` when `bytes` start with the gzip magic number.
+#[inline]
+pub fn is_gzip(bytes: &[u8]) -> bool {
+    bytes.starts_with(&GZIP_MAGIC)
+}
+
+/// Inflates `bytes` when they are gzip-compressed; borrows them otherwise.
+pub fn maybe_gunzip(bytes: &[u8]) -> Result<Cow<'_, [u8]>, String> {
+    if !is_gzip(bytes) {
+        return Ok(Cow::Borrowed(bytes));
+    }
+    let mut out = Vec::with_capacity(bytes.len().saturating_mul(4));
+    GzipReader::new(bytes)
+        .read_to_end(&mut out)
+        .map_err(|e| format!("gunzip: {e}"))?;
+    Ok(Cow::Owned(out))
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Stage {
+    /// Expecting the first member header.
+    Start,
+    Body,
+    /// Expecting another member header or end of input.
+    Member,
+    Done,
+}
+
+/// Streaming gzip decoder.
+///
+/// Handles multi-member files (as written by `bgzip` or `cat a.gz b.gz`) and
+/// checks the CRC-32 and size in each member trailer.
+pub struct GzipReader<R: BufRead> {
+    inner: R,
+    state: Box<InflateState>,
+    out: Vec<u8>,
+    pos: usize,
+    end: usize,
+    stage: Stage,
+    crc: u32,
+    size: u32,
+}
+
+impl<R: BufRead> GzipReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            state: InflateState::new_boxed(DataFormat::Raw),
+            out: vec![0u8; OUT_BUF_LEN],
+            pos: 0,
+            end: 0,
+            stage: Stage::Start,
+            crc: 0,
+            size: 0,
+        }
+    }
+
+    #[inline]
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+
+    #[inline]
+    pub fn into_inner(self) -> R {
+        self.inner
+    }
+
+    /// Inflates the next chunk into `out`; leaves `end == 0` at end of input.
+    fn fill_out(&mut self) -> io::Result<()> {
+        self.pos = 0;
+        self.end = 0;
+
+        loop {
+            match self.stage {
+                Stage::Done => return Ok(()),
+                Stage::Start | Stage::Member => {
+                    // Anything after the last member other than another gzip
+                    // header (e.g. zero padding) is ignored, as gunzip does.
+                    if self.stage == Stage::Member
+                        && self.inner.fill_buf()?.first() != Some(&GZIP_MAGIC[0])
+                    {
+                        self.stage = Stage::Done;
+                        continue;
+                    }
+                    read_member_header(&mut self.inner)?;
+                    self.state.reset(DataFormat::Raw);
+                    self.crc = 0;
+                    self.size = 0;
+                    self.stage = Stage::Body;
+                }
+                Stage::Body => {
+                    let input = self.inner.fill_buf()?;
+                    if input.is_empty() {
+                        return Err(invalid_data("truncated gzip stream"));
+                    }
+
+                    let res = inflate(&mut self.state, input, &mut self.out, MZFlush::None);
+                    self.inner.consume(res.bytes_consumed);
+                    let status = res
+                        .status
+                        .map_err(|e| invalid_data(&format!("inflate failed: {e:?}")))?;
+
+                    let written = &self.out[..res.bytes_written];
+                    self.crc = crc32_update(self.crc, written);
+                    self.size = self.size.wrapping_add(written.len() as u32);
+                    self.end = written.len();
+
+                    if status == MZStatus::StreamEnd {
+                        self.check_trailer()?;
+                        self.stage = Stage::Member;
+                    }
+                    if self.end > 0 {
+                        return Ok(());
+                    }
+                }
+            }
+        }
+    }
+
+    fn check_trailer(&mut self) -> io::Result<()> {
+        let mut trailer = [0u8; 8];
+        self.inner
+            .read_exact(&mut trailer)
+            .map_err(|_| invalid_data("truncated gzip trailer"))?;
+
+        let crc = u32::from_le_bytes(trailer[0..4].try_into().unwrap());
+        let size = u32::from_le_bytes(trailer[4..8].try_into().unwrap());
+        if crc != self.crc {
+            return Err(invalid_data("gzip CRC-32 mismatch"));
+        }
+        if size != self.size {
+            return Err(invalid_data("gzip size mismatch"));
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for GzipReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let available = self.fill_buf()?;
+        let n = available.len().min(buf.len());
+        buf[..n].copy_from_slice(&available[..n]);
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl<R: BufRead> BufRead for GzipReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if self.pos == self.end {
+            self.fill_out()?;
+        }
+        Ok(&self.out[self.pos..self.end])
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        self.pos = (self.pos + amt).min(self.end);
+    }
+}
+
+/// Either a plain or a gzip-compressed source, chosen from its first bytes.
+pub enum MaybeGzip<R: BufRead> {
+    Plain(R),
+    Gzip(GzipReader<R>),
+}
+
+impl<R: BufRead> MaybeGzip<R> {
+    pub fn new(mut inner: R) -> io::Result<Self> {
+        Ok(if is_gzip(inner.fill_buf()?) {
+            Self::Gzip(GzipReader::new(inner))
+        } else {
+            Self::Plain(inner)
+        })
+    }
+
+    #[inline]
+    pub fn is_gzip(&self) -> bool {
+        matches!(self, Self::Gzip(_))
+    }
+}
+
+impl<R: BufRead> Read for MaybeGzip<R> {
+    #[inline]
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            Self::Plain(r) => r.read(buf),
+            Self::Gzip(r) => r.read(buf),
+        }
+    }
+}
+
+impl<R: BufRead> BufRead for MaybeGzip<R> {
+    #[inline]
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        match self {
+            Self::Plain(r) => r.fill_buf(),
+            Self::Gzip(r) => r.fill_buf(),
+        }
+    }
+
+    #[inline]
+    fn consume(&mut self, amt: usize) {
+        match self {
+            Self::Plain(r) => r.consume(amt),
+            Self::Gzip(r) => r.consume(amt),
+        }
+    }
+}
+
+/// Reads a member header (RFC 1952, section 2.3).
+fn read_member_header<R: BufRead>(r: &mut R) -> io::Result<()> {
+    let mut fixed = [0u8; 10];
+    r.read_exact(&mut fixed)
+        .map_err(|_| invalid_data("truncated gzip header"))?;
+    if !is_gzip(&fixed) {
+        return Err(invalid_data("not a gzip stream"));
+    }
+    if fixed[2] != CM_DEFLATE {
+        return Err(invalid_data(&format!(
+            "unsupported gzip compression method {}",
+            fixed[2]
+        )));
+    }
+
+    let flags = fixed[3];
+    if flags & FEXTRA != 0 {
+        let mut len = [0u8; 2];
+        r.read_exact(&mut len)?;
+        let mut extra = vec![0u8; u16::from_le_bytes(len) as usize];
+        r.read_exact(&mut extra)?;
+    }
+    if flags & FNAME != 0 {
+        r.read_until(0, &mut Vec::new())?;
+    }
+    if flags & FCOMMENT != 0 {
+        r.read_until(0, &mut Vec::new())?;
+    }
+    if flags & FHCRC != 0 {
+        r.read_exact(&mut [0u8; 2])?;
+    }
+    Ok(())
+}
+
+#[inline]
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.to_string())
+}
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xEDB8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+/// CRC-32 (IEEE) of `bytes`, continuing from `crc`.
+pub(crate) fn crc32_update(crc: u32, bytes: &[u8]) -> u32 {
+    let mut c = !crc;
+    for &b in bytes {
+        c = CRC32_TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8);
+    }
+    !c
+}
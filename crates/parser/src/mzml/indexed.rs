@@ -0,0 +1,535 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufRead, BufReader, Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use quick_xml::{
+    Reader,
+    events::{BytesStart, Event},
+};
+
+use crate::mzml::{
+    error::{Cause, ParseError, element_path},
+    parse_mzml::{
+        IndexOffset, chromatogram_from_start, get_attr, local_name, parse_chromatogram,
+        parse_index_list, parse_spectrum, spectrum_from_start,
+    },
+    structs::{Chromatogram, Spectrum},
+};
+
+pub(crate) const TAIL_LEN: u64 = 4096;
+
+/// Bytes read at a time while looking for `</indexList>`.
+const INDEX_LIST_CHUNK: u64 = 64 * 1024;
+
+/// Longest `<indexList>` read before the index is treated as missing.
+const INDEX_LIST_MAX_LEN: u64 = 256 * 1024 * 1024;
+
+/// Random access to the spectra and chromatograms of an mzML file.
+///
+/// The `<indexList>` of an indexedmzML file is located through
+/// `<indexListOffset>` at the end of the file. When the offsets are missing,
+/// or an offset does not land on the element it names, the index is rebuilt
+/// by scanning the file once.
+pub struct IndexedMzML<R> {
+    source: R,
+    spectra: ItemIndex,
+    chromatograms: ItemIndex,
+    rebuilt: bool,
+}
+
+#[derive(Default)]
+pub(crate) struct ItemIndex {
+    offsets: Vec<IndexOffset>,
+    by_id: HashMap<String, usize>,
+}
+
+impl ItemIndex {
+    fn new(offsets: Vec<IndexOffset>) -> Self {
+        let mut by_id = HashMap::with_capacity(offsets.len());
+        for (i, o) in offsets.iter().enumerate() {
+            if let Some(id) = o.id_ref.as_deref() {
+                by_id.entry(id.to_string()).or_insert(i);
+            }
+        }
+        Self { offsets, by_id }
+    }
+}
+
+/// Element that can be parsed from an indexed offset.
+pub(crate) trait IndexedItem: Sized {
+    const TAG: &'static [u8];
+    const NAME: &'static str;
+    const LIST: &'static str;
+
+    fn parse<B: BufRead>(reader: &mut Reader<B>, start: &BytesStart) -> Result<Self, Cause>;
+    fn from_empty(start: &BytesStart) -> Self;
+    fn id(&self) -> &str;
+    fn index<R>(mzml: &IndexedMzML<R>) -> &ItemIndex;
+}
+
+impl IndexedItem for Spectrum {
+    const TAG: &'static [u8] = b"spectrum";
+    const NAME: &'static str = "spectrum";
+    const LIST: &'static str = "spectrumList";
+
+    #[inline]
+    fn parse<B: BufRead>(reader: &mut Reader<B>, start: &BytesStart) -> Result<Self, Cause> {
+        parse_spectrum(reader, start)
+    }
+
+    #[inline]
+    fn from_empty(start: &BytesStart) -> Self {
+        spectrum_from_start(start)
+    }
+
+    #[inline]
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    #[inline]
+    fn index<R>(mzml: &IndexedMzML<R>) -> &ItemIndex {
+        &mzml.spectra
+    }
+}
+
+impl IndexedItem for Chromatogram {
+    const TAG: &'static [u8] = b"chromatogram";
+    const NAME: &'static str = "chromatogram";
+    const LIST: &'static str = "chromatogramList";
+
+    #[inline]
+    fn parse<B: BufRead>(reader: &mut Reader<B>, start: &BytesStart) -> Result<Self, Cause> {
+        parse_chromatogram(reader, start)
+    }
+
+    #[inline]
+    fn from_empty(start: &BytesStart) -> Self {
+        chromatogram_from_start(start)
+    }
+
+    #[inline]
+    fn id(&self) -> &str {
+        &self.id
+    }
+
+    #[inline]
+    fn index<R>(mzml: &IndexedMzML<R>) -> &ItemIndex {
+        &mzml.chromatograms
+    }
+}
+
+impl IndexedMzML<BufReader<File>> {
+    /// Opens the mzML file at `path`.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ParseError> {
+        let file = File::open(path.as_ref())
+            .map_err(|e| io_error(0, format!("open {}: {e}", path.as_ref().display())))?;
+        Self::new(BufReader::new(file))
+    }
+}
+
+impl<R: Read + Seek> IndexedMzML<R> {
+    /// Loads the `<indexList>` of `source`, scanning the file when it has none.
+    pub fn new(source: R) -> Result<Self, ParseError> {
+        let mut this = Self {
+            source,
+            spectra: ItemIndex::default(),
+            chromatograms: ItemIndex::default(),
+            rebuilt: false,
+        };
+
+        if !this.load_index_list()? {
+            this.rebuild_index()?;
+        }
+        Ok(this)
+    }
+
+    /// Number of spectra.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.spectra.offsets.len()
+    }
+
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.spectra.offsets.is_empty()
+    }
+
+    /// Number of chromatograms.
+    #[inline]
+    pub fn chromatogram_count(&self) -> usize {
+        self.chromatograms.offsets.len()
+    }
+
+    /// Byte offsets of the `<spectrum>` elements.
+    #[inline]
+    pub fn spectrum_offsets(&self) -> &[IndexOffset] {
+        &self.spectra.offsets
+    }
+
+    /// Byte offsets of the `<chromatogram>` elements.
+    #[inline]
+    pub fn chromatogram_offsets(&self) -> &[IndexOffset] {
+        &self.chromatograms.offsets
+    }
+
+    /// `true` when the offsets come from scanning the file rather than from
+    /// its `<indexList>`.
+    #[inline]
+    pub fn index_rebuilt(&self) -> bool {
+        self.rebuilt
+    }
+
+    /// <spectrum> at ordinal `i`.
+    pub fn spectrum(&mut self, i: usize) -> Result<Spectrum, ParseError> {
+        self.item(i)
+    }
+
+    /// <spectrum> with `id`.
+    pub fn spectrum_by_id(&mut self, id: &str) -> Result<Spectrum, ParseError> {
+        let i = self.ordinal::<Spectrum>(id)?;
+        self.item(i)
+    }
+
+    /// <chromatogram> at ordinal `i`.
+    pub fn chromatogram(&mut self, i: usize) -> Result<Chromatogram, ParseError> {
+        self.item(i)
+    }
+
+    /// <chromatogram> with `id`.
+    pub fn chromatogram_by_id(&mut self, id: &str) -> Result<Chromatogram, ParseError> {
+        let i = self.ordinal::<Chromatogram>(id)?;
+        self.item(i)
+    }
+
+    fn ordinal<T: IndexedItem>(&mut self, id: &str) -> Result<usize, ParseError> {
+        if let Some(&i) = T::index(self).by_id.get(id) {
+            return Ok(i);
+        }
+        if !self.rebuilt {
+            self.rebuild_index()?;
+            if let Some(&i) = T::index(self).by_id.get(id) {
+                return Ok(i);
+            }
+        }
+        Err(Cause::Invalid(format!("{} not found: {id}", T::NAME)).at(0, String::new()))
+    }
+
+    fn item<T: IndexedItem>(&mut self, i: usize) -> Result<T, ParseError> {
+        let count = T::index(self).offsets.len();
+        // An index that lists this kind of item is trusted for the count; only
+        // a missing one is worth a scan.
+        if i >= count && (self.rebuilt || count > 0) {
+            return Err(
+                Cause::Invalid(format!("{} index out of range: {i}", T::NAME)).at(0, String::new()),
+            );
+        }
+        if let Some(item) = self.item_at_offset(i)? {
+            return Ok(item);
+        }
+        if !self.rebuilt {
+            self.rebuild_index()?;
+            if let Some(item) = self.item_at_offset(i)? {
+                return Ok(item);
+            }
+        }
+        Err(Cause::Invalid(format!("{} index out of range: {i}", T::NAME)).at(0, String::new()))
+    }
+
+    /// Parses the element at the indexed offset; `None` when the offset does
+    /// not point at that element.
+    fn item_at_offset<T: IndexedItem>(&mut self, i: usize) -> Result<Option<T>, ParseError> {
+        let Some(entry) = T::index(self).offsets.get(i).cloned() else {
+            return Ok(None);
+        };
+
+        self.source
+            .seek(SeekFrom::Start(entry.offset))
+            .map_err(|e| io_error(entry.offset, format!("seek: {e}")))?;
+
+        let mut reader = Reader::from_reader(BufReader::new(&mut self.source));
+        reader.config_mut().trim_text(true);
+
+        let mut buf = Vec::with_capacity(1024);
+        let parsed = match reader.read_event_into(&mut buf) {
+            Ok(Event::Start(e)) if local_name(e.name().as_ref()) == T::TAG => {
+                T::parse(&mut reader, &e)
+            }
+            Ok(Event::Empty(e)) if local_name(e.name().as_ref()) == T::TAG => Ok(T::from_empty(&e)),
+            _ => return Ok(None),
+        };
+        let item = match parsed {
+            Ok(item) => item,
+            Err(cause) => {
+                let len = cause.offset(&reader);
+                drop(reader);
+                return Err(self.locate::<T>(cause, entry.offset, len));
+            }
+        };
+
+        if entry.id_ref.as_deref().is_some_and(|id| id != item.id()) {
+            return Ok(None);
+        }
+        Ok(Some(item))
+    }
+
+    /// Error for an item that failed `len` bytes past its `offset`, with
+    /// the path re-read from the element's start.
+    fn locate<T: IndexedItem>(&mut self, cause: Cause, offset: u64, len: u64) -> ParseError {
+        let path = match read_range(&mut self.source, offset, offset + len) {
+            Ok(bytes) => element_path(&bytes[..], &["run", T::LIST]),
+            Err(_) => format!("run/{}/{}", T::LIST, T::NAME),
+        };
+        cause.at(offset + len, path)
+    }
+
+    /// Reads `<indexList>` through `<indexListOffset>`; `false` when the file
+    /// has no usable index.
+    fn load_index_list(&mut self) -> Result<bool, ParseError> {
+        let file_len = self
+            .source
+            .seek(SeekFrom::End(0))
+            .map_err(|e| io_error(0, e.to_string()))?;
+
+        let tail_start = file_len.saturating_sub(TAIL_LEN);
+        let tail = read_range(&mut self.source, tail_start, file_len)?;
+        let Some(index_off) = find_index_list_offset(&tail) else {
+            return Ok(false);
+        };
+        if index_off >= file_len {
+            return Ok(false);
+        }
+
+        // A stale offset must not pull the rest of the file into memory:
+        // check the first chunk, then read on only up to `</indexList>`.
+        let limit = file_len.min(index_off.saturating_add(INDEX_LIST_MAX_LEN));
+        let mut bytes = read_range(
+            &mut self.source,
+            index_off,
+            limit.min(index_off + INDEX_LIST_CHUNK),
+        )?;
+        let starts_with_index_list = scan_tags(&bytes).next().is_some_and(|t| {
+            !t.closing && t.name == b"indexList" && bytes[..t.start].trim_ascii().is_empty()
+        });
+        if !starts_with_index_list {
+            return Ok(false);
+        }
+
+        const CLOSE: &[u8] = b"indexList>";
+        let mut searched = 0;
+        let end = loop {
+            if let Some(p) = bytes[searched..]
+                .windows(CLOSE.len())
+                .position(|w| w == CLOSE)
+            {
+                searched += p + 1;
+                let closing = scan_tags(&bytes).find(|t| t.closing && t.name == b"indexList");
+                if let Some(t) = closing {
+                    break t.end;
+                }
+                continue;
+            }
+            let read_to = index_off + bytes.len() as u64;
+            if read_to >= limit {
+                return Ok(false);
+            }
+            searched = bytes.len().saturating_sub(CLOSE.len() - 1);
+            let next = read_range(
+                &mut self.source,
+                read_to,
+                limit.min(read_to + INDEX_LIST_CHUNK),
+            )?;
+            bytes.extend_from_slice(&next);
+        };
+
+        // A malformed index is treated like a missing one.
+        let Some(list) = parse_index_list(&bytes[..end]).ok().flatten() else {
+            return Ok(false);
+        };
+        if list.spectrum.is_empty() && list.chromatogram.is_empty() {
+            return Ok(false);
+        }
+
+        self.spectra = ItemIndex::new(list.spectrum);
+        self.chromatograms = ItemIndex::new(list.chromatogram);
+        Ok(true)
+    }
+
+    /// Rebuilds the offsets by scanning the whole file.
+    fn rebuild_index(&mut self) -> Result<(), ParseError> {
+        self.source
+            .seek(SeekFrom::Start(0))
+            .map_err(|e| io_error(0, e.to_string()))?;
+
+        let mut reader = Reader::from_reader(BufReader::new(&mut self.source));
+        let layout = match scan_layout(&mut reader) {
+            Ok(layout) => layout,
+            Err(e) => {
+                let offset = reader.error_position();
+                drop(reader);
+                let path = read_range(&mut self.source, 0, offset)
+                    .map(|bytes| element_path(&bytes[..], &[]))
+                    .unwrap_or_default();
+                return Err(Cause::from(e).at(offset, path));
+            }
+        };
+
+        self.spectra = ItemIndex::new(layout.spectra);
+        self.chromatograms = ItemIndex::new(layout.chromatograms);
+        self.rebuilt = true;
+        Ok(())
+    }
+}
+
+/// Where the parts of an mzML document are, as found by [`scan_layout`].
+#[derive(Debug, Default)]
+pub(crate) struct Layout {
+    pub(crate) spectra: Vec<IndexOffset>,
+    pub(crate) chromatograms: Vec<IndexOffset>,
+    /// `true` when `<indexedmzML>` wraps the document.
+    pub(crate) indexed: bool,
+    /// Start of `<mzML>`.
+    pub(crate) mzml_start: Option<u64>,
+    /// One past `</mzML>`.
+    pub(crate) mzml_end: Option<u64>,
+    /// Start of `<indexList>`.
+    pub(crate) index_list: Option<u64>,
+}
+
+/// Reads the whole document, noting where each spectrum and chromatogram
+/// starts.
+pub(crate) fn scan_layout<B: BufRead>(reader: &mut Reader<B>) -> Result<Layout, quick_xml::Error> {
+    let mut layout = Layout::default();
+    let mut buf = Vec::with_capacity(1024);
+
+    loop {
+        let offset = reader.buffer_position();
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) | Event::Empty(e) => match local_name(e.name().as_ref()) {
+                b"spectrum" => layout.spectra.push(IndexOffset {
+                    id_ref: get_attr(&e, b"id"),
+                    offset,
+                }),
+                b"chromatogram" => layout.chromatograms.push(IndexOffset {
+                    id_ref: get_attr(&e, b"id"),
+                    offset,
+                }),
+                b"mzML" => layout.mzml_start = Some(offset),
+                b"indexedmzML" => layout.indexed = true,
+                b"indexList" => layout.index_list = Some(offset),
+                _ => {}
+            },
+            Event::End(e) if local_name(e.name().as_ref()) == b"mzML" => {
+                layout.mzml_end = Some(reader.buffer_position());
+            }
+            Event::Eof => break,
+            _ => {}
+        }
+        buf.clear();
+    }
+    Ok(layout)
+}
+
+#[inline]
+fn read_range<R: Read + Seek>(source: &mut R, start: u64, end: u64) -> Result<Vec<u8>, ParseError> {
+    source
+        .seek(SeekFrom::Start(start))
+        .map_err(|e| io_error(start, format!("seek: {e}")))?;
+    let len = usize::try_from(end - start).map_err(|_| io_error(start, "range too large"))?;
+    let mut out = vec![0u8; len];
+    source
+        .read_exact(&mut out)
+        .map_err(|e| io_error(start, format!("read {len} bytes: {e}")))?;
+    Ok(out)
+}
+
+#[inline]
+fn io_error(offset: u64, message: impl Into<String>) -> ParseError {
+    Cause::Io(message.into()).at(offset, String::new())
+}
+
+/// Value of the last `<indexListOffset>` in `tail`.
+#[inline]
+pub(crate) fn find_index_list_offset(tail: &[u8]) -> Option<u64> {
+    let start = scan_tags(tail)
+        .filter(|t| !t.closing && t.name == b"indexListOffset")
+        .last()?
+        .end;
+    let rest = &tail[start..];
+    let end = rest.iter().position(|&b| b == b'<')?;
+    std::str::from_utf8(&rest[..end]).ok()?.trim().parse().ok()
+}
+
+pub(crate) struct Tag<'a> {
+    pub(crate) start: usize,
+    /// One past the closing `>`.
+    pub(crate) end: usize,
+    pub(crate) closing: bool,
+    /// Local name, without any namespace prefix.
+    pub(crate) name: &'a [u8],
+}
+
+/// Position of the `>` closing the markup opened at `bytes[start] == b'<'`.
+/// A `>` inside a quoted attribute value, comment, CDATA section or
+/// processing instruction does not end it.
+fn tag_end(bytes: &[u8], start: usize) -> Option<usize> {
+    let rest = &bytes[start..];
+    for (open, close) in [
+        (&b"<!--"[..], &b"-->"[..]),
+        (b"<![CDATA[", b"]]>"),
+        (b"<?", b"?>"),
+    ] {
+        if rest.starts_with(open) {
+            let end = rest[open.len()..]
+                .windows(close.len())
+                .position(|w| w == close)?;
+            return Some(start + open.len() + end + close.len() - 1);
+        }
+    }
+
+    let mut quote = None;
+    for (i, &b) in rest.iter().enumerate() {
+        match (quote, b) {
+            (None, b'"' | b'\'') => quote = Some(b),
+            (Some(q), _) if b == q => quote = None,
+            (None, b'>') => return Some(start + i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Element tags in `bytes`, skipping declarations, comments and processing
+/// instructions.
+pub(crate) fn scan_tags(bytes: &[u8]) -> impl Iterator<Item = Tag<'_>> {
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        loop {
+            let start = pos + bytes[pos..].iter().position(|&b| b == b'<')?;
+            let gt = tag_end(bytes, start)?;
+            pos = gt + 1;
+
+            let inner = &bytes[start + 1..gt];
+            let (closing, inner) = match inner.strip_prefix(b"/") {
+                Some(rest) => (true, rest),
+                None => (false, inner),
+            };
+            if matches!(inner.first(), Some(b'?' | b'!')) {
+                continue;
+            }
+            let name_len = inner
+                .iter()
+                .position(|&b| b.is_ascii_whitespace() || b == b'/')
+                .unwrap_or(inner.len());
+            return Some(Tag {
+                start,
+                end: gt + 1,
+                closing,
+                name: local_name(&inner[..name_len]),
+            });
+        }
+    })
+}
@@ -0,0 +1,248 @@
+use std::{
+    borrow::Cow,
+    io::{Cursor, Read},
+};
+
+use quick_xml::{
+    Reader,
+    events::{BytesStart, Event},
+};
+
+use crate::mzml::{
+    error::{Cause, ParseError, element_path},
+    gzip::{GzipReader, is_gzip},
+    indexed::{IndexedItem, scan_tags},
+    parse_mzml::{
+        ParseOptions, chromatogram_list_from_start, locate, parse_document,
+        spectrum_list_from_start,
+    },
+    structs::{Chromatogram, MzML, Spectrum},
+};
+
+/// What [`parse_mzml_lenient`] kept and what it had to drop.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Recovery {
+    /// One entry per dropped item (or unreadable header), in document order.
+    pub diagnostics: Vec<ParseError>,
+    /// Length of the (decompressed) document.
+    pub total_bytes: u64,
+    /// Bytes up to the end of the last intact element, or all of them once
+    /// `</mzML>` is reached.
+    pub recovered_bytes: u64,
+    pub spectra: usize,
+    pub dropped_spectra: usize,
+    pub chromatograms: usize,
+    pub dropped_chromatograms: usize,
+    /// `true` when the document reaches `</mzML>`.
+    pub complete: bool,
+}
+
+impl Recovery {
+    /// `true` when the document is complete and nothing was dropped.
+    #[inline]
+    pub fn is_clean(&self) -> bool {
+        self.complete && self.diagnostics.is_empty()
+    }
+
+    /// `recovered_bytes` as a share of `total_bytes`.
+    #[inline]
+    pub fn recovered_fraction(&self) -> f64 {
+        if self.total_bytes == 0 {
+            return 0.0;
+        }
+        self.recovered_bytes as f64 / self.total_bytes as f64
+    }
+}
+
+/// <mzML>, keeping every spectrum and chromatogram that parses intact.
+///
+/// Items that are malformed, fail to decode or are cut off by the end of the
+/// input are dropped and reported in [`Recovery::diagnostics`] instead of
+/// failing the whole document. A truncated gzip stream is inflated as far as
+/// it goes.
+pub fn parse_mzml_lenient(bytes: &[u8]) -> (MzML, Recovery) {
+    let mut recovery = Recovery::default();
+    let bytes = gunzip_partial(bytes, &mut recovery.diagnostics);
+    let bytes = &bytes[..];
+    recovery.total_bytes = bytes.len() as u64;
+
+    let header_end = scan_tags(bytes)
+        .find(|t| !t.closing && matches!(t.name, b"spectrumList" | b"chromatogramList"))
+        .map_or(bytes.len(), |t| t.start);
+
+    let mut mzml = match parse_header(&bytes[..header_end]) {
+        Ok(mzml) => {
+            recovery.recovered_bytes = header_end as u64;
+            mzml
+        }
+        Err(e) => {
+            recovery.diagnostics.push(e);
+            MzML::default()
+        }
+    };
+
+    // Start of the <spectrum> or <chromatogram> still waiting for its end tag.
+    let mut open: Option<(&[u8], usize)> = None;
+
+    for t in scan_tags(&bytes[header_end..]) {
+        let (start, end) = (header_end + t.start, header_end + t.end);
+        let tag = &bytes[start..end];
+        let self_closing = tag.ends_with(b"/>");
+
+        match (t.closing, t.name) {
+            (false, b"spectrumList") => {
+                let list = start_tag(tag, spectrum_list_from_start).unwrap_or_default();
+                mzml.run.spectrum_list = Some(list);
+            }
+            (false, b"chromatogramList") => {
+                let list = start_tag(tag, chromatogram_list_from_start).unwrap_or_default();
+                mzml.run.chromatogram_list = Some(list);
+            }
+            (false, name @ (b"spectrum" | b"chromatogram")) => {
+                if let Some((name, from)) = open.take() {
+                    let e = unterminated(name, &bytes[..start], from, "is not closed");
+                    drop_item(&mut recovery, name, e);
+                }
+                if self_closing {
+                    keep_item(&mut mzml, &mut recovery, name, bytes, start, end);
+                } else {
+                    open = Some((name, start));
+                }
+            }
+            (true, name @ (b"spectrum" | b"chromatogram")) => {
+                if let Some((open_name, from)) = open
+                    && open_name == name
+                {
+                    open = None;
+                    keep_item(&mut mzml, &mut recovery, name, bytes, from, end);
+                }
+            }
+            (true, b"mzML") => {
+                recovery.complete = true;
+                recovery.recovered_bytes = bytes.len() as u64;
+            }
+            _ => {}
+        }
+    }
+
+    if let Some((name, from)) = open {
+        let e = unterminated(name, bytes, from, "is cut off by the end of input");
+        drop_item(&mut recovery, name, e);
+    }
+
+    (mzml, recovery)
+}
+
+/// Inflates `bytes` when they are gzip-compressed, keeping whatever was
+/// inflated before the stream broke off.
+fn gunzip_partial<'a>(bytes: &'a [u8], diagnostics: &mut Vec<ParseError>) -> Cow<'a, [u8]> {
+    if !is_gzip(bytes) {
+        return Cow::Borrowed(bytes);
+    }
+    let mut out = Vec::with_capacity(bytes.len().saturating_mul(4));
+    if let Err(e) = GzipReader::new(bytes).read_to_end(&mut out) {
+        diagnostics.push(Cause::Io(format!("gunzip: {e}")).at(out.len() as u64, String::new()));
+    }
+    Cow::Owned(out)
+}
+
+/// Everything before the first item list.
+fn parse_header(bytes: &[u8]) -> Result<MzML, ParseError> {
+    let mut reader = Reader::from_reader(Cursor::new(bytes));
+    reader.config_mut().trim_text(true);
+    let options = ParseOptions {
+        slim: true,
+        threads: 1,
+        ..Default::default()
+    };
+    parse_document(&mut reader, &options).map_err(|cause| locate(cause, &reader, bytes))
+}
+
+#[inline]
+fn start_tag<T>(tag: &[u8], from_start: fn(&BytesStart) -> T) -> Option<T> {
+    match Reader::from_reader(tag).read_event() {
+        Ok(Event::Start(e) | Event::Empty(e)) => Some(from_start(&e)),
+        _ => None,
+    }
+}
+
+fn keep_item(
+    mzml: &mut MzML,
+    recovery: &mut Recovery,
+    name: &[u8],
+    bytes: &[u8],
+    start: usize,
+    end: usize,
+) {
+    let run = &mut mzml.run;
+    let parsed = if name == Spectrum::TAG {
+        parse_item::<Spectrum>(bytes, start, end).map(|s| {
+            let list = run.spectrum_list.get_or_insert_with(Default::default);
+            list.spectra.push(s);
+        })
+    } else {
+        parse_item::<Chromatogram>(bytes, start, end).map(|c| {
+            let list = run.chromatogram_list.get_or_insert_with(Default::default);
+            list.chromatograms.push(c);
+        })
+    };
+
+    match parsed {
+        Ok(()) => {
+            *counts(recovery, name).0 += 1;
+            recovery.recovered_bytes = end as u64;
+        }
+        Err(e) => drop_item(recovery, name, e),
+    }
+}
+
+fn drop_item(recovery: &mut Recovery, name: &[u8], error: ParseError) {
+    *counts(recovery, name).1 += 1;
+    recovery.diagnostics.push(error);
+}
+
+/// Kept and dropped counters for items called `name`.
+#[inline]
+fn counts<'a>(recovery: &'a mut Recovery, name: &[u8]) -> (&'a mut usize, &'a mut usize) {
+    if name == Spectrum::TAG {
+        (&mut recovery.spectra, &mut recovery.dropped_spectra)
+    } else {
+        (
+            &mut recovery.chromatograms,
+            &mut recovery.dropped_chromatograms,
+        )
+    }
+}
+
+/// Parses the item spanning `bytes[start..end]` on its own.
+fn parse_item<T: IndexedItem>(bytes: &[u8], start: usize, end: usize) -> Result<T, ParseError> {
+    let item = &bytes[start..end];
+    let mut reader = Reader::from_reader(item);
+    reader.config_mut().trim_text(true);
+
+    let mut buf = Vec::with_capacity(1024);
+    let parsed = match reader.read_event_into(&mut buf) {
+        Ok(Event::Start(e)) => T::parse(&mut reader, &e),
+        Ok(Event::Empty(e)) => Ok(T::from_empty(&e)),
+        Ok(_) => Err(Cause::Xml(format!("expected <{}>", T::NAME))),
+        Err(e) => Err(e.into()),
+    };
+
+    parsed.map_err(|cause| {
+        let offset = cause.offset(&reader);
+        let len = usize::try_from(offset).map_or(item.len(), |o| o.min(item.len()));
+        let path = element_path(&item[..len], &["run", T::LIST]);
+        cause.at((start + len) as u64, path)
+    })
+}
+
+/// Error for the item starting at `from` that `bytes` ends inside of.
+fn unterminated(name: &[u8], bytes: &[u8], from: usize, what: &str) -> ParseError {
+    let (name, list) = if name == Spectrum::TAG {
+        (Spectrum::NAME, Spectrum::LIST)
+    } else {
+        (Chromatogram::NAME, Chromatogram::LIST)
+    };
+    let path = element_path(&bytes[from..], &["run", list]);
+    Cause::Xml(format!("<{name}> {what}")).at(bytes.len() as u64, path)
+}
@@ -1,9 +1,27 @@
 pub mod parse_mzml;
-pub use parse_mzml::parse_mzml;
+pub use parse_mzml::{ParseOptions, parse_mzml, parse_mzml_with};
 pub mod attr_meta;
 pub mod bin_to_mzml;
-pub use bin_to_mzml::bin_to_mzml;
+pub use bin_to_mzml::{WriteOptions, bin_to_mzml, bin_to_mzml_with};
+mod binaries;
+pub mod checksum;
+pub use checksum::verify_file_checksum;
 pub mod cv_table;
+pub mod error;
+pub use error::ParseError;
+pub mod gzip;
+pub use gzip::{GzipReader, MaybeGzip};
+pub mod indexed;
+pub use indexed::IndexedMzML;
+pub mod lenient;
+pub use lenient::{Recovery, parse_mzml_lenient};
+pub mod numpress;
+pub mod provenance;
+pub use provenance::Conversion;
+pub mod reader;
+pub use reader::MzMLReader;
+pub mod reindex;
+pub use reindex::{IndexIssue, IndexReport, check_index, reindex};
 pub mod schema;
 pub mod structs;
 
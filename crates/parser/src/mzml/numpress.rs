@@ -0,0 +1,320 @@
+//! MS-Numpress codecs (linear prediction, positive integer and short logged
+//! float), byte-compatible with the reference implementation used by
+//! ProteoWizard.
+
+/// MS-Numpress linear prediction compression
+pub const ACC_NUMPRESS_LINEAR: &str = "MS:1002312";
+/// MS-Numpress positive integer compression
+pub const ACC_NUMPRESS_PIC: &str = "MS:1002313";
+/// MS-Numpress short logged float compression
+pub const ACC_NUMPRESS_SLOF: &str = "MS:1002314";
+/// MS-Numpress linear prediction compression followed by zlib compression
+pub const ACC_NUMPRESS_LINEAR_ZLIB: &str = "MS:1002746";
+/// MS-Numpress positive integer compression followed by zlib compression
+pub const ACC_NUMPRESS_PIC_ZLIB: &str = "MS:1002747";
+/// MS-Numpress short logged float compression followed by zlib compression
+pub const ACC_NUMPRESS_SLOF_ZLIB: &str = "MS:1002748";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Numpress {
+    Linear,
+    Pic,
+    Slof,
+}
+
+impl Numpress {
+    /// Codec and whether zlib is applied on top, from a compression accession.
+    pub fn from_accession(acc: &str) -> Option<(Self, bool)> {
+        Some(match acc {
+            ACC_NUMPRESS_LINEAR => (Self::Linear, false),
+            ACC_NUMPRESS_PIC => (Self::Pic, false),
+            ACC_NUMPRESS_SLOF => (Self::Slof, false),
+            ACC_NUMPRESS_LINEAR_ZLIB => (Self::Linear, true),
+            ACC_NUMPRESS_PIC_ZLIB => (Self::Pic, true),
+            ACC_NUMPRESS_SLOF_ZLIB => (Self::Slof, true),
+            _ => return None,
+        })
+    }
+
+    /// Accession of this codec, optionally followed by zlib.
+    pub fn accession(self, zlib: bool) -> &'static str {
+        match (self, zlib) {
+            (Self::Linear, false) => ACC_NUMPRESS_LINEAR,
+            (Self::Pic, false) => ACC_NUMPRESS_PIC,
+            (Self::Slof, false) => ACC_NUMPRESS_SLOF,
+            (Self::Linear, true) => ACC_NUMPRESS_LINEAR_ZLIB,
+            (Self::Pic, true) => ACC_NUMPRESS_PIC_ZLIB,
+            (Self::Slof, true) => ACC_NUMPRESS_SLOF_ZLIB,
+        }
+    }
+
+    /// Encodes `data` with the fixed point chosen by the codec's heuristic.
+    pub fn encode(self, data: &[f64]) -> Result<Vec<u8>, String> {
+        match self {
+            Self::Linear => encode_linear(data, optimal_linear_fixed_point(data)),
+            Self::Pic => encode_pic(data),
+            Self::Slof => encode_slof(data, optimal_slof_fixed_point(data)),
+        }
+    }
+
+    pub fn decode(self, bytes: &[u8]) -> Result<Vec<f64>, String> {
+        match self {
+            Self::Linear => decode_linear(bytes),
+            Self::Pic => decode_pic(bytes),
+            Self::Slof => decode_slof(bytes),
+        }
+    }
+}
+
+/// Largest fixed point that keeps every linear prediction residual of `data`
+/// within 32 bits.
+pub fn optimal_linear_fixed_point(data: &[f64]) -> f64 {
+    match data {
+        [] => 0.0,
+        [x] => (u32::MAX as f64 / x).floor(),
+        [a, b, rest @ ..] => {
+            let mut max = a.max(*b);
+            let mut prev2 = *a;
+            let mut prev1 = *b;
+            for &x in rest {
+                let extrapol = prev1 + (prev1 - prev2);
+                max = max.max(((x - extrapol).abs() + 1.0).ceil());
+                prev2 = prev1;
+                prev1 = x;
+            }
+            (i32::MAX as f64 / max).floor()
+        }
+    }
+}
+
+/// Largest fixed point that keeps `ln(x + 1)` of every value within 16 bits.
+pub fn optimal_slof_fixed_point(data: &[f64]) -> f64 {
+    if data.is_empty() {
+        return 0.0;
+    }
+    let max = data.iter().fold(1.0f64, |m, &x| m.max((x + 1.0).ln()));
+    (u16::MAX as f64 / max).floor()
+}
+
+pub fn encode_linear(data: &[f64], fixed_point: f64) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(16 + data.len() * 5);
+    out.extend_from_slice(&fixed_point.to_be_bytes());
+
+    let Some((&first, rest)) = data.split_first() else {
+        return Ok(out);
+    };
+    let mut ints = [0i64; 3];
+    ints[1] = to_fixed(first, fixed_point)?;
+    out.extend_from_slice(&(ints[1] as u32).to_le_bytes());
+
+    let Some((&second, rest)) = rest.split_first() else {
+        return Ok(out);
+    };
+    ints[2] = to_fixed(second, fixed_point)?;
+    out.extend_from_slice(&(ints[2] as u32).to_le_bytes());
+
+    let mut nibbles = NibbleWriter::new(out);
+    for &x in rest {
+        ints[0] = ints[1];
+        ints[1] = ints[2];
+        ints[2] = to_fixed(x, fixed_point)?;
+
+        let extrapol = ints[1] + (ints[1] - ints[0]);
+        let diff = i32::try_from(ints[2] - extrapol)
+            .map_err(|_| "numpress linear: residual exceeds 32 bits".to_string())?;
+        nibbles.push_int(diff as u32);
+    }
+    Ok(nibbles.finish())
+}
+
+pub fn decode_linear(bytes: &[u8]) -> Result<Vec<f64>, String> {
+    if bytes.len() < 8 {
+        return Err("numpress linear: missing fixed point".to_string());
+    }
+    let fixed_point = f64::from_be_bytes(bytes[..8].try_into().unwrap());
+    if bytes.len() == 8 {
+        return Ok(Vec::new());
+    }
+
+    let mut ints = [0i64; 3];
+    let mut out = Vec::with_capacity(bytes.len() - 8);
+
+    let first = bytes
+        .get(8..12)
+        .ok_or_else(|| "numpress linear: truncated first value".to_string())?;
+    ints[1] = u32::from_le_bytes(first.try_into().unwrap()) as i64;
+    out.push(ints[1] as f64 / fixed_point);
+    if bytes.len() == 12 {
+        return Ok(out);
+    }
+
+    let second = bytes
+        .get(12..16)
+        .ok_or_else(|| "numpress linear: truncated second value".to_string())?;
+    ints[2] = u32::from_le_bytes(second.try_into().unwrap()) as i64;
+    out.push(ints[2] as f64 / fixed_point);
+
+    let mut nibbles = NibbleReader::new(&bytes[16..]);
+    while let Some(diff) = nibbles.next_int()? {
+        ints[0] = ints[1];
+        ints[1] = ints[2];
+        let extrapol = ints[1] + (ints[1] - ints[0]);
+        ints[2] = extrapol + diff as i32 as i64;
+        out.push(ints[2] as f64 / fixed_point);
+    }
+    Ok(out)
+}
+
+pub fn encode_pic(data: &[f64]) -> Result<Vec<u8>, String> {
+    let mut nibbles = NibbleWriter::new(Vec::with_capacity(data.len() * 3));
+    for &x in data {
+        if !(-0.5..=u32::MAX as f64).contains(&x) {
+            return Err(format!("numpress pic: value {x} out of range"));
+        }
+        nibbles.push_int((x + 0.5) as u32);
+    }
+    Ok(nibbles.finish())
+}
+
+pub fn decode_pic(bytes: &[u8]) -> Result<Vec<f64>, String> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut nibbles = NibbleReader::new(bytes);
+    while let Some(x) = nibbles.next_int()? {
+        out.push(x as f64);
+    }
+    Ok(out)
+}
+
+pub fn encode_slof(data: &[f64], fixed_point: f64) -> Result<Vec<u8>, String> {
+    let mut out = Vec::with_capacity(8 + data.len() * 2);
+    out.extend_from_slice(&fixed_point.to_be_bytes());
+    for &x in data {
+        let scaled = (x + 1.0).ln() * fixed_point + 0.5;
+        if !(0.0..=u16::MAX as f64 + 0.5).contains(&scaled) {
+            return Err(format!("numpress slof: value {x} out of range"));
+        }
+        out.extend_from_slice(&(scaled as u16).to_le_bytes());
+    }
+    Ok(out)
+}
+
+pub fn decode_slof(bytes: &[u8]) -> Result<Vec<f64>, String> {
+    if bytes.len() < 8 {
+        return Err("numpress slof: missing fixed point".to_string());
+    }
+    let fixed_point = f64::from_be_bytes(bytes[..8].try_into().unwrap());
+    Ok(bytes[8..]
+        .chunks_exact(2)
+        .map(|c| (u16::from_le_bytes([c[0], c[1]]) as f64 / fixed_point).exp() - 1.0)
+        .collect())
+}
+
+#[inline]
+fn to_fixed(x: f64, fixed_point: f64) -> Result<i64, String> {
+    let v = x * fixed_point + 0.5;
+    if !(i64::MIN as f64..i64::MAX as f64).contains(&v) {
+        return Err(format!("numpress linear: value {x} out of range"));
+    }
+    Ok(v as i64)
+}
+
+/// Packs integers as a count of leading zero (or one) nibbles followed by
+/// the remaining nibbles, least significant first.
+struct NibbleWriter {
+    out: Vec<u8>,
+    pending: Option<u8>,
+}
+
+impl NibbleWriter {
+    fn new(out: Vec<u8>) -> Self {
+        Self { out, pending: None }
+    }
+
+    #[inline]
+    fn push_nibble(&mut self, n: u8) {
+        match self.pending.take() {
+            Some(hi) => self.out.push((hi << 4) | (n & 0xf)),
+            None => self.pending = Some(n & 0xf),
+        }
+    }
+
+    fn push_int(&mut self, x: u32) {
+        let leading_zeros = (x.leading_zeros() / 4) as u8;
+        let leading_ones = (x.leading_ones() / 4) as u8;
+
+        let (head, skip) = if leading_zeros > 0 {
+            (leading_zeros, leading_zeros)
+        } else if leading_ones > 0 {
+            // All-ones values keep one nibble, as the reference encoder does.
+            let l = leading_ones.min(7);
+            (l + 8, l)
+        } else {
+            (0, 0)
+        };
+
+        self.push_nibble(head);
+        for i in 0..(8 - skip) {
+            self.push_nibble((x >> (4 * i)) as u8);
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if let Some(hi) = self.pending {
+            self.out.push(hi << 4);
+        }
+        self.out
+    }
+}
+
+struct NibbleReader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    high: bool,
+}
+
+impl<'a> NibbleReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes,
+            pos: 0,
+            high: true,
+        }
+    }
+
+    #[inline]
+    fn next_nibble(&mut self) -> Option<u8> {
+        let b = *self.bytes.get(self.pos)?;
+        Some(if self.high {
+            self.high = false;
+            b >> 4
+        } else {
+            self.high = true;
+            self.pos += 1;
+            b & 0xf
+        })
+    }
+
+    fn next_int(&mut self) -> Result<Option<u32>, String> {
+        // A lone zero nibble at the end is padding.
+        let last = self.pos + 1 == self.bytes.len();
+        if self.pos >= self.bytes.len() || (last && !self.high && self.bytes[self.pos] & 0xf == 0) {
+            return Ok(None);
+        }
+
+        let head = self.next_nibble().unwrap();
+        let (skip, mut x) = if head <= 8 {
+            (head as u32, 0u32)
+        } else {
+            let n = (head - 8) as u32;
+            (n, !(u32::MAX >> (4 * n)))
+        };
+
+        for i in 0..(8 - skip) {
+            let n = self
+                .next_nibble()
+                .ok_or_else(|| "numpress: truncated integer".to_string())?;
+            x |= (n as u32) << (4 * i);
+        }
+        Ok(Some(x))
+    }
+}
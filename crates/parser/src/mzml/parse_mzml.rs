@@ -1,11 +1,18 @@
 use base64::Engine;
 use base64::engine::general_purpose::STANDARD;
 use miniz_oxide::inflate::decompress_to_vec_zlib;
-use quick_xml::Reader;
 use quick_xml::events::{BytesStart, Event};
+use quick_xml::{Reader, Writer};
 use serde::{Deserialize, Serialize};
 use std::io::{BufRead, Cursor};
-
+use std::num::NonZeroUsize;
+use std::thread;
+
+use crate::mzml::binaries::Binaries;
+use crate::mzml::checksum::check_file_checksum;
+use crate::mzml::error::{Cause, ParseError, element_path};
+use crate::mzml::gzip::maybe_gunzip;
+use crate::mzml::numpress::Numpress;
 use crate::mzml::structs::*;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -30,17 +37,23 @@ pub struct IndexList {
     pub file_checksum: Option<String>,
 }
 
+/// Whether attribute `key` is one of `names`. Keys are compared whole, prefix
+/// included: mzML attributes are unqualified, so `acme:id` is not `id`.
+#[inline]
+fn is_attribute(key: &[u8], names: &[&[u8]]) -> bool {
+    names.contains(&key)
+}
+
 fn get_attr_any(start: &BytesStart, names: &[&[u8]]) -> Option<String> {
     for a in start.attributes().with_checks(false).flatten() {
-        let key = a.key.as_ref();
-        if names.iter().any(|n| *n == key) {
+        if is_attribute(a.key.as_ref(), names) {
             return a.unescape_value().ok().map(|v| v.to_string());
         }
     }
     None
 }
 
-fn get_attr(start: &BytesStart, name: &[u8]) -> Option<String> {
+pub(crate) fn get_attr(start: &BytesStart, name: &[u8]) -> Option<String> {
     get_attr_any(start, &[name])
 }
 
@@ -52,8 +65,9 @@ fn get_attr_usize(start: &BytesStart, name: &[u8]) -> Option<usize> {
     get_attr(start, name).and_then(|s| s.parse().ok())
 }
 
+/// Element name without its namespace prefix.
 #[inline]
-fn local_name(mut raw: &[u8]) -> &[u8] {
+pub(crate) fn local_name(mut raw: &[u8]) -> &[u8] {
     if raw.first() == Some(&b'{') {
         if let Some(end) = raw.iter().position(|&b| b == b'}') {
             raw = &raw[end + 1..];
@@ -113,18 +127,15 @@ fn parse_source_file_ref(start: &BytesStart) -> SourceFileRef {
     }
 }
 
-fn skip_element<R: BufRead>(reader: &mut Reader<R>, end: &[u8]) -> Result<(), String> {
+pub(crate) fn skip_element<R: BufRead>(reader: &mut Reader<R>, end: &[u8]) -> Result<(), Cause> {
     let mut depth = 1usize;
     let mut buf = Vec::with_capacity(512);
 
     while depth != 0 {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(_) => depth += 1,
             Event::End(e) => {
-                if depth == 1 && e.name().as_ref() == end {
+                if depth == 1 && local_name(e.name().as_ref()) == end {
                     break;
                 }
                 depth = depth.saturating_sub(1);
@@ -137,18 +148,115 @@ fn skip_element<R: BufRead>(reader: &mut Reader<R>, end: &[u8]) -> Result<(), St
     Ok(())
 }
 
-fn read_text_content<R: BufRead>(reader: &mut Reader<R>, end: &[u8]) -> Result<String, String> {
+/// `<mzML>` attributes that are not kept in [`MzML::extensions`]: they are
+/// written anew.
+const MZML_ATTRIBUTES: &[&[u8]] = &[
+    b"xmlns",
+    b"xmlns:xsi",
+    b"xsi:schemaLocation",
+    b"id",
+    b"version",
+];
+
+/// Unmodelled `<mzML>` attributes; a prefix bound to the mzML namespace
+/// itself is dropped, as elements are written unprefixed.
+pub(crate) fn mzml_extensions(start: &BytesStart) -> Extensions {
+    let mut ext = extensions_of(start, MZML_ATTRIBUTES);
+    ext.attributes.retain(|(key, value)| {
+        !(key.starts_with("xmlns:") && value.starts_with("http://psi.hupo.org/"))
+    });
+    ext
+}
+
+/// Attributes of every list element.
+const LIST_ATTRIBUTES: &[&[u8]] = &[b"count"];
+
+/// Unmodelled element `e`, read up to its end tag, kept in `ext` as XML text.
+pub(crate) fn capture_element<R: BufRead>(
+    reader: &mut Reader<R>,
+    e: &BytesStart,
+    ext: &mut Extensions,
+) -> Result<(), Cause> {
+    let mut writer = Writer::new(Vec::new());
+    let mut write = |event: Event| {
+        writer
+            .write_event(event)
+            .map_err(|e| Cause::Xml(e.to_string()))
+    };
+    write(Event::Start(e.borrow()))?;
+
+    // Text inside foreign content is kept as is, surrounding spaces included.
+    let config = reader.config_mut();
+    let trim = (config.trim_text_start, config.trim_text_end);
+    config.trim_text(false);
+
+    let mut depth = 1usize;
+    let mut buf = Vec::with_capacity(512);
+    let copied = loop {
+        if depth == 0 {
+            break Ok(());
+        }
+        let event = match reader.read_event_into(&mut buf) {
+            Ok(event) => event,
+            Err(e) => break Err(e.into()),
+        };
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) => depth -= 1,
+            Event::Eof => break Ok(()),
+            _ => {}
+        }
+        if let Err(e) = write(event) {
+            break Err(e);
+        }
+        buf.clear();
+    };
+
+    let config = reader.config_mut();
+    (config.trim_text_start, config.trim_text_end) = trim;
+    copied?;
+
+    ext.elements
+        .push(String::from_utf8_lossy(&writer.into_inner()).into_owned());
+    Ok(())
+}
+
+/// Unmodelled empty element `e`, kept in `ext` as XML text.
+pub(crate) fn capture_empty(e: &BytesStart, ext: &mut Extensions) {
+    let tag = String::from_utf8_lossy(e);
+    ext.elements.push(format!("<{tag}/>"));
+}
+
+/// [`Extensions`] holding the attributes of `start` not named in `known`.
+pub(crate) fn extensions_of(start: &BytesStart, known: &[&[u8]]) -> Extensions {
+    let attributes = start
+        .attributes()
+        .with_checks(false)
+        .flatten()
+        .filter(|a| !is_attribute(a.key.as_ref(), known))
+        .map(|a| {
+            let value = a.unescape_value().map_or_else(
+                |_| String::from_utf8_lossy(&a.value).into_owned(),
+                |v| v.into(),
+            );
+            (String::from_utf8_lossy(a.key.as_ref()).into_owned(), value)
+        })
+        .collect();
+    Extensions {
+        attributes,
+        elements: Vec::new(),
+    }
+}
+
+fn read_text_content<R: BufRead>(reader: &mut Reader<R>, end: &[u8]) -> Result<String, Cause> {
     let mut buf = Vec::with_capacity(512);
     let mut out = String::new();
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Text(t) => out.push_str(&t.decode().map_err(|e| e.to_string())?),
+        match reader.read_event_into(&mut buf)? {
+            Event::Text(t) => out.push_str(&t.decode().map_err(|e| Cause::Xml(e.to_string()))?),
             Event::CData(t) => out.push_str(&String::from_utf8_lossy(&t.into_inner())),
-            Event::End(e) if e.name().as_ref() == end => break,
+            Event::End(e) if local_name(e.name().as_ref()) == end => break,
             Event::Eof => break,
             _ => {}
         }
@@ -157,13 +265,13 @@ fn read_text_content<R: BufRead>(reader: &mut Reader<R>, end: &[u8]) -> Result<S
     Ok(out)
 }
 
-fn push_params_empty(
+pub(crate) fn push_params_empty(
     e: &BytesStart,
     referenceable_param_group_refs: &mut Vec<ReferenceableParamGroupRef>,
     cv_params: &mut Vec<CvParam>,
     user_params: &mut Vec<UserParam>,
 ) -> bool {
-    match e.name().as_ref() {
+    match local_name(e.name().as_ref()) {
         b"referenceableParamGroupRef" => {
             referenceable_param_group_refs.push(parse_referenceable_param_group_ref(e));
             true
@@ -186,8 +294,8 @@ fn push_params_start<R: BufRead>(
     referenceable_param_group_refs: &mut Vec<ReferenceableParamGroupRef>,
     cv_params: &mut Vec<CvParam>,
     user_params: &mut Vec<UserParam>,
-) -> Result<bool, String> {
-    match e.name().as_ref() {
+) -> Result<bool, Cause> {
+    match local_name(e.name().as_ref()) {
         b"referenceableParamGroupRef" => {
             referenceable_param_group_refs.push(parse_referenceable_param_group_ref(e));
             skip_element(reader, b"referenceableParamGroupRef")?;
@@ -207,6 +315,40 @@ fn push_params_start<R: BufRead>(
     }
 }
 
+/// Param child `e` pushed onto its list; any other element is kept in `ext`.
+fn push_params_or_capture_empty(
+    e: &BytesStart,
+    referenceable_param_group_refs: &mut Vec<ReferenceableParamGroupRef>,
+    cv_params: &mut Vec<CvParam>,
+    user_params: &mut Vec<UserParam>,
+    ext: &mut Extensions,
+) {
+    if !push_params_empty(e, referenceable_param_group_refs, cv_params, user_params) {
+        capture_empty(e, ext);
+    }
+}
+
+/// [`push_params_or_capture_empty`] for a start tag, read up to its end tag.
+fn push_params_or_capture_start<R: BufRead>(
+    reader: &mut Reader<R>,
+    e: &BytesStart,
+    referenceable_param_group_refs: &mut Vec<ReferenceableParamGroupRef>,
+    cv_params: &mut Vec<CvParam>,
+    user_params: &mut Vec<UserParam>,
+    ext: &mut Extensions,
+) -> Result<(), Cause> {
+    if !push_params_start(
+        reader,
+        e,
+        referenceable_param_group_refs,
+        cv_params,
+        user_params,
+    )? {
+        capture_element(reader, e, ext)?;
+    }
+    Ok(())
+}
+
 fn maybe_set_ms_level(spectrum: &mut Spectrum, p: &CvParam) {
     if spectrum.ms_level.is_some() {
         return;
@@ -222,23 +364,73 @@ fn maybe_set_ms_level(spectrum: &mut Spectrum, p: &CvParam) {
     spectrum.ms_level = Some(n);
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ParseOptions {
+    /// Skip the spectrum and chromatogram lists.
+    pub slim: bool,
+    /// Threads decoding `<binary>` payloads (base64, zlib, Numpress); `1`
+    /// decodes them inline while the XML is read.
+    pub threads: usize,
+    /// Check the indexedmzML `<fileChecksum>`, when there is one, before
+    /// parsing.
+    pub verify_checksum: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            slim: false,
+            threads: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            verify_checksum: false,
+        }
+    }
+}
+
+/// <mzML>; gzip-compressed input is inflated first.
+pub fn parse_mzml(bytes: &[u8], slim: bool) -> Result<MzML, ParseError> {
+    parse_mzml_with(
+        bytes,
+        &ParseOptions {
+            slim,
+            ..Default::default()
+        },
+    )
+}
+
 /// <mzML>
-pub fn parse_mzml(bytes: &[u8], slim: bool) -> Result<MzML, String> {
-    let mut reader = Reader::from_reader(Cursor::new(bytes));
+pub fn parse_mzml_with(bytes: &[u8], options: &ParseOptions) -> Result<MzML, ParseError> {
+    let bytes = maybe_gunzip(bytes).map_err(|e| Cause::Io(e).at(0, String::new()))?;
+    if options.verify_checksum {
+        check_file_checksum(&bytes)?;
+    }
+    let mut reader = Reader::from_reader(Cursor::new(&bytes[..]));
     reader.config_mut().trim_text(true);
 
+    parse_document(&mut reader, options).map_err(|cause| locate(cause, &reader, &bytes))
+}
+
+/// Attaches the position `reader` stopped at, and the elements open there,
+/// to `cause`.
+pub(crate) fn locate(cause: Cause, reader: &Reader<Cursor<&[u8]>>, bytes: &[u8]) -> ParseError {
+    let offset = cause.offset(reader);
+    let end = usize::try_from(offset).map_or(bytes.len(), |o| o.min(bytes.len()));
+    cause.at(offset, element_path(&bytes[..end], &[]))
+}
+
+pub(crate) fn parse_document<R: BufRead>(
+    reader: &mut Reader<R>,
+    options: &ParseOptions,
+) -> Result<MzML, Cause> {
     let mut buf = Vec::with_capacity(1024);
     let mut mzml = MzML::default();
     let mut in_mzml = false;
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
-                if e.name().as_ref() == b"mzML" {
+                if local_name(e.name().as_ref()) == b"mzML" {
                     in_mzml = true;
+                    mzml.extensions = mzml_extensions(&e);
                     buf.clear();
                     continue;
                 }
@@ -246,31 +438,10 @@ pub fn parse_mzml(bytes: &[u8], slim: bool) -> Result<MzML, String> {
                     buf.clear();
                     continue;
                 }
-                match e.name().as_ref() {
-                    b"cvList" => mzml.cv_list = Some(parse_cv_list(&mut reader, &e)?),
-                    b"fileDescription" => {
-                        mzml.file_description = parse_file_description(&mut reader, &e)?
-                    }
-                    b"referenceableParamGroupList" => {
-                        mzml.referenceable_param_group_list =
-                            Some(parse_referenceable_param_group_list(&mut reader, &e)?);
-                    }
-                    b"sampleList" => mzml.sample_list = Some(parse_sample_list(&mut reader, &e)?),
-                    b"instrumentList" | b"instrumentConfigurationList" => {
-                        mzml.instrument_list = parse_instrument_list(&mut reader, &e)?;
-                    }
-                    b"softwareList" => {
-                        mzml.software_list = Some(parse_software_list(&mut reader, &e)?)
-                    }
-                    b"dataProcessingList" => {
-                        mzml.data_processing_list =
-                            Some(parse_data_processing_list(&mut reader, &e)?);
-                    }
-                    b"scanSettingsList" | b"acquisitionSettingsList" => {
-                        mzml.scan_settings_list = parse_scan_settings_list(&mut reader, &e)?;
-                    }
-                    b"run" => mzml.run = parse_run(&mut reader, &e, slim)?,
-                    _ => skip_element(&mut reader, e.name().as_ref())?,
+                if local_name(e.name().as_ref()) == b"run" {
+                    mzml.run = parse_run(reader, &e, options)?;
+                } else if !parse_header_section(reader, &e, &mut mzml)? {
+                    capture_element(reader, &e, &mut mzml.extensions)?;
                 }
             }
             Event::Empty(e) => {
@@ -278,41 +449,9 @@ pub fn parse_mzml(bytes: &[u8], slim: bool) -> Result<MzML, String> {
                     buf.clear();
                     continue;
                 }
-                match e.name().as_ref() {
-                    b"referenceableParamGroupList" => {
-                        let mut list = ReferenceableParamGroupList::default();
-                        list.count = get_attr_usize(&e, b"count");
-                        mzml.referenceable_param_group_list = Some(list);
-                    }
-                    b"sampleList" => {
-                        let mut list = SampleList::default();
-                        list.count = get_attr_u32(&e, b"count");
-                        mzml.sample_list = Some(list);
-                    }
-                    b"instrumentList" | b"instrumentConfigurationList" => {
-                        let mut list = InstrumentList::default();
-                        list.count = get_attr_usize(&e, b"count");
-                        mzml.instrument_list = Some(list);
-                    }
-                    b"softwareList" => {
-                        let mut list = SoftwareList::default();
-                        list.count = get_attr_usize(&e, b"count");
-                        mzml.software_list = Some(list);
-                    }
-                    b"dataProcessingList" => {
-                        let mut list = DataProcessingList::default();
-                        list.count = get_attr_usize(&e, b"count");
-                        mzml.data_processing_list = Some(list);
-                    }
-                    b"scanSettingsList" | b"acquisitionSettingsList" => {
-                        let mut list = ScanSettingsList::default();
-                        list.count = get_attr_usize(&e, b"count");
-                        mzml.scan_settings_list = Some(list);
-                    }
-                    _ => {}
-                }
+                parse_empty_header_section(&e, &mut mzml);
             }
-            Event::End(e) if e.name().as_ref() == b"mzML" => break,
+            Event::End(e) if local_name(e.name().as_ref()) == b"mzML" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -322,31 +461,116 @@ pub fn parse_mzml(bytes: &[u8], slim: bool) -> Result<MzML, String> {
     Ok(mzml)
 }
 
+/// <cvList> … <scanSettingsList>; returns `false` for any other element.
+pub(crate) fn parse_header_section<R: BufRead>(
+    reader: &mut Reader<R>,
+    e: &BytesStart,
+    mzml: &mut MzML,
+) -> Result<bool, Cause> {
+    match local_name(e.name().as_ref()) {
+        b"cvList" => mzml.cv_list = Some(parse_cv_list(reader, e)?),
+        b"fileDescription" => mzml.file_description = parse_file_description(reader, e)?,
+        b"referenceableParamGroupList" => {
+            mzml.referenceable_param_group_list =
+                Some(parse_referenceable_param_group_list(reader, e)?);
+        }
+        b"sampleList" => mzml.sample_list = Some(parse_sample_list(reader, e)?),
+        b"instrumentList" | b"instrumentConfigurationList" => {
+            mzml.instrument_list = parse_instrument_list(reader, e)?;
+        }
+        b"softwareList" => mzml.software_list = Some(parse_software_list(reader, e)?),
+        b"dataProcessingList" => {
+            mzml.data_processing_list = Some(parse_data_processing_list(reader, e)?);
+        }
+        b"scanSettingsList" | b"acquisitionSettingsList" => {
+            mzml.scan_settings_list = parse_scan_settings_list(reader, e)?;
+        }
+        _ => return Ok(false),
+    }
+    Ok(true)
+}
+
+/// Empty <cvList/> … <scanSettingsList/> and <run/>
+pub(crate) fn parse_empty_header_section(e: &BytesStart, mzml: &mut MzML) {
+    match local_name(e.name().as_ref()) {
+        b"referenceableParamGroupList" => {
+            let mut list = ReferenceableParamGroupList::default();
+            list.count = get_attr_usize(e, b"count");
+            list.extensions = extensions_of(e, LIST_ATTRIBUTES);
+            mzml.referenceable_param_group_list = Some(list);
+        }
+        b"sampleList" => {
+            let mut list = SampleList::default();
+            list.count = get_attr_u32(e, b"count");
+            list.extensions = extensions_of(e, LIST_ATTRIBUTES);
+            mzml.sample_list = Some(list);
+        }
+        b"instrumentList" | b"instrumentConfigurationList" => {
+            let mut list = InstrumentList::default();
+            list.count = get_attr_usize(e, b"count");
+            list.extensions = extensions_of(e, LIST_ATTRIBUTES);
+            mzml.instrument_list = Some(list);
+        }
+        b"softwareList" => {
+            let mut list = SoftwareList::default();
+            list.count = get_attr_usize(e, b"count");
+            list.extensions = extensions_of(e, LIST_ATTRIBUTES);
+            mzml.software_list = Some(list);
+        }
+        b"dataProcessingList" => {
+            let mut list = DataProcessingList::default();
+            list.count = get_attr_usize(e, b"count");
+            list.extensions = extensions_of(e, LIST_ATTRIBUTES);
+            mzml.data_processing_list = Some(list);
+        }
+        b"scanSettingsList" | b"acquisitionSettingsList" => {
+            let mut list = ScanSettingsList::default();
+            list.count = get_attr_usize(e, b"count");
+            list.extensions = extensions_of(e, LIST_ATTRIBUTES);
+            mzml.scan_settings_list = Some(list);
+        }
+        b"cvList" => {
+            let mut list = CvList::default();
+            list.count = get_attr_usize(e, b"count");
+            list.extensions = extensions_of(e, LIST_ATTRIBUTES);
+            mzml.cv_list = Some(list);
+        }
+        b"fileDescription" => mzml.file_description.extensions = extensions_of(e, &[]),
+        b"run" => mzml.run = run_from_start(e),
+        _ => capture_empty(e, &mut mzml.extensions),
+    }
+}
+
 /// <cvList>
-pub fn parse_cv_list<R: BufRead>(
+pub(crate) fn parse_cv_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<CvList, String> {
+) -> Result<CvList, Cause> {
     let mut list = CvList::default();
     list.count = get_attr_usize(start, b"count");
+    list.extensions = extensions_of(start, LIST_ATTRIBUTES);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) if e.name().as_ref() == b"cv" => list.cv.push(parse_cv_tag(&e)?),
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) if local_name(e.name().as_ref()) == b"cv" => {
+                list.cv.push(parse_cv_tag(&e)?)
+            }
             Event::Start(e) => {
-                if e.name().as_ref() == b"cv" {
+                if local_name(e.name().as_ref()) == b"cv" {
                     list.cv.push(parse_cv_tag(&e)?);
                     skip_element(reader, b"cv")?;
                 } else {
-                    skip_element(reader, e.name().as_ref())?;
+                    capture_element(reader, &e, &mut list.extensions)?;
                 }
             }
-            Event::End(e) if e.name().as_ref() == b"cvList" => break,
-            Event::Eof => return Err("Unexpected EOF while parsing <cvList>".into()),
+            Event::Empty(e) => capture_empty(&e, &mut list.extensions),
+            Event::End(e) if local_name(e.name().as_ref()) == b"cvList" => break,
+            Event::Eof => {
+                return Err(Cause::Xml(
+                    "Unexpected EOF while parsing <cvList>".to_string(),
+                ));
+            }
             _ => {}
         }
         buf.clear();
@@ -354,7 +578,7 @@ pub fn parse_cv_list<R: BufRead>(
     Ok(list)
 }
 
-fn parse_cv_tag(start: &BytesStart) -> Result<Cv, String> {
+fn parse_cv_tag(start: &BytesStart) -> Result<Cv, Cause> {
     Ok(Cv {
         id: get_attr_any(start, &[b"id", b"cvLabel"]).unwrap_or_default(),
         full_name: get_attr(start, b"fullName"),
@@ -366,72 +590,73 @@ fn parse_cv_tag(start: &BytesStart) -> Result<Cv, String> {
 /// <fileDescription>
 fn parse_file_description<R: BufRead>(
     reader: &mut Reader<R>,
-    _start: &BytesStart,
-) -> Result<FileDescription, String> {
-    let mut file_content = FileContent::default();
-    let mut source_file_list = SourceFileList::default();
-    let mut contacts = Vec::new();
+    start: &BytesStart,
+) -> Result<FileDescription, Cause> {
+    let mut fd = FileDescription {
+        extensions: extensions_of(start, &[]),
+        ..Default::default()
+    };
     let mut buf = Vec::with_capacity(512);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Start(e) => match e.name().as_ref() {
-                b"fileContent" => file_content = parse_file_content(reader, &e)?,
-                b"sourceFileList" => source_file_list = parse_source_file_list(reader, &e)?,
-                b"contact" => contacts.push(parse_contact(reader, &e)?),
-                _ => skip_element(reader, e.name().as_ref())?,
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => match local_name(e.name().as_ref()) {
+                b"fileContent" => fd.file_content = parse_file_content(reader, &e)?,
+                b"sourceFileList" => fd.source_file_list = parse_source_file_list(reader, &e)?,
+                b"contact" => fd.contacts.push(parse_contact(reader, &e)?),
+                _ => capture_element(reader, &e, &mut fd.extensions)?,
+            },
+            Event::Empty(e) => match local_name(e.name().as_ref()) {
+                b"fileContent" => fd.file_content.extensions = extensions_of(&e, &[]),
+                b"sourceFileList" => {
+                    fd.source_file_list.count = get_attr_usize(&e, b"count");
+                    fd.source_file_list.extensions = extensions_of(&e, LIST_ATTRIBUTES);
+                }
+                b"contact" => fd.contacts.push(Contact {
+                    extensions: extensions_of(&e, &[]),
+                    ..Default::default()
+                }),
+                _ => capture_empty(&e, &mut fd.extensions),
             },
-            Event::Empty(e) if e.name().as_ref() == b"contact" => contacts.push(Contact::default()),
-            Event::End(e) if e.name().as_ref() == b"fileDescription" => break,
+            Event::End(e) if local_name(e.name().as_ref()) == b"fileDescription" => break,
             Event::Eof => break,
             _ => {}
         }
         buf.clear();
     }
 
-    Ok(FileDescription {
-        file_content,
-        source_file_list,
-        contacts,
-    })
+    Ok(fd)
 }
 
 /// <fileContent>
 fn parse_file_content<R: BufRead>(
     reader: &mut Reader<R>,
-    _start: &BytesStart,
-) -> Result<FileContent, String> {
-    let mut fc = FileContent::default();
+    start: &BytesStart,
+) -> Result<FileContent, Cause> {
+    let mut fc = FileContent {
+        extensions: extensions_of(start, &[]),
+        ..Default::default()
+    };
     let mut buf = Vec::with_capacity(512);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => {
-                push_params_empty(
-                    &e,
-                    &mut fc.referenceable_param_group_refs,
-                    &mut fc.cv_params,
-                    &mut fc.user_params,
-                );
-            }
-            Event::Start(e) => {
-                if !push_params_start(
-                    reader,
-                    &e,
-                    &mut fc.referenceable_param_group_refs,
-                    &mut fc.cv_params,
-                    &mut fc.user_params,
-                )? {
-                    skip_element(reader, e.name().as_ref())?;
-                }
-            }
-            Event::End(e) if e.name().as_ref() == b"fileContent" => break,
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => push_params_or_capture_empty(
+                &e,
+                &mut fc.referenceable_param_group_refs,
+                &mut fc.cv_params,
+                &mut fc.user_params,
+                &mut fc.extensions,
+            ),
+            Event::Start(e) => push_params_or_capture_start(
+                reader,
+                &e,
+                &mut fc.referenceable_param_group_refs,
+                &mut fc.cv_params,
+                &mut fc.user_params,
+                &mut fc.extensions,
+            )?,
+            Event::End(e) if local_name(e.name().as_ref()) == b"fileContent" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -445,33 +670,26 @@ fn parse_file_content<R: BufRead>(
 fn parse_source_file_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<SourceFileList, String> {
+) -> Result<SourceFileList, Cause> {
     let mut list = SourceFileList::default();
     list.count = get_attr_usize(start, b"count");
+    list.extensions = extensions_of(start, LIST_ATTRIBUTES);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
-                if e.name().as_ref() == b"sourceFile" {
+                if local_name(e.name().as_ref()) == b"sourceFile" {
                     list.source_file.push(parse_source_file(reader, &e)?);
                 } else {
-                    skip_element(reader, e.name().as_ref())?;
+                    capture_element(reader, &e, &mut list.extensions)?;
                 }
             }
-            Event::Empty(e) if e.name().as_ref() == b"sourceFile" => {
-                list.source_file.push(SourceFile {
-                    id: get_attr(&e, b"id").unwrap_or_default(),
-                    name: get_attr_any(&e, &[b"name", b"sourceFileName"]).unwrap_or_default(),
-                    location: get_attr_any(&e, &[b"location", b"sourceFileLocation"])
-                        .unwrap_or_default(),
-                    ..Default::default()
-                });
+            Event::Empty(e) if local_name(e.name().as_ref()) == b"sourceFile" => {
+                list.source_file.push(source_file_from_start(&e));
             }
-            Event::End(e) if e.name().as_ref() == b"sourceFileList" => break,
+            Event::Empty(e) => capture_empty(&e, &mut list.extensions),
+            Event::End(e) if local_name(e.name().as_ref()) == b"sourceFileList" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -481,44 +699,52 @@ fn parse_source_file_list<R: BufRead>(
     Ok(list)
 }
 
-/// <sourceFile>
-fn parse_source_file<R: BufRead>(
-    reader: &mut Reader<R>,
-    start: &BytesStart,
-) -> Result<SourceFile, String> {
-    let mut sf = SourceFile {
+/// <sourceFile> attributes
+fn source_file_from_start(start: &BytesStart) -> SourceFile {
+    SourceFile {
         id: get_attr(start, b"id").unwrap_or_default(),
         name: get_attr_any(start, &[b"name", b"sourceFileName"]).unwrap_or_default(),
         location: get_attr_any(start, &[b"location", b"sourceFileLocation"]).unwrap_or_default(),
+        extensions: extensions_of(
+            start,
+            &[
+                b"id",
+                b"name",
+                b"sourceFileName",
+                b"location",
+                b"sourceFileLocation",
+            ],
+        ),
         ..Default::default()
-    };
+    }
+}
+
+/// <sourceFile>
+fn parse_source_file<R: BufRead>(
+    reader: &mut Reader<R>,
+    start: &BytesStart,
+) -> Result<SourceFile, Cause> {
+    let mut sf = source_file_from_start(start);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => {
-                push_params_empty(
-                    &e,
-                    &mut sf.referenceable_param_group_ref,
-                    &mut sf.cv_param,
-                    &mut sf.user_param,
-                );
-            }
-            Event::Start(e) => {
-                if !push_params_start(
-                    reader,
-                    &e,
-                    &mut sf.referenceable_param_group_ref,
-                    &mut sf.cv_param,
-                    &mut sf.user_param,
-                )? {
-                    skip_element(reader, e.name().as_ref())?;
-                }
-            }
-            Event::End(e) if e.name().as_ref() == b"sourceFile" => break,
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => push_params_or_capture_empty(
+                &e,
+                &mut sf.referenceable_param_group_ref,
+                &mut sf.cv_param,
+                &mut sf.user_param,
+                &mut sf.extensions,
+            ),
+            Event::Start(e) => push_params_or_capture_start(
+                reader,
+                &e,
+                &mut sf.referenceable_param_group_ref,
+                &mut sf.cv_param,
+                &mut sf.user_param,
+                &mut sf.extensions,
+            )?,
+            Event::End(e) if local_name(e.name().as_ref()) == b"sourceFile" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -529,38 +755,31 @@ fn parse_source_file<R: BufRead>(
 }
 
 /// <contact>
-fn parse_contact<R: BufRead>(
-    reader: &mut Reader<R>,
-    _start: &BytesStart,
-) -> Result<Contact, String> {
-    let mut c = Contact::default();
+fn parse_contact<R: BufRead>(reader: &mut Reader<R>, start: &BytesStart) -> Result<Contact, Cause> {
+    let mut c = Contact {
+        extensions: extensions_of(start, &[]),
+        ..Default::default()
+    };
     let mut buf = Vec::with_capacity(512);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => {
-                push_params_empty(
-                    &e,
-                    &mut c.referenceable_param_group_refs,
-                    &mut c.cv_params,
-                    &mut c.user_params,
-                );
-            }
-            Event::Start(e) => {
-                if !push_params_start(
-                    reader,
-                    &e,
-                    &mut c.referenceable_param_group_refs,
-                    &mut c.cv_params,
-                    &mut c.user_params,
-                )? {
-                    skip_element(reader, e.name().as_ref())?;
-                }
-            }
-            Event::End(e) if e.name().as_ref() == b"contact" => break,
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => push_params_or_capture_empty(
+                &e,
+                &mut c.referenceable_param_group_refs,
+                &mut c.cv_params,
+                &mut c.user_params,
+                &mut c.extensions,
+            ),
+            Event::Start(e) => push_params_or_capture_start(
+                reader,
+                &e,
+                &mut c.referenceable_param_group_refs,
+                &mut c.cv_params,
+                &mut c.user_params,
+                &mut c.extensions,
+            )?,
+            Event::End(e) if local_name(e.name().as_ref()) == b"contact" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -574,32 +793,30 @@ fn parse_contact<R: BufRead>(
 fn parse_referenceable_param_group_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<ReferenceableParamGroupList, String> {
+) -> Result<ReferenceableParamGroupList, Cause> {
     let mut list = ReferenceableParamGroupList::default();
     list.count = get_attr_usize(start, b"count");
+    list.extensions = extensions_of(start, LIST_ATTRIBUTES);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
-                if e.name().as_ref() == b"referenceableParamGroup" {
+                if local_name(e.name().as_ref()) == b"referenceableParamGroup" {
                     list.referenceable_param_groups
                         .push(parse_referenceable_param_group(reader, &e)?);
                 } else {
-                    skip_element(reader, e.name().as_ref())?;
+                    capture_element(reader, &e, &mut list.extensions)?;
                 }
             }
-            Event::Empty(e) if e.name().as_ref() == b"referenceableParamGroup" => {
+            Event::Empty(e) if local_name(e.name().as_ref()) == b"referenceableParamGroup" => {
                 list.referenceable_param_groups
-                    .push(ReferenceableParamGroup {
-                        id: get_attr(&e, b"id").unwrap_or_default(),
-                        ..Default::default()
-                    });
+                    .push(referenceable_param_group_from_start(&e));
+            }
+            Event::Empty(e) => capture_empty(&e, &mut list.extensions),
+            Event::End(e) if local_name(e.name().as_ref()) == b"referenceableParamGroupList" => {
+                break;
             }
-            Event::End(e) if e.name().as_ref() == b"referenceableParamGroupList" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -609,28 +826,31 @@ fn parse_referenceable_param_group_list<R: BufRead>(
     Ok(list)
 }
 
+/// <referenceableParamGroup> attributes
+fn referenceable_param_group_from_start(start: &BytesStart) -> ReferenceableParamGroup {
+    ReferenceableParamGroup {
+        id: get_attr(start, b"id").unwrap_or_default(),
+        extensions: extensions_of(start, &[b"id"]),
+        ..Default::default()
+    }
+}
+
 /// <referenceableParamGroup>
 fn parse_referenceable_param_group<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<ReferenceableParamGroup, String> {
-    let mut group = ReferenceableParamGroup {
-        id: get_attr(start, b"id").unwrap_or_default(),
-        ..Default::default()
-    };
+) -> Result<ReferenceableParamGroup, Cause> {
+    let mut group = referenceable_param_group_from_start(start);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => match e.name().as_ref() {
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => match local_name(e.name().as_ref()) {
                 b"cvParam" => group.cv_params.push(parse_cv_param(&e)),
                 b"userParam" => group.user_params.push(parse_user_param(&e)),
-                _ => {}
+                _ => capture_empty(&e, &mut group.extensions),
             },
-            Event::Start(e) => match e.name().as_ref() {
+            Event::Start(e) => match local_name(e.name().as_ref()) {
                 b"cvParam" => {
                     group.cv_params.push(parse_cv_param(&e));
                     skip_element(reader, b"cvParam")?;
@@ -639,9 +859,9 @@ fn parse_referenceable_param_group<R: BufRead>(
                     group.user_params.push(parse_user_param(&e));
                     skip_element(reader, b"userParam")?;
                 }
-                _ => skip_element(reader, e.name().as_ref())?,
+                _ => capture_element(reader, &e, &mut group.extensions)?,
             },
-            Event::End(e) if e.name().as_ref() == b"referenceableParamGroup" => break,
+            Event::End(e) if local_name(e.name().as_ref()) == b"referenceableParamGroup" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -655,31 +875,26 @@ fn parse_referenceable_param_group<R: BufRead>(
 fn parse_sample_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<SampleList, String> {
+) -> Result<SampleList, Cause> {
     let mut list = SampleList::default();
     list.count = get_attr_u32(start, b"count");
+    list.extensions = extensions_of(start, LIST_ATTRIBUTES);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
-                if e.name().as_ref() == b"sample" {
+                if local_name(e.name().as_ref()) == b"sample" {
                     list.samples.push(parse_sample(reader, &e)?);
                 } else {
-                    skip_element(reader, e.name().as_ref())?;
+                    capture_element(reader, &e, &mut list.extensions)?;
                 }
             }
-            Event::Empty(e) if e.name().as_ref() == b"sample" => {
-                list.samples.push(Sample {
-                    id: get_attr(&e, b"id").unwrap_or_default(),
-                    name: get_attr(&e, b"name").unwrap_or_default(),
-                    ..Default::default()
-                });
+            Event::Empty(e) if local_name(e.name().as_ref()) == b"sample" => {
+                list.samples.push(sample_from_start(&e));
             }
-            Event::End(e) if e.name().as_ref() == b"sampleList" => break,
+            Event::Empty(e) => capture_empty(&e, &mut list.extensions),
+            Event::End(e) if local_name(e.name().as_ref()) == b"sampleList" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -688,50 +903,39 @@ fn parse_sample_list<R: BufRead>(
     Ok(list)
 }
 
-/// <sample>
-fn parse_sample<R: BufRead>(reader: &mut Reader<R>, start: &BytesStart) -> Result<Sample, String> {
-    let mut sample = Sample {
+/// <sample> attributes
+fn sample_from_start(start: &BytesStart) -> Sample {
+    Sample {
         id: get_attr(start, b"id").unwrap_or_default(),
         name: get_attr(start, b"name").unwrap_or_default(),
+        extensions: extensions_of(start, &[b"id", b"name"]),
         ..Default::default()
-    };
+    }
+}
+
+/// <sample>
+fn parse_sample<R: BufRead>(reader: &mut Reader<R>, start: &BytesStart) -> Result<Sample, Cause> {
+    let mut sample = sample_from_start(start);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => match e.name().as_ref() {
-                b"referenceableParamGroupRef" => {
-                    if sample.referenceable_param_group_ref.is_none() {
-                        sample.referenceable_param_group_ref =
-                            Some(parse_referenceable_param_group_ref(&e));
-                    }
-                }
-                // b"cvParam" => sample.cv_params.push(parse_cv_param(&e)),
-                // b"userParam" => sample.user_params.push(parse_user_param(&e)),
-                _ => {}
-            },
-            Event::Start(e) => match e.name().as_ref() {
-                b"referenceableParamGroupRef" => {
-                    if sample.referenceable_param_group_ref.is_none() {
-                        sample.referenceable_param_group_ref =
-                            Some(parse_referenceable_param_group_ref(&e));
-                    }
-                    skip_element(reader, b"referenceableParamGroupRef")?;
-                }
-                // b"cvParam" => {
-                //     sample.cv_params.push(parse_cv_param(&e));
-                //     skip_element(reader, b"cvParam")?;
-                // }
-                // b"userParam" => {
-                //     sample.user_params.push(parse_user_param(&e));
-                //     skip_element(reader, b"userParam")?;
-                // }
-                _ => skip_element(reader, e.name().as_ref())?,
-            },
-            Event::End(e) if e.name().as_ref() == b"sample" => break,
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => push_params_or_capture_empty(
+                &e,
+                &mut sample.referenceable_param_group_ref,
+                &mut sample.cv_param,
+                &mut sample.user_param,
+                &mut sample.extensions,
+            ),
+            Event::Start(e) => push_params_or_capture_start(
+                reader,
+                &e,
+                &mut sample.referenceable_param_group_ref,
+                &mut sample.cv_param,
+                &mut sample.user_param,
+                &mut sample.extensions,
+            )?,
+            Event::End(e) if local_name(e.name().as_ref()) == b"sample" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -745,28 +949,26 @@ fn parse_sample<R: BufRead>(reader: &mut Reader<R>, start: &BytesStart) -> Resul
 fn parse_instrument_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<Option<InstrumentList>, String> {
+) -> Result<Option<InstrumentList>, Cause> {
     let mut list = InstrumentList::default();
     list.count = get_attr_usize(start, b"count");
+    list.extensions = extensions_of(start, LIST_ATTRIBUTES);
     let end_tag = start.name();
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Start(e) => match e.name().as_ref() {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => match local_name(e.name().as_ref()) {
                 b"instrument" | b"instrumentConfiguration" => {
                     list.instrument.push(parse_instrument(reader, &e, true)?)
                 }
-                _ => skip_element(reader, e.name().as_ref())?,
+                _ => capture_element(reader, &e, &mut list.extensions)?,
             },
-            Event::Empty(e) => match e.name().as_ref() {
+            Event::Empty(e) => match local_name(e.name().as_ref()) {
                 b"instrument" | b"instrumentConfiguration" => {
                     list.instrument.push(parse_instrument(reader, &e, false)?)
                 }
-                _ => {}
+                _ => capture_empty(&e, &mut list.extensions),
             },
             Event::End(e) if e.name() == end_tag => break,
             Event::Eof => break,
@@ -787,7 +989,7 @@ fn parse_instrument<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
     has_body: bool,
-) -> Result<Instrument, String> {
+) -> Result<Instrument, Cause> {
     let scan_settings_ref =
         get_attr(start, b"scanSettingsRef").map(|r| ScanSettingsRef { r#ref: r });
     let software_ref = get_attr(start, b"softwareRef").map(|r| InstrumentSoftwareRef { r#ref: r });
@@ -796,6 +998,7 @@ fn parse_instrument<R: BufRead>(
         id: get_attr(start, b"id").unwrap_or_default(),
         scan_settings_ref,
         software_ref,
+        extensions: extensions_of(start, &[b"id", b"scanSettingsRef", b"softwareRef"]),
         ..Default::default()
     };
 
@@ -806,25 +1009,26 @@ fn parse_instrument<R: BufRead>(
     let end_tag = start.name();
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Empty(e) => {
-                if e.name().as_ref() == b"componentList" {
+                if local_name(e.name().as_ref()) == b"componentList" {
                     instrument.component_list = Some(ComponentList {
                         count: get_attr_usize(&e, b"count"),
                         source: Vec::new(),
                         analyzer: Vec::new(),
                         detector: Vec::new(),
+                        extensions: extensions_of(&e, LIST_ATTRIBUTES),
                     });
-                } else {
-                    push_params_empty(
-                        &e,
-                        &mut instrument.referenceable_param_group_ref,
-                        &mut instrument.cv_param,
-                        &mut instrument.user_param,
-                    );
+                } else if local_name(e.name().as_ref()) == b"softwareRef" {
+                    instrument.software_ref =
+                        get_attr(&e, b"ref").map(|r| InstrumentSoftwareRef { r#ref: r });
+                } else if !push_params_empty(
+                    &e,
+                    &mut instrument.referenceable_param_group_ref,
+                    &mut instrument.cv_param,
+                    &mut instrument.user_param,
+                ) {
+                    capture_empty(&e, &mut instrument.extensions);
                 }
             }
             Event::Start(e) => {
@@ -835,10 +1039,10 @@ fn parse_instrument<R: BufRead>(
                     &mut instrument.cv_param,
                     &mut instrument.user_param,
                 )? {
-                    if e.name().as_ref() == b"componentList" {
+                    if local_name(e.name().as_ref()) == b"componentList" {
                         instrument.component_list = Some(parse_component_list(reader, &e)?);
                     } else {
-                        skip_element(reader, e.name().as_ref())?;
+                        capture_element(reader, &e, &mut instrument.extensions)?;
                     }
                 }
             }
@@ -856,17 +1060,15 @@ fn parse_instrument<R: BufRead>(
 fn parse_component_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<ComponentList, String> {
+) -> Result<ComponentList, Cause> {
     let mut list = ComponentList::default();
     list.count = get_attr_usize(start, b"count");
+    list.extensions = extensions_of(start, LIST_ATTRIBUTES);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Start(e) => match e.name().as_ref() {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => match local_name(e.name().as_ref()) {
                 b"source" => {
                     let s = parse_component(reader, &e)?;
                     list.source.push(Source {
@@ -874,6 +1076,7 @@ fn parse_component_list<R: BufRead>(
                         referenceable_param_group_ref: s.referenceable_param_group_ref,
                         cv_param: s.cv_param,
                         user_param: s.user_param,
+                        extensions: s.extensions,
                     });
                 }
                 b"analyzer" => {
@@ -883,6 +1086,7 @@ fn parse_component_list<R: BufRead>(
                         referenceable_param_group_ref: a.referenceable_param_group_ref,
                         cv_param: a.cv_param,
                         user_param: a.user_param,
+                        extensions: a.extensions,
                     });
                 }
                 b"detector" => {
@@ -892,26 +1096,30 @@ fn parse_component_list<R: BufRead>(
                         referenceable_param_group_ref: d.referenceable_param_group_ref,
                         cv_param: d.cv_param,
                         user_param: d.user_param,
+                        extensions: d.extensions,
                     });
                 }
-                _ => skip_element(reader, e.name().as_ref())?,
+                _ => capture_element(reader, &e, &mut list.extensions)?,
             },
-            Event::Empty(e) => match e.name().as_ref() {
+            Event::Empty(e) => match local_name(e.name().as_ref()) {
                 b"source" => list.source.push(Source {
                     order: get_attr_u32(&e, b"order"),
+                    extensions: extensions_of(&e, &[b"order"]),
                     ..Default::default()
                 }),
                 b"analyzer" => list.analyzer.push(Analyzer {
                     order: get_attr_u32(&e, b"order"),
+                    extensions: extensions_of(&e, &[b"order"]),
                     ..Default::default()
                 }),
                 b"detector" => list.detector.push(Detector {
                     order: get_attr_u32(&e, b"order"),
+                    extensions: extensions_of(&e, &[b"order"]),
                     ..Default::default()
                 }),
-                _ => {}
+                _ => capture_empty(&e, &mut list.extensions),
             },
-            Event::End(e) if e.name().as_ref() == b"componentList" => break,
+            Event::End(e) if local_name(e.name().as_ref()) == b"componentList" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -927,46 +1135,41 @@ struct ComponentTmp {
     referenceable_param_group_ref: Vec<ReferenceableParamGroupRef>,
     cv_param: Vec<CvParam>,
     user_param: Vec<UserParam>,
+    extensions: Extensions,
 }
 
 fn parse_component<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<ComponentTmp, String> {
+) -> Result<ComponentTmp, Cause> {
     let mut tmp = ComponentTmp {
         order: get_attr_u32(start, b"order"),
         referenceable_param_group_ref: Vec::new(),
         cv_param: Vec::new(),
         user_param: Vec::new(),
+        extensions: extensions_of(start, &[b"order"]),
     };
 
     let end_tag = start.name();
     let mut buf = Vec::with_capacity(512);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => {
-                push_params_empty(
-                    &e,
-                    &mut tmp.referenceable_param_group_ref,
-                    &mut tmp.cv_param,
-                    &mut tmp.user_param,
-                );
-            }
-            Event::Start(e) => {
-                if !push_params_start(
-                    reader,
-                    &e,
-                    &mut tmp.referenceable_param_group_ref,
-                    &mut tmp.cv_param,
-                    &mut tmp.user_param,
-                )? {
-                    skip_element(reader, e.name().as_ref())?;
-                }
-            }
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => push_params_or_capture_empty(
+                &e,
+                &mut tmp.referenceable_param_group_ref,
+                &mut tmp.cv_param,
+                &mut tmp.user_param,
+                &mut tmp.extensions,
+            ),
+            Event::Start(e) => push_params_or_capture_start(
+                reader,
+                &e,
+                &mut tmp.referenceable_param_group_ref,
+                &mut tmp.cv_param,
+                &mut tmp.user_param,
+                &mut tmp.extensions,
+            )?,
             Event::End(e) if e.name() == end_tag => break,
             Event::Eof => break,
             _ => {}
@@ -981,30 +1184,26 @@ fn parse_component<R: BufRead>(
 fn parse_scan_settings_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<Option<ScanSettingsList>, String> {
+) -> Result<Option<ScanSettingsList>, Cause> {
     let mut list = ScanSettingsList::default();
     list.count = get_attr_usize(start, b"count");
+    list.extensions = extensions_of(start, LIST_ATTRIBUTES);
     let end_tag = start.name();
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Start(e) => match e.name().as_ref() {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => match local_name(e.name().as_ref()) {
                 b"scanSettings" | b"acquisitionSettings" => {
                     list.scan_settings.push(parse_scan_settings(reader, &e)?)
                 }
-                _ => skip_element(reader, e.name().as_ref())?,
+                _ => capture_element(reader, &e, &mut list.extensions)?,
             },
-            Event::Empty(e) => match e.name().as_ref() {
-                b"scanSettings" | b"acquisitionSettings" => list.scan_settings.push(ScanSettings {
-                    id: get_attr(&e, b"id"),
-                    instrument_configuration_ref: get_attr(&e, b"instrumentConfigurationRef"),
-                    ..Default::default()
-                }),
-                _ => {}
+            Event::Empty(e) => match local_name(e.name().as_ref()) {
+                b"scanSettings" | b"acquisitionSettings" => {
+                    list.scan_settings.push(scan_settings_from_start(&e))
+                }
+                _ => capture_empty(&e, &mut list.extensions),
             },
             Event::End(e) if e.name() == end_tag => break,
             Event::Eof => break,
@@ -1020,33 +1219,35 @@ fn parse_scan_settings_list<R: BufRead>(
     })
 }
 
+/// <scanSettings> attributes
+fn scan_settings_from_start(start: &BytesStart) -> ScanSettings {
+    ScanSettings {
+        id: get_attr(start, b"id"),
+        instrument_configuration_ref: get_attr(start, b"instrumentConfigurationRef"),
+        extensions: extensions_of(start, &[b"id", b"instrumentConfigurationRef"]),
+        ..Default::default()
+    }
+}
+
 /// <scanSettings>
 fn parse_scan_settings<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<ScanSettings, String> {
-    let mut s = ScanSettings {
-        id: get_attr(start, b"id"),
-        instrument_configuration_ref: get_attr(start, b"instrumentConfigurationRef"),
-        ..Default::default()
-    };
+) -> Result<ScanSettings, Cause> {
+    let mut s = scan_settings_from_start(start);
 
     let end_tag = start.name();
     let mut buf = Vec::with_capacity(512);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => {
-                push_params_empty(
-                    &e,
-                    &mut s.referenceable_param_group_refs,
-                    &mut s.cv_params,
-                    &mut s.user_params,
-                );
-            }
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => push_params_or_capture_empty(
+                &e,
+                &mut s.referenceable_param_group_refs,
+                &mut s.cv_params,
+                &mut s.user_params,
+                &mut s.extensions,
+            ),
             Event::Start(e) => {
                 if !push_params_start(
                     reader,
@@ -1055,12 +1256,12 @@ fn parse_scan_settings<R: BufRead>(
                     &mut s.cv_params,
                     &mut s.user_params,
                 )? {
-                    match e.name().as_ref() {
+                    match local_name(e.name().as_ref()) {
                         b"sourceFileRefList" => {
                             s.source_file_ref_list = Some(parse_source_file_ref_list(reader, &e)?)
                         }
                         b"targetList" => s.target_list = Some(parse_target_list(reader, &e)?),
-                        _ => skip_element(reader, e.name().as_ref())?,
+                        _ => capture_element(reader, &e, &mut s.extensions)?,
                     }
                 }
             }
@@ -1078,28 +1279,27 @@ fn parse_scan_settings<R: BufRead>(
 fn parse_source_file_ref_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<SourceFileRefList, String> {
+) -> Result<SourceFileRefList, Cause> {
     let mut list = SourceFileRefList::default();
     list.count = get_attr_usize(start, b"count");
+    list.extensions = extensions_of(start, LIST_ATTRIBUTES);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) if e.name().as_ref() == b"sourceFileRef" => {
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) if local_name(e.name().as_ref()) == b"sourceFileRef" => {
                 list.source_file_refs.push(parse_source_file_ref(&e))
             }
             Event::Start(e) => {
-                if e.name().as_ref() == b"sourceFileRef" {
+                if local_name(e.name().as_ref()) == b"sourceFileRef" {
                     list.source_file_refs.push(parse_source_file_ref(&e));
                     skip_element(reader, b"sourceFileRef")?;
                 } else {
-                    skip_element(reader, e.name().as_ref())?;
+                    capture_element(reader, &e, &mut list.extensions)?;
                 }
             }
-            Event::End(e) if e.name().as_ref() == b"sourceFileRefList" => break,
+            Event::Empty(e) => capture_empty(&e, &mut list.extensions),
+            Event::End(e) if local_name(e.name().as_ref()) == b"sourceFileRefList" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -1113,27 +1313,29 @@ fn parse_source_file_ref_list<R: BufRead>(
 fn parse_target_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<TargetList, String> {
+) -> Result<TargetList, Cause> {
     let mut list = TargetList::default();
     list.count = get_attr_usize(start, b"count");
+    list.extensions = extensions_of(start, LIST_ATTRIBUTES);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
-                if e.name().as_ref() == b"target" {
+                if local_name(e.name().as_ref()) == b"target" {
                     list.targets.push(parse_target(reader, &e)?);
                 } else {
-                    skip_element(reader, e.name().as_ref())?;
+                    capture_element(reader, &e, &mut list.extensions)?;
                 }
             }
-            Event::Empty(e) if e.name().as_ref() == b"target" => {
-                list.targets.push(Target::default())
+            Event::Empty(e) if local_name(e.name().as_ref()) == b"target" => {
+                list.targets.push(Target {
+                    extensions: extensions_of(&e, &[]),
+                    ..Default::default()
+                })
             }
-            Event::End(e) if e.name().as_ref() == b"targetList" => break,
+            Event::Empty(e) => capture_empty(&e, &mut list.extensions),
+            Event::End(e) if local_name(e.name().as_ref()) == b"targetList" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -1144,35 +1346,31 @@ fn parse_target_list<R: BufRead>(
 }
 
 /// <target>
-fn parse_target<R: BufRead>(reader: &mut Reader<R>, _start: &BytesStart) -> Result<Target, String> {
-    let mut target = Target::default();
+fn parse_target<R: BufRead>(reader: &mut Reader<R>, start: &BytesStart) -> Result<Target, Cause> {
+    let mut target = Target {
+        extensions: extensions_of(start, &[]),
+        ..Default::default()
+    };
     let mut buf = Vec::with_capacity(512);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => {
-                push_params_empty(
-                    &e,
-                    &mut target.referenceable_param_group_refs,
-                    &mut target.cv_params,
-                    &mut target.user_params,
-                );
-            }
-            Event::Start(e) => {
-                if !push_params_start(
-                    reader,
-                    &e,
-                    &mut target.referenceable_param_group_refs,
-                    &mut target.cv_params,
-                    &mut target.user_params,
-                )? {
-                    skip_element(reader, e.name().as_ref())?;
-                }
-            }
-            Event::End(e) if e.name().as_ref() == b"target" => break,
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => push_params_or_capture_empty(
+                &e,
+                &mut target.referenceable_param_group_refs,
+                &mut target.cv_params,
+                &mut target.user_params,
+                &mut target.extensions,
+            ),
+            Event::Start(e) => push_params_or_capture_start(
+                reader,
+                &e,
+                &mut target.referenceable_param_group_refs,
+                &mut target.cv_params,
+                &mut target.user_params,
+                &mut target.extensions,
+            )?,
+            Event::End(e) if local_name(e.name().as_ref()) == b"target" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -1186,29 +1384,26 @@ fn parse_target<R: BufRead>(reader: &mut Reader<R>, _start: &BytesStart) -> Resu
 fn parse_software_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<SoftwareList, String> {
+) -> Result<SoftwareList, Cause> {
     let mut list = SoftwareList::default();
     list.count = get_attr_usize(start, b"count");
+    list.extensions = extensions_of(start, LIST_ATTRIBUTES);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) if e.name().as_ref() == b"software" => list.software.push(Software {
-                id: get_attr(&e, b"id").unwrap_or_default(),
-                version: get_attr(&e, b"version"),
-                ..Default::default()
-            }),
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) if local_name(e.name().as_ref()) == b"software" => {
+                list.software.push(software_from_start(&e))
+            }
             Event::Start(e) => {
-                if e.name().as_ref() == b"software" {
+                if local_name(e.name().as_ref()) == b"software" {
                     list.software.push(parse_software(reader, &e)?);
                 } else {
-                    skip_element(reader, e.name().as_ref())?;
+                    capture_element(reader, &e, &mut list.extensions)?;
                 }
             }
-            Event::End(e) if e.name().as_ref() == b"softwareList" => break,
+            Event::Empty(e) => capture_empty(&e, &mut list.extensions),
+            Event::End(e) if local_name(e.name().as_ref()) == b"softwareList" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -1218,38 +1413,54 @@ fn parse_software_list<R: BufRead>(
     Ok(list)
 }
 
+/// <software> attributes
+fn software_from_start(start: &BytesStart) -> Software {
+    Software {
+        id: get_attr(start, b"id").unwrap_or_default(),
+        version: get_attr(start, b"version"),
+        extensions: extensions_of(start, &[b"id", b"version"]),
+        ..Default::default()
+    }
+}
+
 /// <software>
 fn parse_software<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<Software, String> {
-    let mut s = Software {
-        id: get_attr(start, b"id").unwrap_or_default(),
-        version: get_attr(start, b"version"),
-        ..Default::default()
-    };
+) -> Result<Software, Cause> {
+    let mut s = software_from_start(start);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Empty(e) => match local_name(e.name().as_ref()) {
                 b"softwareParam" => s.software_param.push(parse_software_param(&e)),
+                b"referenceableParamGroupRef" => s
+                    .referenceable_param_group_ref
+                    .push(parse_referenceable_param_group_ref(&e)),
                 b"cvParam" => s.cv_param.push(parse_cv_param(&e)),
-                _ => {}
+                b"userParam" => s.user_param.push(parse_user_param(&e)),
+                _ => capture_empty(&e, &mut s.extensions),
             },
             Event::Start(e) => match local_name(e.name().as_ref()) {
                 b"softwareParam" => {
                     s.software_param.push(parse_software_param(&e));
-                    skip_element(reader, e.name().as_ref())?;
+                    skip_element(reader, local_name(e.name().as_ref()))?;
+                }
+                b"referenceableParamGroupRef" => {
+                    s.referenceable_param_group_ref
+                        .push(parse_referenceable_param_group_ref(&e));
+                    skip_element(reader, local_name(e.name().as_ref()))?;
                 }
                 b"cvParam" => {
                     s.cv_param.push(parse_cv_param(&e));
-                    skip_element(reader, e.name().as_ref())?;
+                    skip_element(reader, local_name(e.name().as_ref()))?;
+                }
+                b"userParam" => {
+                    s.user_param.push(parse_user_param(&e));
+                    skip_element(reader, local_name(e.name().as_ref()))?;
                 }
-                _ => skip_element(reader, e.name().as_ref())?,
+                _ => capture_element(reader, &e, &mut s.extensions)?,
             },
             Event::End(e) if local_name(e.name().as_ref()) == b"software" => break,
             Event::Eof => break,
@@ -1265,31 +1476,27 @@ fn parse_software<R: BufRead>(
 fn parse_data_processing_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<DataProcessingList, String> {
+) -> Result<DataProcessingList, Cause> {
     let mut list = DataProcessingList::default();
     list.count = get_attr_usize(start, b"count");
+    list.extensions = extensions_of(start, LIST_ATTRIBUTES);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
-                if e.name().as_ref() == b"dataProcessing" {
+                if local_name(e.name().as_ref()) == b"dataProcessing" {
                     list.data_processing
                         .push(parse_data_processing(reader, &e)?);
                 } else {
-                    skip_element(reader, e.name().as_ref())?;
+                    capture_element(reader, &e, &mut list.extensions)?;
                 }
             }
-            Event::Empty(e) if e.name().as_ref() == b"dataProcessing" => {
-                list.data_processing.push(DataProcessing {
-                    id: get_attr(&e, b"id").unwrap_or_default(),
-                    ..Default::default()
-                })
+            Event::Empty(e) if local_name(e.name().as_ref()) == b"dataProcessing" => {
+                list.data_processing.push(data_processing_from_start(&e))
             }
-            Event::End(e) if e.name().as_ref() == b"dataProcessingList" => break,
+            Event::Empty(e) => capture_empty(&e, &mut list.extensions),
+            Event::End(e) if local_name(e.name().as_ref()) == b"dataProcessingList" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -1298,39 +1505,39 @@ fn parse_data_processing_list<R: BufRead>(
     Ok(list)
 }
 
+/// <dataProcessing> attributes
+fn data_processing_from_start(start: &BytesStart) -> DataProcessing {
+    DataProcessing {
+        id: get_attr(start, b"id").unwrap_or_default(),
+        software_ref: get_attr(start, b"softwareRef"),
+        extensions: extensions_of(start, &[b"id", b"softwareRef"]),
+        ..Default::default()
+    }
+}
+
 /// <dataProcessing>
 fn parse_data_processing<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<DataProcessing, String> {
-    let mut dp = DataProcessing {
-        id: get_attr(start, b"id").unwrap_or_default(),
-        software_ref: get_attr(start, b"softwareRef"),
-        ..Default::default()
-    };
+) -> Result<DataProcessing, Cause> {
+    let mut dp = data_processing_from_start(start);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
-                if e.name().as_ref() == b"processingMethod" {
+                if local_name(e.name().as_ref()) == b"processingMethod" {
                     dp.processing_method
                         .push(parse_processing_method(reader, &e)?);
                 } else {
-                    skip_element(reader, e.name().as_ref())?;
+                    capture_element(reader, &e, &mut dp.extensions)?;
                 }
             }
-            Event::Empty(e) if e.name().as_ref() == b"processingMethod" => {
-                dp.processing_method.push(ProcessingMethod {
-                    order: get_attr_u32(&e, b"order"),
-                    software_ref: get_attr(&e, b"softwareRef"),
-                    ..Default::default()
-                })
+            Event::Empty(e) if local_name(e.name().as_ref()) == b"processingMethod" => {
+                dp.processing_method.push(processing_method_from_start(&e))
             }
-            Event::End(e) if e.name().as_ref() == b"dataProcessing" => break,
+            Event::Empty(e) => capture_empty(&e, &mut dp.extensions),
+            Event::End(e) if local_name(e.name().as_ref()) == b"dataProcessing" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -1339,43 +1546,42 @@ fn parse_data_processing<R: BufRead>(
     Ok(dp)
 }
 
+/// <processingMethod> attributes
+fn processing_method_from_start(start: &BytesStart) -> ProcessingMethod {
+    ProcessingMethod {
+        order: get_attr_u32(start, b"order"),
+        software_ref: get_attr(start, b"softwareRef"),
+        extensions: extensions_of(start, &[b"order", b"softwareRef"]),
+        ..Default::default()
+    }
+}
+
 /// <processingMethod>
 fn parse_processing_method<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<ProcessingMethod, String> {
-    let mut pm = ProcessingMethod {
-        order: get_attr_u32(start, b"order"),
-        software_ref: get_attr(start, b"softwareRef"),
-        ..Default::default()
-    };
+) -> Result<ProcessingMethod, Cause> {
+    let mut pm = processing_method_from_start(start);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => {
-                push_params_empty(
-                    &e,
-                    &mut pm.referenceable_param_group_ref,
-                    &mut pm.cv_param,
-                    &mut pm.user_param,
-                );
-            }
-            Event::Start(e) => {
-                if !push_params_start(
-                    reader,
-                    &e,
-                    &mut pm.referenceable_param_group_ref,
-                    &mut pm.cv_param,
-                    &mut pm.user_param,
-                )? {
-                    skip_element(reader, e.name().as_ref())?;
-                }
-            }
-            Event::End(e) if e.name().as_ref() == b"processingMethod" => break,
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => push_params_or_capture_empty(
+                &e,
+                &mut pm.referenceable_param_group_ref,
+                &mut pm.cv_param,
+                &mut pm.user_param,
+                &mut pm.extensions,
+            ),
+            Event::Start(e) => push_params_or_capture_start(
+                reader,
+                &e,
+                &mut pm.referenceable_param_group_ref,
+                &mut pm.cv_param,
+                &mut pm.user_param,
+                &mut pm.extensions,
+            )?,
+            Event::End(e) if local_name(e.name().as_ref()) == b"processingMethod" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -1388,61 +1594,42 @@ fn parse_processing_method<R: BufRead>(
 fn parse_run<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-    slim: bool,
-) -> Result<Run, String> {
-    let mut run = Run {
-        id: get_attr(start, b"id").unwrap_or_default(),
-        start_time_stamp: get_attr(start, b"startTimeStamp"),
-        default_instrument_configuration_ref: get_attr(start, b"defaultInstrumentConfigurationRef")
-            .or_else(|| get_attr(start, b"instrumentRef")),
-        default_source_file_ref: get_attr(start, b"defaultSourceFileRef"),
-        sample_ref: get_attr(start, b"sampleRef"),
-        ..Default::default()
-    };
+    options: &ParseOptions,
+) -> Result<Run, Cause> {
+    let mut run = run_from_start(start);
 
     let end_tag = start.name();
     let mut buf = Vec::with_capacity(1024);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => {
-                push_params_empty(
-                    &e,
-                    &mut run.referenceable_param_group_refs,
-                    &mut run.cv_params,
-                    &mut run.user_params,
-                );
-            }
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => push_params_or_capture_empty(
+                &e,
+                &mut run.referenceable_param_group_refs,
+                &mut run.cv_params,
+                &mut run.user_params,
+                &mut run.extensions,
+            ),
             Event::Start(e) => {
-                if !push_params_start(
-                    reader,
-                    &e,
-                    &mut run.referenceable_param_group_refs,
-                    &mut run.cv_params,
-                    &mut run.user_params,
-                )? {
-                    match e.name().as_ref() {
-                        b"sourceFileRefList" => {
-                            run.source_file_ref_list = Some(parse_source_file_ref_list(reader, &e)?)
-                        }
+                if !parse_run_child(reader, &e, &mut run)? {
+                    match local_name(e.name().as_ref()) {
                         b"spectrumList" => {
-                            if slim {
+                            if options.slim {
                                 skip_element(reader, b"spectrumList")?;
                             } else {
-                                run.spectrum_list = Some(parse_spectrum_list(reader, &e)?);
+                                run.spectrum_list =
+                                    Some(parse_spectrum_list(reader, &e, options.threads)?);
                             }
                         }
                         b"chromatogramList" => {
-                            if slim {
+                            if options.slim {
                                 skip_element(reader, b"chromatogramList")?;
                             } else {
-                                run.chromatogram_list = Some(parse_chromatogram_list(reader, &e)?);
+                                run.chromatogram_list =
+                                    Some(parse_chromatogram_list(reader, &e, options.threads)?);
                             }
                         }
-                        _ => skip_element(reader, e.name().as_ref())?,
+                        _ => capture_element(reader, &e, &mut run.extensions)?,
                     }
                 }
             }
@@ -1453,59 +1640,126 @@ fn parse_run<R: BufRead>(
         buf.clear();
     }
 
-    Ok(run)
+    Ok(run)
+}
+
+const RUN_ATTRIBUTES: &[&[u8]] = &[
+    b"id",
+    b"startTimeStamp",
+    b"defaultInstrumentConfigurationRef",
+    b"instrumentRef",
+    b"defaultSourceFileRef",
+    b"sampleRef",
+];
+
+/// <run> attributes
+pub(crate) fn run_from_start(start: &BytesStart) -> Run {
+    Run {
+        id: get_attr(start, b"id").unwrap_or_default(),
+        start_time_stamp: get_attr(start, b"startTimeStamp"),
+        default_instrument_configuration_ref: get_attr(start, b"defaultInstrumentConfigurationRef")
+            .or_else(|| get_attr(start, b"instrumentRef")),
+        default_source_file_ref: get_attr(start, b"defaultSourceFileRef"),
+        sample_ref: get_attr(start, b"sampleRef"),
+        extensions: extensions_of(start, RUN_ATTRIBUTES),
+        ..Default::default()
+    }
+}
+
+/// <run> params and <sourceFileRefList>; returns `false` for any other element.
+pub(crate) fn parse_run_child<R: BufRead>(
+    reader: &mut Reader<R>,
+    e: &BytesStart,
+    run: &mut Run,
+) -> Result<bool, Cause> {
+    if push_params_start(
+        reader,
+        e,
+        &mut run.referenceable_param_group_refs,
+        &mut run.cv_params,
+        &mut run.user_params,
+    )? {
+        return Ok(true);
+    }
+    if local_name(e.name().as_ref()) == b"sourceFileRefList" {
+        run.source_file_ref_list = Some(parse_source_file_ref_list(reader, e)?);
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+/// <spectrumList> attributes
+pub(crate) fn spectrum_list_from_start(start: &BytesStart) -> SpectrumList {
+    SpectrumList {
+        count: get_attr_usize(start, b"count"),
+        default_data_processing_ref: get_attr(start, b"defaultDataProcessingRef"),
+        extensions: extensions_of(start, &[b"count", b"defaultDataProcessingRef"]),
+        ..Default::default()
+    }
 }
 
 /// <spectrumList>
 fn parse_spectrum_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<SpectrumList, String> {
-    let mut list = SpectrumList::default();
-    list.count = get_attr_usize(start, b"count");
-    list.default_data_processing_ref = get_attr(start, b"defaultDataProcessingRef");
+    threads: usize,
+) -> Result<SpectrumList, Cause> {
+    let mut list = spectrum_list_from_start(start);
+    let mut binaries = Binaries::new(threads);
+
+    let read = read_spectra(reader, &mut list, &mut binaries);
+    // Pending payloads precede any XML error, so theirs is reported first.
+    binaries.flush(&mut list.spectra)?;
+    read?;
+    Ok(list)
+}
 
+fn read_spectra<R: BufRead>(
+    reader: &mut Reader<R>,
+    list: &mut SpectrumList,
+    binaries: &mut Binaries,
+) -> Result<(), Cause> {
     let mut buf = Vec::with_capacity(1024);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
-                if e.name().as_ref() == b"spectrum" {
-                    list.spectra.push(parse_spectrum(reader, &e)?);
+                if local_name(e.name().as_ref()) == b"spectrum" {
+                    binaries.start_item(list.spectra.len());
+                    list.spectra
+                        .push(parse_spectrum_with(reader, &e, binaries)?);
+                    binaries.flush_if_full(&mut list.spectra)?;
                 } else {
-                    skip_element(reader, e.name().as_ref())?;
-                }
-            }
-            Event::Empty(e) if e.name().as_ref() == b"spectrum" => list.spectra.push(Spectrum {
-                id: get_attr(&e, b"id").unwrap_or_default(),
-                index: get_attr_u32(&e, b"index"),
-                scan_number: get_attr_u32(&e, b"scanNumber"),
-                ms_level: get_attr_u32(&e, b"msLevel"),
-                default_array_length: get_attr_usize(&e, b"defaultArrayLength"),
-                native_id: get_attr(&e, b"nativeID"),
-                data_processing_ref: get_attr(&e, b"dataProcessingRef"),
-                source_file_ref: get_attr(&e, b"sourceFileRef"),
-                spot_id: get_attr(&e, b"spotID"),
-                ..Default::default()
-            }),
-            Event::End(e) if e.name().as_ref() == b"spectrumList" => break,
+                    capture_element(reader, &e, &mut list.extensions)?;
+                }
+            }
+            Event::Empty(e) if local_name(e.name().as_ref()) == b"spectrum" => {
+                list.spectra.push(spectrum_from_start(&e))
+            }
+            Event::Empty(e) => capture_empty(&e, &mut list.extensions),
+            Event::End(e) if local_name(e.name().as_ref()) == b"spectrumList" => break,
             Event::Eof => break,
             _ => {}
         }
         buf.clear();
     }
-
-    Ok(list)
+    Ok(())
 }
 
-/// <spectrum>
-fn parse_spectrum<R: BufRead>(
-    reader: &mut Reader<R>,
-    start: &BytesStart,
-) -> Result<Spectrum, String> {
-    let mut spectrum = Spectrum {
+const SPECTRUM_ATTRIBUTES: &[&[u8]] = &[
+    b"id",
+    b"index",
+    b"scanNumber",
+    b"msLevel",
+    b"defaultArrayLength",
+    b"nativeID",
+    b"dataProcessingRef",
+    b"sourceFileRef",
+    b"spotID",
+];
+
+/// <spectrum> attributes
+pub(crate) fn spectrum_from_start(start: &BytesStart) -> Spectrum {
+    Spectrum {
         id: get_attr(start, b"id").unwrap_or_default(),
         index: get_attr_u32(start, b"index"),
         scan_number: get_attr_u32(start, b"scanNumber"),
@@ -1515,16 +1769,30 @@ fn parse_spectrum<R: BufRead>(
         data_processing_ref: get_attr(start, b"dataProcessingRef"),
         source_file_ref: get_attr(start, b"sourceFileRef"),
         spot_id: get_attr(start, b"spotID"),
+        extensions: extensions_of(start, SPECTRUM_ATTRIBUTES),
         ..Default::default()
-    };
+    }
+}
+
+/// <spectrum>
+pub(crate) fn parse_spectrum<R: BufRead>(
+    reader: &mut Reader<R>,
+    start: &BytesStart,
+) -> Result<Spectrum, Cause> {
+    parse_spectrum_with(reader, start, &mut Binaries::Inline)
+}
+
+fn parse_spectrum_with<R: BufRead>(
+    reader: &mut Reader<R>,
+    start: &BytesStart,
+    binaries: &mut Binaries,
+) -> Result<Spectrum, Cause> {
+    let mut spectrum = spectrum_from_start(start);
 
     let mut buf = Vec::with_capacity(2048);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => match e.name().as_ref() {
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => match local_name(e.name().as_ref()) {
                 b"referenceableParamGroupRef" => spectrum
                     .referenceable_param_group_refs
                     .push(parse_referenceable_param_group_ref(&e)),
@@ -1534,9 +1802,9 @@ fn parse_spectrum<R: BufRead>(
                     spectrum.cv_params.push(p);
                 }
                 b"userParam" => spectrum.user_params.push(parse_user_param(&e)),
-                _ => {}
+                _ => capture_empty(&e, &mut spectrum.extensions),
             },
-            Event::Start(e) => match e.name().as_ref() {
+            Event::Start(e) => match local_name(e.name().as_ref()) {
                 b"referenceableParamGroupRef" => {
                     spectrum
                         .referenceable_param_group_refs
@@ -1563,18 +1831,18 @@ fn parse_spectrum<R: BufRead>(
                 b"productList" => spectrum.product_list = Some(parse_product_list(reader, &e)?),
                 b"binaryDataArrayList" => {
                     spectrum.binary_data_array_list =
-                        Some(parse_binary_data_array_list(reader, &e)?)
+                        Some(parse_binary_data_array_list(reader, &e, binaries)?)
                 }
                 b"binaryDataArray" => {
                     spectrum
                         .binary_data_array_list
                         .get_or_insert_with(Default::default)
                         .binary_data_arrays
-                        .push(parse_binary_data_array(reader, &e)?);
+                        .push(parse_binary_data_array(reader, &e, binaries)?);
                 }
-                _ => skip_element(reader, e.name().as_ref())?,
+                _ => capture_element(reader, &e, &mut spectrum.extensions)?,
             },
-            Event::End(e) if e.name().as_ref() == b"spectrum" => break,
+            Event::End(e) if local_name(e.name().as_ref()) == b"spectrum" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -1587,24 +1855,23 @@ fn parse_spectrum<R: BufRead>(
 /// <spectrumDescription>
 fn parse_spectrum_description<R: BufRead>(
     reader: &mut Reader<R>,
-    _start: &BytesStart,
-) -> Result<SpectrumDescription, String> {
-    let mut sd = SpectrumDescription::default();
+    start: &BytesStart,
+) -> Result<SpectrumDescription, Cause> {
+    let mut sd = SpectrumDescription {
+        extensions: extensions_of(start, &[]),
+        ..Default::default()
+    };
     let mut buf = Vec::with_capacity(1024);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => {
-                push_params_empty(
-                    &e,
-                    &mut sd.referenceable_param_group_refs,
-                    &mut sd.cv_params,
-                    &mut sd.user_params,
-                );
-            }
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => push_params_or_capture_empty(
+                &e,
+                &mut sd.referenceable_param_group_refs,
+                &mut sd.cv_params,
+                &mut sd.user_params,
+                &mut sd.extensions,
+            ),
             Event::Start(e) => {
                 if !push_params_start(
                     reader,
@@ -1613,7 +1880,7 @@ fn parse_spectrum_description<R: BufRead>(
                     &mut sd.cv_params,
                     &mut sd.user_params,
                 )? {
-                    match e.name().as_ref() {
+                    match local_name(e.name().as_ref()) {
                         b"scanList" => sd.scan_list = Some(parse_scan_list(reader, &e)?),
                         b"scan" => {
                             let scan = parse_scan(reader, &e)?;
@@ -1625,13 +1892,15 @@ fn parse_spectrum_description<R: BufRead>(
                             sd.precursor_list = Some(parse_precursor_list(reader, &e)?)
                         }
                         b"productList" => sd.product_list = Some(parse_product_list(reader, &e)?),
-                        _ => skip_element(reader, e.name().as_ref())?,
+                        _ => capture_element(reader, &e, &mut sd.extensions)?,
                     }
                 }
             }
-            Event::End(e) if e.name().as_ref() == b"spectrumDescription" => break,
+            Event::End(e) if local_name(e.name().as_ref()) == b"spectrumDescription" => break,
             Event::Eof => {
-                return Err("unexpected EOF while parsing <spectrumDescription>".to_string());
+                return Err(Cause::Xml(
+                    "unexpected EOF while parsing <spectrumDescription>".to_string(),
+                ));
             }
             _ => {}
         }
@@ -1645,32 +1914,26 @@ fn parse_spectrum_description<R: BufRead>(
 fn parse_scan_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<ScanList, String> {
+) -> Result<ScanList, Cause> {
     let mut list = ScanList::default();
     list.count = get_attr_usize(start, b"count");
+    list.extensions = extensions_of(start, LIST_ATTRIBUTES);
 
     let mut buf = Vec::with_capacity(1024);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
-                if e.name().as_ref() == b"scan" {
+                if local_name(e.name().as_ref()) == b"scan" {
                     list.scans.push(parse_scan(reader, &e)?);
                 } else {
-                    skip_element(reader, e.name().as_ref())?;
-                }
-            }
-            Event::Empty(e) if e.name().as_ref() == b"scan" => list.scans.push(Scan {
-                instrument_configuration_ref: get_attr(&e, b"instrumentConfigurationRef")
-                    .or_else(|| get_attr(&e, b"instrumentRef")),
-                external_spectrum_id: get_attr(&e, b"externalSpectrumID"),
-                source_file_ref: get_attr(&e, b"sourceFileRef"),
-                spectrum_ref: get_attr(&e, b"spectrumRef"),
-                ..Default::default()
-            }),
-            Event::End(e) if e.name().as_ref() == b"scanList" => break,
+                    capture_element(reader, &e, &mut list.extensions)?;
+                }
+            }
+            Event::Empty(e) if local_name(e.name().as_ref()) == b"scan" => {
+                list.scans.push(scan_from_start(&e))
+            }
+            Event::Empty(e) => capture_empty(&e, &mut list.extensions),
+            Event::End(e) if local_name(e.name().as_ref()) == b"scanList" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -1680,31 +1943,42 @@ fn parse_scan_list<R: BufRead>(
     Ok(list)
 }
 
-/// <scan>
-fn parse_scan<R: BufRead>(reader: &mut Reader<R>, start: &BytesStart) -> Result<Scan, String> {
-    let mut scan = Scan {
+/// <scan> attributes
+fn scan_from_start(start: &BytesStart) -> Scan {
+    Scan {
         instrument_configuration_ref: get_attr(start, b"instrumentConfigurationRef")
             .or_else(|| get_attr(start, b"instrumentRef")),
         external_spectrum_id: get_attr(start, b"externalSpectrumID"),
         source_file_ref: get_attr(start, b"sourceFileRef"),
         spectrum_ref: get_attr(start, b"spectrumRef"),
+        extensions: extensions_of(
+            start,
+            &[
+                b"instrumentConfigurationRef",
+                b"instrumentRef",
+                b"externalSpectrumID",
+                b"sourceFileRef",
+                b"spectrumRef",
+            ],
+        ),
         ..Default::default()
-    };
+    }
+}
+
+/// <scan>
+fn parse_scan<R: BufRead>(reader: &mut Reader<R>, start: &BytesStart) -> Result<Scan, Cause> {
+    let mut scan = scan_from_start(start);
 
     let mut buf = Vec::with_capacity(1024);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => {
-                push_params_empty(
-                    &e,
-                    &mut scan.referenceable_param_group_refs,
-                    &mut scan.cv_params,
-                    &mut scan.user_params,
-                );
-            }
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => push_params_or_capture_empty(
+                &e,
+                &mut scan.referenceable_param_group_refs,
+                &mut scan.cv_params,
+                &mut scan.user_params,
+                &mut scan.extensions,
+            ),
             Event::Start(e) => {
                 if !push_params_start(
                     reader,
@@ -1713,16 +1987,20 @@ fn parse_scan<R: BufRead>(reader: &mut Reader<R>, start: &BytesStart) -> Result<
                     &mut scan.cv_params,
                     &mut scan.user_params,
                 )? {
-                    match e.name().as_ref() {
+                    match local_name(e.name().as_ref()) {
                         b"scanWindowList" | b"selectionWindowList" => {
                             scan.scan_window_list = Some(parse_scan_window_list(reader, &e)?)
                         }
-                        _ => skip_element(reader, e.name().as_ref())?,
+                        _ => capture_element(reader, &e, &mut scan.extensions)?,
                     }
                 }
             }
-            Event::End(e) if e.name().as_ref() == b"scan" => break,
-            Event::Eof => return Err("unexpected EOF while parsing <scan>".to_string()),
+            Event::End(e) if local_name(e.name().as_ref()) == b"scan" => break,
+            Event::Eof => {
+                return Err(Cause::Xml(
+                    "unexpected EOF while parsing <scan>".to_string(),
+                ));
+            }
             _ => {}
         }
         buf.clear();
@@ -1735,32 +2013,35 @@ fn parse_scan<R: BufRead>(reader: &mut Reader<R>, start: &BytesStart) -> Result<
 fn parse_scan_window_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<ScanWindowList, String> {
+) -> Result<ScanWindowList, Cause> {
     let mut list = ScanWindowList::default();
     list.count = get_attr_usize(start, b"count");
+    list.extensions = extensions_of(start, LIST_ATTRIBUTES);
 
     let end_tag = start.name();
     let mut buf = Vec::with_capacity(512);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
                 let qname = e.name();
                 let n = local_name(qname.as_ref());
                 if n == b"scanWindow" || n == b"selectionWindow" {
                     list.scan_windows.push(parse_scan_window(reader, &e)?);
                 } else {
-                    skip_element(reader, n)?;
+                    capture_element(reader, &e, &mut list.extensions)?;
                 }
             }
             Event::Empty(e) => {
                 let qname = e.name();
                 let n = local_name(qname.as_ref());
                 if n == b"scanWindow" || n == b"selectionWindow" {
-                    list.scan_windows.push(ScanWindow::default());
+                    list.scan_windows.push(ScanWindow {
+                        extensions: extensions_of(&e, &[]),
+                        ..Default::default()
+                    });
+                } else {
+                    capture_empty(&e, &mut list.extensions);
                 }
             }
             Event::End(e) if e.name() == end_tag => break,
@@ -1776,22 +2057,22 @@ fn parse_scan_window_list<R: BufRead>(
 /// <scanWindow> / <selectionWindow>
 fn parse_scan_window<R: BufRead>(
     reader: &mut Reader<R>,
-    _start: &BytesStart,
-) -> Result<ScanWindow, String> {
-    let mut w = ScanWindow::default();
+    start: &BytesStart,
+) -> Result<ScanWindow, Cause> {
+    let mut w = ScanWindow {
+        extensions: extensions_of(start, &[]),
+        ..Default::default()
+    };
     let mut buf = Vec::with_capacity(512);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => match e.name().as_ref() {
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => match local_name(e.name().as_ref()) {
                 b"cvParam" => w.cv_params.push(parse_cv_param(&e)),
                 b"userParam" => w.user_params.push(parse_user_param(&e)),
-                _ => {}
+                _ => capture_empty(&e, &mut w.extensions),
             },
-            Event::Start(e) => match e.name().as_ref() {
+            Event::Start(e) => match local_name(e.name().as_ref()) {
                 b"cvParam" => {
                     w.cv_params.push(parse_cv_param(&e));
                     skip_element(reader, b"cvParam")?;
@@ -1800,7 +2081,7 @@ fn parse_scan_window<R: BufRead>(
                     w.user_params.push(parse_user_param(&e));
                     skip_element(reader, b"userParam")?;
                 }
-                _ => skip_element(reader, e.name().as_ref())?,
+                _ => capture_element(reader, &e, &mut w.extensions)?,
             },
             Event::End(e)
                 if {
@@ -1824,32 +2105,26 @@ fn parse_scan_window<R: BufRead>(
 fn parse_precursor_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<PrecursorList, String> {
+) -> Result<PrecursorList, Cause> {
     let mut list = PrecursorList::default();
     list.count = get_attr_usize(start, b"count");
+    list.extensions = extensions_of(start, LIST_ATTRIBUTES);
 
     let mut buf = Vec::with_capacity(1024);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
-                if e.name().as_ref() == b"precursor" {
+                if local_name(e.name().as_ref()) == b"precursor" {
                     list.precursors.push(parse_precursor(reader, &e)?);
                 } else {
-                    skip_element(reader, e.name().as_ref())?;
+                    capture_element(reader, &e, &mut list.extensions)?;
                 }
             }
-            Event::Empty(e) if e.name().as_ref() == b"precursor" => {
-                list.precursors.push(Precursor {
-                    spectrum_ref: get_attr(&e, b"spectrumRef"),
-                    source_file_ref: get_attr(&e, b"sourceFileRef"),
-                    external_spectrum_id: get_attr(&e, b"externalSpectrumID"),
-                    ..Default::default()
-                })
+            Event::Empty(e) if local_name(e.name().as_ref()) == b"precursor" => {
+                list.precursors.push(precursor_from_start(&e))
             }
-            Event::End(e) if e.name().as_ref() == b"precursorList" => break,
+            Event::Empty(e) => capture_empty(&e, &mut list.extensions),
+            Event::End(e) if local_name(e.name().as_ref()) == b"precursorList" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -1861,35 +2136,31 @@ fn parse_precursor_list<R: BufRead>(
 /// <ionSelection>
 fn parse_ion_selection<R: BufRead>(
     reader: &mut Reader<R>,
-    _start: &BytesStart,
-) -> Result<SelectedIon, String> {
-    let mut s = SelectedIon::default();
+    start: &BytesStart,
+) -> Result<SelectedIon, Cause> {
+    let mut s = SelectedIon {
+        extensions: extensions_of(start, &[]),
+        ..Default::default()
+    };
     let mut buf = Vec::with_capacity(512);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => {
-                push_params_empty(
-                    &e,
-                    &mut s.referenceable_param_group_refs,
-                    &mut s.cv_params,
-                    &mut s.user_params,
-                );
-            }
-            Event::Start(e) => {
-                if !push_params_start(
-                    reader,
-                    &e,
-                    &mut s.referenceable_param_group_refs,
-                    &mut s.cv_params,
-                    &mut s.user_params,
-                )? {
-                    skip_element(reader, e.name().as_ref())?;
-                }
-            }
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => push_params_or_capture_empty(
+                &e,
+                &mut s.referenceable_param_group_refs,
+                &mut s.cv_params,
+                &mut s.user_params,
+                &mut s.extensions,
+            ),
+            Event::Start(e) => push_params_or_capture_start(
+                reader,
+                &e,
+                &mut s.referenceable_param_group_refs,
+                &mut s.cv_params,
+                &mut s.user_params,
+                &mut s.extensions,
+            )?,
             Event::End(e) if local_name(e.name().as_ref()) == b"ionSelection" => break,
             Event::Eof => break,
             _ => {}
@@ -1900,24 +2171,32 @@ fn parse_ion_selection<R: BufRead>(
     Ok(s)
 }
 
-/// <precursor>
-fn parse_precursor<R: BufRead>(
-    reader: &mut Reader<R>,
-    start: &BytesStart,
-) -> Result<Precursor, String> {
-    let mut p = Precursor {
+/// `spectrumRef`, `sourceFileRef` and `externalSpectrumID` of <precursor>
+/// and <product>.
+const SPECTRUM_REF_ATTRIBUTES: &[&[u8]] =
+    &[b"spectrumRef", b"sourceFileRef", b"externalSpectrumID"];
+
+/// <precursor> attributes
+fn precursor_from_start(start: &BytesStart) -> Precursor {
+    Precursor {
         spectrum_ref: get_attr(start, b"spectrumRef"),
         source_file_ref: get_attr(start, b"sourceFileRef"),
         external_spectrum_id: get_attr(start, b"externalSpectrumID"),
+        extensions: extensions_of(start, SPECTRUM_REF_ATTRIBUTES),
         ..Default::default()
-    };
+    }
+}
+
+/// <precursor>
+fn parse_precursor<R: BufRead>(
+    reader: &mut Reader<R>,
+    start: &BytesStart,
+) -> Result<Precursor, Cause> {
+    let mut p = precursor_from_start(start);
 
     let mut buf = Vec::with_capacity(1024);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
                 let qname = e.name();
                 let raw = qname.as_ref();
@@ -1947,7 +2226,7 @@ fn parse_precursor<R: BufRead>(
                             skip_element(reader, raw)?;
                         }
                     }
-                    _ => skip_element(reader, raw)?,
+                    _ => capture_element(reader, &e, &mut p.extensions)?,
                 }
             }
             Event::Empty(e) => {
@@ -1956,11 +2235,25 @@ fn parse_precursor<R: BufRead>(
                 match local_name(raw) {
                     b"ionSelection" => {
                         let list = p.selected_ion_list.get_or_insert_with(Default::default);
-                        list.selected_ions.push(SelectedIon::default());
+                        list.selected_ions.push(SelectedIon {
+                            extensions: extensions_of(&e, &[]),
+                            ..Default::default()
+                        });
                         list.count = Some(list.selected_ions.len());
                     }
-                    b"isolationWindow" => p.isolation_window = Some(IsolationWindow::default()),
-                    b"selectedIonList" => p.selected_ion_list = Some(SelectedIonList::default()),
+                    b"isolationWindow" => {
+                        p.isolation_window = Some(IsolationWindow {
+                            extensions: extensions_of(&e, &[]),
+                            ..Default::default()
+                        })
+                    }
+                    b"selectedIonList" => {
+                        p.selected_ion_list = Some(SelectedIonList {
+                            count: get_attr_usize(&e, b"count"),
+                            extensions: extensions_of(&e, LIST_ATTRIBUTES),
+                            ..Default::default()
+                        })
+                    }
                     b"referenceableParamGroupRef" | b"cvParam" | b"userParam" => {
                         let w = p.isolation_window.get_or_insert_with(Default::default);
                         push_params_empty(
@@ -1970,7 +2263,7 @@ fn parse_precursor<R: BufRead>(
                             &mut w.user_params,
                         );
                     }
-                    _ => {}
+                    _ => capture_empty(&e, &mut p.extensions),
                 }
             }
             Event::End(e) if local_name(e.name().as_ref()) == b"precursor" => break,
@@ -1986,35 +2279,31 @@ fn parse_precursor<R: BufRead>(
 /// <isolationWindow>
 fn parse_isolation_window<R: BufRead>(
     reader: &mut Reader<R>,
-    _start: &BytesStart,
-) -> Result<IsolationWindow, String> {
-    let mut w = IsolationWindow::default();
+    start: &BytesStart,
+) -> Result<IsolationWindow, Cause> {
+    let mut w = IsolationWindow {
+        extensions: extensions_of(start, &[]),
+        ..Default::default()
+    };
     let mut buf = Vec::with_capacity(512);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => {
-                push_params_empty(
-                    &e,
-                    &mut w.referenceable_param_group_refs,
-                    &mut w.cv_params,
-                    &mut w.user_params,
-                );
-            }
-            Event::Start(e) => {
-                if !push_params_start(
-                    reader,
-                    &e,
-                    &mut w.referenceable_param_group_refs,
-                    &mut w.cv_params,
-                    &mut w.user_params,
-                )? {
-                    skip_element(reader, e.name().as_ref())?;
-                }
-            }
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => push_params_or_capture_empty(
+                &e,
+                &mut w.referenceable_param_group_refs,
+                &mut w.cv_params,
+                &mut w.user_params,
+                &mut w.extensions,
+            ),
+            Event::Start(e) => push_params_or_capture_start(
+                reader,
+                &e,
+                &mut w.referenceable_param_group_refs,
+                &mut w.cv_params,
+                &mut w.user_params,
+                &mut w.extensions,
+            )?,
             Event::End(e) if local_name(e.name().as_ref()) == b"isolationWindow" => break,
             Event::Eof => break,
             _ => {}
@@ -2029,27 +2318,26 @@ fn parse_isolation_window<R: BufRead>(
 fn parse_selected_ion_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<SelectedIonList, String> {
+) -> Result<SelectedIonList, Cause> {
     let mut list = SelectedIonList::default();
     list.count = get_attr_usize(start, b"count");
+    list.extensions = extensions_of(start, LIST_ATTRIBUTES);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
-                if e.name().as_ref() == b"selectedIon" {
+                if local_name(e.name().as_ref()) == b"selectedIon" {
                     list.selected_ions.push(parse_selected_ion(reader, &e)?);
                 } else {
-                    skip_element(reader, e.name().as_ref())?;
+                    capture_element(reader, &e, &mut list.extensions)?;
                 }
             }
-            Event::Empty(e) if e.name().as_ref() == b"selectedIon" => {
+            Event::Empty(e) if local_name(e.name().as_ref()) == b"selectedIon" => {
                 list.selected_ions.push(SelectedIon::default())
             }
-            Event::End(e) if e.name().as_ref() == b"selectedIonList" => break,
+            Event::Empty(e) => capture_empty(&e, &mut list.extensions),
+            Event::End(e) if local_name(e.name().as_ref()) == b"selectedIonList" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -2062,36 +2350,32 @@ fn parse_selected_ion_list<R: BufRead>(
 /// <selectedIon>
 fn parse_selected_ion<R: BufRead>(
     reader: &mut Reader<R>,
-    _start: &BytesStart,
-) -> Result<SelectedIon, String> {
-    let mut s = SelectedIon::default();
+    start: &BytesStart,
+) -> Result<SelectedIon, Cause> {
+    let mut s = SelectedIon {
+        extensions: extensions_of(start, &[]),
+        ..Default::default()
+    };
     let mut buf = Vec::with_capacity(512);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => {
-                push_params_empty(
-                    &e,
-                    &mut s.referenceable_param_group_refs,
-                    &mut s.cv_params,
-                    &mut s.user_params,
-                );
-            }
-            Event::Start(e) => {
-                if !push_params_start(
-                    reader,
-                    &e,
-                    &mut s.referenceable_param_group_refs,
-                    &mut s.cv_params,
-                    &mut s.user_params,
-                )? {
-                    skip_element(reader, e.name().as_ref())?;
-                }
-            }
-            Event::End(e) if e.name().as_ref() == b"selectedIon" => break,
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => push_params_or_capture_empty(
+                &e,
+                &mut s.referenceable_param_group_refs,
+                &mut s.cv_params,
+                &mut s.user_params,
+                &mut s.extensions,
+            ),
+            Event::Start(e) => push_params_or_capture_start(
+                reader,
+                &e,
+                &mut s.referenceable_param_group_refs,
+                &mut s.cv_params,
+                &mut s.user_params,
+                &mut s.extensions,
+            )?,
+            Event::End(e) if local_name(e.name().as_ref()) == b"selectedIon" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -2104,35 +2388,31 @@ fn parse_selected_ion<R: BufRead>(
 /// <activation>
 fn parse_activation<R: BufRead>(
     reader: &mut Reader<R>,
-    _start: &BytesStart,
-) -> Result<Activation, String> {
-    let mut a = Activation::default();
+    start: &BytesStart,
+) -> Result<Activation, Cause> {
+    let mut a = Activation {
+        extensions: extensions_of(start, &[]),
+        ..Default::default()
+    };
     let mut buf = Vec::with_capacity(512);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => {
-                push_params_empty(
-                    &e,
-                    &mut a.referenceable_param_group_refs,
-                    &mut a.cv_params,
-                    &mut a.user_params,
-                );
-            }
-            Event::Start(e) => {
-                if !push_params_start(
-                    reader,
-                    &e,
-                    &mut a.referenceable_param_group_refs,
-                    &mut a.cv_params,
-                    &mut a.user_params,
-                )? {
-                    skip_element(reader, e.name().as_ref())?;
-                }
-            }
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => push_params_or_capture_empty(
+                &e,
+                &mut a.referenceable_param_group_refs,
+                &mut a.cv_params,
+                &mut a.user_params,
+                &mut a.extensions,
+            ),
+            Event::Start(e) => push_params_or_capture_start(
+                reader,
+                &e,
+                &mut a.referenceable_param_group_refs,
+                &mut a.cv_params,
+                &mut a.user_params,
+                &mut a.extensions,
+            )?,
             Event::End(e) if local_name(e.name().as_ref()) == b"activation" => break,
             Event::Eof => break,
             _ => {}
@@ -2147,30 +2427,26 @@ fn parse_activation<R: BufRead>(
 fn parse_product_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<ProductList, String> {
+) -> Result<ProductList, Cause> {
     let mut list = ProductList::default();
     list.count = get_attr_usize(start, b"count");
+    list.extensions = extensions_of(start, LIST_ATTRIBUTES);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
-                if e.name().as_ref() == b"product" {
+                if local_name(e.name().as_ref()) == b"product" {
                     list.products.push(parse_product(reader, &e)?);
                 } else {
-                    skip_element(reader, e.name().as_ref())?;
+                    capture_element(reader, &e, &mut list.extensions)?;
                 }
             }
-            Event::Empty(e) if e.name().as_ref() == b"product" => list.products.push(Product {
-                spectrum_ref: get_attr(&e, b"spectrumRef"),
-                source_file_ref: get_attr(&e, b"sourceFileRef"),
-                external_spectrum_id: get_attr(&e, b"externalSpectrumID"),
-                ..Default::default()
-            }),
-            Event::End(e) if e.name().as_ref() == b"productList" => break,
+            Event::Empty(e) if local_name(e.name().as_ref()) == b"product" => {
+                list.products.push(product_from_start(&e))
+            }
+            Event::Empty(e) => capture_empty(&e, &mut list.extensions),
+            Event::End(e) if local_name(e.name().as_ref()) == b"productList" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -2180,31 +2456,32 @@ fn parse_product_list<R: BufRead>(
     Ok(list)
 }
 
-/// <product>
-fn parse_product<R: BufRead>(
-    reader: &mut Reader<R>,
-    start: &BytesStart,
-) -> Result<Product, String> {
-    let mut p = Product {
+/// <product> attributes
+fn product_from_start(start: &BytesStart) -> Product {
+    Product {
         spectrum_ref: get_attr(start, b"spectrumRef"),
         source_file_ref: get_attr(start, b"sourceFileRef"),
         external_spectrum_id: get_attr(start, b"externalSpectrumID"),
+        extensions: extensions_of(start, SPECTRUM_REF_ATTRIBUTES),
         ..Default::default()
-    };
+    }
+}
+
+/// <product>
+fn parse_product<R: BufRead>(reader: &mut Reader<R>, start: &BytesStart) -> Result<Product, Cause> {
+    let mut p = product_from_start(start);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
                 if local_name(e.name().as_ref()) == b"isolationWindow" {
                     p.isolation_window = Some(parse_isolation_window(reader, &e)?);
                 } else {
-                    skip_element(reader, e.name().as_ref())?;
+                    capture_element(reader, &e, &mut p.extensions)?;
                 }
             }
+            Event::Empty(e) => capture_empty(&e, &mut p.extensions),
             Event::End(e) if local_name(e.name().as_ref()) == b"product" => break,
             Event::Eof => break,
             _ => {}
@@ -2219,33 +2496,30 @@ fn parse_product<R: BufRead>(
 fn parse_binary_data_array_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<BinaryDataArrayList, String> {
+    binaries: &mut Binaries,
+) -> Result<BinaryDataArrayList, Cause> {
     let mut list = BinaryDataArrayList::default();
     list.count = get_attr_usize(start, b"count");
+    list.extensions = extensions_of(start, LIST_ATTRIBUTES);
 
     let mut buf = Vec::with_capacity(512);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
-                if e.name().as_ref() == b"binaryDataArray" {
+                if local_name(e.name().as_ref()) == b"binaryDataArray" {
                     list.binary_data_arrays
-                        .push(parse_binary_data_array(reader, &e)?);
+                        .push(parse_binary_data_array(reader, &e, binaries)?);
                 } else {
-                    skip_element(reader, e.name().as_ref())?;
+                    capture_element(reader, &e, &mut list.extensions)?;
                 }
             }
-            Event::Empty(e) if e.name().as_ref() == b"binaryDataArray" => {
-                list.binary_data_arrays.push(BinaryDataArray {
-                    array_length: get_attr_usize(&e, b"arrayLength"),
-                    encoded_length: get_attr_usize(&e, b"encodedLength"),
-                    data_processing_ref: get_attr(&e, b"dataProcessingRef"),
-                    ..Default::default()
-                })
+            Event::Empty(e) if local_name(e.name().as_ref()) == b"binaryDataArray" => {
+                list.binary_data_arrays
+                    .push(binary_data_array_from_start(&e));
+                binaries.end_array();
             }
-            Event::End(e) if e.name().as_ref() == b"binaryDataArrayList" => break,
+            Event::Empty(e) => capture_empty(&e, &mut list.extensions),
+            Event::End(e) if local_name(e.name().as_ref()) == b"binaryDataArrayList" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -2255,34 +2529,39 @@ fn parse_binary_data_array_list<R: BufRead>(
     Ok(list)
 }
 
-/// <binaryDataArray>
-fn parse_binary_data_array<R: BufRead>(
-    reader: &mut Reader<R>,
-    start: &BytesStart,
-) -> Result<BinaryDataArray, String> {
-    let mut a = BinaryDataArray {
+/// <binaryDataArray> attributes
+fn binary_data_array_from_start(start: &BytesStart) -> BinaryDataArray {
+    BinaryDataArray {
         array_length: get_attr_usize(start, b"arrayLength"),
         encoded_length: get_attr_usize(start, b"encodedLength"),
         data_processing_ref: get_attr(start, b"dataProcessingRef"),
+        extensions: extensions_of(
+            start,
+            &[b"arrayLength", b"encodedLength", b"dataProcessingRef"],
+        ),
         ..Default::default()
-    };
+    }
+}
+
+/// <binaryDataArray>
+fn parse_binary_data_array<R: BufRead>(
+    reader: &mut Reader<R>,
+    start: &BytesStart,
+    binaries: &mut Binaries,
+) -> Result<BinaryDataArray, Cause> {
+    let mut a = binary_data_array_from_start(start);
 
-    let mut binary_b64: Option<String> = None;
     let mut buf = Vec::with_capacity(1024);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => {
-                push_params_empty(
-                    &e,
-                    &mut a.referenceable_param_group_refs,
-                    &mut a.cv_params,
-                    &mut a.user_params,
-                );
-            }
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => push_params_or_capture_empty(
+                &e,
+                &mut a.referenceable_param_group_refs,
+                &mut a.cv_params,
+                &mut a.user_params,
+                &mut a.extensions,
+            ),
             Event::Start(e) => {
                 if !push_params_start(
                     reader,
@@ -2291,30 +2570,39 @@ fn parse_binary_data_array<R: BufRead>(
                     &mut a.cv_params,
                     &mut a.user_params,
                 )? {
-                    if e.name().as_ref() == b"binary" {
-                        binary_b64 = Some(read_text_content(reader, b"binary")?);
+                    if local_name(e.name().as_ref()) == b"binary" {
+                        // <binary> is the last child, so every cvParam is known.
+                        let text = read_text_content(reader, b"binary")?;
+                        binaries.push(&mut a, text, reader.buffer_position())?;
                     } else {
-                        skip_element(reader, e.name().as_ref())?;
+                        capture_element(reader, &e, &mut a.extensions)?;
                     }
                 }
             }
-            Event::End(e) if e.name().as_ref() == b"binaryDataArray" => break,
+            Event::End(e) if local_name(e.name().as_ref()) == b"binaryDataArray" => break,
             Event::Eof => break,
             _ => {}
         }
         buf.clear();
     }
 
-    let flags = binary_array_flags(&a);
-    a.numeric_type = Some(flags.numeric_type);
-
-    let Some(b64_raw) = binary_b64.as_deref() else {
-        return Ok(a);
-    };
+    if a.numeric_type.is_none() {
+        a.numeric_type = Some(binary_array_flags(&a)?.numeric_type);
+    }
+    binaries.end_array();
+    Ok(a)
+}
 
+/// base64, then zlib or Numpress, then the numeric type in `flags`; at most
+/// `array_length` values are kept.
+pub(crate) fn decode_payload(
+    b64_raw: &str,
+    flags: BinaryArrayFlags,
+    array_length: Option<usize>,
+) -> Result<Option<BinaryData>, Cause> {
     let cleaned: String = b64_raw.chars().filter(|c| !c.is_whitespace()).collect();
     if cleaned.is_empty() {
-        return Ok(a);
+        return Ok(None);
     }
 
     let mut bytes = Vec::new();
@@ -2322,11 +2610,22 @@ fn parse_binary_data_array<R: BufRead>(
 
     STANDARD
         .decode_vec(cleaned.as_bytes(), &mut bytes)
-        .map_err(|e| format!("base64 decode failed: {e}"))?;
+        .map_err(|e| Cause::Binary(format!("base64 decode failed: {e}")))?;
 
     if flags.is_zlib {
-        bytes =
-            decompress_to_vec_zlib(&bytes).map_err(|e| format!("zlib decompress failed: {e:?}"))?;
+        bytes = decompress_to_vec_zlib(&bytes)
+            .map_err(|e| Cause::Binary(format!("zlib decompress failed: {e:?}")))?;
+    }
+
+    if let Some(codec) = flags.numpress {
+        let mut values = codec.decode(&bytes).map_err(Cause::Binary)?;
+        if let Some(n) = array_length {
+            values.truncate(n);
+        }
+        if values.is_empty() {
+            return Ok(None);
+        }
+        return Ok(Some(numpress_values_to_binary(values, flags.numeric_type)));
     }
 
     match flags.numeric_type {
@@ -2334,100 +2633,116 @@ fn parse_binary_data_array<R: BufRead>(
             let elem = 8usize;
             let usable = bytes.len() - (bytes.len() % elem);
             let avail = usable / elem;
-            let want = a.array_length.unwrap_or(avail);
+            let want = array_length.unwrap_or(avail);
             let n = want.min(avail);
 
             if n == 0 {
-                return Ok(a);
+                return Ok(None);
             }
 
             let mut out = Vec::with_capacity(n);
             for c in bytes[..n * elem].chunks_exact(elem) {
                 out.push(f64::from_le_bytes(c.try_into().unwrap()));
             }
-            a.binary = Some(BinaryData::F64(out));
+            Ok(Some(BinaryData::F64(out)))
         }
 
         NumericType::Float32 => {
             let elem = 4usize;
             let usable = bytes.len() - (bytes.len() % elem);
             let avail = usable / elem;
-            let want = a.array_length.unwrap_or(avail);
+            let want = array_length.unwrap_or(avail);
             let n = want.min(avail);
 
             if n == 0 {
-                return Ok(a);
+                return Ok(None);
             }
 
             let mut out = Vec::with_capacity(n);
             for c in bytes[..n * elem].chunks_exact(elem) {
                 out.push(f32::from_le_bytes(c.try_into().unwrap()));
             }
-            a.binary = Some(BinaryData::F32(out));
+            Ok(Some(BinaryData::F32(out)))
         }
 
         NumericType::Int64 => {
             let elem = 8usize;
             let usable = bytes.len() - (bytes.len() % elem);
             let avail = usable / elem;
-            let want = a.array_length.unwrap_or(avail);
+            let want = array_length.unwrap_or(avail);
             let n = want.min(avail);
 
             if n == 0 {
-                return Ok(a);
+                return Ok(None);
             }
 
             let mut out = Vec::with_capacity(n);
             for c in bytes[..n * elem].chunks_exact(elem) {
                 out.push(i64::from_le_bytes(c.try_into().unwrap()));
             }
-            a.binary = Some(BinaryData::I64(out));
+            Ok(Some(BinaryData::I64(out)))
         }
 
         NumericType::Int32 => {
             let elem = 4usize;
             let usable = bytes.len() - (bytes.len() % elem);
             let avail = usable / elem;
-            let want = a.array_length.unwrap_or(avail);
+            let want = array_length.unwrap_or(avail);
             let n = want.min(avail);
 
             if n == 0 {
-                return Ok(a);
+                return Ok(None);
             }
 
             let mut out = Vec::with_capacity(n);
             for c in bytes[..n * elem].chunks_exact(elem) {
                 out.push(i32::from_le_bytes(c.try_into().unwrap()));
             }
-            a.binary = Some(BinaryData::I32(out));
+            Ok(Some(BinaryData::I32(out)))
         }
 
         NumericType::Int16 => {
             let elem = 2usize;
             let usable = bytes.len() - (bytes.len() % elem);
             let avail = usable / elem;
-            let want = a.array_length.unwrap_or(avail);
+            let want = array_length.unwrap_or(avail);
             let n = want.min(avail);
 
             if n == 0 {
-                return Ok(a);
+                return Ok(None);
             }
 
             let mut out = Vec::with_capacity(n);
             for c in bytes[..n * elem].chunks_exact(elem) {
                 out.push(i16::from_le_bytes(c.try_into().unwrap()));
             }
-            a.binary = Some(BinaryData::I16(out));
+            Ok(Some(BinaryData::I16(out)))
         }
     }
-
-    Ok(a)
 }
 
 #[derive(Debug, Clone, Copy)]
-struct BinaryArrayFlags {
+pub(crate) struct BinaryArrayFlags {
     is_zlib: bool,
-    numeric_type: NumericType,
+    numpress: Option<Numpress>,
+    pub(crate) numeric_type: NumericType,
+}
+
+/// Numpress always decodes to doubles; narrow them to the declared type.
+fn numpress_values_to_binary(values: Vec<f64>, numeric_type: NumericType) -> BinaryData {
+    match numeric_type {
+        NumericType::Float64 => BinaryData::F64(values),
+        NumericType::Float32 => BinaryData::F32(values.into_iter().map(|x| x as f32).collect()),
+        NumericType::Int64 => {
+            BinaryData::I64(values.into_iter().map(|x| x.round() as i64).collect())
+        }
+        NumericType::Int32 => {
+            BinaryData::I32(values.into_iter().map(|x| x.round() as i32).collect())
+        }
+        NumericType::Int16 => {
+            BinaryData::I16(values.into_iter().map(|x| x.round() as i16).collect())
+        }
+    }
 }
 
 fn has_acc(cv_params: &[CvParam], acc: &str) -> bool {
@@ -2436,35 +2751,48 @@ fn has_acc(cv_params: &[CvParam], acc: &str) -> bool {
         .any(|p| p.accession.as_deref() == Some(acc))
 }
 
-fn binary_array_flags(binary_data_array: &BinaryDataArray) -> BinaryArrayFlags {
+const NUMERIC_TYPE_CVS: [(&str, NumericType); 5] = [
+    ("MS:1000523", NumericType::Float64), // 64-bit float
+    ("MS:1000521", NumericType::Float32), // 32-bit float
+    ("MS:1000522", NumericType::Int64),   // 64-bit integer
+    ("MS:1000519", NumericType::Int32),   // 32-bit integer
+    ("MS:1000518", NumericType::Int16),   // 16-bit integer
+];
+
+/// Numeric type from the binary data type cvParam; `Float64` when there is
+/// none, an error when several different ones are given.
+fn binary_numeric_type(cv_params: &[CvParam]) -> Result<NumericType, Cause> {
+    let mut found: Option<(&str, NumericType)> = None;
+    for (acc, ty) in NUMERIC_TYPE_CVS {
+        if !has_acc(cv_params, acc) {
+            continue;
+        }
+        if let Some((prev, _)) = found {
+            return Err(format!(
+                "binaryDataArray has conflicting binary data types: {prev} and {acc}"
+            )
+            .into());
+        }
+        found = Some((acc, ty));
+    }
+    Ok(found.map_or(NumericType::Float64, |(_, ty)| ty))
+}
+
+pub(crate) fn binary_array_flags(
+    binary_data_array: &BinaryDataArray,
+) -> Result<BinaryArrayFlags, Cause> {
     let cv = &binary_data_array.cv_params;
 
-    let is_zlib = has_acc(cv, "MS:1000574");
-
-    let has_f64 = has_acc(cv, "MS:1000523"); // 64-bit float
-    let has_f32 = has_acc(cv, "MS:1000521"); // 32-bit float
-    let has_i64 = has_acc(cv, "MS:1000522"); // 64-bit integer
-
-    let numeric_type = if has_i64 && !has_f64 && !has_f32 {
-        NumericType::Int64
-    } else if has_f64 && !has_f32 && !has_i64 {
-        NumericType::Float64
-    } else if has_f32 && !has_f64 && !has_i64 {
-        NumericType::Float32
-    } else if has_i64 {
-        NumericType::Int64
-    } else if has_f64 {
-        NumericType::Float64
-    } else if has_f32 {
-        NumericType::Float32
-    } else {
-        NumericType::Float64
-    };
+    let numpress = cv
+        .iter()
+        .find_map(|p| p.accession.as_deref().and_then(Numpress::from_accession));
+    let is_zlib = has_acc(cv, "MS:1000574") || numpress.is_some_and(|(_, zlib)| zlib);
 
-    BinaryArrayFlags {
+    Ok(BinaryArrayFlags {
         is_zlib,
-        numeric_type,
-    }
+        numpress: numpress.map(|(codec, _)| codec),
+        numeric_type: binary_numeric_type(cv)?,
+    })
 }
 
 fn decode_f64_into(bytes: &[u8], little: bool, want: usize, out: &mut Vec<f64>) {
@@ -2503,84 +2831,118 @@ fn decode_f32_into(bytes: &[u8], little: bool, want: usize, out: &mut Vec<f32>)
     }
 }
 
+/// <chromatogramList> attributes
+pub(crate) fn chromatogram_list_from_start(start: &BytesStart) -> ChromatogramList {
+    ChromatogramList {
+        count: get_attr_usize(start, b"count"),
+        default_data_processing_ref: get_attr(start, b"defaultDataProcessingRef"),
+        extensions: extensions_of(start, &[b"count", b"defaultDataProcessingRef"]),
+        ..Default::default()
+    }
+}
+
 /// <chromatogramList>
 fn parse_chromatogram_list<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<ChromatogramList, String> {
-    let mut list = ChromatogramList::default();
-    list.count = get_attr_usize(start, b"count");
-    list.default_data_processing_ref = get_attr(start, b"defaultDataProcessingRef");
+    threads: usize,
+) -> Result<ChromatogramList, Cause> {
+    let mut list = chromatogram_list_from_start(start);
+    let mut binaries = Binaries::new(threads);
+
+    let read = read_chromatograms(reader, &mut list, &mut binaries);
+    binaries.flush(&mut list.chromatograms)?;
+    read?;
+    Ok(list)
+}
 
+fn read_chromatograms<R: BufRead>(
+    reader: &mut Reader<R>,
+    list: &mut ChromatogramList,
+    binaries: &mut Binaries,
+) -> Result<(), Cause> {
     let mut buf = Vec::with_capacity(1024);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
+        match reader.read_event_into(&mut buf)? {
             Event::Start(e) => {
-                if e.name().as_ref() == b"chromatogram" {
-                    list.chromatograms.push(parse_chromatogram(reader, &e)?);
+                if local_name(e.name().as_ref()) == b"chromatogram" {
+                    binaries.start_item(list.chromatograms.len());
+                    list.chromatograms
+                        .push(parse_chromatogram_with(reader, &e, binaries)?);
+                    binaries.flush_if_full(&mut list.chromatograms)?;
                 } else {
-                    skip_element(reader, e.name().as_ref())?;
+                    capture_element(reader, &e, &mut list.extensions)?;
                 }
             }
-            Event::Empty(e) if e.name().as_ref() == b"chromatogram" => {
-                list.chromatograms.push(Chromatogram {
-                    id: get_attr(&e, b"id").unwrap_or_default(),
-                    native_id: get_attr(&e, b"nativeID"),
-                    index: get_attr_u32(&e, b"index"),
-                    default_array_length: get_attr_usize(&e, b"defaultArrayLength"),
-                    data_processing_ref: get_attr(&e, b"dataProcessingRef"),
-                    ..Default::default()
-                })
+            Event::Empty(e) if local_name(e.name().as_ref()) == b"chromatogram" => {
+                list.chromatograms.push(chromatogram_from_start(&e))
             }
-            Event::End(e) if e.name().as_ref() == b"chromatogramList" => break,
+            Event::Empty(e) => capture_empty(&e, &mut list.extensions),
+            Event::End(e) if local_name(e.name().as_ref()) == b"chromatogramList" => break,
             Event::Eof => break,
             _ => {}
         }
         buf.clear();
     }
-
-    Ok(list)
+    Ok(())
 }
 
-/// <chromatogram>
-fn parse_chromatogram<R: BufRead>(
-    reader: &mut Reader<R>,
-    start: &BytesStart,
-) -> Result<Chromatogram, String> {
-    let mut c = Chromatogram {
+/// <chromatogram> attributes
+pub(crate) fn chromatogram_from_start(start: &BytesStart) -> Chromatogram {
+    Chromatogram {
         id: get_attr(start, b"id").unwrap_or_default(),
         native_id: get_attr(start, b"nativeID"),
         index: get_attr_u32(start, b"index"),
         default_array_length: get_attr_usize(start, b"defaultArrayLength"),
         data_processing_ref: get_attr(start, b"dataProcessingRef"),
+        extensions: extensions_of(
+            start,
+            &[
+                b"id",
+                b"nativeID",
+                b"index",
+                b"defaultArrayLength",
+                b"dataProcessingRef",
+            ],
+        ),
         ..Default::default()
-    };
+    }
+}
+
+/// <chromatogram>
+pub(crate) fn parse_chromatogram<R: BufRead>(
+    reader: &mut Reader<R>,
+    start: &BytesStart,
+) -> Result<Chromatogram, Cause> {
+    parse_chromatogram_with(reader, start, &mut Binaries::Inline)
+}
+
+fn parse_chromatogram_with<R: BufRead>(
+    reader: &mut Reader<R>,
+    start: &BytesStart,
+    binaries: &mut Binaries,
+) -> Result<Chromatogram, Cause> {
+    let mut c = chromatogram_from_start(start);
 
     let mut buf = Vec::with_capacity(1024);
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Empty(e) => match e.name().as_ref() {
+        match reader.read_event_into(&mut buf)? {
+            Event::Empty(e) => match local_name(e.name().as_ref()) {
                 b"referenceableParamGroupRef" => c
                     .referenceable_param_group_refs
                     .push(parse_referenceable_param_group_ref(&e)),
                 b"cvParam" => c.cv_params.push(parse_cv_param(&e)),
                 b"userParam" => c.user_params.push(parse_user_param(&e)),
                 b"binaryDataArray" => {
-                    let a = parse_binary_data_array(reader, &e)?;
+                    let a = parse_binary_data_array(reader, &e, binaries)?;
                     c.binary_data_array_list
                         .get_or_insert_with(Default::default)
                         .binary_data_arrays
                         .push(a);
                 }
-                _ => {}
+                _ => capture_empty(&e, &mut c.extensions),
             },
-            Event::Start(e) => match e.name().as_ref() {
+            Event::Start(e) => match local_name(e.name().as_ref()) {
                 b"referenceableParamGroupRef" => {
                     c.referenceable_param_group_refs
                         .push(parse_referenceable_param_group_ref(&e));
@@ -2597,18 +2959,19 @@ fn parse_chromatogram<R: BufRead>(
                 b"precursor" => c.precursor = Some(parse_precursor(reader, &e)?),
                 b"product" => c.product = Some(parse_product(reader, &e)?),
                 b"binaryDataArrayList" => {
-                    c.binary_data_array_list = Some(parse_binary_data_array_list(reader, &e)?)
+                    c.binary_data_array_list =
+                        Some(parse_binary_data_array_list(reader, &e, binaries)?)
                 }
                 b"binaryDataArray" => {
-                    let a = parse_binary_data_array(reader, &e)?;
+                    let a = parse_binary_data_array(reader, &e, binaries)?;
                     c.binary_data_array_list
                         .get_or_insert_with(Default::default)
                         .binary_data_arrays
                         .push(a);
                 }
-                _ => skip_element(reader, e.name().as_ref())?,
+                _ => capture_element(reader, &e, &mut c.extensions)?,
             },
-            Event::End(e) if e.name().as_ref() == b"chromatogram" => break,
+            Event::End(e) if local_name(e.name().as_ref()) == b"chromatogram" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -2619,10 +2982,14 @@ fn parse_chromatogram<R: BufRead>(
 }
 
 /// <indexList> / <indexListOffset> / <fileChecksum>
-pub fn parse_index_list(bytes: &[u8]) -> Result<Option<IndexList>, String> {
+pub fn parse_index_list(bytes: &[u8]) -> Result<Option<IndexList>, ParseError> {
     let mut reader = Reader::from_reader(Cursor::new(bytes));
     reader.config_mut().trim_text(true);
 
+    index_list_from_reader(&mut reader).map_err(|cause| locate(cause, &reader, bytes))
+}
+
+fn index_list_from_reader<R: BufRead>(reader: &mut Reader<R>) -> Result<Option<IndexList>, Cause> {
     let mut out = IndexList {
         spectrum: Vec::new(),
         chromatogram: Vec::new(),
@@ -2634,13 +3001,10 @@ pub fn parse_index_list(bytes: &[u8]) -> Result<Option<IndexList>, String> {
     let mut buf = Vec::with_capacity(1024);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Start(e) => match e.name().as_ref() {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) => match local_name(e.name().as_ref()) {
                 b"indexList" => {
-                    let (s, c) = parse_index_list_tag(&mut reader, &e)?;
+                    let (s, c) = parse_index_list_tag(reader, &e)?;
                     if !s.is_empty() || !c.is_empty() {
                         out.spectrum = s;
                         out.chromatogram = c;
@@ -2648,14 +3012,14 @@ pub fn parse_index_list(bytes: &[u8]) -> Result<Option<IndexList>, String> {
                     }
                 }
                 b"indexListOffset" => {
-                    let t = read_text_content(&mut reader, b"indexListOffset")?;
+                    let t = read_text_content(reader, b"indexListOffset")?;
                     out.index_list_offset = t.trim().parse::<u64>().ok();
                     if out.index_list_offset.is_some() {
                         saw_any = true;
                     }
                 }
                 b"fileChecksum" => {
-                    out.file_checksum = Some(read_text_content(&mut reader, b"fileChecksum")?);
+                    out.file_checksum = Some(read_text_content(reader, b"fileChecksum")?);
                     if out
                         .file_checksum
                         .as_deref()
@@ -2680,17 +3044,14 @@ pub fn parse_index_list(bytes: &[u8]) -> Result<Option<IndexList>, String> {
 fn parse_index_list_tag<R: BufRead>(
     reader: &mut Reader<R>,
     _start: &BytesStart,
-) -> Result<(Vec<IndexOffset>, Vec<IndexOffset>), String> {
+) -> Result<(Vec<IndexOffset>, Vec<IndexOffset>), Cause> {
     let mut spectrum = Vec::new();
     let mut chromatogram = Vec::new();
     let mut buf = Vec::with_capacity(1024);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Start(e) if e.name().as_ref() == b"index" => {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) if local_name(e.name().as_ref()) == b"index" => {
                 let (name, offsets) = parse_index_tag(reader, &e)?;
                 if name == "spectrum" {
                     spectrum = offsets;
@@ -2698,7 +3059,7 @@ fn parse_index_list_tag<R: BufRead>(
                     chromatogram = offsets;
                 }
             }
-            Event::End(e) if e.name().as_ref() == b"indexList" => break,
+            Event::End(e) if local_name(e.name().as_ref()) == b"indexList" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -2712,24 +3073,20 @@ fn parse_index_list_tag<R: BufRead>(
 fn parse_index_tag<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<(String, Vec<IndexOffset>), String> {
+) -> Result<(String, Vec<IndexOffset>), Cause> {
     let name = get_attr(start, b"name").unwrap_or_default();
     let mut offsets = Vec::new();
     let mut buf = Vec::with_capacity(1024);
 
     loop {
-        match reader
-            .read_event_into(&mut buf)
-            .map_err(|e| e.to_string())?
-        {
-            Event::Start(e) if e.name().as_ref() == b"offset" => {
+        match reader.read_event_into(&mut buf)? {
+            Event::Start(e) if local_name(e.name().as_ref()) == b"offset" => {
                 offsets.push(parse_offset_tag(reader, &e)?)
             }
-            Event::Empty(e) if e.name().as_ref() == b"offset" => offsets.push(IndexOffset {
-                id_ref: get_attr_any(&e, &[b"idRef", b"idref"]),
-                offset: 0,
-            }),
-            Event::End(e) if e.name().as_ref() == b"index" => break,
+            Event::Empty(e) if local_name(e.name().as_ref()) == b"offset" => {
+                return Err(Cause::Invalid("<offset> is empty".to_string()));
+            }
+            Event::End(e) if local_name(e.name().as_ref()) == b"index" => break,
             Event::Eof => break,
             _ => {}
         }
@@ -2743,9 +3100,12 @@ fn parse_index_tag<R: BufRead>(
 fn parse_offset_tag<R: BufRead>(
     reader: &mut Reader<R>,
     start: &BytesStart,
-) -> Result<IndexOffset, String> {
+) -> Result<IndexOffset, Cause> {
     let id_ref = get_attr_any(start, &[b"idRef", b"idref"]);
     let t = read_text_content(reader, b"offset")?;
-    let offset = t.trim().parse::<u64>().unwrap_or(0);
+    let offset = t
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| Cause::Invalid(format!("<offset> is not a byte offset: {t:?}")))?;
     Ok(IndexOffset { id_ref, offset })
 }
@@ -0,0 +1,159 @@
+use crate::mzml::{cv_table, structs::*};
+
+/// Version written on octo's `<software>` entry.
+pub const OCTO_VERSION: &str = env!("CARGO_PKG_VERSION");
+const OCTO_SOFTWARE_ID: &str = "octo";
+const OCTO_DATA_PROCESSING_ID: &str = "octo_conversion";
+
+const ACC_CUSTOM_SOFTWARE: &str = "MS:1000799";
+const ACC_CONVERSION_SOFTWARE: &str = "MS:1002333";
+const ACC_FILE_FORMAT_CONVERSION: &str = "MS:1000530";
+const ACC_CONVERSION_TO_MZML: &str = "MS:1000544";
+const ACC_MANTISSA_BITS_TRUNCATED: &str = "MS:1003092";
+
+/// Mantissa bits lost narrowing f64 (52) to f32 (23).
+const F64_TO_F32_TRUNCATED_BITS: u32 = 29;
+
+/// A conversion done by octo, recorded as a `<dataProcessing>` step.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Conversion {
+    /// mzML to B000.
+    ToB000 {
+        /// zstd level; 0 stores blocks uncompressed.
+        compression_level: u8,
+        /// 64-bit arrays were narrowed to f32.
+        precision_loss: bool,
+    },
+    /// B000 to mzML.
+    ToMzML,
+}
+
+/// `software_list` and `data_processing_list` with octo's `<software>` and a
+/// `<dataProcessing>` step for `conversion` appended. An existing `<software>`
+/// for this octo version is reused.
+pub fn with_provenance(
+    software_list: Option<&SoftwareList>,
+    data_processing_list: Option<&DataProcessingList>,
+    conversion: Conversion,
+) -> (SoftwareList, DataProcessingList) {
+    let mut software_list = software_list.cloned().unwrap_or_default();
+    let mut data_processing_list = data_processing_list.cloned().unwrap_or_default();
+
+    let software_ref = match software_list
+        .software
+        .iter()
+        .find(|s| is_octo(s) && s.version.as_deref() == Some(OCTO_VERSION))
+    {
+        Some(s) => s.id.clone(),
+        None => {
+            let id = unique_id(
+                OCTO_SOFTWARE_ID,
+                software_list.software.iter().map(|s| s.id.as_str()),
+            );
+            software_list.software.push(octo_software(id.clone()));
+            id
+        }
+    };
+    software_list.count = Some(software_list.software.len());
+
+    let id = unique_id(
+        OCTO_DATA_PROCESSING_ID,
+        data_processing_list
+            .data_processing
+            .iter()
+            .map(|dp| dp.id.as_str()),
+    );
+    data_processing_list.data_processing.push(DataProcessing {
+        id,
+        software_ref: None,
+        processing_method: vec![processing_method(software_ref, conversion)],
+        ..Default::default()
+    });
+    data_processing_list.count = Some(data_processing_list.data_processing.len());
+
+    (software_list, data_processing_list)
+}
+
+/// <software>
+#[inline]
+fn octo_software(id: String) -> Software {
+    Software {
+        id,
+        version: Some(OCTO_VERSION.to_string()),
+        cv_param: vec![
+            ms_cv(ACC_CUSTOM_SOFTWARE, Some(OCTO_SOFTWARE_ID)),
+            ms_cv(ACC_CONVERSION_SOFTWARE, None),
+        ],
+        ..Default::default()
+    }
+}
+
+#[inline]
+fn is_octo(software: &Software) -> bool {
+    software.cv_param.iter().any(|cv| {
+        cv.accession.as_deref() == Some(ACC_CUSTOM_SOFTWARE)
+            && cv.value.as_deref() == Some(OCTO_SOFTWARE_ID)
+    })
+}
+
+/// <processingMethod>
+fn processing_method(software_ref: String, conversion: Conversion) -> ProcessingMethod {
+    let mut cv_param = Vec::new();
+    let mut user_param = Vec::new();
+
+    match conversion {
+        Conversion::ToB000 {
+            compression_level,
+            precision_loss,
+        } => {
+            cv_param.push(ms_cv(ACC_FILE_FORMAT_CONVERSION, Some("B000")));
+            if precision_loss {
+                cv_param.push(ms_cv(
+                    ACC_MANTISSA_BITS_TRUNCATED,
+                    Some(&F64_TO_F32_TRUNCATED_BITS.to_string()),
+                ));
+            }
+            user_param.push(UserParam {
+                name: "zstd compression level".to_string(),
+                r#type: Some("xsd:int".to_string()),
+                value: Some(compression_level.to_string()),
+                ..Default::default()
+            });
+        }
+        Conversion::ToMzML => cv_param.push(ms_cv(ACC_CONVERSION_TO_MZML, None)),
+    }
+
+    ProcessingMethod {
+        order: Some(0),
+        software_ref: Some(software_ref),
+        cv_param,
+        user_param,
+        ..Default::default()
+    }
+}
+
+/// <cvParam>
+#[inline]
+fn ms_cv(accession: &str, value: Option<&str>) -> CvParam {
+    CvParam {
+        cv_ref: Some("MS".to_string()),
+        accession: Some(accession.to_string()),
+        name: cv_table::get(accession)
+            .and_then(|v| v.as_str())
+            .unwrap_or(accession)
+            .to_string(),
+        value: Some(value.unwrap_or_default().to_string()),
+        ..Default::default()
+    }
+}
+
+/// `base`, or `base_N` for the first N not among `taken`.
+fn unique_id<'a>(base: &str, taken: impl Iterator<Item = &'a str> + Clone) -> String {
+    if !taken.clone().any(|t| t == base) {
+        return base.to_string();
+    }
+    (1..)
+        .map(|n| format!("{base}_{n}"))
+        .find(|id| !taken.clone().any(|t| t == id))
+        .unwrap_or_default()
+}
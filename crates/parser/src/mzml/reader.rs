@@ -0,0 +1,293 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::Path,
+};
+
+use quick_xml::{
+    Reader,
+    events::{BytesStart, Event},
+};
+
+use crate::mzml::{
+    error::{Cause, ParseError},
+    gzip::MaybeGzip,
+    parse_mzml::{
+        capture_element, capture_empty, chromatogram_from_start, chromatogram_list_from_start,
+        get_attr, local_name, mzml_extensions, parse_chromatogram, parse_empty_header_section,
+        parse_header_section, parse_run_child, parse_spectrum, push_params_empty, run_from_start,
+        skip_element, spectrum_from_start, spectrum_list_from_start,
+    },
+    structs::{Chromatogram, MzML, Spectrum},
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum State {
+    Spectra,
+    Chromatograms,
+    Done,
+}
+
+/// Pull parser over an mzML document.
+///
+/// [`MzMLReader::new`] parses everything before the first `<spectrumList>` or
+/// `<chromatogramList>` into [`MzMLReader::header`]; spectra and chromatograms
+/// are then parsed one at a time as they are requested, so only the current
+/// item is held in memory.
+///
+/// The bytes already consumed are not kept, so a [`ParseError`] path stops
+/// at the element being parsed (e.g. `run/spectrumList/spectrum[id=…]`).
+pub struct MzMLReader<R: BufRead> {
+    reader: Reader<R>,
+    header: MzML,
+    state: State,
+    /// Path of the element being parsed, for errors.
+    context: String,
+}
+
+impl MzMLReader<MaybeGzip<BufReader<File>>> {
+    /// Opens the mzML file at `path`, inflating it on the fly when it is
+    /// gzip-compressed.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self, ParseError> {
+        let io_error = |e: std::io::Error| {
+            Cause::Io(format!("{}: {e}", path.as_ref().display())).at(0, String::new())
+        };
+        let file = File::open(path.as_ref()).map_err(io_error)?;
+        let source = MaybeGzip::new(BufReader::new(file)).map_err(io_error)?;
+        Self::new(source)
+    }
+}
+
+impl<R: BufRead> MzMLReader<R> {
+    /// Parses the header sections and `<run>` attributes from `source`.
+    pub fn new(source: R) -> Result<Self, ParseError> {
+        let mut reader = Reader::from_reader(source);
+        reader.config_mut().trim_text(true);
+
+        let mut this = Self {
+            reader,
+            header: MzML::default(),
+            state: State::Done,
+            context: String::new(),
+        };
+        this.read_header().map_err(|c| this.locate(c))?;
+        Ok(this)
+    }
+
+    /// Everything parsed so far except the spectra and chromatograms
+    /// themselves. The list attributes of `<spectrumList>` and
+    /// `<chromatogramList>` appear once the reader has reached them.
+    #[inline]
+    pub fn header(&self) -> &MzML {
+        &self.header
+    }
+
+    #[inline]
+    pub fn into_header(self) -> MzML {
+        self.header
+    }
+
+    /// Next <spectrum>, or `None` once the `<spectrumList>` is exhausted.
+    pub fn next_spectrum(&mut self) -> Result<Option<Spectrum>, ParseError> {
+        self.read_spectrum().map_err(|c| self.locate(c))
+    }
+
+    /// Next <chromatogram>; any spectra not read yet are skipped.
+    pub fn next_chromatogram(&mut self) -> Result<Option<Chromatogram>, ParseError> {
+        self.read_chromatogram().map_err(|c| self.locate(c))
+    }
+
+    /// Iterator over the remaining spectra.
+    pub fn spectra(&mut self) -> impl Iterator<Item = Result<Spectrum, ParseError>> + '_ {
+        std::iter::from_fn(move || self.next_spectrum().transpose())
+    }
+
+    /// Iterator over the remaining chromatograms.
+    pub fn chromatograms(&mut self) -> impl Iterator<Item = Result<Chromatogram, ParseError>> + '_ {
+        std::iter::from_fn(move || self.next_chromatogram().transpose())
+    }
+
+    fn locate(&self, cause: Cause) -> ParseError {
+        let offset = cause.offset(&self.reader);
+        cause.at(offset, self.context.clone())
+    }
+
+    /// Sets the error path to `parent/<e>`, labelled with its id if any.
+    fn enter(&mut self, parent: &str, e: &BytesStart) {
+        let name = String::from_utf8_lossy(local_name(e.name().as_ref())).into_owned();
+        self.context = match get_attr(e, b"id") {
+            Some(id) if !parent.is_empty() => format!("{parent}/{name}[id={id}]"),
+            _ if !parent.is_empty() => format!("{parent}/{name}"),
+            _ => name,
+        };
+    }
+
+    fn read_header(&mut self) -> Result<(), Cause> {
+        let mut buf = Vec::with_capacity(1024);
+        let mut in_mzml = false;
+
+        loop {
+            match self.reader.read_event_into(&mut buf)? {
+                Event::Start(e) if local_name(e.name().as_ref()) == b"mzML" => {
+                    in_mzml = true;
+                    self.header.extensions = mzml_extensions(&e);
+                }
+                Event::Start(e) if in_mzml => {
+                    if local_name(e.name().as_ref()) == b"run" {
+                        self.context = "run".to_string();
+                        self.header.run = run_from_start(&e);
+                        return self.advance_run();
+                    }
+                    self.enter("", &e);
+                    if !parse_header_section(&mut self.reader, &e, &mut self.header)? {
+                        capture_element(&mut self.reader, &e, &mut self.header.extensions)?;
+                    }
+                    self.context.clear();
+                }
+                Event::Empty(e) if in_mzml => {
+                    if local_name(e.name().as_ref()) == b"run" {
+                        self.header.run = run_from_start(&e);
+                        return Ok(());
+                    }
+                    parse_empty_header_section(&e, &mut self.header);
+                }
+                Event::End(e) if local_name(e.name().as_ref()) == b"mzML" => return Ok(()),
+                Event::Eof => return Ok(()),
+                _ => {}
+            }
+            buf.clear();
+        }
+    }
+
+    fn read_spectrum(&mut self) -> Result<Option<Spectrum>, Cause> {
+        let mut buf = Vec::with_capacity(1024);
+        while self.state == State::Spectra {
+            self.context = "run/spectrumList".to_string();
+            match self.reader.read_event_into(&mut buf)? {
+                Event::Start(e) => {
+                    self.enter("run/spectrumList", &e);
+                    if local_name(e.name().as_ref()) == b"spectrum" {
+                        return parse_spectrum(&mut self.reader, &e).map(Some);
+                    }
+                    let list = self
+                        .header
+                        .run
+                        .spectrum_list
+                        .get_or_insert_with(Default::default);
+                    capture_element(&mut self.reader, &e, &mut list.extensions)?;
+                }
+                Event::Empty(e) if local_name(e.name().as_ref()) == b"spectrum" => {
+                    return Ok(Some(spectrum_from_start(&e)));
+                }
+                Event::Empty(e) => {
+                    let list = self
+                        .header
+                        .run
+                        .spectrum_list
+                        .get_or_insert_with(Default::default);
+                    capture_empty(&e, &mut list.extensions);
+                }
+                Event::End(e) if local_name(e.name().as_ref()) == b"spectrumList" => {
+                    self.advance_run()?
+                }
+                Event::Eof => self.state = State::Done,
+                _ => {}
+            }
+            buf.clear();
+        }
+        Ok(None)
+    }
+
+    fn read_chromatogram(&mut self) -> Result<Option<Chromatogram>, Cause> {
+        if self.state == State::Spectra {
+            self.context = "run/spectrumList".to_string();
+            skip_element(&mut self.reader, b"spectrumList")?;
+            self.advance_run()?;
+        }
+
+        let mut buf = Vec::with_capacity(1024);
+        while self.state == State::Chromatograms {
+            self.context = "run/chromatogramList".to_string();
+            match self.reader.read_event_into(&mut buf)? {
+                Event::Start(e) => {
+                    self.enter("run/chromatogramList", &e);
+                    if local_name(e.name().as_ref()) == b"chromatogram" {
+                        return parse_chromatogram(&mut self.reader, &e).map(Some);
+                    }
+                    let run = &mut self.header.run;
+                    let list = run.chromatogram_list.get_or_insert_with(Default::default);
+                    capture_element(&mut self.reader, &e, &mut list.extensions)?;
+                }
+                Event::Empty(e) if local_name(e.name().as_ref()) == b"chromatogram" => {
+                    return Ok(Some(chromatogram_from_start(&e)));
+                }
+                Event::Empty(e) => {
+                    let run = &mut self.header.run;
+                    let list = run.chromatogram_list.get_or_insert_with(Default::default);
+                    capture_empty(&e, &mut list.extensions);
+                }
+                Event::End(e) if local_name(e.name().as_ref()) == b"chromatogramList" => {
+                    self.advance_run()?
+                }
+                Event::Eof => self.state = State::Done,
+                _ => {}
+            }
+            buf.clear();
+        }
+        Ok(None)
+    }
+
+    /// Reads `<run>` children up to the next item list.
+    fn advance_run(&mut self) -> Result<(), Cause> {
+        let mut buf = Vec::with_capacity(1024);
+
+        loop {
+            self.context = "run".to_string();
+            let run = &mut self.header.run;
+            match self.reader.read_event_into(&mut buf)? {
+                Event::Empty(e) => match local_name(e.name().as_ref()) {
+                    b"spectrumList" => run.spectrum_list = Some(spectrum_list_from_start(&e)),
+                    b"chromatogramList" => {
+                        run.chromatogram_list = Some(chromatogram_list_from_start(&e))
+                    }
+                    _ => {
+                        if !push_params_empty(
+                            &e,
+                            &mut run.referenceable_param_group_refs,
+                            &mut run.cv_params,
+                            &mut run.user_params,
+                        ) {
+                            capture_empty(&e, &mut run.extensions);
+                        }
+                    }
+                },
+                Event::Start(e) => match local_name(e.name().as_ref()) {
+                    b"spectrumList" => {
+                        run.spectrum_list = Some(spectrum_list_from_start(&e));
+                        self.state = State::Spectra;
+                        return Ok(());
+                    }
+                    b"chromatogramList" => {
+                        run.chromatogram_list = Some(chromatogram_list_from_start(&e));
+                        self.state = State::Chromatograms;
+                        return Ok(());
+                    }
+                    _ => {
+                        self.enter("run", &e);
+                        let run = &mut self.header.run;
+                        if !parse_run_child(&mut self.reader, &e, run)? {
+                            capture_element(&mut self.reader, &e, &mut run.extensions)?;
+                        }
+                    }
+                },
+                Event::End(e) if local_name(e.name().as_ref()) == b"run" => break,
+                Event::Eof => break,
+                _ => {}
+            }
+            buf.clear();
+        }
+
+        self.state = State::Done;
+        Ok(())
+    }
+}
@@ -0,0 +1,335 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    io::Cursor,
+};
+
+use quick_xml::{Reader, events::Event};
+
+use crate::mzml::{
+    bin_to_mzml::append_index,
+    error::{Cause, ParseError},
+    gzip::maybe_gunzip,
+    indexed::{Layout, TAIL_LEN, find_index_list_offset, scan_layout, scan_tags},
+    parse_mzml::{IndexOffset, get_attr, locate},
+};
+
+/// Opening tag that [`reindex`] wraps a plain `<mzML>` document in.
+const INDEXED_MZML_START: &str = concat!(
+    r#"<indexedmzML xmlns="http://psi.hupo.org/ms/mzml" "#,
+    r#"xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance" "#,
+    r#"xsi:schemaLocation="http://psi.hupo.org/ms/mzml "#,
+    r#"http://psidev.info/files/ms/mzML/xsd/mzML1.1.2_idx.xsd">"#,
+    "\n"
+);
+
+/// Something wrong with the `<indexList>` of an indexedmzML document.
+///
+/// `index` is the `name` of the `<index>` the entry belongs to, `spectrum` or
+/// `chromatogram`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexIssue {
+    /// `<indexListOffset>` is missing or does not point at `<indexList>`;
+    /// `actual` is `None` when there is no `<indexList>` at all.
+    ListOffset {
+        recorded: Option<u64>,
+        actual: Option<u64>,
+    },
+    /// `<offset>` whose text is not a byte offset.
+    Unparsable {
+        index: &'static str,
+        id_ref: Option<String>,
+        text: String,
+    },
+    /// Offset that does not land on the element with `id_ref`; `actual` is
+    /// where that element starts, `None` when the document has no such element.
+    Wrong {
+        index: &'static str,
+        id_ref: Option<String>,
+        offset: u64,
+        actual: Option<u64>,
+    },
+    /// Element without an `<offset>`.
+    Missing {
+        index: &'static str,
+        id: String,
+        actual: u64,
+    },
+    /// Element with more than one `<offset>`.
+    Duplicate {
+        index: &'static str,
+        id_ref: String,
+        count: usize,
+    },
+}
+
+impl fmt::Display for IndexIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let id = |id: &Option<String>| id.as_deref().map_or("(no idRef)", |s| s).to_string();
+        match self {
+            IndexIssue::ListOffset { recorded, actual } => {
+                match recorded {
+                    Some(r) => write!(f, "indexListOffset is {r}")?,
+                    None => write!(f, "indexListOffset is missing")?,
+                }
+                match actual {
+                    Some(a) => write!(f, ", <indexList> starts at byte {a}"),
+                    None => write!(f, ", there is no <indexList>"),
+                }
+            }
+            IndexIssue::Unparsable {
+                index,
+                id_ref,
+                text,
+            } => write!(
+                f,
+                "{index} {}: offset {text:?} is not a byte offset",
+                id(id_ref)
+            ),
+            IndexIssue::Wrong {
+                index,
+                id_ref,
+                offset,
+                actual,
+            } => {
+                write!(f, "{index} {}: offset {offset} is wrong", id(id_ref))?;
+                match actual {
+                    Some(a) => write!(f, ", element starts at byte {a}"),
+                    None => write!(f, ", no such element"),
+                }
+            }
+            IndexIssue::Missing { index, id, actual } => {
+                write!(
+                    f,
+                    "{index} {id}: not indexed, element starts at byte {actual}"
+                )
+            }
+            IndexIssue::Duplicate {
+                index,
+                id_ref,
+                count,
+            } => write!(f, "{index} {id_ref}: indexed {count} times"),
+        }
+    }
+}
+
+/// What [`check_index`] found.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IndexReport {
+    /// `true` when `<indexedmzML>` wraps the document.
+    pub indexed: bool,
+    pub spectra: usize,
+    pub chromatograms: usize,
+    /// In document order: the list offset, then the entries of each index,
+    /// then the elements without one.
+    pub issues: Vec<IndexIssue>,
+}
+
+impl IndexReport {
+    /// `true` for an indexedmzML document whose index is correct.
+    #[inline]
+    pub fn is_valid(&self) -> bool {
+        self.indexed && self.issues.is_empty()
+    }
+}
+
+/// Checks every `<offset>` of an indexedmzML document against where its
+/// spectrum or chromatogram actually starts (gzip-compressed input is
+/// inflated first).
+///
+/// A plain `<mzML>` document is reported with `indexed: false` and no issues.
+pub fn check_index(bytes: &[u8]) -> Result<IndexReport, ParseError> {
+    let bytes = maybe_gunzip(bytes).map_err(|e| Cause::Io(e).at(0, String::new()))?;
+    let bytes = &bytes[..];
+    let layout = layout(bytes)?;
+
+    let mut report = IndexReport {
+        indexed: layout.indexed,
+        spectra: layout.spectra.len(),
+        chromatograms: layout.chromatograms.len(),
+        issues: Vec::new(),
+    };
+    if !layout.indexed {
+        return Ok(report);
+    }
+
+    let tail_start = bytes.len().saturating_sub(TAIL_LEN as usize);
+    let recorded = find_index_list_offset(&bytes[tail_start..]);
+    if recorded != layout.index_list {
+        report.issues.push(IndexIssue::ListOffset {
+            recorded,
+            actual: layout.index_list,
+        });
+    }
+    let Some(index_list) = layout.index_list else {
+        return Ok(report);
+    };
+
+    let entries = index_entries(&bytes[index_list as usize..]);
+    for (index, actual) in [
+        ("spectrum", &layout.spectra),
+        ("chromatogram", &layout.chromatograms),
+    ] {
+        check_entries(index, &entries, actual, &mut report.issues);
+    }
+    Ok(report)
+}
+
+/// Writes `bytes` back with a freshly computed `<indexList>`,
+/// `<indexListOffset>` and `<fileChecksum>` (gzip-compressed input is
+/// inflated first).
+///
+/// A plain `<mzML>` document is wrapped into `<indexedmzML>`. Everything up
+/// to `</mzML>`, binary payloads included, is copied as is.
+pub fn reindex(bytes: &[u8]) -> Result<Vec<u8>, ParseError> {
+    let bytes = maybe_gunzip(bytes).map_err(|e| Cause::Io(e).at(0, String::new()))?;
+    let bytes = &bytes[..];
+    let mut layout = layout(bytes)?;
+
+    let (Some(start), Some(end)) = (layout.mzml_start, layout.mzml_end) else {
+        let cause = Cause::Invalid("no complete <mzML> element".to_string());
+        return Err(cause.at(bytes.len() as u64, String::new()));
+    };
+    let (start, end) = (start as usize, end as usize);
+
+    let mut doc = Vec::with_capacity(end + INDEXED_MZML_START.len() + 4096);
+    if layout.indexed {
+        doc.extend_from_slice(&bytes[..end]);
+    } else {
+        doc.extend_from_slice(&bytes[..start]);
+        doc.extend_from_slice(INDEXED_MZML_START.as_bytes());
+        doc.extend_from_slice(&bytes[start..end]);
+
+        let shift = INDEXED_MZML_START.len() as u64;
+        for o in layout.spectra.iter_mut().chain(&mut layout.chromatograms) {
+            o.offset += shift;
+        }
+    }
+
+    append_index(doc, &layout.spectra, &layout.chromatograms)
+        .map_err(|e| Cause::Io(e).at(0, String::new()))
+}
+
+fn layout(bytes: &[u8]) -> Result<Layout, ParseError> {
+    let mut reader = Reader::from_reader(Cursor::new(bytes));
+    scan_layout(&mut reader).map_err(|e| locate(e.into(), &reader, bytes))
+}
+
+/// One `<offset>` of an `<indexList>`.
+struct Entry<'a> {
+    index: Option<String>,
+    id_ref: Option<String>,
+    text: &'a [u8],
+}
+
+/// The `<offset>` entries of the `<indexList>` that `bytes` start with, read
+/// as text so unparsable values can be reported.
+fn index_entries(bytes: &[u8]) -> Vec<Entry<'_>> {
+    let mut entries = Vec::new();
+    let mut index = None;
+
+    for t in scan_tags(bytes) {
+        let tag = &bytes[t.start..t.end];
+        match (t.closing, t.name) {
+            (false, b"index") => index = attr(tag, &[b"name"]),
+            (true, b"index") => index = None,
+            (true, b"indexList") => break,
+            (false, b"offset") => {
+                let text = if tag.ends_with(b"/>") {
+                    &[][..]
+                } else {
+                    let rest = &bytes[t.end..];
+                    &rest[..rest.iter().position(|&b| b == b'<').unwrap_or(rest.len())]
+                };
+                entries.push(Entry {
+                    index: index.clone(),
+                    id_ref: attr(tag, &[b"idRef", b"idref"]),
+                    text: text.trim_ascii(),
+                });
+            }
+            _ => {}
+        }
+    }
+    entries
+}
+
+#[inline]
+fn attr(tag: &[u8], names: &[&[u8]]) -> Option<String> {
+    match Reader::from_reader(tag).read_event() {
+        Ok(Event::Start(e) | Event::Empty(e)) => names.iter().find_map(|n| get_attr(&e, n)),
+        _ => None,
+    }
+}
+
+/// Issues of the `<index name="{index}">` entries against the elements found
+/// at `actual`.
+fn check_entries(
+    index: &'static str,
+    entries: &[Entry],
+    actual: &[IndexOffset],
+    issues: &mut Vec<IndexIssue>,
+) {
+    let mut starts: HashMap<&str, u64> = HashMap::with_capacity(actual.len());
+    for o in actual {
+        if let Some(id) = o.id_ref.as_deref() {
+            starts.entry(id).or_insert(o.offset);
+        }
+    }
+
+    let entries: Vec<&Entry> = entries
+        .iter()
+        .filter(|e| e.index.as_deref() == Some(index))
+        .collect();
+    let mut counts: HashMap<&str, usize> = HashMap::with_capacity(entries.len());
+    for e in &entries {
+        if let Some(id) = e.id_ref.as_deref() {
+            *counts.entry(id).or_default() += 1;
+        }
+    }
+
+    let mut reported = HashSet::new();
+    for e in &entries {
+        let id = e.id_ref.as_deref();
+        if let Some(id) = id
+            && counts[id] > 1
+            && reported.insert(id)
+        {
+            issues.push(IndexIssue::Duplicate {
+                index,
+                id_ref: id.to_string(),
+                count: counts[id],
+            });
+        }
+
+        let expected = id.and_then(|id| starts.get(id).copied());
+        let parsed = std::str::from_utf8(e.text)
+            .ok()
+            .and_then(|s| s.parse::<u64>().ok());
+        match parsed {
+            None => issues.push(IndexIssue::Unparsable {
+                index,
+                id_ref: e.id_ref.clone(),
+                text: String::from_utf8_lossy(e.text).into_owned(),
+            }),
+            Some(offset) if Some(offset) != expected => issues.push(IndexIssue::Wrong {
+                index,
+                id_ref: e.id_ref.clone(),
+                offset,
+                actual: expected,
+            }),
+            Some(_) => {}
+        }
+    }
+
+    for o in actual {
+        if let Some(id) = o.id_ref.as_deref()
+            && !counts.contains_key(id)
+        {
+            issues.push(IndexIssue::Missing {
+                index,
+                id: id.to_string(),
+                actual: o.offset,
+            });
+        }
+    }
+}
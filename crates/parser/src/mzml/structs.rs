@@ -12,6 +12,26 @@ pub struct MzML {
     pub data_processing_list: Option<DataProcessingList>,
     pub scan_settings_list: Option<ScanSettingsList>,
     pub run: Run,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
+}
+
+/// Attributes and child elements this crate does not model, kept as read so
+/// they can be written back.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, PartialEq, Eq)]
+pub struct Extensions {
+    /// `(name, value)` in document order, `value` unescaped.
+    pub attributes: Vec<(String, String)>,
+    /// Each element as XML text, in document order.
+    pub elements: Vec<String>,
+}
+
+impl Extensions {
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty() && self.elements.is_empty()
+    }
 }
 
 /// <cvList>
@@ -19,6 +39,9 @@ pub struct MzML {
 pub struct CvList {
     pub count: Option<usize>,
     pub cv: Vec<Cv>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <cv>
@@ -64,6 +87,9 @@ pub struct ReferenceableParamGroupRef {
 pub struct DataProcessingList {
     pub count: Option<usize>,
     pub data_processing: Vec<DataProcessing>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <dataProcessing>
@@ -72,6 +98,9 @@ pub struct DataProcessing {
     pub id: String,
     pub software_ref: Option<String>,
     pub processing_method: Vec<ProcessingMethod>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <processingMethod>
@@ -82,6 +111,9 @@ pub struct ProcessingMethod {
     pub referenceable_param_group_ref: Vec<ReferenceableParamGroupRef>,
     pub cv_param: Vec<CvParam>,
     pub user_param: Vec<UserParam>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <fileDescription>
@@ -90,6 +122,9 @@ pub struct FileDescription {
     pub file_content: FileContent,
     pub source_file_list: SourceFileList,
     pub contacts: Vec<Contact>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <sourceFileList>
@@ -97,6 +132,9 @@ pub struct FileDescription {
 pub struct SourceFileList {
     pub count: Option<usize>,
     pub source_file: Vec<SourceFile>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <sourceFile>
@@ -108,6 +146,9 @@ pub struct SourceFile {
     pub referenceable_param_group_ref: Vec<ReferenceableParamGroupRef>,
     pub cv_param: Vec<CvParam>,
     pub user_param: Vec<UserParam>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <fileContent>
@@ -116,6 +157,9 @@ pub struct FileContent {
     pub referenceable_param_group_refs: Vec<ReferenceableParamGroupRef>,
     pub cv_params: Vec<CvParam>,
     pub user_params: Vec<UserParam>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <contact>
@@ -124,6 +168,9 @@ pub struct Contact {
     pub referenceable_param_group_refs: Vec<ReferenceableParamGroupRef>,
     pub cv_params: Vec<CvParam>,
     pub user_params: Vec<UserParam>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <instrumentList> / <instrumentConfigurationList>
@@ -131,6 +178,9 @@ pub struct Contact {
 pub struct InstrumentList {
     pub count: Option<usize>,
     pub instrument: Vec<Instrument>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <instrument> / <instrumentConfiguration>
@@ -143,6 +193,9 @@ pub struct Instrument {
     pub referenceable_param_group_ref: Vec<ReferenceableParamGroupRef>,
     pub component_list: Option<ComponentList>,
     pub software_ref: Option<InstrumentSoftwareRef>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// attribute scanSettingsRef
@@ -158,6 +211,9 @@ pub struct ComponentList {
     pub source: Vec<Source>,
     pub analyzer: Vec<Analyzer>,
     pub detector: Vec<Detector>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <source>
@@ -167,6 +223,9 @@ pub struct Source {
     pub referenceable_param_group_ref: Vec<ReferenceableParamGroupRef>,
     pub cv_param: Vec<CvParam>,
     pub user_param: Vec<UserParam>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <analyzer>
@@ -176,6 +235,9 @@ pub struct Analyzer {
     pub referenceable_param_group_ref: Vec<ReferenceableParamGroupRef>,
     pub cv_param: Vec<CvParam>,
     pub user_param: Vec<UserParam>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <detector>
@@ -185,6 +247,9 @@ pub struct Detector {
     pub referenceable_param_group_ref: Vec<ReferenceableParamGroupRef>,
     pub cv_param: Vec<CvParam>,
     pub user_param: Vec<UserParam>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <instrumentSoftwareRef> / <softwareRef>
@@ -198,6 +263,9 @@ pub struct InstrumentSoftwareRef {
 pub struct ReferenceableParamGroupList {
     pub count: Option<usize>,
     pub referenceable_param_groups: Vec<ReferenceableParamGroup>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <referenceableParamGroup>
@@ -206,6 +274,9 @@ pub struct ReferenceableParamGroup {
     pub id: String,
     pub cv_params: Vec<CvParam>,
     pub user_params: Vec<UserParam>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <sampleList>
@@ -213,6 +284,9 @@ pub struct ReferenceableParamGroup {
 pub struct SampleList {
     pub count: Option<u32>,
     pub samples: Vec<Sample>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <sample>
@@ -220,7 +294,12 @@ pub struct SampleList {
 pub struct Sample {
     pub id: String,
     pub name: String,
-    pub referenceable_param_group_ref: Option<ReferenceableParamGroupRef>,
+    pub referenceable_param_group_ref: Vec<ReferenceableParamGroupRef>,
+    pub cv_param: Vec<CvParam>,
+    pub user_param: Vec<UserParam>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <scanSettingsList> / <acquisitionSettingsList>
@@ -228,6 +307,9 @@ pub struct Sample {
 pub struct ScanSettingsList {
     pub count: Option<usize>,
     pub scan_settings: Vec<ScanSettings>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <scanSettings> / <acquisitionSettings>
@@ -240,6 +322,9 @@ pub struct ScanSettings {
     pub user_params: Vec<UserParam>,
     pub source_file_ref_list: Option<SourceFileRefList>,
     pub target_list: Option<TargetList>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <sourceFileRefList>
@@ -247,6 +332,9 @@ pub struct ScanSettings {
 pub struct SourceFileRefList {
     pub count: Option<usize>,
     pub source_file_refs: Vec<SourceFileRef>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <sourceFileRef>
@@ -260,6 +348,9 @@ pub struct SourceFileRef {
 pub struct TargetList {
     pub count: Option<usize>,
     pub targets: Vec<Target>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <target>
@@ -268,6 +359,9 @@ pub struct Target {
     pub referenceable_param_group_refs: Vec<ReferenceableParamGroupRef>,
     pub cv_params: Vec<CvParam>,
     pub user_params: Vec<UserParam>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <softwareList>
@@ -275,6 +369,9 @@ pub struct Target {
 pub struct SoftwareList {
     pub count: Option<usize>,
     pub software: Vec<Software>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <software>
@@ -283,7 +380,12 @@ pub struct Software {
     pub id: String,
     pub version: Option<String>,
     pub software_param: Vec<SoftwareParam>,
+    pub referenceable_param_group_ref: Vec<ReferenceableParamGroupRef>,
     pub cv_param: Vec<CvParam>,
+    pub user_param: Vec<UserParam>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <softwareParam>
@@ -311,6 +413,9 @@ pub struct Run {
     pub source_file_ref_list: Option<SourceFileRefList>,
     pub spectrum_list: Option<SpectrumList>,
     pub chromatogram_list: Option<ChromatogramList>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <spectrumList>
@@ -319,6 +424,9 @@ pub struct SpectrumList {
     pub count: Option<usize>,
     pub default_data_processing_ref: Option<String>,
     pub spectra: Vec<Spectrum>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <spectrumDescription> (1.0.0)
@@ -331,6 +439,9 @@ pub struct SpectrumDescription {
     pub scan_list: Option<ScanList>,
     pub precursor_list: Option<PrecursorList>,
     pub product_list: Option<ProductList>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <scanList>
@@ -340,6 +451,9 @@ pub struct ScanList {
     pub cv_params: Vec<CvParam>,
     pub user_params: Vec<UserParam>,
     pub scans: Vec<Scan>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <scan>
@@ -355,6 +469,9 @@ pub struct Scan {
     pub user_params: Vec<UserParam>,
 
     pub scan_window_list: Option<ScanWindowList>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <scanWindowList> / <selectionWindowList>
@@ -362,6 +479,9 @@ pub struct Scan {
 pub struct ScanWindowList {
     pub count: Option<usize>,
     pub scan_windows: Vec<ScanWindow>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <scanWindow> / <selectionWindow>
@@ -369,6 +489,9 @@ pub struct ScanWindowList {
 pub struct ScanWindow {
     pub cv_params: Vec<CvParam>,
     pub user_params: Vec<UserParam>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <precursorList>
@@ -378,6 +501,9 @@ pub struct PrecursorList {
     pub cv_params: Vec<CvParam>,
     pub user_params: Vec<UserParam>,
     pub precursors: Vec<Precursor>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <precursor>
@@ -390,6 +516,9 @@ pub struct Precursor {
     pub isolation_window: Option<IsolationWindow>,
     pub selected_ion_list: Option<SelectedIonList>,
     pub activation: Option<Activation>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <isolationWindow>
@@ -398,6 +527,9 @@ pub struct IsolationWindow {
     pub referenceable_param_group_refs: Vec<ReferenceableParamGroupRef>,
     pub cv_params: Vec<CvParam>,
     pub user_params: Vec<UserParam>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <selectedIonList>
@@ -405,6 +537,9 @@ pub struct IsolationWindow {
 pub struct SelectedIonList {
     pub count: Option<usize>,
     pub selected_ions: Vec<SelectedIon>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <selectedIon>
@@ -413,6 +548,9 @@ pub struct SelectedIon {
     pub referenceable_param_group_refs: Vec<ReferenceableParamGroupRef>,
     pub cv_params: Vec<CvParam>,
     pub user_params: Vec<UserParam>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <activation>
@@ -421,6 +559,9 @@ pub struct Activation {
     pub referenceable_param_group_refs: Vec<ReferenceableParamGroupRef>,
     pub cv_params: Vec<CvParam>,
     pub user_params: Vec<UserParam>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <productList>
@@ -428,6 +569,9 @@ pub struct Activation {
 pub struct ProductList {
     pub count: Option<usize>,
     pub products: Vec<Product>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <product>
@@ -438,6 +582,9 @@ pub struct Product {
     pub external_spectrum_id: Option<String>,
 
     pub isolation_window: Option<IsolationWindow>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <binaryDataArrayList>
@@ -445,6 +592,9 @@ pub struct Product {
 pub struct BinaryDataArrayList {
     pub count: Option<usize>,
     pub binary_data_arrays: Vec<BinaryDataArray>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Copy, Eq)]
@@ -469,6 +619,9 @@ pub struct BinaryDataArray {
 
     pub numeric_type: Option<NumericType>,
     pub binary: Option<BinaryData>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -486,6 +639,9 @@ pub struct ChromatogramList {
     pub count: Option<usize>,
     pub default_data_processing_ref: Option<String>,
     pub chromatograms: Vec<Chromatogram>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <chromatogram>
@@ -505,6 +661,9 @@ pub struct Chromatogram {
     pub product: Option<Product>,
 
     pub binary_data_array_list: Option<BinaryDataArrayList>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
 
 /// <spectrum>
@@ -534,4 +693,7 @@ pub struct Spectrum {
     pub precursor_list: Option<PrecursorList>,
     pub product_list: Option<ProductList>,
     pub binary_data_array_list: Option<BinaryDataArrayList>,
+
+    #[serde(default, skip_serializing_if = "Extensions::is_empty")]
+    pub extensions: Extensions,
 }
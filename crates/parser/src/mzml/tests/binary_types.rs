@@ -0,0 +1,107 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+
+use crate::{
+    BinaryData, NumericType, ParseError,
+    mzml::{bin_to_mzml::bin_to_mzml, parse_mzml::parse_mzml, structs::MzML},
+};
+
+fn cv(acc: &str, name: &str) -> String {
+    format!(r#"<cvParam cvRef="MS" accession="{acc}" name="{name}" value=""/>"#)
+}
+
+/// One spectrum with a single intensity array typed by `type_cvs`.
+fn mzml_with_array(type_cvs: &[(&str, &str)], raw: &[u8], len: usize) -> String {
+    let types: String = type_cvs.iter().map(|(a, n)| cv(a, n)).collect();
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<mzML xmlns="http://psi.hupo.org/ms/mzml" version="1.1.0">
+  <run id="r">
+    <spectrumList count="1">
+      <spectrum index="0" id="s0" defaultArrayLength="{len}">
+        <binaryDataArrayList count="1">
+          <binaryDataArray encodedLength="0">
+            {types}
+            {no_comp}
+            {intensity}
+            <binary>{b64}</binary>
+          </binaryDataArray>
+        </binaryDataArrayList>
+      </spectrum>
+    </spectrumList>
+  </run>
+</mzML>"#,
+        no_comp = cv("MS:1000576", "no compression"),
+        intensity = cv("MS:1000515", "intensity array"),
+        b64 = STANDARD.encode(raw),
+    )
+}
+
+fn first_array(mzml: &MzML) -> (&BinaryData, Option<NumericType>) {
+    let bda = &mzml.run.spectrum_list.as_ref().unwrap().spectra[0]
+        .binary_data_array_list
+        .as_ref()
+        .unwrap()
+        .binary_data_arrays[0];
+    (bda.binary.as_ref().unwrap(), bda.numeric_type)
+}
+
+#[test]
+fn int32_arrays_decode_as_i32() {
+    let values = [7i32, -3, 1_000_000];
+    let raw: Vec<u8> = values.iter().flat_map(|x| x.to_le_bytes()).collect();
+    let xml = mzml_with_array(&[("MS:1000519", "32-bit integer")], &raw, values.len());
+
+    let mzml = parse_mzml(xml.as_bytes(), false).unwrap();
+    let (binary, numeric_type) = first_array(&mzml);
+    assert_eq!(numeric_type, Some(NumericType::Int32));
+    assert!(matches!(binary, BinaryData::I32(v) if v == &values));
+
+    let again = parse_mzml(bin_to_mzml(&mzml).unwrap().as_bytes(), false).unwrap();
+    assert!(matches!(first_array(&again).0, BinaryData::I32(v) if v == &values));
+}
+
+#[test]
+fn int16_arrays_decode_as_i16() {
+    let values = [1i16, -2, 32_000, 4];
+    let raw: Vec<u8> = values.iter().flat_map(|x| x.to_le_bytes()).collect();
+    let xml = mzml_with_array(&[("MS:1000518", "16-bit integer")], &raw, values.len());
+
+    let mzml = parse_mzml(xml.as_bytes(), false).unwrap();
+    let (binary, numeric_type) = first_array(&mzml);
+    assert_eq!(numeric_type, Some(NumericType::Int16));
+    assert!(matches!(binary, BinaryData::I16(v) if v == &values));
+
+    let again = parse_mzml(bin_to_mzml(&mzml).unwrap().as_bytes(), false).unwrap();
+    assert!(matches!(first_array(&again).0, BinaryData::I16(v) if v == &values));
+}
+
+#[test]
+fn missing_type_defaults_to_f64() {
+    let raw = 2.5f64.to_le_bytes();
+    let xml = mzml_with_array(&[], &raw, 1);
+
+    let mzml = parse_mzml(xml.as_bytes(), false).unwrap();
+    let (binary, numeric_type) = first_array(&mzml);
+    assert_eq!(numeric_type, Some(NumericType::Float64));
+    assert!(matches!(binary, BinaryData::F64(v) if v == &[2.5]));
+}
+
+#[test]
+fn conflicting_types_are_an_error() {
+    let raw = [0u8; 8];
+    let xml = mzml_with_array(
+        &[
+            ("MS:1000523", "64-bit float"),
+            ("MS:1000519", "32-bit integer"),
+        ],
+        &raw,
+        1,
+    );
+
+    let err = parse_mzml(xml.as_bytes(), false).unwrap_err();
+    assert!(matches!(err, ParseError::Invalid { .. }), "{err}");
+    assert!(
+        err.message().contains("MS:1000523") && err.message().contains("MS:1000519"),
+        "{err}"
+    );
+}
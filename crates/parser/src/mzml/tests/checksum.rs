@@ -0,0 +1,106 @@
+use std::sync::OnceLock;
+
+use crate::{
+    MzML,
+    mzml::{
+        ParseError,
+        bin_to_mzml::bin_to_mzml,
+        checksum::{Sha1, sha1_hex, verify_file_checksum},
+        parse_mzml::{ParseOptions, parse_mzml, parse_mzml_with},
+        tests::gzip::gzip_member,
+    },
+    utilities::test::{load_mzml_bytes, mzml},
+};
+
+static MZML_CACHE: OnceLock<MzML> = OnceLock::new();
+
+const PATH: &str = "data/mzml/tiny.pwiz.mzML0.99.10.mzML";
+
+fn verifying() -> ParseOptions {
+    ParseOptions {
+        verify_checksum: true,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn sha1_known_answers() {
+    assert_eq!(sha1_hex(b""), "da39a3ee5e6b4b0d3255bfef95601890afd80709");
+    assert_eq!(sha1_hex(b"abc"), "a9993e364706816aba3e25717850c26c9cd0d89d");
+    assert_eq!(
+        sha1_hex(b"abcdbcdecdefdefgefghfghighijhijkijkljklmklmnlmnomnopnopq"),
+        "84983e441c3bd26ebaae4aa1f95129e5e54670f1"
+    );
+
+    let million = vec![b'a'; 1_000_000];
+    assert_eq!(
+        sha1_hex(&million),
+        "34aa973cd4c4daa4f61eeb2bdbad27316534016f"
+    );
+}
+
+#[test]
+fn sha1_is_independent_of_chunking() {
+    let bytes: Vec<u8> = (0..1000u32).map(|i| (i * 7) as u8).collect();
+    for step in [1, 3, 63, 64, 65, 200] {
+        let mut h = Sha1::default();
+        for chunk in bytes.chunks(step) {
+            h.update(chunk);
+        }
+        let hex: String = h.finish().iter().map(|b| format!("{b:02x}")).collect();
+        assert_eq!(hex, sha1_hex(&bytes), "step {step}");
+    }
+}
+
+/// The fixtures were edited after conversion, so the checksum they carry no
+/// longer matches.
+#[test]
+fn stale_checksum_is_reported() {
+    let bytes = load_mzml_bytes("data/mzml/test.mzML");
+    let e = verify_file_checksum(&bytes).unwrap_err();
+    assert_eq!(
+        e.message(),
+        "SHA-1 mismatch: file says 7c2fac3dd5cb3a7986efede1d3248daf6387daa5, \
+         content is 9368a60190ffb9851295d46860429217b9ec3fc1"
+    );
+    assert_eq!(e.offset(), 15087);
+}
+
+#[test]
+fn plain_mzml_has_nothing_to_verify() {
+    let bytes = load_mzml_bytes("data/mzml/tiny1.mzML0.99.0.mzML");
+    assert_eq!(verify_file_checksum(&bytes), Ok(false));
+    assert!(parse_mzml_with(&bytes, &verifying()).is_ok());
+}
+
+#[test]
+fn written_checksum_verifies() {
+    let mzml = mzml(&MZML_CACHE, PATH);
+    let xml = bin_to_mzml(mzml).unwrap();
+    assert!(xml.contains("<fileChecksum>"));
+    assert_eq!(verify_file_checksum(xml.as_bytes()), Ok(true));
+    assert_eq!(verify_file_checksum(&gzip_member(xml.as_bytes())), Ok(true));
+
+    let reparsed = parse_mzml_with(xml.as_bytes(), &verifying()).unwrap();
+    let ids = |m: &MzML| -> Vec<String> {
+        let list = m.run.spectrum_list.as_ref().unwrap();
+        list.spectra.iter().map(|s| s.id.clone()).collect()
+    };
+    assert_eq!(ids(&reparsed), ids(mzml));
+}
+
+#[test]
+fn altered_document_fails_verification() {
+    let bytes = load_mzml_bytes(PATH);
+    let pos = bytes.windows(4).position(|w| w == b"scan").unwrap();
+    let mut altered = bytes.to_vec();
+    altered[pos] = b'S';
+
+    let e = verify_file_checksum(&altered).unwrap_err();
+    assert!(matches!(e, ParseError::Invalid { .. }), "{e}");
+    assert_eq!(e.path(), "fileChecksum");
+    assert!(e.message().contains("SHA-1 mismatch"), "{e}");
+
+    assert_eq!(parse_mzml_with(&altered, &verifying()).unwrap_err(), e);
+    assert!(parse_mzml(&altered, false).is_ok());
+}
@@ -0,0 +1,141 @@
+use crate::{
+    MzML,
+    b64::{
+        decode, encode,
+        utilities::{Extended, extension_rows},
+    },
+    mzml::{
+        bin_to_mzml::bin_to_mzml, parse_mzml::parse_mzml, reader::MzMLReader, structs::Extensions,
+    },
+    utilities::test::load_mzml_bytes,
+};
+
+const PATH: &str = "data/mzml/tiny.pwiz.mzML0.99.10.mzML";
+
+const FOCUS: &str = r#"<acme:focus level="3">on <acme:b>x &amp; y</acme:b> </acme:focus>"#;
+
+/// The test file with vendor additions at the root, header and spectrum level.
+fn extended() -> Vec<u8> {
+    let xml = String::from_utf8(load_mzml_bytes(PATH)).unwrap();
+    let xml = xml
+        .replacen(
+            "<mzML ",
+            r#"<mzML xmlns:acme="urn:acme" acme:build="4&lt;2" "#,
+            1,
+        )
+        .replacen("<fileDescription>", "<fileDescription>\n<acme:note/>", 1)
+        .replacen(
+            r#"<spectrum index="0" id="S19""#,
+            r#"<spectrum acme:tag="first" index="0" id="S19""#,
+            1,
+        )
+        .replacen(
+            r#"<scan instrumentConfigurationRef="LCQDeca">"#,
+            &format!(r#"<scan instrumentConfigurationRef="LCQDeca">{FOCUS}"#),
+            1,
+        );
+    xml.into_bytes()
+}
+
+fn attrs(pairs: &[(&str, &str)]) -> Extensions {
+    Extensions {
+        attributes: pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect(),
+        elements: Vec::new(),
+    }
+}
+
+/// Extension rows of `root`, as JSON.
+fn rows_of<T: Extended>(root: &T) -> Vec<String> {
+    extension_rows(root)
+        .into_iter()
+        .filter_map(|cv| cv.value)
+        .collect()
+}
+
+/// Extension rows of the document and of every spectrum.
+fn all_rows(mzml: &MzML) -> Vec<String> {
+    let mut rows = rows_of(mzml);
+    for spectrum in &mzml.run.spectrum_list.as_ref().unwrap().spectra {
+        rows.extend(rows_of(spectrum));
+    }
+    rows
+}
+
+#[test]
+fn unknown_content_is_captured() {
+    let mzml = parse_mzml(&extended(), false).unwrap();
+
+    assert_eq!(
+        mzml.extensions,
+        attrs(&[
+            ("xmlns:acme", "urn:acme"),
+            ("acme:build", "4<2"),
+            ("accession", "test accession"),
+        ])
+    );
+    assert_eq!(
+        mzml.file_description.extensions.elements,
+        vec!["<acme:note/>".to_string()]
+    );
+
+    let spectra = &mzml.run.spectrum_list.as_ref().unwrap().spectra;
+    assert_eq!(spectra[0].extensions, attrs(&[("acme:tag", "first")]));
+    let rows = rows_of(&spectra[0]);
+    assert_eq!(rows.len(), 2, "{rows:?}");
+    assert!(rows[1].contains("acme:focus"), "{rows:?}");
+    assert!(
+        rows[1].starts_with(r#"{"path":"spectrumDescription/scanList/scan[0]""#),
+        "{rows:?}"
+    );
+    assert!(rows_of(&spectra[1]).is_empty());
+}
+
+#[test]
+fn mzml_round_trip_keeps_extensions() {
+    let mzml = parse_mzml(&extended(), false).unwrap();
+
+    let xml = bin_to_mzml(&mzml).unwrap();
+    assert!(xml.contains(r#"acme:build="4&lt;2""#), "{xml}");
+    assert!(xml.contains(FOCUS), "{xml}");
+
+    let again = parse_mzml(xml.as_bytes(), false).unwrap();
+    assert_eq!(all_rows(&again), all_rows(&mzml));
+}
+
+#[test]
+fn b000_round_trip_keeps_extensions() {
+    let mzml = parse_mzml(&extended(), false).unwrap();
+    let decoded = decode(&encode(&mzml, 3, false)).unwrap();
+
+    assert_eq!(decoded.extensions, mzml.extensions);
+    assert_eq!(
+        decoded.file_description.extensions,
+        mzml.file_description.extensions
+    );
+
+    let expected = &mzml.run.spectrum_list.as_ref().unwrap().spectra;
+    let got = &decoded.run.spectrum_list.as_ref().unwrap().spectra;
+    assert_eq!(got[0].extensions, expected[0].extensions);
+    assert_eq!(rows_of(&got[0]), rows_of(&expected[0]));
+    assert!(rows_of(&got[1]).is_empty());
+
+    let xml = bin_to_mzml(&decoded).unwrap();
+    assert!(xml.contains(FOCUS), "{xml}");
+}
+
+#[test]
+fn reader_captures_item_extensions() {
+    let xml = extended();
+    let expected = parse_mzml(&xml, false).unwrap();
+
+    let mut reader = MzMLReader::new(&xml[..]).unwrap();
+    assert_eq!(reader.header().extensions, expected.extensions);
+    let spectra: Vec<_> = reader.spectra().collect::<Result<_, _>>().unwrap();
+    assert_eq!(
+        format!("{spectra:?}"),
+        format!("{:?}", expected.run.spectrum_list.as_ref().unwrap().spectra)
+    );
+}
@@ -0,0 +1,98 @@
+use std::io::{BufReader, Cursor, Read};
+
+use miniz_oxide::deflate::compress_to_vec;
+
+use crate::{
+    mzml::{
+        gzip::{GzipReader, MaybeGzip, crc32_update, is_gzip, maybe_gunzip},
+        parse_mzml::parse_mzml,
+        reader::MzMLReader,
+    },
+    utilities::test::load_mzml_bytes,
+};
+
+const PATH: &str = "data/mzml/tiny.pwiz.mzML0.99.10.mzML";
+
+/// One gzip member, with a file name in the header like `gzip` writes.
+pub(super) fn gzip_member(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x1f, 0x8b, 8, 0x08, 0, 0, 0, 0, 0, 3];
+    out.extend_from_slice(b"tiny.mzML\0");
+    out.extend_from_slice(&compress_to_vec(data, 6));
+    out.extend_from_slice(&crc32_update(0, data).to_le_bytes());
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out
+}
+
+#[test]
+fn crc32_matches_reference() {
+    assert_eq!(crc32_update(0, b"123456789"), 0xCBF4_3926);
+    assert_eq!(
+        crc32_update(crc32_update(0, b"1234"), b"56789"),
+        0xCBF4_3926
+    );
+}
+
+#[test]
+fn parse_mzml_accepts_gzip() {
+    let plain = load_mzml_bytes(PATH);
+    let gz = gzip_member(&plain);
+    assert!(is_gzip(&gz));
+
+    let expected = parse_mzml(&plain, false).unwrap();
+    let got = parse_mzml(&gz, false).unwrap();
+    assert_eq!(format!("{got:?}"), format!("{expected:?}"));
+}
+
+#[test]
+fn reader_streams_gzip() {
+    let plain = load_mzml_bytes(PATH);
+    let gz = gzip_member(&plain);
+    let expected = parse_mzml(&plain, false).unwrap();
+
+    let source = MaybeGzip::new(BufReader::with_capacity(64, Cursor::new(gz))).unwrap();
+    assert!(source.is_gzip());
+    let mut reader = MzMLReader::new(source).unwrap();
+    let spectra: Vec<_> = reader.spectra().collect::<Result<_, _>>().unwrap();
+
+    let want = &expected.run.spectrum_list.as_ref().unwrap().spectra;
+    assert_eq!(format!("{spectra:?}"), format!("{want:?}"));
+}
+
+#[test]
+fn plain_input_passes_through() {
+    let plain = load_mzml_bytes(PATH);
+    assert!(!MaybeGzip::new(Cursor::new(&plain[..])).unwrap().is_gzip());
+    assert!(matches!(
+        maybe_gunzip(&plain).unwrap(),
+        std::borrow::Cow::Borrowed(_)
+    ));
+}
+
+#[test]
+fn multi_member_and_large_output() {
+    let data: Vec<u8> = (0..300_000u32).map(|i| (i % 251) as u8).collect();
+    let (a, b) = data.split_at(123_457);
+    let mut gz = gzip_member(a);
+    gz.extend_from_slice(&gzip_member(b));
+    gz.extend_from_slice(&[0, 0, 0, 0]);
+
+    let mut out = Vec::new();
+    GzipReader::new(BufReader::with_capacity(7, Cursor::new(gz)))
+        .read_to_end(&mut out)
+        .unwrap();
+    assert_eq!(out, data);
+}
+
+#[test]
+fn corrupt_or_truncated_input_is_an_error() {
+    let plain = load_mzml_bytes(PATH);
+    let gz = gzip_member(&plain);
+
+    let mut bad_crc = gz.clone();
+    let n = bad_crc.len();
+    bad_crc[n - 8] ^= 0xff;
+    assert!(maybe_gunzip(&bad_crc).unwrap_err().contains("CRC-32"));
+
+    assert!(maybe_gunzip(&gz[..gz.len() / 2]).is_err());
+    assert!(parse_mzml(&gz[..5], false).is_err());
+}
@@ -0,0 +1,243 @@
+use std::{
+    cell::Cell,
+    io::{Cursor, Read, Seek, SeekFrom},
+    rc::Rc,
+};
+
+use crate::{
+    mzml::{
+        indexed::{IndexedMzML, scan_tags},
+        parse_mzml::parse_mzml,
+        structs::MzML,
+    },
+    utilities::test::load_mzml_bytes,
+};
+
+fn spectrum_ids(mzml: &MzML) -> Vec<String> {
+    let list = mzml.run.spectrum_list.as_ref().unwrap();
+    list.spectra.iter().map(|s| s.id.clone()).collect()
+}
+
+fn chromatogram_ids(mzml: &MzML) -> Vec<String> {
+    let list = mzml.run.chromatogram_list.as_ref().unwrap();
+    list.chromatograms.iter().map(|c| c.id.clone()).collect()
+}
+
+fn element_offset(bytes: &[u8], tag: &str, id: &str) -> usize {
+    let needle = format!("<{tag} ");
+    let id_attr = format!("id=\"{id}\"");
+    let at = bytes
+        .windows(id_attr.len())
+        .position(|w| w == id_attr.as_bytes())
+        .unwrap();
+    bytes[..at]
+        .windows(needle.len())
+        .rposition(|w| w == needle.as_bytes())
+        .unwrap()
+}
+
+/// Replaces the `<indexList>` of `bytes` with one pointing `shift` bytes past
+/// the real offsets.
+pub(super) fn with_index(bytes: &[u8], shift: usize) -> Vec<u8> {
+    let mzml = parse_mzml(bytes, false).unwrap();
+    let end = b"</mzML>";
+    let cut = bytes.windows(end.len()).position(|w| w == end).unwrap() + end.len();
+
+    let mut out = bytes[..cut].to_vec();
+    out.extend_from_slice(b"\n");
+    let index_off = out.len();
+
+    let mut list = String::from("<indexList count=\"2\">\n<index name=\"spectrum\">\n");
+    for id in spectrum_ids(&mzml) {
+        let off = element_offset(bytes, "spectrum", &id) + shift;
+        list.push_str(&format!("<offset idRef=\"{id}\">{off}</offset>\n"));
+    }
+    list.push_str("</index>\n<index name=\"chromatogram\">\n");
+    for id in chromatogram_ids(&mzml) {
+        let off = element_offset(bytes, "chromatogram", &id) + shift;
+        list.push_str(&format!("<offset idRef=\"{id}\">{off}</offset>\n"));
+    }
+    list.push_str("</index>\n</indexList>\n");
+    list.push_str(&format!(
+        "<indexListOffset>{index_off}</indexListOffset>\n</indexedmzML>\n"
+    ));
+
+    out.extend_from_slice(list.as_bytes());
+    out
+}
+
+fn assert_items_match(indexed: &mut IndexedMzML<Cursor<Vec<u8>>>, full: &MzML) {
+    let spectra = &full.run.spectrum_list.as_ref().unwrap().spectra;
+    assert_eq!(indexed.len(), spectra.len());
+    for (i, expected) in spectra.iter().enumerate().rev() {
+        let by_ordinal = indexed.spectrum(i).unwrap();
+        assert_eq!(format!("{by_ordinal:?}"), format!("{expected:?}"));
+        let by_id = indexed.spectrum_by_id(&expected.id).unwrap();
+        assert_eq!(format!("{by_id:?}"), format!("{expected:?}"));
+    }
+
+    let chromatograms = full
+        .run
+        .chromatogram_list
+        .as_ref()
+        .map_or(&[][..], |l| &l.chromatograms[..]);
+    assert_eq!(indexed.chromatogram_count(), chromatograms.len());
+    for (i, expected) in chromatograms.iter().enumerate() {
+        let by_ordinal = indexed.chromatogram(i).unwrap();
+        assert_eq!(format!("{by_ordinal:?}"), format!("{expected:?}"));
+        let by_id = indexed.chromatogram_by_id(&expected.id).unwrap();
+        assert_eq!(format!("{by_id:?}"), format!("{expected:?}"));
+    }
+}
+
+#[test]
+fn valid_index_is_used_without_scanning() {
+    let bytes = with_index(&load_mzml_bytes("data/mzml/tiny.pwiz.mzML0.99.10.mzML"), 0);
+    let full = parse_mzml(&bytes, false).unwrap();
+
+    let mut indexed = IndexedMzML::new(Cursor::new(bytes)).unwrap();
+    assert!(!indexed.index_rebuilt());
+    assert_items_match(&mut indexed, &full);
+    assert!(!indexed.index_rebuilt());
+}
+
+#[test]
+fn wrong_offsets_fall_back_to_scanning() {
+    let bytes = with_index(&load_mzml_bytes("data/mzml/tiny.pwiz.mzML0.99.10.mzML"), 7);
+    let full = parse_mzml(&bytes, false).unwrap();
+
+    let mut indexed = IndexedMzML::new(Cursor::new(bytes)).unwrap();
+    assert!(!indexed.index_rebuilt());
+    assert_items_match(&mut indexed, &full);
+    assert!(indexed.index_rebuilt());
+}
+
+#[test]
+fn gt_inside_attribute_values_does_not_end_the_tag() {
+    let xml =
+        br#"<?xml version="1.0"?><!-- it's > here --><a x="1->2"><b y='>'/><![CDATA[<c>]]></a>"#;
+    let tags: Vec<_> = scan_tags(xml)
+        .map(|t| {
+            (
+                std::str::from_utf8(t.name).unwrap(),
+                t.closing,
+                &xml[t.start..t.end],
+            )
+        })
+        .collect();
+    assert_eq!(
+        tags,
+        [
+            ("a", false, &br#"<a x="1->2">"#[..]),
+            ("b", false, &br#"<b y='>'/>"#[..]),
+            ("a", true, &b"</a>"[..]),
+        ]
+    );
+}
+
+#[test]
+fn unreachable_index_list_offset_falls_back_to_scanning() {
+    // The offsets in this fixture were taken from a much larger file.
+    let bytes = load_mzml_bytes("data/mzml/test.mzML");
+    let full = parse_mzml(&bytes, false).unwrap();
+
+    let mut indexed = IndexedMzML::new(Cursor::new(bytes)).unwrap();
+    assert_items_match(&mut indexed, &full);
+    assert!(indexed.index_rebuilt());
+}
+
+#[test]
+fn missing_index_is_built_by_scanning() {
+    let bytes = load_mzml_bytes("data/mzml/tiny1.mzML0.99.0.mzML");
+    let full = parse_mzml(&bytes, false).unwrap();
+
+    let mut indexed = IndexedMzML::new(Cursor::new(bytes)).unwrap();
+    assert!(indexed.index_rebuilt());
+    assert_items_match(&mut indexed, &full);
+}
+
+#[test]
+fn unknown_items_are_errors() {
+    let bytes = load_mzml_bytes("data/mzml/tiny.pwiz.mzML0.99.10.mzML");
+    let mut indexed = IndexedMzML::new(Cursor::new(bytes)).unwrap();
+    assert!(indexed.spectrum_by_id("no such spectrum").is_err());
+    assert!(indexed.spectrum(indexed.len()).is_err());
+    assert!(indexed.chromatogram(indexed.chromatogram_count()).is_err());
+}
+
+#[test]
+fn out_of_range_ordinals_do_not_rescan_a_valid_index() {
+    let bytes = with_index(&load_mzml_bytes("data/mzml/tiny.pwiz.mzML0.99.10.mzML"), 0);
+    let mut indexed = IndexedMzML::new(Cursor::new(bytes)).unwrap();
+    assert!(indexed.spectrum(indexed.len()).is_err());
+    assert!(indexed.chromatogram(indexed.chromatogram_count()).is_err());
+    assert!(!indexed.index_rebuilt());
+}
+
+/// Source recording the longest single read.
+struct LongestRead {
+    inner: Cursor<Vec<u8>>,
+    longest: Rc<Cell<usize>>,
+}
+
+impl Read for LongestRead {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.longest.set(self.longest.get().max(n));
+        Ok(n)
+    }
+}
+
+impl Seek for LongestRead {
+    fn seek(&mut self, pos: SeekFrom) -> std::io::Result<u64> {
+        self.inner.seek(pos)
+    }
+}
+
+#[test]
+fn stale_index_list_offset_is_not_read_to_the_end() {
+    let xml = String::from_utf8(load_mzml_bytes("data/mzml/tiny.pwiz.mzML0.99.10.mzML")).unwrap();
+    let padding = format!("<!--{}-->", " ".repeat(1 << 20));
+    let xml = xml.replacen("<mzML", &format!("{padding}\n<mzML"), 1);
+    let bytes = with_index(xml.as_bytes(), 0);
+    let text = String::from_utf8(bytes.clone()).unwrap();
+    let at = text.find("<indexListOffset>").unwrap() + "<indexListOffset>".len();
+    let end = at + text[at..].find('<').unwrap();
+    let stale = format!("{}0{}", &text[..at], &text[end..]).into_bytes();
+    let full = parse_mzml(&stale, false).unwrap();
+
+    let longest = Rc::new(Cell::new(0));
+    let source = LongestRead {
+        inner: Cursor::new(stale),
+        longest: Rc::clone(&longest),
+    };
+    let mut indexed = IndexedMzML::new(source).unwrap();
+    assert!(indexed.index_rebuilt());
+    assert!(
+        longest.get() <= 64 * 1024,
+        "read {} bytes at once",
+        longest.get()
+    );
+
+    let spectra = &full.run.spectrum_list.as_ref().unwrap().spectra;
+    assert_eq!(indexed.spectrum(1).unwrap().id, spectra[1].id);
+}
+
+#[test]
+fn index_list_longer_than_one_read_is_loaded() {
+    let bytes = with_index(&load_mzml_bytes("data/mzml/tiny.pwiz.mzML0.99.10.mzML"), 0);
+    let padding = format!("<!--{}-->", " ".repeat(200 * 1024));
+    let bytes = String::from_utf8(bytes)
+        .unwrap()
+        .replacen(
+            "<index name=\"chromatogram\">",
+            &format!("{padding}<index name=\"chromatogram\">"),
+            1,
+        )
+        .into_bytes();
+    let full = parse_mzml(&bytes, false).unwrap();
+
+    let mut indexed = IndexedMzML::new(Cursor::new(bytes)).unwrap();
+    assert_items_match(&mut indexed, &full);
+    assert!(!indexed.index_rebuilt());
+}
@@ -0,0 +1,129 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+
+use crate::{
+    mzml::{
+        ParseError,
+        lenient::parse_mzml_lenient,
+        parse_mzml::parse_mzml,
+        tests::{
+            gzip::gzip_member,
+            parse_error::{BAD_PAYLOAD, document},
+        },
+    },
+    utilities::test::load_mzml_bytes,
+};
+
+const PATH: &str = "data/mzml/tiny.pwiz.mzML0.99.10.mzML";
+
+/// Offset of the `n`-th (0-based) occurrence of `needle`.
+fn nth(bytes: &[u8], needle: &[u8], n: usize) -> usize {
+    bytes
+        .windows(needle.len())
+        .enumerate()
+        .filter(|(_, w)| *w == needle)
+        .nth(n)
+        .unwrap()
+        .0
+}
+
+#[test]
+fn intact_file_matches_strict_parse() {
+    let bytes = load_mzml_bytes(PATH);
+    let expected = parse_mzml(&bytes, false).unwrap();
+
+    let (mzml, recovery) = parse_mzml_lenient(&bytes);
+    assert_eq!(format!("{mzml:?}"), format!("{expected:?}"));
+    assert!(recovery.is_clean(), "{recovery:?}");
+    assert_eq!((recovery.spectra, recovery.chromatograms), (2, 2));
+    assert_eq!(recovery.recovered_bytes, bytes.len() as u64);
+    assert_eq!(recovery.recovered_fraction(), 1.0);
+}
+
+#[test]
+fn truncated_file_keeps_complete_spectra() {
+    let bytes = load_mzml_bytes(PATH);
+    let expected = parse_mzml(&bytes, false).unwrap();
+    let expected_first = &expected.run.spectrum_list.as_ref().unwrap().spectra[0];
+
+    let second = nth(&bytes, b"<spectrum ", 1);
+    let cut = &bytes[..second + (nth(&bytes, b"</spectrum>", 1) - second) / 2];
+    let first_end = nth(&bytes, b"</spectrum>", 0) + b"</spectrum>".len();
+
+    let (mzml, recovery) = parse_mzml_lenient(cut);
+    let spectra = &mzml.run.spectrum_list.as_ref().unwrap().spectra;
+    assert_eq!(spectra.len(), 1);
+    assert_eq!(format!("{:?}", spectra[0]), format!("{expected_first:?}"));
+    assert_eq!(
+        format!("{:?}", mzml.instrument_list),
+        format!("{:?}", expected.instrument_list)
+    );
+
+    assert!(!recovery.complete);
+    assert_eq!((recovery.spectra, recovery.dropped_spectra), (1, 1));
+    assert_eq!(recovery.recovered_bytes, first_end as u64);
+    assert_eq!(recovery.total_bytes, cut.len() as u64);
+    assert!(recovery.recovered_fraction() < 1.0);
+
+    let [e] = &recovery.diagnostics[..] else {
+        panic!("{:?}", recovery.diagnostics);
+    };
+    assert!(matches!(e, ParseError::Xml { .. }), "{e}");
+    assert_eq!(e.offset(), cut.len() as u64);
+    assert!(e.path().starts_with("run/spectrumList/spectrum[id="), "{e}");
+}
+
+#[test]
+fn corrupt_spectrum_is_dropped_and_the_rest_kept() {
+    let xml = document(BAD_PAYLOAD);
+    assert!(parse_mzml(xml.as_bytes(), false).is_err());
+
+    let (mzml, recovery) = parse_mzml_lenient(xml.as_bytes());
+    let spectra = &mzml.run.spectrum_list.as_ref().unwrap().spectra;
+    assert_eq!(spectra.len(), 1);
+    assert_eq!(spectra[0].id, "scan=1");
+
+    assert!(recovery.complete && !recovery.is_clean());
+    assert_eq!((recovery.spectra, recovery.dropped_spectra), (1, 1));
+    assert_eq!(recovery.recovered_bytes, xml.len() as u64);
+
+    let [e] = &recovery.diagnostics[..] else {
+        panic!("{:?}", recovery.diagnostics);
+    };
+    assert!(matches!(e, ParseError::Binary { .. }), "{e}");
+    assert_eq!(
+        e.path(),
+        "run/spectrumList/spectrum[id=scan=2]/binaryDataArrayList/binaryDataArray[2]"
+    );
+}
+
+#[test]
+fn malformed_spectrum_does_not_hide_the_next_one() {
+    let good = STANDARD.encode(2.5f64.to_le_bytes());
+    let xml = document(&good).replacen("</binaryDataArrayList>", "</binaryDataArray>", 1);
+
+    let (mzml, recovery) = parse_mzml_lenient(xml.as_bytes());
+    let spectra = &mzml.run.spectrum_list.as_ref().unwrap().spectra;
+    assert_eq!(spectra.len(), 1);
+    assert_eq!(spectra[0].id, "scan=2");
+    assert_eq!(recovery.dropped_spectra, 1);
+    assert!(matches!(recovery.diagnostics[0], ParseError::Xml { .. }));
+    assert!(
+        recovery.diagnostics[0]
+            .path()
+            .contains("spectrum[id=scan=1]")
+    );
+}
+
+#[test]
+fn truncated_gzip_is_inflated_as_far_as_possible() {
+    let bytes = load_mzml_bytes(PATH);
+    let gz = gzip_member(&bytes);
+    let cut = &gz[..gz.len() * 3 / 4];
+    assert!(parse_mzml(cut, false).is_err());
+
+    let (mzml, recovery) = parse_mzml_lenient(cut);
+    assert!(!recovery.complete);
+    assert!(matches!(recovery.diagnostics[0], ParseError::Io { .. }));
+    assert!(recovery.total_bytes > 0 && recovery.total_bytes < bytes.len() as u64);
+    assert!(mzml.instrument_list.is_some());
+}
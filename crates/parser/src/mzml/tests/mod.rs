@@ -1,3 +1,17 @@
+mod binary_types;
+mod checksum;
+mod extensions;
+pub(super) mod gzip;
+pub(super) mod indexed;
+mod lenient;
+mod namespaces;
+mod numpress;
+mod parallel;
+pub(super) mod parse_error;
+mod provenance;
+mod reader;
+mod reindex;
+mod sample_software;
 mod test_mzml;
 mod tiny_msdata_mzml0_99_10;
 mod tiny_msdata_mzml0_99_9;
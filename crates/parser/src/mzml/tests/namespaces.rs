@@ -0,0 +1,119 @@
+use std::io::Cursor;
+
+use crate::{
+    mzml::{
+        indexed::IndexedMzML, parse_mzml::parse_mzml, reader::MzMLReader,
+        tests::indexed::with_index,
+    },
+    utilities::test::load_mzml_bytes,
+};
+
+const PATH: &str = "data/mzml/tiny.pwiz.mzML0.99.10.mzML";
+
+/// Moves every element of `xml` into the `mzml:` prefix.
+fn prefixed(xml: &[u8]) -> Vec<u8> {
+    let xml = std::str::from_utf8(xml)
+        .unwrap()
+        .replace("xmlns=", "xmlns:mzml=");
+    let mut out = String::with_capacity(xml.len() * 2);
+    let mut rest = xml.as_str();
+    while let Some(lt) = rest.find('<') {
+        out.push_str(&rest[..=lt]);
+        rest = &rest[lt + 1..];
+        if rest.starts_with('/') {
+            out.push('/');
+            rest = &rest[1..];
+        }
+        if !rest.starts_with(['?', '!']) {
+            out.push_str("mzml:");
+        }
+    }
+    out.push_str(rest);
+    out.into_bytes()
+}
+
+#[test]
+fn prefixed_elements_parse_like_unprefixed() {
+    let plain = load_mzml_bytes(PATH);
+    let xml = prefixed(&plain);
+    assert!(xml.windows(14).any(|w| w == b"<mzml:spectrum"));
+
+    let expected = parse_mzml(&plain, false).unwrap();
+    let got = parse_mzml(&xml, false).unwrap();
+    assert_eq!(format!("{got:?}"), format!("{expected:?}"));
+    assert!(!got.run.spectrum_list.as_ref().unwrap().spectra.is_empty());
+}
+
+#[test]
+fn reader_handles_prefixed_elements() {
+    let plain = load_mzml_bytes(PATH);
+    let expected = parse_mzml(&plain, false).unwrap();
+
+    let xml = prefixed(&plain);
+    let mut reader = MzMLReader::new(&xml[..]).unwrap();
+    let spectra: Vec<_> = reader.spectra().collect::<Result<_, _>>().unwrap();
+    let chromatograms: Vec<_> = reader.chromatograms().collect::<Result<_, _>>().unwrap();
+
+    let run = &expected.run;
+    assert_eq!(
+        format!("{spectra:?}"),
+        format!("{:?}", run.spectrum_list.as_ref().unwrap().spectra)
+    );
+    assert_eq!(
+        format!("{chromatograms:?}"),
+        format!(
+            "{:?}",
+            run.chromatogram_list.as_ref().unwrap().chromatograms
+        )
+    );
+}
+
+#[test]
+fn indexed_reader_handles_prefixed_index_list() {
+    let bytes = with_index(&load_mzml_bytes(PATH), 0);
+    let expected = parse_mzml(&bytes, false).unwrap();
+
+    // Prefix only the index so the element offsets stay valid.
+    let end = b"</mzML>";
+    let cut = bytes.windows(end.len()).position(|w| w == end).unwrap() + end.len();
+    let mut xml = bytes[..cut].to_vec();
+    xml.extend_from_slice(&prefixed(&bytes[cut..]));
+
+    let mut indexed = IndexedMzML::new(Cursor::new(xml)).unwrap();
+    assert_eq!(indexed.len(), 2);
+    let id = &expected.run.spectrum_list.as_ref().unwrap().spectra[1].id;
+    assert_eq!(&indexed.spectrum_by_id(id).unwrap().id, id);
+    assert!(!indexed.index_rebuilt());
+}
+
+#[test]
+fn indexed_reader_scans_prefixed_documents() {
+    let plain = load_mzml_bytes(PATH);
+    let expected = parse_mzml(&plain, false).unwrap();
+
+    let mut indexed = IndexedMzML::new(Cursor::new(prefixed(&plain))).unwrap();
+    let want = &expected.run.spectrum_list.as_ref().unwrap().spectra;
+    assert_eq!(indexed.len(), want.len());
+    for (i, s) in want.iter().enumerate() {
+        assert_eq!(
+            format!("{:?}", indexed.spectrum(i).unwrap()),
+            format!("{s:?}")
+        );
+    }
+}
+
+#[test]
+fn prefixed_attributes_do_not_stand_in_for_mzml_ones() {
+    let plain = String::from_utf8(load_mzml_bytes(PATH)).unwrap();
+    let xml = plain.replacen(
+        r#"<spectrum index="0" id="S19""#,
+        r#"<spectrum xmlns:acme="urn:acme" acme:id="vendor-1" index="0" id="S19""#,
+        1,
+    );
+    assert_ne!(xml, plain);
+
+    let mzml = parse_mzml(xml.as_bytes(), false).unwrap();
+    let spectrum = &mzml.run.spectrum_list.as_ref().unwrap().spectra[0];
+    assert_eq!(spectrum.id, "S19");
+    assert_eq!(spectrum.index, Some(0));
+}
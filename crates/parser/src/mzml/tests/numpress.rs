@@ -0,0 +1,192 @@
+use std::sync::OnceLock;
+
+use crate::{
+    BinaryData, BinaryDataArray, CvParam,
+    mzml::{
+        bin_to_mzml::bin_to_mzml,
+        numpress::{
+            Numpress, decode_linear, decode_pic, decode_slof, encode_linear, encode_pic,
+            encode_slof, optimal_linear_fixed_point, optimal_slof_fixed_point,
+        },
+        parse_mzml::parse_mzml,
+        structs::MzML,
+    },
+    utilities::test::mzml,
+};
+
+static MZML_CACHE: OnceLock<MzML> = OnceLock::new();
+
+const PATH: &str = "data/mzml/test.mzML";
+
+fn values(bda: &BinaryDataArray) -> Vec<f64> {
+    match bda.binary.as_ref().expect("binary decoded") {
+        BinaryData::F64(v) => v.clone(),
+        BinaryData::F32(v) => v.iter().map(|&x| x as f64).collect(),
+        BinaryData::I64(v) => v.iter().map(|&x| x as f64).collect(),
+        BinaryData::I32(v) => v.iter().map(|&x| x as f64).collect(),
+        BinaryData::I16(v) => v.iter().map(|&x| x as f64).collect(),
+    }
+}
+
+fn has_acc(bda: &BinaryDataArray, acc: &str) -> bool {
+    bda.cv_params
+        .iter()
+        .any(|p| p.accession.as_deref() == Some(acc))
+}
+
+fn set_compression(bda: &mut BinaryDataArray, accessions: &[&str]) {
+    bda.cv_params.retain(|p| {
+        !matches!(
+            p.accession.as_deref(),
+            Some("MS:1000574") | Some("MS:1000576")
+        )
+    });
+    for acc in accessions {
+        bda.cv_params.push(CvParam {
+            cv_ref: Some("MS".to_string()),
+            accession: Some(acc.to_string()),
+            name: acc.to_string(),
+            value: Some(String::new()),
+            ..Default::default()
+        });
+    }
+}
+
+fn all_arrays(mzml: &MzML) -> Vec<&BinaryDataArray> {
+    let spectra = mzml.run.spectrum_list.iter().flat_map(|l| &l.spectra);
+    let chromatograms = mzml
+        .run
+        .chromatogram_list
+        .iter()
+        .flat_map(|l| &l.chromatograms);
+    spectra
+        .filter_map(|s| s.binary_data_array_list.as_ref())
+        .chain(chromatograms.filter_map(|c| c.binary_data_array_list.as_ref()))
+        .flat_map(|l| &l.binary_data_arrays)
+        .collect()
+}
+
+#[test]
+fn int_nibbles_match_reference_layout() {
+    // 1 -> head 7 + one nibble, 0 -> head 8, then a padding nibble.
+    assert_eq!(encode_pic(&[1.0, 0.0]).unwrap(), vec![0x71, 0x80]);
+    assert_eq!(decode_pic(&[0x71, 0x80]).unwrap(), vec![1.0, 0.0]);
+
+    // A residual of -1 is all ones: head 15 + one nibble.
+    let bytes = encode_linear(&[1.0, 2.0, 2.0], 1.0).unwrap();
+    assert_eq!(&bytes[..8], &1.0f64.to_be_bytes());
+    assert_eq!(&bytes[8..16], &[1, 0, 0, 0, 2, 0, 0, 0]);
+    assert_eq!(&bytes[16..], &[0xff]);
+    assert_eq!(decode_linear(&bytes).unwrap(), vec![1.0, 2.0, 2.0]);
+}
+
+#[test]
+fn codecs_round_trip_within_precision() {
+    let mz: Vec<f64> = (0..500).map(|i| 100.0 + i as f64 * 0.731_234_5).collect();
+    let fp = optimal_linear_fixed_point(&mz);
+    let back = decode_linear(&encode_linear(&mz, fp).unwrap()).unwrap();
+    assert_eq!(back.len(), mz.len());
+    for (a, b) in back.iter().zip(&mz) {
+        assert!((a - b).abs() <= 1.0 / fp, "{a} vs {b}");
+    }
+
+    let counts: Vec<f64> = (0..300).map(|i| ((i * 7919) % 100_003) as f64).collect();
+    assert_eq!(decode_pic(&encode_pic(&counts).unwrap()).unwrap(), counts);
+
+    let intensities: Vec<f64> = (0..300).map(|i| (i as f64 * 0.37).exp()).collect();
+    let fp = optimal_slof_fixed_point(&intensities);
+    let back = decode_slof(&encode_slof(&intensities, fp).unwrap()).unwrap();
+    for (a, b) in back.iter().zip(&intensities) {
+        assert!((a - b).abs() <= (b + 1.0) / fp, "{a} vs {b}");
+    }
+
+    assert!(encode_pic(&[-3.0]).is_err());
+    assert!(decode_linear(&[0; 5]).is_err());
+    assert!(decode_pic(&[0x05]).is_err());
+}
+
+#[test]
+fn accessions_round_trip() {
+    for codec in [Numpress::Linear, Numpress::Pic, Numpress::Slof] {
+        for zlib in [false, true] {
+            assert_eq!(
+                Numpress::from_accession(codec.accession(zlib)),
+                Some((codec, zlib))
+            );
+        }
+    }
+    assert_eq!(Numpress::from_accession("MS:1000574"), None);
+}
+
+#[test]
+fn mzml_numpress_arrays_write_and_read_back() {
+    let original = mzml(&MZML_CACHE, PATH);
+    let mut mzml = original.clone();
+
+    for s in mzml
+        .run
+        .spectrum_list
+        .iter_mut()
+        .flat_map(|l| &mut l.spectra)
+    {
+        for bda in s
+            .binary_data_array_list
+            .iter_mut()
+            .flat_map(|l| &mut l.binary_data_arrays)
+        {
+            if has_acc(bda, "MS:1000514") {
+                set_compression(bda, &["MS:1002312"]);
+            } else {
+                set_compression(bda, &["MS:1002748"]);
+            }
+        }
+    }
+    for c in mzml
+        .run
+        .chromatogram_list
+        .iter_mut()
+        .flat_map(|l| &mut l.chromatograms)
+    {
+        for bda in c
+            .binary_data_array_list
+            .iter_mut()
+            .flat_map(|l| &mut l.binary_data_arrays)
+        {
+            if has_acc(bda, "MS:1000595") {
+                set_compression(bda, &["MS:1002746"]);
+            } else {
+                // Older writers list numpress and zlib as separate terms.
+                set_compression(bda, &["MS:1002313", "MS:1000574"]);
+            }
+        }
+    }
+
+    let xml = bin_to_mzml(&mzml).unwrap();
+    for acc in ["MS:1002312", "MS:1002748", "MS:1002746", "MS:1002313"] {
+        assert!(xml.contains(acc), "{acc} missing");
+    }
+
+    let reparsed = parse_mzml(xml.as_bytes(), false).unwrap();
+    let before = all_arrays(original);
+    let after = all_arrays(&reparsed);
+    assert_eq!(before.len(), after.len());
+    assert!(!before.is_empty());
+
+    for (want, got) in before.iter().zip(&after) {
+        let (want_v, got_v) = (values(want), values(got));
+        assert_eq!(want_v.len(), got_v.len());
+
+        let tolerance = |x: f64| {
+            if has_acc(got, "MS:1002748") {
+                (x + 1.0) * 2e-4
+            } else if has_acc(got, "MS:1002313") {
+                0.5
+            } else {
+                1e-6 * x.abs().max(1.0)
+            }
+        };
+        for (a, b) in got_v.iter().zip(&want_v) {
+            assert!((a - b).abs() <= tolerance(*b), "{a} vs {b}");
+        }
+    }
+}
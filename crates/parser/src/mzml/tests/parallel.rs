@@ -0,0 +1,153 @@
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+use miniz_oxide::deflate::compress_to_vec_zlib;
+
+use crate::{
+    BinaryData, BinaryDataArray,
+    mzml::{
+        ParseError,
+        parse_mzml::{ParseOptions, parse_mzml_with},
+        tests::parse_error::{BAD_PAYLOAD, document},
+    },
+    utilities::test::load_mzml_bytes,
+};
+
+const PATHS: [&str; 4] = [
+    "data/mzml/test.mzML",
+    "data/mzml/tiny.pwiz.mzML0.99.10.mzML",
+    "data/mzml/tiny1.mzML0.99.0.mzML",
+    "data/mzml/tiny4_LTQ-FT.mzML0.99.1.mzML",
+];
+
+fn with_threads(threads: usize) -> ParseOptions {
+    ParseOptions {
+        threads,
+        ..Default::default()
+    }
+}
+
+/// `n` spectra with zlib-compressed m/z and intensity arrays, and as many
+/// chromatograms.
+fn many_items(n: usize) -> String {
+    let array = |acc: &str, name: &str, values: &[f64]| {
+        let raw: Vec<u8> = values.iter().flat_map(|x| x.to_le_bytes()).collect();
+        let payload = STANDARD.encode(compress_to_vec_zlib(&raw, 6));
+        format!(
+            r#"<binaryDataArray encodedLength="{}">
+              <cvParam cvRef="MS" accession="MS:1000523" name="64-bit float" value=""/>
+              <cvParam cvRef="MS" accession="MS:1000574" name="zlib compression" value=""/>
+              <cvParam cvRef="MS" accession="{acc}" name="{name}" value=""/>
+              <binary>{payload}</binary>
+            </binaryDataArray>"#,
+            payload.len()
+        )
+    };
+    let values = |i: usize| -> Vec<f64> { (0..100 + i).map(|j| (i * 1000 + j) as f64).collect() };
+
+    let spectra: String = (0..n)
+        .map(|i| {
+            format!(
+                r#"<spectrum index="{i}" id="scan={i}" defaultArrayLength="{}">
+          <binaryDataArrayList count="2">{}{}</binaryDataArrayList>
+        </spectrum>"#,
+                values(i).len(),
+                array("MS:1000514", "m/z array", &values(i)),
+                array("MS:1000515", "intensity array", &values(i + n)),
+            )
+        })
+        .collect();
+    let chromatograms: String = (0..n)
+        .map(|i| {
+            format!(
+                r#"<chromatogram index="{i}" id="c{i}" defaultArrayLength="{}">
+          <binaryDataArrayList count="2">{}{}</binaryDataArrayList>
+        </chromatogram>"#,
+                values(i).len(),
+                array("MS:1000595", "time array", &values(i)),
+                array("MS:1000515", "intensity array", &values(i + n)),
+            )
+        })
+        .collect();
+
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<mzML xmlns="http://psi.hupo.org/ms/mzml" version="1.1.0">
+  <run id="r">
+    <spectrumList count="{n}">{spectra}</spectrumList>
+    <chromatogramList count="{n}">{chromatograms}</chromatogramList>
+  </run>
+</mzML>
+"#
+    )
+}
+
+#[test]
+fn thread_count_does_not_change_the_result() {
+    for path in PATHS {
+        let bytes = load_mzml_bytes(path);
+        let inline = parse_mzml_with(&bytes, &with_threads(1)).unwrap();
+        for threads in [2, 8] {
+            let parallel = parse_mzml_with(&bytes, &with_threads(threads)).unwrap();
+            assert_eq!(
+                format!("{parallel:?}"),
+                format!("{inline:?}"),
+                "{path}, {threads} threads"
+            );
+        }
+    }
+}
+
+#[test]
+fn arrays_stay_in_item_order() {
+    let n = 64;
+    let xml = many_items(n);
+    let mzml = parse_mzml_with(xml.as_bytes(), &with_threads(8)).unwrap();
+    assert_eq!(
+        format!("{mzml:?}"),
+        format!(
+            "{:?}",
+            parse_mzml_with(xml.as_bytes(), &with_threads(1)).unwrap()
+        )
+    );
+
+    let spectra = &mzml.run.spectrum_list.as_ref().unwrap().spectra;
+    let chromatograms = &mzml.run.chromatogram_list.as_ref().unwrap().chromatograms;
+    assert_eq!((spectra.len(), chromatograms.len()), (n, n));
+
+    for i in 0..n {
+        assert_eq!(spectra[i].id, format!("scan={i}"));
+        let arrays = &spectra[i].binary_data_array_list.as_ref().unwrap();
+        let [mz, intensity] = &arrays.binary_data_arrays[..] else {
+            panic!("spectrum {i}");
+        };
+        let first = |a: &BinaryDataArray| match a.binary.as_ref() {
+            Some(BinaryData::F64(v)) => (v[0], v.len()),
+            other => panic!("{other:?}"),
+        };
+        assert_eq!(first(mz), ((i * 1000) as f64, 100 + i));
+        assert_eq!(first(intensity), (((i + n) * 1000) as f64, 100 + i + n));
+
+        let arrays = &chromatograms[i].binary_data_array_list.as_ref().unwrap();
+        assert_eq!(
+            first(&arrays.binary_data_arrays[1]).0,
+            ((i + n) * 1000) as f64
+        );
+    }
+}
+
+#[test]
+fn parallel_errors_match_inline_errors() {
+    let xml = document(BAD_PAYLOAD);
+    let inline = parse_mzml_with(xml.as_bytes(), &with_threads(1)).unwrap_err();
+    let parallel = parse_mzml_with(xml.as_bytes(), &with_threads(4)).unwrap_err();
+    assert!(matches!(parallel, ParseError::Binary { .. }), "{parallel}");
+    assert_eq!(parallel, inline);
+}
+
+#[test]
+fn earlier_binary_error_wins_over_later_xml_error() {
+    let xml = document(BAD_PAYLOAD).replace("</spectrumList>", "</chromatogramList>");
+    let inline = parse_mzml_with(xml.as_bytes(), &with_threads(1)).unwrap_err();
+    let parallel = parse_mzml_with(xml.as_bytes(), &with_threads(4)).unwrap_err();
+    assert!(matches!(parallel, ParseError::Binary { .. }), "{parallel}");
+    assert_eq!(parallel, inline);
+}
@@ -0,0 +1,120 @@
+use std::io::Cursor;
+
+use base64::{Engine as _, engine::general_purpose::STANDARD};
+
+use crate::mzml::{ParseError, indexed::IndexedMzML, parse_mzml::parse_mzml, reader::MzMLReader};
+
+pub(super) const BAD_PAYLOAD: &str = "@@not*base64@@";
+
+fn array(acc: &str, name: &str, payload: &str) -> String {
+    format!(
+        r#"<binaryDataArray encodedLength="{len}">
+            <cvParam cvRef="MS" accession="MS:1000523" name="64-bit float" value=""/>
+            <cvParam cvRef="MS" accession="MS:1000576" name="no compression" value=""/>
+            <cvParam cvRef="MS" accession="{acc}" name="{name}" value=""/>
+            <binary>{payload}</binary>
+          </binaryDataArray>"#,
+        len = payload.len()
+    )
+}
+
+fn spectrum(id: &str, intensity: &str) -> String {
+    let mz = STANDARD.encode(1.5f64.to_le_bytes());
+    format!(
+        r#"<spectrum index="0" id="{id}" defaultArrayLength="1">
+        <binaryDataArrayList count="2">
+          {}
+          {}
+        </binaryDataArrayList>
+      </spectrum>"#,
+        array("MS:1000514", "m/z array", &mz),
+        array("MS:1000515", "intensity array", intensity),
+    )
+}
+
+/// Two spectra; the intensity array of the second one is `intensity`.
+pub(super) fn document(intensity: &str) -> String {
+    let good = STANDARD.encode(2.5f64.to_le_bytes());
+    format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<mzML xmlns="http://psi.hupo.org/ms/mzml" version="1.1.0">
+  <run id="r">
+    <spectrumList count="2">
+      {}
+      {}
+    </spectrumList>
+  </run>
+</mzML>
+"#,
+        spectrum("scan=1", &good),
+        spectrum("scan=2", intensity),
+    )
+}
+
+fn assert_bad_payload(err: &ParseError, xml: &str) {
+    assert!(matches!(err, ParseError::Binary { .. }), "{err}");
+    let payload = xml.find(BAD_PAYLOAD).unwrap() as u64;
+    let close = xml[payload as usize..].find("</binary>").unwrap() as u64 + payload;
+    assert!(
+        (payload..=close + 9).contains(&err.offset()),
+        "offset {} outside {payload}..{close}: {err}",
+        err.offset()
+    );
+}
+
+#[test]
+fn bad_base64_reports_array_path_and_offset() {
+    let xml = document(BAD_PAYLOAD);
+    let err = parse_mzml(xml.as_bytes(), false).unwrap_err();
+
+    assert_bad_payload(&err, &xml);
+    assert_eq!(
+        err.path(),
+        "run/spectrumList/spectrum[id=scan=2]/binaryDataArrayList/binaryDataArray[2]"
+    );
+    assert!(err.to_string().contains("spectrum[id=scan=2]"), "{err}");
+}
+
+#[test]
+fn indexed_access_reports_the_same_path() {
+    let xml = document(BAD_PAYLOAD);
+    let mut indexed = IndexedMzML::new(Cursor::new(xml.as_bytes())).unwrap();
+
+    assert!(indexed.spectrum(0).is_ok());
+    let err = indexed.spectrum(1).unwrap_err();
+    assert_bad_payload(&err, &xml);
+    assert_eq!(
+        err.path(),
+        "run/spectrumList/spectrum[id=scan=2]/binaryDataArrayList/binaryDataArray[2]"
+    );
+}
+
+#[test]
+fn reader_reports_the_item_path() {
+    let xml = document(BAD_PAYLOAD);
+    let mut reader = MzMLReader::new(xml.as_bytes()).unwrap();
+
+    assert!(reader.next_spectrum().unwrap().is_some());
+    let err = reader.next_spectrum().unwrap_err();
+    assert_bad_payload(&err, &xml);
+    assert_eq!(err.path(), "run/spectrumList/spectrum[id=scan=2]");
+}
+
+#[test]
+fn malformed_xml_is_an_xml_error() {
+    let xml = document(&STANDARD.encode(2.5f64.to_le_bytes())).replace("</spectrumList>", "");
+    let err = parse_mzml(xml.as_bytes(), false).unwrap_err();
+
+    assert!(matches!(err, ParseError::Xml { .. }), "{err}");
+    assert!(
+        err.offset() > 0 && err.offset() <= xml.len() as u64,
+        "{err}"
+    );
+}
+
+#[test]
+fn valid_document_still_parses() {
+    let xml = document(&STANDARD.encode(2.5f64.to_le_bytes()));
+    let mzml = parse_mzml(xml.as_bytes(), false).unwrap();
+    assert_eq!(mzml.run.spectrum_list.unwrap().spectra.len(), 2);
+}
@@ -0,0 +1,126 @@
+use std::sync::OnceLock;
+
+use crate::{
+    MzML,
+    b64::{EncodeOptions, decode, encode_with},
+    mzml::{
+        bin_to_mzml::{WriteOptions, bin_to_mzml_with},
+        parse_mzml::parse_mzml,
+        provenance::OCTO_VERSION,
+        structs::{DataProcessing, Software},
+    },
+    utilities::test::mzml,
+};
+
+static MZML_CACHE: OnceLock<MzML> = OnceLock::new();
+
+const PATH: &str = "data/mzml/test.mzML";
+
+fn software(mzml: &MzML) -> &[Software] {
+    mzml.software_list
+        .as_ref()
+        .map_or(&[], |l| l.software.as_slice())
+}
+
+fn data_processing(mzml: &MzML) -> &[DataProcessing] {
+    mzml.data_processing_list
+        .as_ref()
+        .map_or(&[], |l| l.data_processing.as_slice())
+}
+
+fn octo(mzml: &MzML) -> Vec<&Software> {
+    software(mzml).iter().filter(|s| s.id == "octo").collect()
+}
+
+fn accessions(dp: &DataProcessing) -> Vec<(&str, &str)> {
+    dp.processing_method[0]
+        .cv_param
+        .iter()
+        .map(|cv| {
+            (
+                cv.accession.as_deref().unwrap_or_default(),
+                cv.value.as_deref().unwrap_or_default(),
+            )
+        })
+        .collect()
+}
+
+fn round_trip(src: &MzML, options: &EncodeOptions) -> MzML {
+    let bytes = encode_with(src, options).expect("encode failed");
+    decode(&bytes).expect("decode failed")
+}
+
+#[test]
+fn encode_appends_conversion_step() {
+    let src = mzml(&MZML_CACHE, PATH);
+    let decoded = round_trip(src, &EncodeOptions::default());
+
+    let sw = octo(&decoded);
+    assert_eq!(sw.len(), 1);
+    assert_eq!(sw[0].version.as_deref(), Some(OCTO_VERSION));
+    assert_eq!(software(&decoded).len(), software(src).len() + 1);
+
+    let dps = data_processing(&decoded);
+    assert_eq!(dps.len(), data_processing(src).len() + 1);
+    let step = dps.last().unwrap();
+    assert_eq!(step.id, "octo_conversion");
+
+    let method = &step.processing_method[0];
+    assert_eq!(method.order, Some(0));
+    assert_eq!(method.software_ref.as_deref(), Some("octo"));
+    assert_eq!(accessions(step), [("MS:1000530", "B000")]);
+    assert_eq!(method.user_param[0].name, "zstd compression level");
+    assert_eq!(method.user_param[0].value.as_deref(), Some("12"));
+}
+
+#[test]
+fn f32_narrowing_is_flagged() {
+    let src = mzml(&MZML_CACHE, PATH);
+    let decoded = round_trip(src, &EncodeOptions::new(3, true));
+
+    let step = data_processing(&decoded).last().unwrap();
+    assert_eq!(
+        accessions(step),
+        [("MS:1000530", "B000"), ("MS:1003092", "29")]
+    );
+    assert_eq!(
+        step.processing_method[0].user_param[0].value.as_deref(),
+        Some("3")
+    );
+}
+
+#[test]
+fn provenance_can_be_turned_off() {
+    let src = mzml(&MZML_CACHE, PATH);
+    let options = EncodeOptions {
+        provenance: false,
+        ..EncodeOptions::default()
+    };
+    let decoded = round_trip(src, &options);
+    assert!(octo(&decoded).is_empty());
+    assert_eq!(data_processing(&decoded).len(), data_processing(src).len());
+
+    let xml = bin_to_mzml_with(&decoded, &WriteOptions { provenance: false }).unwrap();
+    assert!(!xml.contains("octo"));
+}
+
+#[test]
+fn bin_to_mzml_reuses_octo_software() {
+    let decoded = round_trip(mzml(&MZML_CACHE, PATH), &EncodeOptions::default());
+
+    let xml = bin_to_mzml_with(&decoded, &WriteOptions::default()).unwrap();
+    let reparsed = parse_mzml(xml.as_bytes(), false).expect("reparse failed");
+
+    assert_eq!(octo(&reparsed).len(), 1);
+
+    let dps = data_processing(&reparsed);
+    let steps: Vec<&str> = dps.iter().map(|dp| dp.id.as_str()).collect();
+    assert!(steps.ends_with(&["octo_conversion", "octo_conversion_1"]));
+
+    let step = dps.last().unwrap();
+    assert_eq!(accessions(step), [("MS:1000544", "")]);
+    assert_eq!(
+        step.processing_method[0].software_ref.as_deref(),
+        Some("octo")
+    );
+}
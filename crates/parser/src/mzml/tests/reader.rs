@@ -0,0 +1,109 @@
+use std::io::{BufReader, Cursor};
+
+use crate::{
+    mzml::{parse_mzml::parse_mzml, reader::MzMLReader, structs::MzML},
+    utilities::test::load_mzml_bytes,
+};
+
+const PATHS: [&str; 3] = [
+    "data/mzml/test.mzML",
+    "data/mzml/tiny.pwiz.mzML0.99.10.mzML",
+    "data/mzml/tiny4_LTQ-FT.mzML0.99.1.mzML",
+];
+
+fn header_text(mzml: &MzML) -> String {
+    format!(
+        "{:?} {:?} {:?} {:?} {:?} {:?} {:?} {:?}",
+        mzml.cv_list,
+        mzml.file_description,
+        mzml.referenceable_param_group_list,
+        mzml.sample_list,
+        mzml.instrument_list,
+        mzml.software_list,
+        mzml.data_processing_list,
+        mzml.scan_settings_list,
+    )
+}
+
+fn run_text(mzml: &MzML) -> String {
+    let run = &mzml.run;
+    format!(
+        "{} {:?} {:?} {:?} {:?} {:?} {:?} {:?}",
+        run.id,
+        run.start_time_stamp,
+        run.default_instrument_configuration_ref,
+        run.default_source_file_ref,
+        run.sample_ref,
+        run.cv_params,
+        run.user_params,
+        run.source_file_ref_list,
+    )
+}
+
+#[test]
+fn streamed_items_match_parse_mzml() {
+    for path in PATHS {
+        let bytes = load_mzml_bytes(path);
+        let full = parse_mzml(&bytes, false).unwrap();
+
+        // A tiny buffer makes every element span several reads.
+        let source = BufReader::with_capacity(16, Cursor::new(bytes));
+        let mut reader = MzMLReader::new(source).unwrap();
+        assert_eq!(header_text(reader.header()), header_text(&full), "{path}");
+
+        let spectra: Vec<String> = reader
+            .spectra()
+            .map(|s| format!("{:?}", s.unwrap()))
+            .collect();
+        let expected: Vec<String> = full
+            .run
+            .spectrum_list
+            .iter()
+            .flat_map(|l| &l.spectra)
+            .map(|s| format!("{s:?}"))
+            .collect();
+        assert_eq!(spectra, expected, "{path}");
+
+        let chromatograms: Vec<String> = reader
+            .chromatograms()
+            .map(|c| format!("{:?}", c.unwrap()))
+            .collect();
+        let expected: Vec<String> = full
+            .run
+            .chromatogram_list
+            .iter()
+            .flat_map(|l| &l.chromatograms)
+            .map(|c| format!("{c:?}"))
+            .collect();
+        assert_eq!(chromatograms, expected, "{path}");
+
+        let header = reader.into_header();
+        assert_eq!(run_text(&header), run_text(&full), "{path}");
+        assert_eq!(
+            header.run.spectrum_list.as_ref().map(|l| l.count),
+            full.run.spectrum_list.as_ref().map(|l| l.count),
+            "{path}"
+        );
+        assert_eq!(
+            header.run.chromatogram_list.as_ref().map(|l| l.count),
+            full.run.chromatogram_list.as_ref().map(|l| l.count),
+            "{path}"
+        );
+    }
+}
+
+#[test]
+fn chromatograms_can_be_read_without_spectra() {
+    let bytes = load_mzml_bytes(PATHS[0]);
+    let full = parse_mzml(&bytes, false).unwrap();
+    let expected = &full.run.chromatogram_list.as_ref().unwrap().chromatograms;
+
+    let mut reader = MzMLReader::new(Cursor::new(bytes)).unwrap();
+    assert!(reader.next_spectrum().unwrap().is_some());
+
+    let first = reader.next_chromatogram().unwrap().unwrap();
+    assert_eq!(first.id, expected[0].id);
+    assert!(reader.next_spectrum().unwrap().is_none());
+    assert_eq!(reader.chromatograms().count(), expected.len() - 1);
+    assert!(reader.next_chromatogram().unwrap().is_none());
+}
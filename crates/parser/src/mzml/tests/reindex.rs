@@ -0,0 +1,198 @@
+use std::io::Cursor;
+
+use crate::{
+    mzml::{
+        checksum::verify_file_checksum,
+        indexed::IndexedMzML,
+        parse_mzml::parse_mzml,
+        reindex::{IndexIssue, check_index, reindex},
+        tests::{gzip::gzip_member, indexed::with_index},
+    },
+    utilities::test::load_mzml_bytes,
+};
+
+const PATH: &str = "data/mzml/tiny.pwiz.mzML0.99.10.mzML";
+
+/// `bytes` with the first occurrence of `from` replaced by `to`.
+fn replace(bytes: &[u8], from: &str, to: &str) -> Vec<u8> {
+    String::from_utf8(bytes.to_vec())
+        .unwrap()
+        .replacen(from, to, 1)
+        .into_bytes()
+}
+
+/// The `<offset>` line of `id` in an index written by [`with_index`].
+fn offset_line(bytes: &[u8], id: &str) -> String {
+    let text = std::str::from_utf8(bytes).unwrap();
+    let needle = format!("<offset idRef=\"{id}\">");
+    let start = text.find(&needle).unwrap();
+    let end = start + text[start..].find('\n').unwrap();
+    text[start..end].to_string()
+}
+
+#[test]
+fn correct_index_has_no_issues() {
+    let bytes = with_index(&load_mzml_bytes(PATH), 0);
+    let report = check_index(&bytes).unwrap();
+    assert!(report.is_valid(), "{report:?}");
+    assert_eq!((report.spectra, report.chromatograms), (2, 2));
+}
+
+#[test]
+fn shifted_offsets_are_wrong() {
+    let good = check_index(&with_index(&load_mzml_bytes(PATH), 0)).unwrap();
+    assert!(good.is_valid());
+
+    let report = check_index(&with_index(&load_mzml_bytes(PATH), 7)).unwrap();
+    assert_eq!(report.issues.len(), 4, "{report:?}");
+    for issue in &report.issues {
+        let IndexIssue::Wrong { offset, actual, .. } = issue else {
+            panic!("{issue}");
+        };
+        assert_eq!(*offset, actual.unwrap() + 7);
+    }
+}
+
+#[test]
+fn unparsable_missing_and_duplicate_entries_are_reported() {
+    let bytes = with_index(&load_mzml_bytes(PATH), 0);
+    let mzml = parse_mzml(&bytes, false).unwrap();
+    let spectra = &mzml.run.spectrum_list.as_ref().unwrap().spectra;
+    let (first, second) = (&spectra[0].id, &spectra[1].id);
+
+    let line = offset_line(&bytes, first);
+    let bytes = replace(
+        &bytes,
+        &line,
+        &format!("<offset idRef=\"{first}\">12x</offset>"),
+    );
+    let line = offset_line(&bytes, second);
+    let bytes = replace(&bytes, &line, &format!("{line}\n{line}"));
+
+    let chromatogram = &mzml.run.chromatogram_list.as_ref().unwrap().chromatograms[1];
+    let line = offset_line(&bytes, &chromatogram.id);
+    let bytes = replace(&bytes, &line, "");
+
+    let report = check_index(&bytes).unwrap();
+    assert!(!report.is_valid());
+    let issues = &report.issues;
+    assert!(
+        matches!(&issues[0], IndexIssue::Unparsable { index: "spectrum", text, .. } if text == "12x"),
+        "{issues:?}"
+    );
+    assert!(
+        matches!(&issues[1], IndexIssue::Duplicate { id_ref, count: 2, .. } if id_ref == second),
+        "{issues:?}"
+    );
+    assert!(
+        matches!(&issues[2], IndexIssue::Missing { index: "chromatogram", id, .. } if *id == chromatogram.id),
+        "{issues:?}"
+    );
+    assert_eq!(issues.len(), 3, "{issues:?}");
+}
+
+#[test]
+fn wrong_index_list_offset_is_reported() {
+    let bytes = with_index(&load_mzml_bytes(PATH), 0);
+    let at = bytes
+        .windows(b"<indexList ".len())
+        .position(|w| w == b"<indexList ")
+        .unwrap();
+    let bytes = replace(
+        &bytes,
+        &format!("<indexListOffset>{at}<"),
+        &format!("<indexListOffset>{}<", at + 1),
+    );
+
+    let report = check_index(&bytes).unwrap();
+    assert_eq!(
+        report.issues,
+        vec![IndexIssue::ListOffset {
+            recorded: Some(at as u64 + 1),
+            actual: Some(at as u64),
+        }]
+    );
+}
+
+#[test]
+fn unparsable_offset_makes_the_reader_scan() {
+    let bytes = with_index(&load_mzml_bytes(PATH), 0);
+    let mzml = parse_mzml(&bytes, false).unwrap();
+    let id = &mzml.run.spectrum_list.as_ref().unwrap().spectra[0].id;
+    let line = offset_line(&bytes, id);
+    let bytes = replace(&bytes, &line, &format!("<offset idRef=\"{id}\"></offset>"));
+
+    let mut indexed = IndexedMzML::new(Cursor::new(bytes)).unwrap();
+    assert!(indexed.index_rebuilt());
+    assert_eq!(&indexed.spectrum(0).unwrap().id, id);
+}
+
+#[test]
+fn reindex_repairs_a_broken_index() {
+    let original = with_index(&load_mzml_bytes(PATH), 7);
+    let fixed = reindex(&original).unwrap();
+
+    let report = check_index(&fixed).unwrap();
+    assert!(report.is_valid(), "{report:?}");
+    assert_eq!(verify_file_checksum(&fixed), Ok(true));
+
+    let end = b"</mzML>";
+    let cut = original.windows(end.len()).position(|w| w == end).unwrap() + end.len();
+    assert_eq!(&fixed[..cut], &original[..cut]);
+
+    let indexed = IndexedMzML::new(Cursor::new(fixed)).unwrap();
+    assert!(!indexed.index_rebuilt());
+}
+
+#[test]
+fn reindex_wraps_plain_mzml() {
+    let plain = load_mzml_bytes("data/mzml/tiny1.mzML0.99.0.mzML");
+    assert!(!check_index(&plain).unwrap().indexed);
+
+    let fixed = reindex(&gzip_member(&plain)).unwrap();
+    let report = check_index(&fixed).unwrap();
+    assert!(report.is_valid(), "{report:?}");
+    assert_eq!(report.spectra, 2);
+
+    let expected = parse_mzml(&plain, false).unwrap();
+    let wrapped = parse_mzml(&fixed, false).unwrap();
+    assert_eq!(format!("{wrapped:?}"), format!("{expected:?}"));
+
+    let mut indexed = IndexedMzML::new(Cursor::new(fixed)).unwrap();
+    assert!(!indexed.index_rebuilt());
+    let first = &expected.run.spectrum_list.as_ref().unwrap().spectra[0];
+    assert_eq!(
+        format!("{:?}", indexed.spectrum(0).unwrap()),
+        format!("{first:?}")
+    );
+}
+
+#[test]
+fn reindex_needs_a_complete_document() {
+    let bytes = load_mzml_bytes(PATH);
+    let cut = &bytes[..bytes.len() / 2];
+    assert!(reindex(cut).is_err());
+}
+
+#[test]
+fn gt_inside_attribute_values_is_indexed() {
+    let body = replace(
+        &load_mzml_bytes(PATH),
+        r#"nativeID="sic native""#,
+        r#"nativeID="sic->native""#,
+    );
+    let bytes = replace(
+        &with_index(&body, 0),
+        r#"<offset idRef="sic">"#,
+        r#"<offset idRef="sic" nativeID="sic_1000.00->500.00">"#,
+    );
+
+    let report = check_index(&bytes).unwrap();
+    assert!(report.is_valid(), "{report:?}");
+    assert_eq!((report.spectra, report.chromatograms), (2, 2));
+
+    let fixed = reindex(&bytes).unwrap();
+    let report = check_index(&fixed).unwrap();
+    assert!(report.is_valid(), "{report:?}");
+    assert_eq!((report.spectra, report.chromatograms), (2, 2));
+}
@@ -0,0 +1,129 @@
+use std::sync::OnceLock;
+
+use crate::{
+    MzML,
+    b64::{decode, encode},
+    mzml::{bin_to_mzml::bin_to_mzml, parse_mzml::parse_mzml},
+    utilities::test::load_mzml_bytes,
+};
+
+const PATH: &str = "data/mzml/tiny.pwiz.mzML0.99.10.mzML";
+
+const SAMPLE: &str = r#"<sample id="SP1" name="Sample1">
+        <referenceableParamGroupRef ref="CommonMS1SpectrumParams"/>
+        <referenceableParamGroupRef ref="CommonMS2SpectrumParams"/>
+        <cvParam cvRef="MS" accession="MS:1000004" name="sample mass" value="1.5" unitCvRef="UO" unitAccession="UO:0000021" unitName="gram"/>
+        <userParam name="organism" value="Homo sapiens" type="xsd:string"/>
+        <userParam name="tissue" value="liver"/>
+      </sample>"#;
+
+const SOFTWARE: &str = r#"<software id="pwiz" version="1.0">
+        <referenceableParamGroupRef ref="CommonMS1SpectrumParams"/>
+        <cvParam cvRef="MS" accession="MS:1000615" name="ProteoWizard software" value=""/>
+        <userParam name="software name" value="msconvert"/>
+      </software>"#;
+
+static MZML_CACHE: OnceLock<MzML> = OnceLock::new();
+
+/// The test file with a fully populated `<sample>` and `<software>`.
+fn source() -> &'static MzML {
+    MZML_CACHE.get_or_init(|| {
+        let xml = String::from_utf8(load_mzml_bytes(PATH)).unwrap();
+        let (head, rest) = xml.split_once(r#"<sample id="SP1""#).unwrap();
+        let rest = &rest[rest.find("</sample>").unwrap() + "</sample>".len()..];
+        let xml = format!("{head}{SAMPLE}{rest}");
+
+        let start = xml.find(r#"<software id="pwiz">"#).unwrap();
+        let end = start + xml[start..].find("</software>").unwrap() + "</software>".len();
+        let xml = format!("{}{SOFTWARE}{}", &xml[..start], &xml[end..]);
+
+        parse_mzml(xml.as_bytes(), false).unwrap()
+    })
+}
+
+fn names(params: impl IntoIterator<Item = String>) -> Vec<String> {
+    params.into_iter().collect()
+}
+
+#[test]
+fn sample_and_software_params_are_parsed() {
+    let mzml = source();
+
+    let sample = &mzml.sample_list.as_ref().unwrap().samples[0];
+    let refs: Vec<_> = sample
+        .referenceable_param_group_ref
+        .iter()
+        .map(|r| r.r#ref.as_str())
+        .collect();
+    assert_eq!(refs, ["CommonMS1SpectrumParams", "CommonMS2SpectrumParams"]);
+    assert_eq!(sample.cv_param.len(), 1);
+    assert_eq!(sample.cv_param[0].value.as_deref(), Some("1.5"));
+    assert_eq!(sample.cv_param[0].unit_name.as_deref(), Some("gram"));
+    assert_eq!(
+        names(sample.user_param.iter().map(|p| p.name.clone())),
+        ["organism", "tissue"]
+    );
+    assert_eq!(sample.user_param[0].r#type.as_deref(), Some("xsd:string"));
+    assert!(sample.extensions.is_empty(), "{:?}", sample.extensions);
+
+    let software = &mzml.software_list.as_ref().unwrap().software[1];
+    assert_eq!(software.id, "pwiz");
+    assert_eq!(software.referenceable_param_group_ref.len(), 1);
+    assert_eq!(software.cv_param[0].name, "ProteoWizard software");
+    assert_eq!(software.user_param[0].value.as_deref(), Some("msconvert"));
+    assert!(software.extensions.is_empty(), "{:?}", software.extensions);
+}
+
+#[test]
+fn mzml_round_trip_keeps_sample_and_software_params() {
+    let mzml = source();
+    let again = parse_mzml(bin_to_mzml(mzml).unwrap().as_bytes(), false).unwrap();
+
+    assert_eq!(
+        format!("{:?}", again.sample_list),
+        format!("{:?}", mzml.sample_list)
+    );
+    assert_eq!(
+        format!("{:?}", again.software_list.unwrap().software[1]),
+        format!("{:?}", mzml.software_list.as_ref().unwrap().software[1])
+    );
+}
+
+#[test]
+fn b000_round_trip_keeps_sample_and_software_params() {
+    let mzml = source();
+    let decoded = decode(&encode(mzml, 3, false)).unwrap();
+
+    // Referenced groups are stored expanded, ahead of the element's own params.
+    let sample = &decoded.sample_list.as_ref().unwrap().samples[0];
+    assert_eq!(
+        names(sample.cv_param.iter().map(|p| p.name.clone())),
+        [
+            "positive scan",
+            "full scan",
+            "positive scan",
+            "full scan",
+            "sample mass"
+        ]
+    );
+    assert_eq!(sample.cv_param[4].value.as_deref(), Some("1.5"));
+    assert_eq!(
+        sample.cv_param[4].unit_accession.as_deref(),
+        Some("UO:0000021")
+    );
+    assert_eq!(
+        format!("{:?}", sample.user_param),
+        format!(
+            "{:?}",
+            mzml.sample_list.as_ref().unwrap().samples[0].user_param
+        )
+    );
+
+    let software = &decoded.software_list.as_ref().unwrap().software[1];
+    assert_eq!(
+        names(software.cv_param.iter().map(|p| p.name.clone())),
+        ["positive scan", "full scan", "ProteoWizard software"]
+    );
+    assert_eq!(software.user_param[0].name, "software name");
+    assert_eq!(software.user_param[0].value.as_deref(), Some("msconvert"));
+}